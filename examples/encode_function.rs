@@ -1,30 +1,31 @@
 extern crate x86asm;
 
-use std::io::Cursor;
-use x86asm::{Instruction, InstructionWriter, Mnemonic, Mode, Operand, OperandSize, Reg};
+use std::io::{self, Cursor};
+use x86asm::{write_assembly_listing, ArgumentType, CallingConvention, Frame, Instruction, InstructionWriter, Listing, Mnemonic, Mode, Operand, Reg};
 
 fn main() {
     let buffer = Cursor::new(Vec::new());
     let mut writer = InstructionWriter::new(buffer, Mode::Protected); 
+    writer.enable_listing();
 
-    let instructions = &[
-        Instruction::new1(Mnemonic::PUSH, Operand::Direct(Reg::EBP)),
-        Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::EBP), Operand::Direct(Reg::ESP)),
-        Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::EAX), Operand::IndirectDisplaced(Reg::EBP, 12, Some(OperandSize::Dword), None)),
-        Instruction::new2(Mnemonic::ADD, Operand::Direct(Reg::EAX), Operand::IndirectDisplaced(Reg::EBP, 8, Some(OperandSize::Dword), None)),
-        Instruction::new0(Mnemonic::LEAVE),
-        Instruction::new0(Mnemonic::RET),
-    ];
+    // int add(int a, int b), as cdecl: the arguments are at [ebp+8] and [ebp+12] after the prologue.
+    let frame = Frame::new(CallingConvention::Cdecl, &[ArgumentType::Integer, ArgumentType::Integer], &[Reg::EAX], 0, 0);
+    let mut instructions = frame.prologue();
+    instructions.push(Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::EAX), frame.argument(1).unwrap()));
+    instructions.push(Instruction::new2(Mnemonic::ADD, Operand::Direct(Reg::EAX), frame.argument(0).unwrap()));
+    instructions.extend(frame.epilogue());
 
     let mut bytes_written = 0;
 
-    for instr in instructions {
+    for instr in &instructions {
         bytes_written += writer.write(instr).unwrap();
     }
     
-    print!("Output ({} bytes): ", bytes_written);
-    for byte in writer.get_inner_writer_ref().get_ref().iter() {
-        print!("{:02X} ", byte);
-    }
-    println!("");
+    println!("Output ({} bytes):", bytes_written);
+    write_assembly_listing(&mut io::stdout(), writer.get_listing()).unwrap();
+
+    println!("\nDisassembly:");
+    let mut listing = Listing::new();
+    listing.add_symbol(0x1000, "add");
+    listing.write_code(&mut io::stdout(), writer.get_inner_writer_ref().get_ref(), 0x1000, Mode::Protected).unwrap();
 }
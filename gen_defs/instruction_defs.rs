@@ -4,7 +4,7 @@ use ::instruction_def::*;
 use ::instruction_def::OperandType::*;
 use ::instruction_def::OperandSizePrefixBehavior::*;
 
-pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
+pub static INSTR_DEFS: [InstructionDefinition; 3637] = [
 	InstructionDefinition {
 	    mnemonic: Mnemonic::AAA,
 	    allow_prefix: true,
@@ -4581,6 +4581,32 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    valid_16: true,
 	    desc: ""
 	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::CLRSSBSY,
+	    allow_prefix: false,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Always,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 174,
+	    secondary_opcode: None,
+	    opcode_ext: Some(6),
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::ReadWrite, size: OperandSize::Qword, op_type: OperandType::Mem(Some(OperandSize::Qword)) }), None, None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: false,
+	    desc: ""
+	},
 	InstructionDefinition {
 	    mnemonic: Mnemonic::CLTS,
 	    allow_prefix: true,
@@ -6765,6 +6791,240 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    valid_16: false,
 	    desc: ""
 	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::CMOVPO,
+	    allow_prefix: true,
+	    operand_size_prefix: NotReal,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 75,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: true,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmReg, access: OperandAccess::ReadWrite, size: OperandSize::Word, op_type: OperandType::Reg(General) }), Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Word, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Word))]) }), None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: true,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::CMOVPO,
+	    allow_prefix: true,
+	    operand_size_prefix: RealOnly,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 75,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: true,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmReg, access: OperandAccess::ReadWrite, size: OperandSize::Dword, op_type: OperandType::Reg(General) }), Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Dword, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Dword))]) }), None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: true,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::CMOVPO,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: Some(CompositePrefix::Rex { size_64: Some(true) }),
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 75,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: true,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmReg, access: OperandAccess::ReadWrite, size: OperandSize::Qword, op_type: OperandType::Reg(General) }), Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Qword, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Qword))]) }), None, None],
+	    valid_64: true,
+	    valid_32: false,
+	    valid_16: false,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::CMOVS,
+	    allow_prefix: true,
+	    operand_size_prefix: NotReal,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 72,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: true,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmReg, access: OperandAccess::ReadWrite, size: OperandSize::Word, op_type: OperandType::Reg(General) }), Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Word, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Word))]) }), None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: true,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::CMOVS,
+	    allow_prefix: true,
+	    operand_size_prefix: RealOnly,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 72,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: true,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmReg, access: OperandAccess::ReadWrite, size: OperandSize::Dword, op_type: OperandType::Reg(General) }), Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Dword, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Dword))]) }), None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: true,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::CMOVS,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: Some(CompositePrefix::Rex { size_64: Some(true) }),
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 72,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: true,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmReg, access: OperandAccess::ReadWrite, size: OperandSize::Qword, op_type: OperandType::Reg(General) }), Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Qword, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Qword))]) }), None, None],
+	    valid_64: true,
+	    valid_32: false,
+	    valid_16: false,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::CMOVZ,
+	    allow_prefix: true,
+	    operand_size_prefix: NotReal,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 68,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: true,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmReg, access: OperandAccess::ReadWrite, size: OperandSize::Word, op_type: OperandType::Reg(General) }), Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Word, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Word))]) }), None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: true,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::CMOVZ,
+	    allow_prefix: true,
+	    operand_size_prefix: RealOnly,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 68,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: true,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmReg, access: OperandAccess::ReadWrite, size: OperandSize::Dword, op_type: OperandType::Reg(General) }), Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Dword, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Dword))]) }), None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: true,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::CMOVZ,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: Some(CompositePrefix::Rex { size_64: Some(true) }),
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 68,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: true,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmReg, access: OperandAccess::ReadWrite, size: OperandSize::Qword, op_type: OperandType::Reg(General) }), Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Qword, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Qword))]) }), None, None],
+	    valid_64: true,
+	    valid_32: false,
+	    valid_16: false,
+	    desc: ""
+	},
 	InstructionDefinition {
 	    mnemonic: Mnemonic::CMP,
 	    allow_prefix: true,
@@ -9417,6 +9677,58 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    valid_16: true,
 	    desc: ""
 	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::ENDBR32,
+	    allow_prefix: false,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Always,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 30,
+	    secondary_opcode: Some(251),
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [None, None, None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: true,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::ENDBR64,
+	    allow_prefix: false,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Always,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 30,
+	    secondary_opcode: Some(250),
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [None, None, None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: true,
+	    desc: ""
+	},
 	InstructionDefinition {
 	    mnemonic: Mnemonic::ENTER,
 	    allow_prefix: true,
@@ -14409,6 +14721,58 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    valid_16: false,
 	    desc: ""
 	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::INCSSPD,
+	    allow_prefix: false,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Always,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 174,
+	    secondary_opcode: None,
+	    opcode_ext: Some(5),
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Dword, op_type: OperandType::Reg(General) }), None, None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: false,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::INCSSPQ,
+	    allow_prefix: false,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Always,
+	    composite_prefix: Some(CompositePrefix::Rex { size_64: Some(true) }),
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 174,
+	    secondary_opcode: None,
+	    opcode_ext: Some(5),
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Qword, op_type: OperandType::Reg(General) }), None, None, None],
+	    valid_64: true,
+	    valid_32: false,
+	    valid_16: false,
+	    desc: ""
+	},
 	InstructionDefinition {
 	    mnemonic: Mnemonic::INS,
 	    allow_prefix: true,
@@ -14617,6 +14981,32 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    valid_16: true,
 	    desc: ""
 	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::INT,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: false,
+	    primary_opcode: 205,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::Imm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Imm }), None, None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: true,
+	    desc: ""
+	},
 	InstructionDefinition {
 	    mnemonic: Mnemonic::INTO,
 	    allow_prefix: true,
@@ -17402,7 +17792,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	InstructionDefinition {
 	    mnemonic: Mnemonic::JS,
 	    allow_prefix: true,
-	    operand_size_prefix: Never,
+	    operand_size_prefix: NotReal,
 	    address_size_prefix: None,
 	    f2_prefix: PrefixBehavior::Never,
 	    f3_prefix: PrefixBehavior::Never,
@@ -17425,6 +17815,32 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    valid_16: true,
 	    desc: ""
 	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::JS,
+	    allow_prefix: true,
+	    operand_size_prefix: RealOnly,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 136,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::Imm, access: OperandAccess::Read, size: OperandSize::Dword, op_type: OperandType::Rel(OperandSize::Dword) }), None, None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: true,
+	    desc: ""
+	},
 	InstructionDefinition {
 	    mnemonic: Mnemonic::JZ,
 	    allow_prefix: true,
@@ -33779,6 +34195,58 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    valid_16: false,
 	    desc: ""
 	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::RDSSPD,
+	    allow_prefix: false,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Always,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 30,
+	    secondary_opcode: None,
+	    opcode_ext: Some(1),
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Write, size: OperandSize::Dword, op_type: OperandType::Reg(General) }), None, None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: false,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::RDSSPQ,
+	    allow_prefix: false,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Always,
+	    composite_prefix: Some(CompositePrefix::Rex { size_64: Some(true) }),
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 30,
+	    secondary_opcode: None,
+	    opcode_ext: Some(1),
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Write, size: OperandSize::Qword, op_type: OperandType::Reg(General) }), None, None, None],
+	    valid_64: true,
+	    valid_32: false,
+	    valid_16: false,
+	    desc: ""
+	},
 	InstructionDefinition {
 	    mnemonic: Mnemonic::RDTSC,
 	    allow_prefix: true,
@@ -34949,6 +35417,32 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    valid_16: false,
 	    desc: ""
 	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::RSTORSSP,
+	    allow_prefix: false,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Always,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 1,
+	    secondary_opcode: None,
+	    opcode_ext: Some(5),
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::ReadWrite, size: OperandSize::Qword, op_type: OperandType::Mem(Some(OperandSize::Qword)) }), None, None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: false,
+	    desc: ""
+	},
 	InstructionDefinition {
 	    mnemonic: Mnemonic::SAHF,
 	    allow_prefix: true,
@@ -35807,6 +36301,32 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    valid_16: false,
 	    desc: ""
 	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::SAVEPREVSSP,
+	    allow_prefix: false,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Always,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 1,
+	    secondary_opcode: Some(234),
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [None, None, None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: false,
+	    desc: ""
+	},
 	InstructionDefinition {
 	    mnemonic: Mnemonic::SBB,
 	    allow_prefix: true,
@@ -36692,7 +37212,579 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETB,
+	    mnemonic: Mnemonic::SETB,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 146,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: true,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::SETB,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 146,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
+	    valid_64: true,
+	    valid_32: false,
+	    valid_16: false,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::SETBE,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 150,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: true,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::SETBE,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 150,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
+	    valid_64: true,
+	    valid_32: false,
+	    valid_16: false,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::SETC,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 146,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: true,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::SETC,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 146,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
+	    valid_64: true,
+	    valid_32: false,
+	    valid_16: false,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::SETE,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 148,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: true,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::SETE,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 148,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
+	    valid_64: true,
+	    valid_32: false,
+	    valid_16: false,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::SETG,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 159,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: true,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::SETG,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 159,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
+	    valid_64: true,
+	    valid_32: false,
+	    valid_16: false,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::SETGE,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 157,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: true,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::SETGE,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 157,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
+	    valid_64: true,
+	    valid_32: false,
+	    valid_16: false,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::SETL,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 156,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: true,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::SETL,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 156,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
+	    valid_64: true,
+	    valid_32: false,
+	    valid_16: false,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::SETLE,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 158,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: true,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::SETLE,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 158,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
+	    valid_64: true,
+	    valid_32: false,
+	    valid_16: false,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::SETNA,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 150,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: true,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::SETNA,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 150,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
+	    valid_64: true,
+	    valid_32: false,
+	    valid_16: false,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::SETNAE,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 146,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: true,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::SETNAE,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 146,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
+	    valid_64: true,
+	    valid_32: false,
+	    valid_16: false,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::SETNB,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 147,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: true,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::SETNB,
+	    allow_prefix: true,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 147,
+	    secondary_opcode: None,
+	    opcode_ext: None,
+	    has_mod_rm: false,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
+	    valid_64: true,
+	    valid_32: false,
+	    valid_16: false,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::SETNBE,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -36701,7 +37793,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: None,
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 146,
+	    primary_opcode: 151,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -36718,7 +37810,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETB,
+	    mnemonic: Mnemonic::SETNBE,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -36727,7 +37819,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 146,
+	    primary_opcode: 151,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -36744,7 +37836,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETBE,
+	    mnemonic: Mnemonic::SETNC,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -36753,7 +37845,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: None,
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 150,
+	    primary_opcode: 147,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -36770,7 +37862,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETBE,
+	    mnemonic: Mnemonic::SETNC,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -36779,7 +37871,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 150,
+	    primary_opcode: 147,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -36796,7 +37888,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETC,
+	    mnemonic: Mnemonic::SETNE,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -36805,7 +37897,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: None,
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 146,
+	    primary_opcode: 149,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -36822,7 +37914,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETC,
+	    mnemonic: Mnemonic::SETNE,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -36831,7 +37923,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 146,
+	    primary_opcode: 149,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -36848,7 +37940,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETE,
+	    mnemonic: Mnemonic::SETNG,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -36857,7 +37949,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: None,
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 148,
+	    primary_opcode: 158,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -36874,7 +37966,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETE,
+	    mnemonic: Mnemonic::SETNG,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -36883,7 +37975,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 148,
+	    primary_opcode: 158,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -36900,7 +37992,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETG,
+	    mnemonic: Mnemonic::SETNGE,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -36909,7 +38001,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: None,
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 159,
+	    primary_opcode: 156,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -36926,7 +38018,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETG,
+	    mnemonic: Mnemonic::SETNGE,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -36935,7 +38027,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 159,
+	    primary_opcode: 156,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -36952,7 +38044,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETGE,
+	    mnemonic: Mnemonic::SETNL,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -36978,7 +38070,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETGE,
+	    mnemonic: Mnemonic::SETNL,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -37004,7 +38096,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETL,
+	    mnemonic: Mnemonic::SETNLE,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -37013,7 +38105,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: None,
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 156,
+	    primary_opcode: 159,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -37030,33 +38122,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETL,
-	    allow_prefix: true,
-	    operand_size_prefix: Never,
-	    address_size_prefix: None,
-	    f2_prefix: PrefixBehavior::Never,
-	    f3_prefix: PrefixBehavior::Never,
-	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
-	    fwait: false,
-	    two_byte_opcode: true,
-	    primary_opcode: 156,
-	    secondary_opcode: None,
-	    opcode_ext: None,
-	    has_mod_rm: false,
-	    fixed_mod_rm_mod: None,
-	    fixed_mod_rm_reg: None,
-	    allow_mask: false,
-	    allow_merge_mode: false,
-	    allow_rounding: false,
-	    allow_sae: false,
-	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
-	    valid_64: true,
-	    valid_32: false,
-	    valid_16: false,
-	    desc: ""
-	},
-	InstructionDefinition {
-	    mnemonic: Mnemonic::SETLE,
+	    mnemonic: Mnemonic::SETNO,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -37065,7 +38131,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: None,
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 158,
+	    primary_opcode: 145,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -37082,7 +38148,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETLE,
+	    mnemonic: Mnemonic::SETNO,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -37091,7 +38157,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 158,
+	    primary_opcode: 145,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -37108,7 +38174,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETNA,
+	    mnemonic: Mnemonic::SETNP,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -37117,7 +38183,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: None,
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 150,
+	    primary_opcode: 155,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -37134,7 +38200,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETNA,
+	    mnemonic: Mnemonic::SETNP,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -37143,7 +38209,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 150,
+	    primary_opcode: 155,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -37160,7 +38226,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETNAE,
+	    mnemonic: Mnemonic::SETNS,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -37169,7 +38235,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: None,
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 146,
+	    primary_opcode: 153,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -37186,7 +38252,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETNAE,
+	    mnemonic: Mnemonic::SETNS,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -37195,7 +38261,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 146,
+	    primary_opcode: 153,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -37212,7 +38278,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETNB,
+	    mnemonic: Mnemonic::SETNZ,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -37221,7 +38287,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: None,
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 147,
+	    primary_opcode: 149,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -37238,7 +38304,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETNB,
+	    mnemonic: Mnemonic::SETNZ,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -37247,7 +38313,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 147,
+	    primary_opcode: 149,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -37264,7 +38330,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETNBE,
+	    mnemonic: Mnemonic::SETO,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -37273,7 +38339,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: None,
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 151,
+	    primary_opcode: 144,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -37290,7 +38356,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETNBE,
+	    mnemonic: Mnemonic::SETO,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -37299,7 +38365,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 151,
+	    primary_opcode: 144,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -37316,7 +38382,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETNC,
+	    mnemonic: Mnemonic::SETP,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -37325,7 +38391,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: None,
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 147,
+	    primary_opcode: 154,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -37342,7 +38408,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETNC,
+	    mnemonic: Mnemonic::SETP,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -37351,7 +38417,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 147,
+	    primary_opcode: 154,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -37368,7 +38434,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETNE,
+	    mnemonic: Mnemonic::SETPE,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -37377,7 +38443,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: None,
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 149,
+	    primary_opcode: 154,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -37394,7 +38460,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETNE,
+	    mnemonic: Mnemonic::SETPE,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -37403,7 +38469,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 149,
+	    primary_opcode: 154,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -37420,7 +38486,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETNG,
+	    mnemonic: Mnemonic::SETPO,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -37429,7 +38495,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: None,
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 158,
+	    primary_opcode: 155,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -37446,7 +38512,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETNG,
+	    mnemonic: Mnemonic::SETPO,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -37455,7 +38521,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 158,
+	    primary_opcode: 155,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -37472,7 +38538,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETNGE,
+	    mnemonic: Mnemonic::SETS,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -37481,7 +38547,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: None,
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 156,
+	    primary_opcode: 152,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -37498,7 +38564,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETNGE,
+	    mnemonic: Mnemonic::SETS,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
@@ -37507,7 +38573,7 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 156,
+	    primary_opcode: 152,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -37524,17 +38590,17 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETNL,
-	    allow_prefix: true,
+	    mnemonic: Mnemonic::SETSSBSY,
+	    allow_prefix: false,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
 	    f2_prefix: PrefixBehavior::Never,
-	    f3_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Always,
 	    composite_prefix: None,
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 157,
-	    secondary_opcode: None,
+	    primary_opcode: 1,
+	    secondary_opcode: Some(232),
 	    opcode_ext: None,
 	    has_mod_rm: false,
 	    fixed_mod_rm_mod: None,
@@ -37543,23 +38609,23 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    allow_merge_mode: false,
 	    allow_rounding: false,
 	    allow_sae: false,
-	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
+	    operands: [None, None, None, None],
 	    valid_64: true,
 	    valid_32: true,
-	    valid_16: true,
+	    valid_16: false,
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETNL,
+	    mnemonic: Mnemonic::SETZ,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
 	    f2_prefix: PrefixBehavior::Never,
 	    f3_prefix: PrefixBehavior::Never,
-	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
+	    composite_prefix: None,
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 157,
+	    primary_opcode: 148,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -37571,21 +38637,21 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    allow_sae: false,
 	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
 	    valid_64: true,
-	    valid_32: false,
-	    valid_16: false,
+	    valid_32: true,
+	    valid_16: true,
 	    desc: ""
 	},
 	InstructionDefinition {
-	    mnemonic: Mnemonic::SETNLE,
+	    mnemonic: Mnemonic::SETZ,
 	    allow_prefix: true,
 	    operand_size_prefix: Never,
 	    address_size_prefix: None,
 	    f2_prefix: PrefixBehavior::Never,
 	    f3_prefix: PrefixBehavior::Never,
-	    composite_prefix: None,
+	    composite_prefix: Some(CompositePrefix::Rex { size_64: None }),
 	    fwait: false,
 	    two_byte_opcode: true,
-	    primary_opcode: 159,
+	    primary_opcode: 148,
 	    secondary_opcode: None,
 	    opcode_ext: None,
 	    has_mod_rm: false,
@@ -37597,8 +38663,8 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    allow_sae: false,
 	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Read, size: OperandSize::Byte, op_type: OperandType::Set(&[Reg(General), Mem(Some(OperandSize::Byte))]) }), None, None, None],
 	    valid_64: true,
-	    valid_32: true,
-	    valid_16: true,
+	    valid_32: false,
+	    valid_16: false,
 	    desc: ""
 	},
 	InstructionDefinition {
@@ -91603,6 +92669,110 @@ pub static INSTR_DEFS: [InstructionDefinition; 3592] = [
 	    valid_16: true,
 	    desc: ""
 	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::WRSSD,
+	    allow_prefix: false,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 56,
+	    secondary_opcode: Some(246),
+	    opcode_ext: None,
+	    has_mod_rm: true,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Write, size: OperandSize::Dword, op_type: OperandType::Mem(Some(OperandSize::Dword)) }), Some(OperandDefinition { encoding: OperandEncoding::ModRmReg, access: OperandAccess::Read, size: OperandSize::Dword, op_type: OperandType::Reg(General) }), None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: false,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::WRSSQ,
+	    allow_prefix: false,
+	    operand_size_prefix: Never,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: Some(CompositePrefix::Rex { size_64: Some(true) }),
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 56,
+	    secondary_opcode: Some(246),
+	    opcode_ext: None,
+	    has_mod_rm: true,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Write, size: OperandSize::Qword, op_type: OperandType::Mem(Some(OperandSize::Qword)) }), Some(OperandDefinition { encoding: OperandEncoding::ModRmReg, access: OperandAccess::Read, size: OperandSize::Qword, op_type: OperandType::Reg(General) }), None, None],
+	    valid_64: true,
+	    valid_32: false,
+	    valid_16: false,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::WRUSSD,
+	    allow_prefix: false,
+	    operand_size_prefix: Always,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: None,
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 56,
+	    secondary_opcode: Some(245),
+	    opcode_ext: None,
+	    has_mod_rm: true,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Write, size: OperandSize::Dword, op_type: OperandType::Mem(Some(OperandSize::Dword)) }), Some(OperandDefinition { encoding: OperandEncoding::ModRmReg, access: OperandAccess::Read, size: OperandSize::Dword, op_type: OperandType::Reg(General) }), None, None],
+	    valid_64: true,
+	    valid_32: true,
+	    valid_16: false,
+	    desc: ""
+	},
+	InstructionDefinition {
+	    mnemonic: Mnemonic::WRUSSQ,
+	    allow_prefix: false,
+	    operand_size_prefix: Always,
+	    address_size_prefix: None,
+	    f2_prefix: PrefixBehavior::Never,
+	    f3_prefix: PrefixBehavior::Never,
+	    composite_prefix: Some(CompositePrefix::Rex { size_64: Some(true) }),
+	    fwait: false,
+	    two_byte_opcode: true,
+	    primary_opcode: 56,
+	    secondary_opcode: Some(245),
+	    opcode_ext: None,
+	    has_mod_rm: true,
+	    fixed_mod_rm_mod: None,
+	    fixed_mod_rm_reg: None,
+	    allow_mask: false,
+	    allow_merge_mode: false,
+	    allow_rounding: false,
+	    allow_sae: false,
+	    operands: [Some(OperandDefinition { encoding: OperandEncoding::ModRmRm, access: OperandAccess::Write, size: OperandSize::Qword, op_type: OperandType::Mem(Some(OperandSize::Qword)) }), Some(OperandDefinition { encoding: OperandEncoding::ModRmReg, access: OperandAccess::Read, size: OperandSize::Qword, op_type: OperandType::Reg(General) }), None, None],
+	    valid_64: true,
+	    valid_32: false,
+	    valid_16: false,
+	    desc: ""
+	},
 	InstructionDefinition {
 	    mnemonic: Mnemonic::XABORT,
 	    allow_prefix: true,
@@ -1,7 +1,10 @@
 #!/bin/bash
+# Usage: make_all.sh x86.csv
+# Reads the csv from the Intel reference manual, along with extra_defs.csv (see README.md).
+if [ -z "$1" ]; then echo "Usage: $0 x86.csv" >&2; exit 1; fi
 rm instruction_defs.rs
 
-cargo run -- --tests \
+cargo run -- --input "$1" --tests \
     | sed 's/^/\t/g' \
     | sed 's/encoding: /encoding: OperandEncoding::/g' \
     | sed 's/access: /access: OperandAccess::/g' \
@@ -38,7 +38,9 @@ use instruction_def::RegType;
 use instruction_def::VexOperandBehavior;
 use operand::OperandSize;
 
-const CSV_INPUT: &'static str = "/home/gregory/Downloads/x86.csv";
+// Instructions the csv is missing, i.e. condition code aliases and newer extensions, in the same
+// format plus an Opcode ModRM column. This file is maintained by hand.
+const EXTRA_CSV_INPUT: &'static str = "extra_defs.csv";
 const TEST_OUTPUT: &'static str = "test";
 
 #[derive(Deserialize, Debug)]
@@ -54,13 +56,18 @@ struct EncodingRecord {
     #[serde(rename="Operand 3")] operand3: String,
     #[serde(rename="Operand 4")] operand4: String,
     #[serde(rename="Tuple Type")] tuple_type: String,
-    #[serde(rename="Description")] description: String
+    #[serde(rename="Description")] description: String,
+    // Only in extra_defs.csv -- "Yes" if the opcode ends with a whole ModR/M byte (see
+    // parse_record).
+    #[serde(rename="Opcode ModRM", default)] whole_mod_rm: String
 }
 
 fn main() {
     let args = parse_args();
-    let file = File::open(CSV_INPUT).expect("Error opening input file.");
+    let file = File::open(&args.input).expect("Error opening input file.");
     let mut reader = csv::Reader::from_reader(file);
+    let extra_file = File::open(EXTRA_CSV_INPUT).expect("Error opening extra input file.");
+    let mut extra_reader = csv::Reader::from_reader(extra_file);
 
     // Clean & recreate output dir
     std::fs::remove_dir_all(TEST_OUTPUT);
@@ -69,7 +76,8 @@ fn main() {
     // Track how many tests have been emitted for each mnemonic for numbering purposes
     let mut test_count = HashMap::new();
 
-    for group in reader.deserialize().map(|r| r.expect("Error reading record."))
+    for group in reader.deserialize().chain(extra_reader.deserialize())
+        .map(|r| r.expect("Error reading record."))
         .filter(|r: &EncodingRecord| args.filter.as_ref().map_or(true, 
             // |f| r.instruction.find(f.as_str()).is_some()))
             |f| r.instruction.as_str().starts_with(f)))
@@ -120,11 +128,13 @@ fn main() {
 }
 
 struct ParsedArgs {
+    input: String,
     emit_tests: bool,
     filter: Option<String>
 }
 
 fn parse_args() -> ParsedArgs {
+    let mut input = None;
     let mut emit_tests = false;
     let mut filter = None;
 
@@ -132,6 +142,9 @@ fn parse_args() -> ParsedArgs {
     args_iter.next(); // Skip path
     while let Some(arg) = args_iter.next() {
         match arg.as_str() {
+            "--input" => {
+                input = Some(args_iter.next().expect("Invalid argument -- missing input path."))
+            },
             "--tests" => { emit_tests = true; },
             "--filter" => {
                 filter = Some(args_iter.next().expect("Invalid argument -- missing filter value."))
@@ -140,7 +153,8 @@ fn parse_args() -> ParsedArgs {
         }
     }
 
-    ParsedArgs { emit_tests, filter }
+    let input = input.expect("Missing argument -- the csv to read, i.e. --input x86.csv.");
+    ParsedArgs { input, emit_tests, filter }
 }
 
 fn parse_record(record: &EncodingRecord) -> instruction_def::InstructionDefinition {
@@ -231,6 +245,11 @@ fn parse_record(record: &EncodingRecord) -> instruction_def::InstructionDefiniti
                 _ if instr.primary_opcode == 0x38 || instr.primary_opcode == 0x3A => {
                     instr.secondary_opcode = Some(b);
                 },
+                // These are told apart from the instructions sharing their opcode by the whole
+                // ModR/M byte, which is matched as a secondary opcode.
+                _ if instr.primary_opcode != 0 && record.whole_mod_rm == "Yes" => {
+                    instr.secondary_opcode = Some(b);
+                },
                 _ if instr.primary_opcode == 0x9B => {
                     instr.fwait = true;
                     instr.primary_opcode = b;
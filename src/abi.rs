@@ -0,0 +1,643 @@
+use std::error::Error;
+use std::fmt;
+use ::{Instruction, Mnemonic, Mode, Operand, OperandSize, Reg};
+
+const SYSV_INTEGER: [Reg; 6] = [Reg::RDI, Reg::RSI, Reg::RDX, Reg::RCX, Reg::R8, Reg::R9];
+const SYSV_FLOAT: [Reg; 8] = [Reg::XMM0, Reg::XMM1, Reg::XMM2, Reg::XMM3, Reg::XMM4, Reg::XMM5,
+    Reg::XMM6, Reg::XMM7];
+const SYSV_SAVED: [Reg; 6] = [Reg::RBX, Reg::RBP, Reg::R12, Reg::R13, Reg::R14, Reg::R15];
+const WIN64_INTEGER: [Reg; 4] = [Reg::RCX, Reg::RDX, Reg::R8, Reg::R9];
+const WIN64_FLOAT: [Reg; 4] = [Reg::XMM0, Reg::XMM1, Reg::XMM2, Reg::XMM3];
+const WIN64_SAVED: [Reg; 18] = [Reg::RBX, Reg::RBP, Reg::RDI, Reg::RSI, Reg::R12, Reg::R13,
+    Reg::R14, Reg::R15, Reg::XMM6, Reg::XMM7, Reg::XMM8, Reg::XMM9, Reg::XMM10, Reg::XMM11,
+    Reg::XMM12, Reg::XMM13, Reg::XMM14, Reg::XMM15];
+const FASTCALL_INTEGER: [Reg; 2] = [Reg::ECX, Reg::EDX];
+const X86_SAVED: [Reg; 4] = [Reg::EBX, Reg::EBP, Reg::ESI, Reg::EDI];
+const NEAR16_SAVED: [Reg; 3] = [Reg::BP, Reg::SI, Reg::DI];
+
+// Scratch register for moving arguments from memory to the stack in long mode. It isn't used to
+// pass arguments in either convention.
+const SCRATCH: Reg = Reg::R11;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CallingConvention {
+    // SysV - System V AMD64 (Linux, macOS, BSD). Integer arguments go in RDI, RSI, RDX, RCX, R8
+    // and R9, floating point ones in XMM0-XMM7 and the rest on the stack.
+    SysV,
+
+    // Win64 - Microsoft x64. The first four arguments go in RCX, RDX, R8 and R9 or XMM0-XMM3 by
+    // position, and the caller reserves 32 bytes of shadow space for them above the return address.
+    Win64,
+
+    // Cdecl - 32-bit, arguments on the stack and removed by the caller.
+    Cdecl,
+
+    // Stdcall - 32-bit, arguments on the stack and removed by the callee (i.e. Win32 APIs).
+    Stdcall,
+
+    // Fastcall - 32-bit Microsoft fastcall. The first two integer arguments go in ECX and EDX and
+    // the rest on the stack, removed by the callee.
+    Fastcall,
+
+    // Near16 - 16-bit near calls, with the arguments on the stack removed by the caller. Floating
+    // point arguments aren't supported.
+    Near16,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ArgumentType {
+    // Integer - An integer or pointer, up to the size of a pointer.
+    Integer,
+
+    // Float - A single precision float.
+    Float,
+
+    // Double - A double precision float.
+    Double,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ArgumentLocation {
+    // Register - Passed in the register.
+    Register(Reg),
+
+    // Stack - Passed on the stack, at this offset from the stack pointer at the call.
+    Stack(u32),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum AbiError {
+    // OutgoingSpace - The frame doesn't reserve the stack space (of the given size) the arguments
+    // of the call need.
+    OutgoingSpace(u32),
+
+    // InvalidArgument - The argument at the index can't be moved to where it's passed, i.e. a
+    // float from a literal or a general register.
+    InvalidArgument(usize),
+
+    // ConflictingArguments - The arguments, or the call target, read registers that other
+    // arguments are passed in, in a way that can't be resolved by reordering or exchanging them.
+    ConflictingArguments,
+}
+
+impl fmt::Display for AbiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AbiError::OutgoingSpace(size) =>
+                write!(f, "the call needs {} bytes of outgoing argument space", size),
+            AbiError::InvalidArgument(index) =>
+                write!(f, "argument {} can't be passed from the given operand", index),
+            AbiError::ConflictingArguments =>
+                f.write_str("the arguments read registers other arguments are passed in"),
+        }
+    }
+}
+
+impl Error for AbiError { }
+
+impl CallingConvention {
+    pub fn get_mode(&self) -> Mode {
+        match *self {
+            CallingConvention::SysV | CallingConvention::Win64 => Mode::Long,
+            CallingConvention::Cdecl | CallingConvention::Stdcall | CallingConvention::Fastcall =>
+                Mode::Protected,
+            CallingConvention::Near16 => Mode::Real,
+        }
+    }
+
+    fn pointer(&self) -> u32 { self.get_mode().pointer_size().bits() / 8 }
+
+    pub fn get_stack_pointer(&self) -> Reg {
+        match self.get_mode() {
+            Mode::Long => Reg::RSP,
+            Mode::Protected => Reg::ESP,
+            Mode::Real => Reg::SP,
+        }
+    }
+
+    pub fn get_frame_pointer(&self) -> Reg {
+        match self.get_mode() {
+            Mode::Long => Reg::RBP,
+            Mode::Protected => Reg::EBP,
+            Mode::Real => Reg::BP,
+        }
+    }
+
+    // The registers a function has to preserve, including the frame pointer. Win64 only preserves
+    // the low 128 bits of XMM6-XMM15.
+    pub fn get_callee_saved(&self) -> &'static [Reg] {
+        match *self {
+            CallingConvention::SysV => &SYSV_SAVED,
+            CallingConvention::Win64 => &WIN64_SAVED,
+            CallingConvention::Cdecl | CallingConvention::Stdcall | CallingConvention::Fastcall =>
+                &X86_SAVED,
+            CallingConvention::Near16 => &NEAR16_SAVED,
+        }
+    }
+
+    pub fn get_integer_registers(&self) -> &'static [Reg] {
+        match *self {
+            CallingConvention::SysV => &SYSV_INTEGER,
+            CallingConvention::Win64 => &WIN64_INTEGER,
+            CallingConvention::Fastcall => &FASTCALL_INTEGER,
+            _ => &[]
+        }
+    }
+
+    pub fn get_float_registers(&self) -> &'static [Reg] {
+        match *self {
+            CallingConvention::SysV => &SYSV_FLOAT,
+            CallingConvention::Win64 => &WIN64_FLOAT,
+            _ => &[]
+        }
+    }
+
+    // Where a value of the type is returned. Outside of long mode floats are returned in ST0.
+    pub fn get_return_register(&self, ty: ArgumentType) -> Reg {
+        match (self.get_mode(), ty) {
+            (Mode::Long, ArgumentType::Integer) => Reg::RAX,
+            (Mode::Long, _) => Reg::XMM0,
+            (Mode::Protected, ArgumentType::Integer) => Reg::EAX,
+            (Mode::Real, ArgumentType::Integer) => Reg::AX,
+            _ => Reg::ST0
+        }
+    }
+
+    // The space the caller reserves below the stack arguments for the register arguments.
+    pub fn get_shadow_space(&self) -> u32 {
+        if *self == CallingConvention::Win64 { 32 } else { 0 }
+    }
+
+    pub fn is_callee_pop(&self) -> bool {
+        *self == CallingConvention::Stdcall || *self == CallingConvention::Fastcall
+    }
+
+    // The alignment of the stack pointer at a call.
+    pub fn get_stack_alignment(&self) -> u32 {
+        if self.get_mode() == Mode::Real { 2 } else { 16 }
+    }
+
+    fn slot_size(&self, ty: ArgumentType) -> u32 {
+        match (self.get_mode(), ty) {
+            (Mode::Long, _) => 8,
+            (_, ArgumentType::Integer) => self.pointer(),
+            (_, ArgumentType::Float) => 4,
+            (_, ArgumentType::Double) => 8,
+        }
+    }
+
+    fn assign(&self, args: &[ArgumentType]) -> (Vec<ArgumentLocation>, u32) {
+        let (mut integers, mut floats) = (0, 0);
+        let mut stack = self.get_shadow_space();
+        let locations = args.iter().enumerate().map(|(i, ty)| {
+            let reg = match (*self, *ty) {
+                (CallingConvention::Win64, ArgumentType::Integer) => WIN64_INTEGER.get(i),
+                (CallingConvention::Win64, _) => WIN64_FLOAT.get(i),
+                (_, ArgumentType::Integer) => {
+                    integers += 1;
+                    self.get_integer_registers().get(integers - 1)
+                },
+                _ => {
+                    floats += 1;
+                    self.get_float_registers().get(floats - 1)
+                }
+            };
+            match reg {
+                Some(reg) => ArgumentLocation::Register(*reg),
+                None => {
+                    stack += self.slot_size(*ty);
+                    ArgumentLocation::Stack(stack - self.slot_size(*ty))
+                }
+            }
+        }).collect();
+        (locations, stack)
+    }
+
+    pub fn get_argument_locations(&self, args: &[ArgumentType]) -> Vec<ArgumentLocation> {
+        self.assign(args).0
+    }
+
+    // The stack space the arguments of a call take, including the shadow space.
+    pub fn get_argument_stack_size(&self, args: &[ArgumentType]) -> u32 {
+        self.assign(args).1
+    }
+}
+
+// The stack frame of a function. The prologue saves the frame pointer and the callee-saved
+// registers the function uses, and reserves space for its locals and for the arguments of the
+// calls it makes (in long mode), keeping the stack pointer aligned for calls. Locals and stack
+// arguments are addressed from the frame pointer.
+//
+//     arguments          [bp + 2 * pointer size...]
+//     return address
+//     saved bp           <- bp
+//     saved registers
+//     saved XMM registers (Win64)
+//     locals
+//     padding
+//     outgoing arguments <- sp
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Frame {
+    convention: CallingConvention,
+    arguments: Vec<ArgumentType>,
+    saved: Vec<Reg>,
+    saved_vectors: Vec<Reg>,
+    locals: u32,
+    allocation: u32,
+    outgoing: u32,
+}
+
+fn immediate(value: u32, mode: Mode) -> Operand {
+    if value <= 0x7F { Operand::Literal8(value as u8) }
+    else if mode == Mode::Real { Operand::Literal16(value as u16) }
+    else { Operand::Literal32(value) }
+}
+
+fn round_up(value: u32, alignment: u32) -> u32 { value.div_ceil(alignment) * alignment }
+
+// The registers read to get the value of an operand.
+fn reads(op: &Operand) -> Vec<Reg> {
+    match *op {
+        Operand::Direct(reg) => vec![reg],
+        _ => op.base_reg().into_iter().chain(op.index_reg()).collect()
+    }
+}
+
+fn reads_any(op: &Operand, regs: &[Reg]) -> bool {
+    reads(op).iter().any(|r| regs.iter().any(|reg| r.overlaps(*reg)))
+}
+
+// Gives a memory operand a size and moves it by `by` bytes, i.e. to get the high half of a double.
+fn memory_at(op: Operand, size: OperandSize, by: u64) -> Option<Operand> {
+    let size = Some(size);
+    Some(match op {
+        Operand::Indirect(base, _, seg) if by == 0 => Operand::Indirect(base, size, seg),
+        Operand::Indirect(base, _, seg) => Operand::IndirectDisplaced(base, by, size, seg),
+        Operand::IndirectDisplaced(base, disp, _, seg) =>
+            Operand::IndirectDisplaced(base, disp.wrapping_add(by), size, seg),
+        Operand::IndirectScaledIndexed(base, index, scale, _, seg) if by == 0 =>
+            Operand::IndirectScaledIndexed(base, index, scale, size, seg),
+        Operand::IndirectScaledIndexed(base, index, scale, _, seg) =>
+            Operand::IndirectScaledIndexedDisplaced(base, index, scale, by, size, seg),
+        Operand::IndirectScaledIndexedDisplaced(base, index, scale, disp, _, seg) =>
+            Operand::IndirectScaledIndexedDisplaced(base, index, scale, disp.wrapping_add(by),
+                size, seg),
+        Operand::IndirectScaledDisplaced(index, scale, disp, _, seg) =>
+            Operand::IndirectScaledDisplaced(index, scale, disp.wrapping_add(by), size, seg),
+        Operand::Memory(address, _, seg) => Operand::Memory(address.wrapping_add(by), size, seg),
+        Operand::LabelMemory(label, _, seg) if by == 0 => Operand::LabelMemory(label, size, seg),
+        _ => return None
+    })
+}
+
+// A general register that can hold an integer argument.
+fn is_integer(reg: Reg) -> bool { reg.is_general() && !reg.is_high_byte() }
+
+impl Frame {
+    // A frame for a function taking `arguments` that uses the `used` registers (the callee-saved
+    // ones among them are saved), with `locals` bytes of local variables. In long mode `outgoing`
+    // is the stack space for the arguments of the calls it makes, see get_argument_stack_size.
+    pub fn new(convention: CallingConvention, arguments: &[ArgumentType], used: &[Reg],
+        locals: u32, outgoing: u32) -> Frame {
+        let bp = convention.get_frame_pointer();
+        let is_used = |reg: &&Reg| used.iter().any(|u| u.overlaps(**reg));
+        let saved: Vec<Reg> = convention.get_callee_saved().iter()
+            .filter(|r| r.is_general() && **r != bp).filter(is_used).cloned().collect();
+        let saved_vectors: Vec<Reg> = convention.get_callee_saved().iter()
+            .filter(|r| r.is_sse()).filter(is_used).cloned().collect();
+
+        // The return address, the frame pointer and the saved registers are pushed, and the
+        // stack pointer is aligned at the call to this function.
+        let pushed = (2 + saved.len() as u32) * convention.pointer();
+        let body = saved_vectors.len() as u32 * 16 + locals + outgoing;
+        let allocation = round_up(pushed + body, convention.get_stack_alignment()) - pushed;
+        Frame { convention, arguments: arguments.to_vec(), saved, saved_vectors, locals,
+            allocation, outgoing }
+    }
+
+    pub fn get_convention(&self) -> CallingConvention { self.convention }
+
+    // The callee-saved registers the prologue saves, not counting the frame pointer.
+    pub fn get_saved(&self) -> Vec<Reg> {
+        self.saved.iter().chain(self.saved_vectors.iter()).cloned().collect()
+    }
+
+    // The bytes the stack pointer is moved down by after the registers are pushed.
+    pub fn get_allocation(&self) -> u32 { self.allocation }
+
+    fn sp(&self) -> Operand { Operand::Direct(self.convention.get_stack_pointer()) }
+
+    fn bp(&self) -> Operand { Operand::Direct(self.convention.get_frame_pointer()) }
+
+    fn mode(&self) -> Mode { self.convention.get_mode() }
+
+    // The size of the saved general registers, below the frame pointer.
+    fn pushed(&self) -> i64 { self.saved.len() as i64 * self.convention.pointer() as i64 }
+
+    fn bp_relative(&self, offset: i64, size: OperandSize) -> Operand {
+        let bp = self.convention.get_frame_pointer();
+        Operand::IndirectDisplaced(bp, offset as u64, Some(size), None)
+    }
+
+    fn vector_slot(&self, index: usize) -> Operand {
+        self.bp_relative(-self.pushed() - 16 * (index as i64 + 1), OperandSize::Xmmword)
+    }
+
+    // A local variable at `offset` within the locals.
+    pub fn local(&self, offset: u32, size: OperandSize) -> Operand {
+        let locals = self.pushed() + self.saved_vectors.len() as i64 * 16 + self.locals as i64;
+        self.bp_relative(offset as i64 - locals, size)
+    }
+
+    // Where the argument at `index` is on entry, after the prologue.
+    pub fn argument(&self, index: usize) -> Option<Operand> {
+        let ty = *self.arguments.get(index)?;
+        Some(match self.convention.get_argument_locations(&self.arguments)[index] {
+            ArgumentLocation::Register(reg) => Operand::Direct(reg),
+            ArgumentLocation::Stack(offset) => {
+                let size = match ty {
+                    ArgumentType::Integer => self.mode().pointer_size(),
+                    ArgumentType::Float => OperandSize::Dword,
+                    ArgumentType::Double => OperandSize::Qword,
+                };
+                self.bp_relative(2 * self.convention.pointer() as i64 + offset as i64, size)
+            }
+        })
+    }
+
+    pub fn prologue(&self) -> Vec<Instruction> {
+        let mut instrs = vec![Instruction::new1(Mnemonic::PUSH, self.bp()),
+            Instruction::new2(Mnemonic::MOV, self.bp(), self.sp())];
+        for reg in self.saved.iter() {
+            instrs.push(Instruction::new1(Mnemonic::PUSH, Operand::Direct(*reg)));
+        }
+        if self.allocation > 0 {
+            let size = immediate(self.allocation, self.mode());
+            instrs.push(Instruction::new2(Mnemonic::SUB, self.sp(), size));
+        }
+        for (i, reg) in self.saved_vectors.iter().enumerate() {
+            let slot = self.vector_slot(i);
+            instrs.push(Instruction::new2(Mnemonic::MOVDQU, slot, Operand::Direct(*reg)));
+        }
+        instrs
+    }
+
+    // Restores the saved registers and returns, removing the stack arguments for callee-pop
+    // conventions.
+    pub fn epilogue(&self) -> Vec<Instruction> {
+        let mut instrs = Vec::new();
+        for (i, reg) in self.saved_vectors.iter().enumerate() {
+            let slot = self.vector_slot(i);
+            instrs.push(Instruction::new2(Mnemonic::MOVDQU, Operand::Direct(*reg), slot));
+        }
+        if !self.saved.is_empty() {
+            if self.allocation > 0 {
+                let saved = self.bp_relative(-self.pushed(), self.mode().pointer_size());
+                instrs.push(Instruction::new2(Mnemonic::LEA, self.sp(), saved));
+            }
+            for reg in self.saved.iter().rev() {
+                instrs.push(Instruction::new1(Mnemonic::POP, Operand::Direct(*reg)));
+            }
+            instrs.push(Instruction::new1(Mnemonic::POP, self.bp()));
+        } else if self.allocation > 0 {
+            instrs.push(Instruction::new0(Mnemonic::LEAVE));
+        } else {
+            instrs.push(Instruction::new1(Mnemonic::POP, self.bp()));
+        }
+        let arguments = self.convention.get_argument_stack_size(&self.arguments);
+        instrs.push(if self.convention.is_callee_pop() && arguments > 0 {
+            Instruction::new1(Mnemonic::RET, Operand::Literal16(arguments as u16))
+        } else {
+            Instruction::new0(Mnemonic::RET)
+        });
+        instrs
+    }
+
+    // Calls `target` with the arguments, moving each value (a register, literal, memory operand or
+    // a label for its address) to where it's passed. In long mode stack arguments are stored in
+    // the outgoing space reserved by the frame, otherwise they're pushed, with the stack pointer
+    // kept aligned, and removed again after the call by caller-pop conventions.
+    pub fn call(&self, target: Operand, args: &[(ArgumentType, Operand)])
+        -> Result<Vec<Instruction>, AbiError> {
+        let types: Vec<ArgumentType> = args.iter().map(|a| a.0).collect();
+        let (locations, stack_size) = self.convention.assign(&types);
+        let pad = round_up(stack_size, self.convention.get_stack_alignment()) - stack_size;
+        let mut instrs = Vec::new();
+        let mut moves = Vec::new();
+        let mut uses_scratch = false;
+
+        if self.mode() == Mode::Long {
+            if stack_size > self.outgoing { return Err(AbiError::OutgoingSpace(stack_size)); }
+            for (i, (&(ty, value), location)) in args.iter().zip(locations.iter()).enumerate() {
+                match *location {
+                    ArgumentLocation::Register(reg) => moves.push((reg, ty, value, i)),
+                    ArgumentLocation::Stack(offset) =>
+                        uses_scratch |= self.store(&mut instrs, offset, ty, value, i)?,
+                }
+            }
+        } else {
+            if pad > 0 {
+                let size = immediate(pad, self.mode());
+                instrs.push(Instruction::new2(Mnemonic::SUB, self.sp(), size));
+            }
+            let arguments = args.iter().zip(locations.iter()).enumerate();
+            for (i, (&(ty, value), location)) in arguments.rev() {
+                match *location {
+                    ArgumentLocation::Register(reg) => moves.push((reg, ty, value, i)),
+                    ArgumentLocation::Stack(_) => self.push(&mut instrs, ty, value, i)?,
+                }
+            }
+            moves.reverse();
+        }
+
+        let destinations: Vec<Reg> = moves.iter().map(|m| m.0).collect();
+        let scratch_read = reads_any(&target, &[SCRATCH]) ||
+            args.iter().any(|a| reads_any(&a.1, &[SCRATCH]));
+        if reads_any(&target, &destinations) || (uses_scratch && scratch_read) {
+            return Err(AbiError::ConflictingArguments);
+        }
+        self.move_registers(&mut instrs, moves)?;
+        instrs.push(Instruction::new1(Mnemonic::CALL, target));
+
+        if self.mode() != Mode::Long {
+            let pushed = if self.convention.is_callee_pop() { pad } else { stack_size + pad };
+            if pushed > 0 {
+                let size = immediate(pushed, self.mode());
+                instrs.push(Instruction::new2(Mnemonic::ADD, self.sp(), size));
+            }
+        }
+        Ok(instrs)
+    }
+
+    // Loads an argument into the register it's passed in.
+    fn load(&self, dest: Reg, ty: ArgumentType, value: Operand, index: usize)
+        -> Result<Instruction, AbiError> {
+        let invalid = AbiError::InvalidArgument(index);
+        let sized = |size| dest.with_size(size).map(Operand::Direct).ok_or(invalid);
+        let long = self.mode() == Mode::Long;
+        Ok(match (ty, value) {
+            (ArgumentType::Integer, Operand::Direct(src)) if is_integer(src) =>
+                Instruction::new2(Mnemonic::MOV, sized(src.size())?, value),
+            // Smaller literals are zero extended.
+            (ArgumentType::Integer, Operand::Literal64(_)) if long =>
+                Instruction::new2(Mnemonic::MOV, Operand::Direct(dest), value),
+            (ArgumentType::Integer, literal) if literal.is_literal() => {
+                let value = literal.get_literal().unwrap_or(0);
+                if self.mode() == Mode::Real {
+                    Instruction::new2(Mnemonic::MOV, sized(OperandSize::Word)?,
+                        Operand::Literal16(value as u16))
+                } else {
+                    Instruction::new2(Mnemonic::MOV, sized(OperandSize::Dword)?,
+                        Operand::Literal32(value as u32))
+                }
+            },
+            (ArgumentType::Integer, Operand::Label(label)) if long =>
+                Instruction::new2(Mnemonic::LEA, Operand::Direct(dest),
+                    Operand::LabelMemory(label, None, None)),
+            (ArgumentType::Integer, Operand::Label(_)) =>
+                Instruction::new2(Mnemonic::MOV, Operand::Direct(dest), value),
+            (ArgumentType::Integer, memory) if memory.is_memory() =>
+                Instruction::new2(Mnemonic::MOV, sized(memory.size().unwrap_or(dest.size()))?,
+                    memory),
+            (_, Operand::Direct(src)) if src.is_sse() && dest.is_sse() =>
+                Instruction::new2(Mnemonic::MOVAPS, Operand::Direct(dest), value),
+            (ArgumentType::Float, memory) if memory.is_memory() && dest.is_sse() =>
+                Instruction::new2(Mnemonic::MOVSS, Operand::Direct(dest),
+                    memory_at(memory, OperandSize::Dword, 0).ok_or(invalid)?),
+            (ArgumentType::Double, memory) if memory.is_memory() && dest.is_sse() =>
+                Instruction::new2(Mnemonic::MOVSD, Operand::Direct(dest),
+                    memory_at(memory, OperandSize::Qword, 0).ok_or(invalid)?),
+            _ => return Err(invalid)
+        })
+    }
+
+    // Stores an argument in the outgoing space in long mode, returning whether the scratch
+    // register was needed.
+    fn store(&self, instrs: &mut Vec<Instruction>, offset: u32, ty: ArgumentType, value: Operand,
+        index: usize) -> Result<bool, AbiError> {
+        let size = if ty == ArgumentType::Float { OperandSize::Dword } else { OperandSize::Qword };
+        let slot = |size| if offset == 0 { Operand::Indirect(Reg::RSP, Some(size), None) }
+            else { Operand::IndirectDisplaced(Reg::RSP, offset as u64, Some(size), None) };
+        let literal = value.get_literal().unwrap_or(0);
+        match (ty, value) {
+            (ArgumentType::Integer, Operand::Direct(src)) if is_integer(src) => {
+                instrs.push(Instruction::new2(Mnemonic::MOV, slot(src.size()), value));
+                Ok(false)
+            },
+            (_, Operand::Direct(src)) if src.is_sse() => {
+                let mnemonic = if ty == ArgumentType::Float { Mnemonic::MOVSS }
+                    else { Mnemonic::MOVSD };
+                instrs.push(Instruction::new2(mnemonic, slot(size), value));
+                Ok(false)
+            },
+            // Sign extended to 64 bits.
+            (_, value) if value.is_literal() && literal <= 0x7FFF_FFFF => {
+                let value = Operand::Literal32(literal as u32);
+                instrs.push(Instruction::new2(Mnemonic::MOV, slot(size), value));
+                Ok(false)
+            },
+            _ => {
+                // Through the scratch register, as bits for floating point values.
+                let scratch = SCRATCH.with_size(size).ok_or(AbiError::InvalidArgument(index))?;
+                instrs.push(match memory_at(value, size, 0) {
+                    Some(memory) if value.is_memory() =>
+                        Instruction::new2(Mnemonic::MOV, Operand::Direct(scratch), memory),
+                    _ => self.load(scratch, ArgumentType::Integer, value, index)?
+                });
+                instrs.push(Instruction::new2(Mnemonic::MOV, slot(size), Operand::Direct(scratch)));
+                Ok(true)
+            }
+        }
+    }
+
+    // Pushes a stack argument outside of long mode.
+    fn push(&self, instrs: &mut Vec<Instruction>, ty: ArgumentType, value: Operand, index: usize)
+        -> Result<(), AbiError> {
+        let invalid = AbiError::InvalidArgument(index);
+        let pointer = self.mode().pointer_size();
+        let sp = self.convention.get_stack_pointer();
+        let push = |instrs: &mut Vec<Instruction>, op| {
+            instrs.push(Instruction::new1(Mnemonic::PUSH, op))
+        };
+        let push_memory = |instrs: &mut Vec<Instruction>, by| {
+            memory_at(value, pointer, by).map(|op| push(instrs, op)).ok_or(invalid)
+        };
+        // The stack pointer moves as arguments are pushed.
+        if reads_any(&value, &[sp]) { return Err(invalid); }
+        if self.mode() == Mode::Real && ty != ArgumentType::Integer { return Err(invalid); }
+        match (ty, value) {
+            (ArgumentType::Integer, Operand::Direct(src)) if is_integer(src) =>
+                push(instrs, Operand::Direct(src.with_size(pointer).ok_or(invalid)?)),
+            (ArgumentType::Integer, Operand::Label(_)) => push(instrs, value),
+            (ArgumentType::Integer, literal) | (ArgumentType::Float, literal)
+                if literal.is_literal() => {
+                let literal = literal.get_literal().unwrap_or(0);
+                push(instrs, if pointer == OperandSize::Word { Operand::Literal16(literal as u16) }
+                    else { Operand::Literal32(literal as u32) });
+            },
+            (ArgumentType::Double, Operand::Literal64(literal)) => {
+                push(instrs, Operand::Literal32((literal >> 32) as u32));
+                push(instrs, Operand::Literal32(literal as u32));
+            },
+            (ArgumentType::Double, memory) if memory.is_memory() => {
+                push_memory(instrs, 4)?;
+                push_memory(instrs, 0)?;
+            },
+            (_, memory) if memory.is_memory() => push_memory(instrs, 0)?,
+            (_, Operand::Direct(src)) if src.is_sse() => {
+                let (size, mnemonic) = if ty == ArgumentType::Float {
+                    (OperandSize::Dword, Mnemonic::MOVSS)
+                } else {
+                    (OperandSize::Qword, Mnemonic::MOVSD)
+                };
+                let bytes = Operand::Literal8((size.bits() / 8) as u8);
+                instrs.push(Instruction::new2(Mnemonic::SUB, self.sp(), bytes));
+                instrs.push(Instruction::new2(mnemonic, Operand::Indirect(sp, Some(size), None),
+                    value));
+            },
+            _ => return Err(invalid)
+        }
+        Ok(())
+    }
+
+    // Moves the register arguments in an order that doesn't overwrite a register before it's
+    // read, exchanging general registers that have to swap.
+    fn move_registers(&self, instrs: &mut Vec<Instruction>,
+        mut moves: Vec<(Reg, ArgumentType, Operand, usize)>) -> Result<(), AbiError> {
+        let conflict = AbiError::ConflictingArguments;
+        while !moves.is_empty() {
+            let ready = (0..moves.len()).find(|i| moves.iter().enumerate()
+                .all(|(j, m)| j == *i || !reads_any(&m.2, &[moves[*i].0])));
+            if let Some(i) = ready {
+                let (dest, ty, value, index) = moves.remove(i);
+                if value != Operand::Direct(dest) {
+                    instrs.push(self.load(dest, ty, value, index)?);
+                }
+                continue;
+            }
+
+            // Every remaining move is blocked by another, so they form cycles of registers.
+            let (dest, _, value, _) = moves.remove(0);
+            let pointer = self.mode().pointer_size();
+            let (dest, src) = match value {
+                Operand::Direct(src) if src.is_general() && dest.is_general() =>
+                    (dest.with_size(pointer).ok_or(conflict)?,
+                        src.with_size(pointer).ok_or(conflict)?),
+                _ => return Err(conflict)
+            };
+            instrs.push(Instruction::new2(Mnemonic::XCHG, Operand::Direct(dest),
+                Operand::Direct(src)));
+            for m in moves.iter_mut() {
+                m.2 = match m.2 {
+                    Operand::Direct(r) if r.overlaps(src) =>
+                        Operand::Direct(dest.with_size(r.size()).ok_or(conflict)?),
+                    Operand::Direct(r) if r.overlaps(dest) =>
+                        Operand::Direct(src.with_size(r.size()).ok_or(conflict)?),
+                    other if reads_any(&other, &[src, dest]) => return Err(conflict),
+                    other => other
+                };
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+extern crate rustyline;
+extern crate x86asm;
+
+use std::borrow::Cow;
+use std::io::{self, Write};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use x86asm::{explain, get_mnemonics, parse_instruction, parse_number, InstructionDecodingError,
+    Listing, Mnemonic, Mode, Reg};
+
+const HELP: &str = "Type an instruction to encode it, i.e. \"mov rax, [rip+0x10]\", or hex bytes to
+decode them, i.e. \"48 8b 05 10 00 00 00\". Both advance the address.
+
+  .mode 16|32|64   Switches the mode
+  .org ADDR        Sets the address of the next instruction
+  .help            Shows this text
+  .quit            Exits (as does Ctrl-D)";
+
+// Completes the first word of a line from the mnemonics and the others from the register names.
+struct NameCompleter {
+    mnemonics: Vec<String>,
+    registers: Vec<String>,
+}
+
+impl NameCompleter {
+    fn new() -> NameCompleter {
+        NameCompleter {
+            mnemonics: get_mnemonics().iter().map(|m| format!("{:?}", m).to_lowercase()).collect(),
+            registers: Reg::all().iter().map(|r| format!("{:?}", r).to_lowercase()).collect(),
+        }
+    }
+}
+
+impl Completer for NameCompleter {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _: &Context)
+        -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos].rfind(|c: char| !c.is_ascii_alphanumeric()).map_or(0, |at| at + 1);
+        let word = line[start..pos].to_lowercase();
+        let first = line[..start].trim().is_empty() || line[..start].trim() == "lock";
+        let names = if first { &self.mnemonics } else { &self.registers };
+        Ok((start, names.iter().filter(|n| n.starts_with(&word)).cloned().collect()))
+    }
+}
+
+impl Hinter for NameCompleter { type Hint = String; }
+
+impl Highlighter for NameCompleter {
+    fn highlight_prompt<'b, 's: 'b, 'p: 'b>(&'s self, prompt: &'p str, _: bool) -> Cow<'b, str> {
+        Cow::Borrowed(prompt)
+    }
+}
+
+impl Validator for NameCompleter { }
+
+impl Helper for NameCompleter { }
+
+struct Session {
+    mode: Mode,
+    address: u64,
+}
+
+impl Session {
+    fn prompt(&self) -> String {
+        let bits = match self.mode { Mode::Real => 16, Mode::Protected => 32, Mode::Long => 64 };
+        format!("[{} {:x}]> ", bits, self.address)
+    }
+
+    fn command(&mut self, line: &str) -> Result<bool, String> {
+        let mut words = line.split_whitespace();
+        match (words.next().unwrap_or(""), words.next()) {
+            (".mode", Some(bits)) => self.mode = match bits {
+                "16" => Mode::Real,
+                "32" => Mode::Protected,
+                "64" => Mode::Long,
+                _ => return Err(format!("invalid mode {}, expected 16, 32 or 64", bits))
+            },
+            (".org", Some(address)) =>
+                self.address = parse_number(address).ok_or(format!("invalid address {}", address))?,
+            (".help", None) => println!("{}", HELP),
+            (".quit", None) | (".exit", None) => return Ok(false),
+            _ => return Err(format!("unknown command {}, see .help", line))
+        }
+        Ok(true)
+    }
+
+    fn encode(&mut self, line: &str) -> Result<(), String> {
+        let instr = parse_instruction(line, self.mode).map_err(|e| e.to_string())?;
+        let mut bytes = Vec::new();
+        instr.encode(&mut bytes, self.mode).map_err(|e| e.to_string())?;
+        self.decode(&bytes)
+    }
+
+    // Lists each instruction with its encoding broken down. Bytes that can't be decoded are
+    // listed one at a time as "(bad)".
+    fn decode(&mut self, bytes: &[u8]) -> Result<(), String> {
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let explanation = explain(&bytes[offset..], self.mode);
+            let len = explanation.as_ref().map_or(1, |e| e.len());
+            let instr = explanation.as_ref().map(|e| e.instruction)
+                .map_err(|_| InstructionDecodingError::InvalidInstruction);
+            Listing::new().write_instruction(&mut out, self.address, &bytes[offset..offset + len],
+                &instr, self.mode).map_err(|e| e.to_string())?;
+            if let Ok(explanation) = explanation {
+                let _ = writeln!(out, "  {}", explanation.definition);
+                for field in explanation.fields {
+                    let _ = writeln!(out, "    {:<20} {:<7} {}", hex(&field.bytes), field.kind,
+                        field.notes.join(", "));
+                }
+            }
+            self.address = self.address.wrapping_add(len as u64);
+            offset += len;
+        }
+        Ok(())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ")
+}
+
+// Hex bytes separated by spaces, i.e. "48 8b 05", or run together, i.e. "488b05".
+fn parse_hex(line: &str) -> Option<Vec<u8>> {
+    let digits: String = line.split_whitespace().collect();
+    if digits.is_empty() || !digits.len().is_multiple_of(2) ||
+        !digits.bytes().all(|b| b.is_ascii_hexdigit()) ||
+        line.split_whitespace().any(|w| !w.len().is_multiple_of(2)) {
+        return None;
+    }
+    (0..digits.len()).step_by(2).map(|i| u8::from_str_radix(&digits[i..i + 2], 16).ok()).collect()
+}
+
+fn is_instruction(line: &str) -> bool {
+    let word = line.split_whitespace().next().unwrap_or("").to_uppercase();
+    word == "LOCK" || Mnemonic::parse(&word).is_ok()
+}
+
+fn main() {
+    let mut editor: Editor<NameCompleter, DefaultHistory> = match Editor::new() {
+        Ok(editor) => editor,
+        Err(error) => { eprintln!("x86asm-repl: {}", error); return; }
+    };
+    editor.set_helper(Some(NameCompleter::new()));
+    let mut session = Session { mode: Mode::Long, address: 0 };
+    println!("x86asm interactive shell, type .help for help");
+
+    loop {
+        let line = match editor.readline(&session.prompt()) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(error) => { eprintln!("x86asm-repl: {}", error); break; }
+        };
+        let line = line.trim();
+        if line.is_empty() { continue; }
+        let _ = editor.add_history_entry(line);
+
+        let result = if line.starts_with('.') {
+            match session.command(line) {
+                Ok(true) => Ok(()),
+                Ok(false) => break,
+                Err(error) => Err(error)
+            }
+        } else if is_instruction(line) {
+            session.encode(line)
+        } else if let Some(bytes) = parse_hex(line) {
+            session.decode(&bytes)
+        } else {
+            session.encode(line)
+        };
+        if let Err(error) = result { println!("error: {}", error); }
+    }
+}
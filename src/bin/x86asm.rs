@@ -0,0 +1,204 @@
+extern crate x86asm;
+
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::process;
+use x86asm::{assemble, get_instruction_defs, parse_number, write_assembly_listing, ElfFile,
+    FlatBinary, Listing, Mnemonic, Mode, Section};
+
+const USAGE: &str = "usage: x86asm <command> [options]
+
+commands:
+  asm [FILE] [-e LINE]... [--mode 16|32|64] [--format bin|hex|elf|listing] [--org ADDR] [-o FILE]
+      Assembles Intel syntax from FILE (or the -e lines). bin and hex output only take .text.
+  disasm [FILE] [-x HEX] [--mode 16|32|64] [--base ADDR] [--offset N] [--length N]
+      Disassembles raw code from FILE (or the -x hex string). ELF files are disassembled by
+      section and symbol unless a mode, base, offset or length is given.
+  info MNEMONIC
+      Lists the forms of an instruction, with their opcodes and the modes they're valid in.";
+
+// Options of the form "--name value", "-n value" and positional arguments, in order.
+struct Args {
+    options: Vec<(String, String)>,
+    positional: Vec<String>,
+}
+
+impl Args {
+    fn parse(args: &[String]) -> Result<Args, String> {
+        let (mut options, mut positional) = (Vec::new(), Vec::new());
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if arg.starts_with('-') && arg.len() > 1 {
+                let value = iter.next().ok_or_else(|| format!("missing value for {}", arg))?;
+                options.push((arg.trim_start_matches('-').to_owned(), value.clone()));
+            } else {
+                positional.push(arg.clone());
+            }
+        }
+        Ok(Args { options, positional })
+    }
+
+    fn get(&self, names: &[&str]) -> Option<&str> {
+        self.options.iter().rev().find(|o| names.contains(&&o.0[..])).map(|o| &o.1[..])
+    }
+
+    fn get_all(&self, name: &str) -> Vec<&str> {
+        self.options.iter().filter(|o| o.0 == name).map(|o| &o.1[..]).collect()
+    }
+
+    fn get_number(&self, name: &str) -> Result<Option<u64>, String> {
+        match self.get(&[name]) {
+            Some(value) => parse_number(value).map(Some)
+                .ok_or_else(|| format!("invalid number for --{}: {}", name, value)),
+            None => Ok(None)
+        }
+    }
+
+    fn get_mode(&self) -> Result<Option<Mode>, String> {
+        match self.get(&["mode", "m"]) {
+            Some("16") => Ok(Some(Mode::Real)),
+            Some("32") => Ok(Some(Mode::Protected)),
+            Some("64") => Ok(Some(Mode::Long)),
+            Some(other) => Err(format!("invalid mode {}, expected 16, 32 or 64", other)),
+            None => Ok(None)
+        }
+    }
+
+    fn check(&self, allowed: &[&str], max_positional: usize) -> Result<(), String> {
+        if let Some(option) = self.options.iter().find(|o| !allowed.contains(&&o.0[..])) {
+            return Err(format!("unknown option -{}", option.0));
+        }
+        if self.positional.len() > max_positional {
+            return Err(format!("unexpected argument {}", self.positional[max_positional]));
+        }
+        Ok(())
+    }
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn write_output(path: Option<&str>, bytes: &[u8]) -> Result<(), String> {
+    match path {
+        Some(path) => fs::write(path, bytes).map_err(|e| format!("{}: {}", path, e)),
+        None => io::stdout().write_all(bytes).map_err(|e| e.to_string())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ")
+}
+
+fn parse_hex(text: &str) -> Result<Vec<u8>, String> {
+    let digits: String = text.chars().filter(|c| !c.is_whitespace() && *c != ',')
+        .collect::<String>().to_lowercase().replace("0x", "");
+    if let Some(c) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(format!("invalid hex digit {}", c));
+    }
+    if !digits.len().is_multiple_of(2) { return Err("odd number of hex digits".to_owned()); }
+    (0..digits.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16)
+            .map_err(|_| format!("invalid hex byte {}", &digits[i..i + 2])))
+        .collect()
+}
+
+fn asm(args: &Args) -> Result<(), String> {
+    args.check(&["e", "mode", "m", "format", "f", "org", "o"], 1)?;
+    let source = match args.positional.first() {
+        Some(path) => String::from_utf8(read_file(path)?).map_err(|e| format!("{}: {}", path, e))?,
+        None => args.get_all("e").join("\n")
+    };
+    let obj = assemble(&source, args.get_mode()?.unwrap_or(Mode::Long)).map_err(|e| e.to_string())?;
+    let output = args.get(&["o"]);
+
+    let format = args.get(&["format", "f"]).unwrap_or(if output.is_some() { "bin" } else { "hex" });
+    let flat = || {
+        if let Some(section) = [Section::Data, Section::Rodata, Section::Bss].iter()
+            .find(|s| !obj.get_section(**s).get_inner_writer_ref().is_empty()) {
+            return Err(format!("{} isn't empty, use --format elf", section.name()));
+        }
+        FlatBinary::new(args.get_number("org")?.unwrap_or(0)).build(obj.get_section(Section::Text))
+            .map_err(|e| e.to_string())
+    };
+    match format {
+        "bin" => write_output(output, &flat()?),
+        "hex" => write_output(output, format!("{}\n", hex(&flat()?)).as_bytes()),
+        "elf" => {
+            let mut bytes = Vec::new();
+            obj.write_elf(&mut bytes).map_err(|e| e.to_string())?;
+            write_output(output, &bytes)
+        },
+        "listing" => {
+            let mut bytes = Vec::new();
+            for section in [Section::Text, Section::Rodata, Section::Data, Section::Bss].iter() {
+                let listing = obj.get_section(*section).get_listing();
+                if listing.is_empty() { continue; }
+                writeln!(bytes, "{}:", section.name()).unwrap();
+                write_assembly_listing(&mut bytes, listing).unwrap();
+            }
+            write_output(output, &bytes)
+        },
+        other => Err(format!("unknown format {}, expected bin, hex, elf or listing", other))
+    }
+}
+
+fn disasm(args: &Args) -> Result<(), String> {
+    args.check(&["x", "mode", "m", "base", "offset", "length"], 1)?;
+    let bytes = match (args.positional.first(), args.get(&["x"])) {
+        (Some(path), None) => read_file(path)?,
+        (None, Some(text)) => parse_hex(text)?,
+        _ => return Err("expected a file or -x HEX".to_owned())
+    };
+    let mode = args.get_mode()?;
+    let (base, offset, length) =
+        (args.get_number("base")?, args.get_number("offset")?, args.get_number("length")?);
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    if mode.is_none() && base.is_none() && offset.is_none() && length.is_none() {
+        if let Ok(file) = ElfFile::parse(&bytes) {
+            return Listing::from_elf(&file).write_elf(&mut out, &file).map_err(|e| e.to_string());
+        }
+    }
+
+    let offset = offset.unwrap_or(0) as usize;
+    if offset > bytes.len() { return Err(format!("offset {} is past the end", offset)); }
+    let end = length.map_or(bytes.len(), |l| bytes.len().min(offset.saturating_add(l as usize)));
+    let address = base.unwrap_or(0).checked_add(offset as u64).ok_or("base is out of range")?;
+    Listing::new().write_code(&mut out, &bytes[offset..end], address,
+        mode.unwrap_or(Mode::Long)).map_err(|e| e.to_string())
+}
+
+fn info(args: &Args) -> Result<(), String> {
+    args.check(&[], 1)?;
+    let name = args.positional.first().ok_or("expected a mnemonic")?;
+    let mnemonic = Mnemonic::parse(&name.to_uppercase())
+        .map_err(|_| format!("unknown mnemonic {}", name))?;
+    for def in get_instruction_defs(mnemonic) {
+        let modes: Vec<_> = [(def.valid_16, "16"), (def.valid_32, "32"), (def.valid_64, "64")].iter()
+            .filter(|m| m.0).map(|m| m.1).collect();
+        println!("{:<36} {:<28} {}", def.to_string(), def.describe_opcode(), modes.join("/"));
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let command = match args.first() {
+        Some(command) => &command[..],
+        None => { eprintln!("{}", USAGE); process::exit(2); }
+    };
+    let result = Args::parse(&args[1..]).and_then(|parsed| match command {
+        "asm" => asm(&parsed),
+        "disasm" => disasm(&parsed),
+        "info" => info(&parsed),
+        "help" | "--help" | "-h" => { println!("{}", USAGE); Ok(()) },
+        _ => Err(format!("unknown command {}\n\n{}", command, USAGE))
+    });
+    if let Err(error) = result {
+        eprintln!("x86asm: {}", error);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,123 @@
+use ::{Instruction, Mnemonic, Mode, Operand, Reg, SegmentReg};
+use ::condition::{jcc_condition, Condition};
+use ::encoding::InstructionLayout;
+use ::flow::FlowKind;
+
+// Jumps mustn't cross or end at a multiple of this (Intel's JCC erratum).
+pub const BRANCH_BOUNDARY: u64 = 32;
+
+pub const CACHE_LINE_SIZE: u64 = 64;
+
+// The most instructions held back to be padded with prefixes. A jump never needs more than
+// BRANCH_BOUNDARY - 1 bytes of padding, at most MAX_PREFIXES per instruction.
+pub const MAX_HELD: usize = 8;
+
+// More prefixes than this slow down decoding on some processors.
+const MAX_PREFIXES: usize = 5;
+
+const MAX_INSTRUCTION: usize = 15;
+
+// An instruction that's been encoded but not written yet, so it can still be padded.
+pub struct HeldInstruction {
+    pub instr: Instruction,
+    pub bytes: Vec<u8>,
+    pub layout: InstructionLayout,
+}
+
+impl HeldInstruction {
+    // Adds `count` copies of the prefix, moving the fields of the instruction after them.
+    fn add_prefixes(&mut self, prefix: u8, count: usize) {
+        self.bytes.splice(0..0, vec![prefix; count]);
+        let layout = &mut self.layout;
+        layout.len += count;
+        for field in [&mut layout.displacement, &mut layout.immediate, &mut layout.immediate2,
+            &mut layout.segment].iter_mut() {
+            if let Some(ref mut field) = **field { field.offset += count; }
+        }
+        for fixup in layout.fixups.iter_mut() { fixup.offset += count as u64; }
+    }
+}
+
+// Whether the erratum applies to the instruction: jumps, calls and returns.
+pub fn is_jump(instr: &Instruction) -> bool {
+    let kind = instr.flow_kind();
+    kind.is_branch() && kind != FlowKind::Interrupt
+}
+
+// Whether the processor fuses `first` with the Jcc after it, so the pair has to be kept off the
+// boundary as a whole.
+pub fn is_fused(first: &Instruction, jcc: &Instruction) -> bool {
+    let cond = match jcc_condition(jcc.mnemonic) {
+        Some(cond) => cond,
+        None => return false
+    };
+    let operands = [first.operand1, first.operand2];
+    let memory = operands.iter().any(|op| op.is_some_and(|op| op.is_memory()));
+    let literal = operands.iter().any(|op| op.is_some_and(|op| op.is_literal()));
+    let rip_relative = operands.iter().any(|op| matches!(*op, Some(Operand::LabelMemory(..))));
+    if (memory && literal) || rip_relative { return false; }
+    // CMP, ADD and SUB don't fuse with jumps on the overflow, sign or parity flags.
+    let other_flags = matches!(cond, Condition::O | Condition::NO | Condition::S | Condition::NS |
+        Condition::P | Condition::NP);
+    let carry = cond == Condition::B || cond == Condition::AE || cond == Condition::BE ||
+        cond == Condition::A;
+    match first.mnemonic {
+        Mnemonic::TEST | Mnemonic::AND => true,
+        Mnemonic::CMP | Mnemonic::ADD | Mnemonic::SUB => !other_flags,
+        Mnemonic::INC | Mnemonic::DEC => !other_flags && !carry,
+        _ => false
+    }
+}
+
+// The padding that keeps `len` bytes of jump at `offset` from crossing or ending at a boundary.
+pub fn branch_padding(offset: u64, len: usize) -> usize {
+    let end = offset + len as u64;
+    if offset / BRANCH_BOUNDARY == end / BRANCH_BOUNDARY { return 0; }
+    (BRANCH_BOUNDARY - offset % BRANCH_BOUNDARY) as usize
+}
+
+// A segment override that doesn't change what the instruction does: ignored in long mode, and the
+// segment it uses anyway otherwise.
+fn padding_prefix(instr: &Instruction, mode: Mode) -> Option<u8> {
+    let operands = [instr.operand1, instr.operand2, instr.operand3, instr.operand4];
+    if operands.iter().any(|op| op.is_some_and(|op| op.segment_reg().is_some())) { return None; }
+    if mode == Mode::Long { return Some(segment_prefix(SegmentReg::CS)); }
+    let memory = operands.iter().filter_map(|op| *op).find(|op| op.is_memory());
+    let stack = memory.and_then(|op| op.base_reg())
+        .is_some_and(|base| matches!(base, Reg::BP | Reg::SP | Reg::EBP | Reg::ESP));
+    Some(segment_prefix(if stack { SegmentReg::SS } else { SegmentReg::DS }))
+}
+
+fn segment_prefix(segment: SegmentReg) -> u8 {
+    match segment {
+        SegmentReg::ES => 0x26,
+        SegmentReg::CS => 0x2E,
+        SegmentReg::SS => 0x36,
+        SegmentReg::DS => 0x3E,
+        SegmentReg::FS => 0x64,
+        SegmentReg::GS => 0x65,
+    }
+}
+
+fn is_legacy_prefix(byte: u8) -> bool {
+    matches!(byte, 0xF0 | 0xF2 | 0xF3 | 0x26 | 0x2E | 0x36 | 0x3E | 0x64 | 0x65 | 0x66 | 0x67)
+}
+
+// Pads the instructions with up to `len` bytes of prefixes, last ones first, and returns how much
+// padding is left for NOPs.
+pub fn add_padding_prefixes(held: &mut [HeldInstruction], len: usize, mode: Mode) -> usize {
+    let mut remaining = len;
+    for instr in held.iter_mut().rev() {
+        if remaining == 0 { break; }
+        let prefix = match padding_prefix(&instr.instr, mode) {
+            Some(prefix) => prefix,
+            None => continue
+        };
+        let existing = instr.bytes.iter().take_while(|&&b| is_legacy_prefix(b)).count();
+        let count = remaining.min(MAX_PREFIXES.saturating_sub(existing))
+            .min(MAX_INSTRUCTION - instr.bytes.len());
+        instr.add_prefixes(prefix, count);
+        remaining -= count;
+    }
+    remaining
+}
@@ -0,0 +1,244 @@
+use byteorder::{LittleEndian, WriteBytesExt};
+use ::{Fixup, FixupKind, Instruction, Label, Mnemonic, Mode, ObjectError, Operand, OperandSize, Reg,
+    RegType};
+
+const DW_CFA_NOP: u8 = 0x00;
+const DW_CFA_ADVANCE_LOC: u8 = 0x40;
+const DW_CFA_ADVANCE_LOC1: u8 = 0x02;
+const DW_CFA_ADVANCE_LOC2: u8 = 0x03;
+const DW_CFA_ADVANCE_LOC4: u8 = 0x04;
+const DW_CFA_OFFSET: u8 = 0x80;
+const DW_CFA_DEF_CFA: u8 = 0x0C;
+const DW_CFA_DEF_CFA_REGISTER: u8 = 0x0D;
+const DW_CFA_DEF_CFA_OFFSET: u8 = 0x0E;
+const DW_EH_PE_ABSPTR: u8 = 0x00;
+const DW_EH_PE_PCREL_SDATA4: u8 = 0x1B;
+
+// A step of a function prolog, as recorded in DWARF call frame information. The CFA (canonical
+// frame address) is the stack pointer before the call, so the return address is just below it.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CfiOp {
+    // CfaOffset - The CFA is this many bytes above the register it's computed from, i.e. after a
+    // PUSH or SUB RSP, n (DW_CFA_def_cfa_offset).
+    CfaOffset(u32),
+
+    // CfaRegister - The CFA is computed from this register, i.e. after MOV RBP, RSP
+    // (DW_CFA_def_cfa_register).
+    CfaRegister(Reg),
+
+    // Saved - The register is saved at this (negative) offset from the CFA (DW_CFA_offset).
+    Saved(Reg, i32),
+}
+
+// The extent of a function and how its prolog changes the frame, for unwinding through it. Each
+// step is paired with a label placed right after its instruction.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FrameInfo {
+    pub start: Label,
+    pub end: Label,
+    pub steps: Vec<(Label, CfiOp)>,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FrameFormat {
+    // EhFrame - .eh_frame, used by the unwinder for exceptions and panics. Functions are referred
+    // to PC-relatively, as in object files.
+    EhFrame,
+
+    // EhFrameAbsolute - .eh_frame referring to functions by address, for code registered at run
+    // time that may be more than 2GB away from the frame info.
+    EhFrameAbsolute,
+
+    // DebugFrame - .debug_frame, used by debuggers. Functions are referred to by address.
+    DebugFrame,
+}
+
+// Follows the prolog of a function as it's written, see InstructionWriter::begin_frame.
+pub struct FrameRecorder {
+    pub info: FrameInfo,
+    mode: Mode,
+    cfa_register: Reg,
+    // The distance from the CFA to the stack pointer.
+    depth: u32,
+    in_prolog: bool,
+}
+
+fn stack_pointer(mode: Mode) -> Reg { if mode == Mode::Long { Reg::RSP } else { Reg::ESP } }
+
+fn frame_pointer(mode: Mode) -> Reg { if mode == Mode::Long { Reg::RBP } else { Reg::EBP } }
+
+impl FrameRecorder {
+    pub fn new(start: Label, mode: Mode) -> FrameRecorder {
+        FrameRecorder {
+            info: FrameInfo { start, end: start, steps: Vec::new() },
+            mode,
+            cfa_register: stack_pointer(mode),
+            depth: mode.pointer_size().bits() / 8,
+            in_prolog: true,
+        }
+    }
+
+    // Records the steps of an instruction that was just written, paired with `after`, returning
+    // whether there were any (so the label has to be placed). The prolog ends at the first
+    // instruction that isn't a PUSH of a register, MOV RBP, RSP or SUB RSP, n.
+    pub fn record(&mut self, instr: &Instruction, after: Label) -> bool {
+        if !self.in_prolog { return false; }
+        let (sp, bp) = (stack_pointer(self.mode), frame_pointer(self.mode));
+        let cfa_is_sp = self.cfa_register == sp;
+        let steps = match (instr.mnemonic, instr.operand1, instr.operand2) {
+            (Mnemonic::PUSH, Some(Operand::Direct(reg)), None)
+                if dwarf_register(reg, self.mode).is_some() => {
+                self.depth += self.mode.pointer_size().bits() / 8;
+                let saved = CfiOp::Saved(reg, -(self.depth as i32));
+                if cfa_is_sp { vec![CfiOp::CfaOffset(self.depth), saved] } else { vec![saved] }
+            },
+            (Mnemonic::MOV, Some(Operand::Direct(dest)), Some(Operand::Direct(src)))
+                if dest == bp && src == sp => {
+                self.cfa_register = bp;
+                vec![CfiOp::CfaRegister(bp)]
+            },
+            (Mnemonic::SUB, Some(Operand::Direct(dest)), Some(size)) if dest == sp => {
+                self.depth += match size {
+                    Operand::Literal8(size) => size as u32,
+                    Operand::Literal32(size) => size,
+                    _ => { self.in_prolog = false; return false; }
+                };
+                if cfa_is_sp { vec![CfiOp::CfaOffset(self.depth)] } else { Vec::new() }
+            },
+            _ => { self.in_prolog = false; return false; }
+        };
+        let recorded = !steps.is_empty();
+        self.info.steps.extend(steps.into_iter().map(|step| (after, step)));
+        recorded
+    }
+}
+
+// The DWARF number of a general purpose register, which isn't its encoding in long mode.
+fn dwarf_register(reg: Reg, mode: Mode) -> Option<u8> {
+    if reg.reg_type() != Some(RegType::General) || reg.size() != mode.pointer_size() { return None; }
+    let code = reg.get_reg_code();
+    Some(if mode == Mode::Long { [0, 2, 1, 3, 7, 6, 4, 5][code as usize % 8] | (code & 8) }
+        else { code })
+}
+
+fn write_uleb128(bytes: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 { bytes.push(byte); return; }
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn write_sleb128(bytes: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+// Pads the entry starting at `start` with DW_CFA_nop to a multiple of `alignment` and fills in
+// its length.
+fn finish_entry(bytes: &mut Vec<u8>, start: usize, alignment: usize) {
+    while !(bytes.len() - start).is_multiple_of(alignment) { bytes.push(DW_CFA_NOP); }
+    let length = (bytes.len() - start - 4) as u32;
+    (&mut bytes[start..start + 4]).write_u32::<LittleEndian>(length).unwrap();
+}
+
+// Builds the contents of .eh_frame or .debug_frame for the frames: a CIE shared by all of them and
+// an FDE for each. `offset` finds the labels in the code, i.e. InstructionWriter::get_label_offset.
+//
+// The fields referring to the start of each function are written as zero and returned as fixups,
+// to be applied once the address of the code and of the section are known. __register_frame
+// expects .eh_frame contents to be followed by a zero terminator (4 zero bytes).
+pub fn build_frame_section<F>(frames: &[FrameInfo], mode: Mode, format: FrameFormat, offset: F)
+    -> Result<(Vec<u8>, Vec<Fixup>), ObjectError> where F: Fn(Label) -> Option<u64> {
+    if mode == Mode::Real { return Err(ObjectError::UnsupportedMode(mode)); }
+    let pointer_size = mode.pointer_size();
+    let pointer = (pointer_size.bits() / 8) as usize;
+    let return_address = if mode == Mode::Long { 16 } else { 8 };
+    let sp = dwarf_register(stack_pointer(mode), mode).expect("The stack pointer is a register.");
+    let is_eh = format != FrameFormat::DebugFrame;
+    let mut bytes = Vec::new();
+    let mut fixups = Vec::new();
+
+    // CIE - on entry the CFA is just above the return address.
+    bytes.write_u32::<LittleEndian>(0).unwrap();
+    bytes.write_u32::<LittleEndian>(if is_eh { 0 } else { 0xFFFF_FFFF }).unwrap();
+    bytes.push(1);
+    bytes.extend_from_slice(if is_eh { b"zR\0" } else { b"\0" });
+    write_uleb128(&mut bytes, 1);
+    write_sleb128(&mut bytes, -(pointer as i64));
+    bytes.push(return_address);
+    if is_eh {
+        write_uleb128(&mut bytes, 1);
+        bytes.push(if format == FrameFormat::EhFrame { DW_EH_PE_PCREL_SDATA4 }
+            else { DW_EH_PE_ABSPTR });
+    }
+    bytes.extend_from_slice(&[DW_CFA_DEF_CFA, sp, pointer as u8,
+        DW_CFA_OFFSET | return_address, 1]);
+    finish_entry(&mut bytes, 0, pointer);
+
+    for info in frames {
+        let invalid = ObjectError::InvalidUnwindInfo(info.start);
+        let start = offset(info.start).ok_or(ObjectError::UndefinedLabel(info.start))?;
+        let end = offset(info.end).ok_or(ObjectError::UndefinedLabel(info.end))?;
+        if end < start { return Err(invalid); }
+
+        // FDE - the CIE pointer is relative to itself in .eh_frame and an offset in .debug_frame.
+        let entry = bytes.len();
+        bytes.write_u32::<LittleEndian>(0).unwrap();
+        bytes.write_u32::<LittleEndian>(if is_eh { entry as u32 + 4 } else { 0 }).unwrap();
+        let (size, kind) = match format {
+            FrameFormat::EhFrame => (OperandSize::Dword, FixupKind::Relative),
+            _ => (pointer_size, FixupKind::Absolute)
+        };
+        let field = (size.bits() / 8) as usize;
+        fixups.push(Fixup { offset: bytes.len() as u64, size, label: info.start, kind, addend: 0 });
+        bytes.resize(bytes.len() + field, 0);
+        bytes.write_uint::<LittleEndian>(end - start, field).unwrap();
+        if is_eh { write_uleb128(&mut bytes, 0); }
+
+        let mut location = start;
+        for &(label, op) in info.steps.iter() {
+            let at = offset(label).ok_or(ObjectError::UndefinedLabel(label))?;
+            if at < location || at > end { return Err(invalid); }
+            let delta = at - location;
+            if delta > 0 && delta < 0x40 {
+                bytes.push(DW_CFA_ADVANCE_LOC | delta as u8);
+            } else if delta > 0 && delta <= 0xFF {
+                bytes.extend_from_slice(&[DW_CFA_ADVANCE_LOC1, delta as u8]);
+            } else if delta > 0 && delta <= 0xFFFF {
+                bytes.push(DW_CFA_ADVANCE_LOC2);
+                bytes.write_u16::<LittleEndian>(delta as u16).unwrap();
+            } else if delta > 0 {
+                bytes.push(DW_CFA_ADVANCE_LOC4);
+                bytes.write_u32::<LittleEndian>(delta as u32).unwrap();
+            }
+            location = at;
+
+            match op {
+                CfiOp::CfaOffset(cfa_offset) => {
+                    bytes.push(DW_CFA_DEF_CFA_OFFSET);
+                    write_uleb128(&mut bytes, cfa_offset as u64);
+                },
+                CfiOp::CfaRegister(reg) => {
+                    bytes.push(DW_CFA_DEF_CFA_REGISTER);
+                    write_uleb128(&mut bytes, dwarf_register(reg, mode).ok_or(invalid)? as u64);
+                },
+                CfiOp::Saved(reg, saved) if saved < 0 && saved % pointer as i32 == 0 => {
+                    bytes.push(DW_CFA_OFFSET | dwarf_register(reg, mode).ok_or(invalid)?);
+                    write_uleb128(&mut bytes, (-saved / pointer as i32) as u64);
+                },
+                CfiOp::Saved(..) => return Err(invalid)
+            }
+        }
+        finish_entry(&mut bytes, entry, pointer);
+    }
+    Ok((bytes, fixups))
+}
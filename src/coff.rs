@@ -0,0 +1,358 @@
+use std::io::Write;
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+use ::{FixupKind, Label, Mode, OperandSize, Reg};
+use ::object::{Binding, ObjectError, ObjectWriter, Relocation, RelocationTarget, Section, SECTIONS};
+
+pub const IMAGE_FILE_MACHINE_I386: u16 = 0x14C;
+pub const IMAGE_FILE_MACHINE_AMD64: u16 = 0x8664;
+
+pub const IMAGE_SCN_CNT_CODE: u32 = 0x20;
+pub const IMAGE_SCN_CNT_INITIALIZED_DATA: u32 = 0x40;
+pub const IMAGE_SCN_CNT_UNINITIALIZED_DATA: u32 = 0x80;
+pub const IMAGE_SCN_ALIGN_4BYTES: u32 = 0x30_0000;
+pub const IMAGE_SCN_MEM_EXECUTE: u32 = 0x2000_0000;
+pub const IMAGE_SCN_MEM_READ: u32 = 0x4000_0000;
+pub const IMAGE_SCN_MEM_WRITE: u32 = 0x8000_0000;
+
+pub const IMAGE_SYM_UNDEFINED: i16 = 0;
+pub const IMAGE_SYM_ABSOLUTE: i16 = -1;
+pub const IMAGE_SYM_DTYPE_FUNCTION: u16 = 0x20;
+pub const IMAGE_SYM_CLASS_EXTERNAL: u8 = 2;
+pub const IMAGE_SYM_CLASS_STATIC: u8 = 3;
+pub const IMAGE_SYM_CLASS_WEAK_EXTERNAL: u8 = 105;
+pub const IMAGE_WEAK_EXTERN_SEARCH_ALIAS: u32 = 3;
+
+pub const IMAGE_REL_AMD64_ADDR64: u16 = 0x1;
+pub const IMAGE_REL_AMD64_ADDR32: u16 = 0x2;
+pub const IMAGE_REL_AMD64_ADDR32NB: u16 = 0x3;
+pub const IMAGE_REL_AMD64_REL32: u16 = 0x4;
+pub const IMAGE_REL_I386_DIR32: u16 = 0x6;
+pub const IMAGE_REL_I386_REL32: u16 = 0x14;
+
+pub const UWOP_PUSH_NONVOL: u8 = 0;
+pub const UWOP_ALLOC_LARGE: u8 = 1;
+pub const UWOP_ALLOC_SMALL: u8 = 2;
+pub const UWOP_SET_FPREG: u8 = 3;
+
+const FILE_HEADER_SIZE: u32 = 20;
+const SECTION_HEADER_SIZE: u32 = 40;
+const RELOCATION_SIZE: u32 = 10;
+const SYMBOL_SIZE: u32 = 18;
+
+// A step of a function prolog, as recorded in Windows x64 unwind info.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum UnwindOp {
+    // PushNonVolatile - PUSH of a callee-saved register.
+    PushNonVolatile(Reg),
+
+    // Alloc - SUB RSP, n. The size must be a multiple of 8.
+    Alloc(u32),
+
+    // SetFramePointer - LEA reg, [RSP+offset]. The offset must be a multiple of 16, up to 240.
+    SetFramePointer(Reg, u32),
+}
+
+// The extent and prolog of a function in .text. Each step of the prolog is paired with a label
+// placed right after its instruction.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnwindInfo {
+    pub start: Label,
+    pub end: Label,
+    pub prolog: Vec<(Label, UnwindOp)>,
+}
+
+// A COFF section being built, with relocations as (offset, symbol index, type).
+struct CoffSection {
+    name: &'static str,
+    characteristics: u32,
+    contents: Vec<u8>,
+    size: u32,
+    relocations: Vec<(u32, u32, u16)>,
+}
+
+fn section_characteristics(section: Section) -> u32 {
+    let contents = match section {
+        Section::Text => IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE,
+        Section::Bss => IMAGE_SCN_CNT_UNINITIALIZED_DATA,
+        Section::Data | Section::Rodata => IMAGE_SCN_CNT_INITIALIZED_DATA
+    };
+    let alignment = (section.alignment().trailing_zeros() + 1) << 20;
+    contents | alignment | IMAGE_SCN_MEM_READ |
+        if section.is_writable() { IMAGE_SCN_MEM_WRITE } else { 0 }
+}
+
+// Windows names .rodata .rdata, otherwise the sections match the ELF ones.
+fn section_name(section: Section) -> &'static str {
+    if section == Section::Rodata { ".rdata" } else { section.name() }
+}
+
+// A COFF symbol name, stored inline if it fits in 8 bytes and in the string table otherwise.
+fn symbol_name(name: &str, strings: &mut Vec<u8>) -> [u8; 8] {
+    let mut field = [0; 8];
+    if name.len() <= 8 {
+        field[..name.len()].copy_from_slice(name.as_bytes());
+    } else {
+        LittleEndian::write_u32(&mut field[4..], strings.len() as u32 + 4);
+        strings.extend_from_slice(name.as_bytes());
+        strings.push(0);
+    }
+    field
+}
+
+fn write_symbol(table: &mut Vec<u8>, name: [u8; 8], value: u32, section: i16, symbol_type: u16,
+    storage_class: u8, aux_count: u8) {
+    table.extend_from_slice(&name);
+    table.write_u32::<LittleEndian>(value).unwrap();
+    table.write_i16::<LittleEndian>(section).unwrap();
+    table.write_u16::<LittleEndian>(symbol_type).unwrap();
+    table.push(storage_class);
+    table.push(aux_count);
+}
+
+impl ObjectWriter {
+    // Writes a COFF object: AMD64 in long mode, i386 in protected mode. Unwind info added with
+    // `add_unwind_info` is written to .xdata, with its .pdata entries referring to it.
+    pub fn write_coff<W: Write>(&self, writer: &mut W) -> Result<usize, ObjectError> {
+        let is_64 = match self.get_mode() {
+            Mode::Long => true,
+            Mode::Protected if self.get_unwind_info().is_empty() => false,
+            mode => return Err(ObjectError::UnsupportedMode(mode))
+        };
+        let (contents, relocations) = self.resolve(false)?;
+
+        let mut sections: Vec<CoffSection> = contents.into_iter().map(|(section, bytes)| {
+            CoffSection {
+                name: section_name(section),
+                characteristics: section_characteristics(section),
+                size: bytes.len() as u32,
+                contents: if section.has_contents() { bytes } else { Vec::new() },
+                relocations: Vec::new(),
+            }
+        }).collect();
+        let section_index = |section: Section| SECTIONS.iter().position(|s| *s == section)
+            .expect("Every section is written.");
+
+        // Symbols - one per section (with an aux record), then the declared symbols. Weak symbols
+        // are weak externals, which fall back to a `.weak.<name>.default` symbol.
+        let unwind_sections = if self.get_unwind_info().is_empty() { 0 } else { 2 };
+        let section_count = sections.len() + unwind_sections;
+        let mut table = Vec::new();
+        let mut strings = Vec::new();
+        let mut indices = Vec::new();
+        let mut index = 2 * section_count as u32;
+        for symbol in self.get_symbols().iter() {
+            let location = self.get_label_location(symbol.label);
+            if location.is_none() && symbol.binding == Binding::Local {
+                return Err(ObjectError::UndefinedLabel(symbol.label));
+            }
+            let (value, section) = location.map_or((0, IMAGE_SYM_UNDEFINED),
+                |(s, offset)| (offset as u32, section_index(s) as i16 + 1));
+            let symbol_type = match location {
+                Some((Section::Text, _)) => IMAGE_SYM_DTYPE_FUNCTION,
+                _ => 0
+            };
+            let name = symbol_name(&symbol.name, &mut strings);
+            indices.push(index);
+            match symbol.binding {
+                Binding::Local | Binding::Global => {
+                    let storage_class = if symbol.binding == Binding::Local {
+                        IMAGE_SYM_CLASS_STATIC
+                    } else { IMAGE_SYM_CLASS_EXTERNAL };
+                    write_symbol(&mut table, name, value, section, symbol_type, storage_class, 0);
+                    index += 1;
+                },
+                Binding::Weak => {
+                    write_symbol(&mut table, name, 0, IMAGE_SYM_UNDEFINED, symbol_type,
+                        IMAGE_SYM_CLASS_WEAK_EXTERNAL, 1);
+                    table.write_u32::<LittleEndian>(index + 2).unwrap(); // Default symbol
+                    table.write_u32::<LittleEndian>(IMAGE_WEAK_EXTERN_SEARCH_ALIAS).unwrap();
+                    table.extend_from_slice(&[0; 10]);
+                    let default = symbol_name(&format!(".weak.{}.default", symbol.name),
+                        &mut strings);
+                    let section = if location.is_some() { section } else { IMAGE_SYM_ABSOLUTE };
+                    write_symbol(&mut table, default, value, section, symbol_type,
+                        IMAGE_SYM_CLASS_EXTERNAL, 0);
+                    index += 3;
+                }
+            }
+        }
+
+        // Relocations, with the addends stored in the fields. REL32 is relative to the end of a
+        // 4 byte field, where the fixups are relative to the end of the instruction.
+        for relocation in relocations.iter() {
+            let (symbol, relocation_type) = match relocation.target {
+                RelocationTarget::Symbol(i) => (indices[i], get_relocation_type(relocation, is_64)?),
+                RelocationTarget::Section(s) =>
+                    (2 * section_index(s) as u32, get_relocation_type(relocation, is_64)?)
+            };
+            let relative = relocation.kind == FixupKind::Branch ||
+                relocation.kind == FixupKind::Relative;
+            let addend = if relative { relocation.addend + 4 } else { relocation.addend };
+            let section = &mut sections[section_index(relocation.section)];
+            let field = &mut section.contents[relocation.offset as usize..];
+            if relocation.size == OperandSize::Qword {
+                LittleEndian::write_i64(field, addend);
+            } else if (i32::MIN as i64..=u32::MAX as i64).contains(&addend) {
+                LittleEndian::write_u32(field, addend as u32);
+            } else {
+                return Err(ObjectError::OutOfRange(relocation.label));
+            }
+            section.relocations.push((relocation.offset as u32, symbol, relocation_type));
+        }
+
+        if unwind_sections > 0 {
+            let (xdata, pdata) = self.get_unwind_sections(2 * section_index(Section::Text) as u32,
+                2 * sections.len() as u32)?;
+            sections.push(xdata);
+            sections.push(pdata);
+        }
+
+        // Section symbols, which come first in the table.
+        let mut section_symbols = Vec::new();
+        for (i, section) in sections.iter().enumerate() {
+            write_symbol(&mut section_symbols, symbol_name(section.name, &mut Vec::new()), 0, i as i16 + 1,
+                0, IMAGE_SYM_CLASS_STATIC, 1);
+            section_symbols.write_u32::<LittleEndian>(section.size).unwrap();
+            section_symbols.write_u16::<LittleEndian>(section.relocations.len() as u16).unwrap();
+            section_symbols.extend_from_slice(&[0; 12]); // Line numbers, checksum, COMDAT
+        }
+
+        // File header, section headers, then the contents and relocations of each section.
+        let mut offset = FILE_HEADER_SIZE + SECTION_HEADER_SIZE * sections.len() as u32;
+        let mut headers = Vec::new();
+        let mut data = Vec::new();
+        for section in sections.iter() {
+            let contents_offset = if section.contents.is_empty() { 0 } else { offset };
+            offset += section.contents.len() as u32;
+            let relocations_offset = if section.relocations.is_empty() { 0 } else { offset };
+            offset += RELOCATION_SIZE * section.relocations.len() as u32;
+
+            headers.extend_from_slice(&symbol_name(section.name, &mut Vec::new()));
+            headers.write_u32::<LittleEndian>(0).unwrap(); // Virtual size
+            headers.write_u32::<LittleEndian>(0).unwrap(); // Virtual address
+            headers.write_u32::<LittleEndian>(section.size).unwrap();
+            headers.write_u32::<LittleEndian>(contents_offset).unwrap();
+            headers.write_u32::<LittleEndian>(relocations_offset).unwrap();
+            headers.write_u32::<LittleEndian>(0).unwrap(); // Line numbers
+            headers.write_u16::<LittleEndian>(section.relocations.len() as u16).unwrap();
+            headers.write_u16::<LittleEndian>(0).unwrap();
+            headers.write_u32::<LittleEndian>(section.characteristics).unwrap();
+
+            data.extend_from_slice(&section.contents);
+            for &(offset, symbol, relocation_type) in section.relocations.iter() {
+                data.write_u32::<LittleEndian>(offset).unwrap();
+                data.write_u32::<LittleEndian>(symbol).unwrap();
+                data.write_u16::<LittleEndian>(relocation_type).unwrap();
+            }
+        }
+
+        let mut bytes = Vec::new();
+        bytes.write_u16::<LittleEndian>(if is_64 { IMAGE_FILE_MACHINE_AMD64 }
+            else { IMAGE_FILE_MACHINE_I386 }).unwrap();
+        bytes.write_u16::<LittleEndian>(sections.len() as u16).unwrap();
+        bytes.write_u32::<LittleEndian>(0).unwrap(); // Timestamp
+        bytes.write_u32::<LittleEndian>(offset).unwrap(); // Symbol table
+        bytes.write_u32::<LittleEndian>((section_symbols.len() + table.len()) as u32 / SYMBOL_SIZE)
+            .unwrap();
+        bytes.write_u16::<LittleEndian>(0).unwrap(); // Optional header size
+        bytes.write_u16::<LittleEndian>(0).unwrap(); // Characteristics
+        bytes.extend_from_slice(&headers);
+        bytes.extend_from_slice(&data);
+        bytes.extend_from_slice(&section_symbols);
+        bytes.extend_from_slice(&table);
+        bytes.write_u32::<LittleEndian>(strings.len() as u32 + 4).unwrap();
+        bytes.extend_from_slice(&strings);
+
+        writer.write_all(&bytes).map_err(|_| ObjectError::WriteFailed)?;
+        Ok(bytes.len())
+    }
+
+    // Builds .xdata (an UNWIND_INFO per function) and .pdata (a RUNTIME_FUNCTION per function,
+    // pointing at the function and its UNWIND_INFO).
+    fn get_unwind_sections(&self, text_symbol: u32, xdata_symbol: u32)
+        -> Result<(CoffSection, CoffSection), ObjectError> {
+        let mut xdata = Vec::new();
+        let mut pdata = Vec::new();
+        let mut relocations = Vec::new();
+        let text_offset = |label| match self.get_label_location(label) {
+            Some((Section::Text, offset)) => Ok(offset),
+            _ => Err(ObjectError::UndefinedLabel(label))
+        };
+
+        for info in self.get_unwind_info() {
+            let start = text_offset(info.start)?;
+            let invalid = ObjectError::InvalidUnwindInfo(info.start);
+            let mut codes: Vec<u16> = Vec::new();
+            let (mut prolog_size, mut frame) = (0, 0);
+            for &(label, op) in info.prolog.iter().rev() {
+                let offset = text_offset(label)?.checked_sub(start).filter(|o| *o <= 0xFF)
+                    .ok_or(invalid)?;
+                prolog_size = prolog_size.max(offset);
+                let code = |op: u8, op_info: u8| offset as u16 | (op as u16 | (op_info as u16) << 4) << 8;
+                // Only the 64-bit general registers can be saved or used as the frame pointer.
+                let gpr = |reg: Reg| if reg.is_general() && reg.size() == OperandSize::Qword {
+                    reg.reg_code().ok_or(invalid)
+                } else {
+                    Err(invalid)
+                };
+                match op {
+                    UnwindOp::PushNonVolatile(reg) =>
+                        codes.push(code(UWOP_PUSH_NONVOL, gpr(reg)?)),
+                    UnwindOp::Alloc(size) if size == 0 || size % 8 != 0 => return Err(invalid),
+                    UnwindOp::Alloc(size) if size <= 128 =>
+                        codes.push(code(UWOP_ALLOC_SMALL, (size / 8 - 1) as u8)),
+                    UnwindOp::Alloc(size) if size / 8 <= 0xFFFF => {
+                        codes.push(code(UWOP_ALLOC_LARGE, 0));
+                        codes.push((size / 8) as u16);
+                    },
+                    UnwindOp::Alloc(size) => {
+                        codes.push(code(UWOP_ALLOC_LARGE, 1));
+                        codes.push(size as u16);
+                        codes.push((size >> 16) as u16);
+                    },
+                    UnwindOp::SetFramePointer(reg, offset) if offset % 16 == 0 && offset <= 240 => {
+                        codes.push(code(UWOP_SET_FPREG, 0));
+                        frame = gpr(reg)? | ((offset / 16) as u8) << 4;
+                    },
+                    UnwindOp::SetFramePointer(..) => return Err(invalid)
+                }
+            }
+            if codes.len() > 0xFF { return Err(invalid); }
+
+            // RUNTIME_FUNCTION, with image relative addresses of the function and its info.
+            let entry = pdata.len() as u32;
+            for (i, &(symbol, addend)) in [(text_symbol, start), (text_symbol, text_offset(info.end)?),
+                (xdata_symbol, xdata.len() as u64)].iter().enumerate() {
+                relocations.push((entry + 4 * i as u32, symbol, IMAGE_REL_AMD64_ADDR32NB));
+                pdata.write_u32::<LittleEndian>(addend as u32).unwrap();
+            }
+
+            // UNWIND_INFO - version 1, no handlers, with an even number of codes.
+            xdata.extend_from_slice(&[1, prolog_size as u8, codes.len() as u8, frame]);
+            if codes.len() % 2 == 1 { codes.push(0); }
+            for code in codes { xdata.write_u16::<LittleEndian>(code).unwrap(); }
+        }
+
+        let characteristics = IMAGE_SCN_CNT_INITIALIZED_DATA | IMAGE_SCN_ALIGN_4BYTES |
+            IMAGE_SCN_MEM_READ;
+        Ok((CoffSection { name: ".xdata", characteristics, size: xdata.len() as u32,
+                contents: xdata, relocations: Vec::new() },
+            CoffSection { name: ".pdata", characteristics, size: pdata.len() as u32,
+                contents: pdata, relocations }))
+    }
+}
+
+fn get_relocation_type(relocation: &Relocation, is_64: bool) -> Result<u16, ObjectError> {
+    let relative = relocation.kind == FixupKind::Branch || relocation.kind == FixupKind::Relative;
+    let relocation_type = match (is_64, relative, relocation.size) {
+        // Windows has no GOT, imports go through __imp_ symbols instead.
+        _ if relocation.got || relocation.kind == FixupKind::Segment => None,
+        (true, true, OperandSize::Dword) => Some(IMAGE_REL_AMD64_REL32),
+        (true, false, OperandSize::Qword) => Some(IMAGE_REL_AMD64_ADDR64),
+        (true, false, OperandSize::Dword) => Some(IMAGE_REL_AMD64_ADDR32),
+        (false, true, OperandSize::Dword) => Some(IMAGE_REL_I386_REL32),
+        (false, false, OperandSize::Dword) => Some(IMAGE_REL_I386_DIR32),
+        _ => None
+    };
+    relocation_type.ok_or(ObjectError::UnsupportedRelocation(relocation.kind, relocation.size))
+}
@@ -0,0 +1,217 @@
+use ::Mnemonic;
+
+// Condition codes tested by the Jcc, SETcc and CMOVcc instruction families. The variants are
+// declared in the order of their encoding (the low nibble of the opcode).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Condition {
+    O,  // Overflow (OF=1)
+    NO, // Not overflow (OF=0)
+    B,  // Below (CF=1)
+    AE, // Above or equal (CF=0)
+    E,  // Equal (ZF=1)
+    NE, // Not equal (ZF=0)
+    BE, // Below or equal (CF=1 or ZF=1)
+    A,  // Above (CF=0 and ZF=0)
+    S,  // Sign (SF=1)
+    NS, // Not sign (SF=0)
+    P,  // Parity (PF=1)
+    NP, // Not parity (PF=0)
+    L,  // Less (SF!=OF)
+    GE, // Greater or equal (SF=OF)
+    LE, // Less or equal (ZF=1 or SF!=OF)
+    G,  // Greater (ZF=0 and SF=OF)
+}
+
+impl Condition {
+    // The condition code as encoded in the low nibble of the Jcc/SETcc/CMOVcc opcode.
+    pub fn get_code(&self) -> u8 {
+        match *self {
+            Condition::O => 0x0,
+            Condition::NO => 0x1,
+            Condition::B => 0x2,
+            Condition::AE => 0x3,
+            Condition::E => 0x4,
+            Condition::NE => 0x5,
+            Condition::BE => 0x6,
+            Condition::A => 0x7,
+            Condition::S => 0x8,
+            Condition::NS => 0x9,
+            Condition::P => 0xA,
+            Condition::NP => 0xB,
+            Condition::L => 0xC,
+            Condition::GE => 0xD,
+            Condition::LE => 0xE,
+            Condition::G => 0xF
+        }
+    }
+
+    pub fn from_code(code: u8) -> Option<Condition> {
+        match code {
+            0x0 => Some(Condition::O),
+            0x1 => Some(Condition::NO),
+            0x2 => Some(Condition::B),
+            0x3 => Some(Condition::AE),
+            0x4 => Some(Condition::E),
+            0x5 => Some(Condition::NE),
+            0x6 => Some(Condition::BE),
+            0x7 => Some(Condition::A),
+            0x8 => Some(Condition::S),
+            0x9 => Some(Condition::NS),
+            0xA => Some(Condition::P),
+            0xB => Some(Condition::NP),
+            0xC => Some(Condition::L),
+            0xD => Some(Condition::GE),
+            0xE => Some(Condition::LE),
+            0xF => Some(Condition::G),
+            _ => None
+        }
+    }
+
+    // Gets the condition from the low nibble of an opcode (i.e. 0x74 or 0x0F 0x94 give E).
+    pub fn from_opcode(opcode: u8) -> Condition {
+        Condition::from_code(opcode & 0xF).unwrap()
+    }
+
+    // Returns the opposite condition. Conditions come in pairs that only differ in the lowest bit.
+    pub fn invert(&self) -> Condition {
+        Condition::from_opcode(self.get_code() ^ 1)
+    }
+}
+
+impl Mnemonic {
+    // Gets the condition tested by a Jcc, SETcc or CMOVcc mnemonic (including aliases).
+    pub fn condition(&self) -> Option<Condition> {
+        jcc_condition(*self)
+            .or_else(|| setcc_condition(*self))
+            .or_else(|| cmovcc_condition(*self))
+    }
+
+    pub fn jcc(cond: Condition) -> Mnemonic {
+        match cond {
+            Condition::O => Mnemonic::JO,
+            Condition::NO => Mnemonic::JNO,
+            Condition::B => Mnemonic::JB,
+            Condition::AE => Mnemonic::JAE,
+            Condition::E => Mnemonic::JE,
+            Condition::NE => Mnemonic::JNE,
+            Condition::BE => Mnemonic::JBE,
+            Condition::A => Mnemonic::JA,
+            Condition::S => Mnemonic::JS,
+            Condition::NS => Mnemonic::JNS,
+            Condition::P => Mnemonic::JP,
+            Condition::NP => Mnemonic::JNP,
+            Condition::L => Mnemonic::JL,
+            Condition::GE => Mnemonic::JGE,
+            Condition::LE => Mnemonic::JLE,
+            Condition::G => Mnemonic::JG
+        }
+    }
+
+    pub fn setcc(cond: Condition) -> Mnemonic {
+        match cond {
+            Condition::O => Mnemonic::SETO,
+            Condition::NO => Mnemonic::SETNO,
+            Condition::B => Mnemonic::SETB,
+            Condition::AE => Mnemonic::SETAE,
+            Condition::E => Mnemonic::SETE,
+            Condition::NE => Mnemonic::SETNE,
+            Condition::BE => Mnemonic::SETBE,
+            Condition::A => Mnemonic::SETA,
+            Condition::S => Mnemonic::SETS,
+            Condition::NS => Mnemonic::SETNS,
+            Condition::P => Mnemonic::SETP,
+            Condition::NP => Mnemonic::SETNP,
+            Condition::L => Mnemonic::SETL,
+            Condition::GE => Mnemonic::SETGE,
+            Condition::LE => Mnemonic::SETLE,
+            Condition::G => Mnemonic::SETG
+        }
+    }
+
+    pub fn cmovcc(cond: Condition) -> Mnemonic {
+        match cond {
+            Condition::O => Mnemonic::CMOVO,
+            Condition::NO => Mnemonic::CMOVNO,
+            Condition::B => Mnemonic::CMOVB,
+            Condition::AE => Mnemonic::CMOVAE,
+            Condition::E => Mnemonic::CMOVE,
+            Condition::NE => Mnemonic::CMOVNE,
+            Condition::BE => Mnemonic::CMOVBE,
+            Condition::A => Mnemonic::CMOVA,
+            Condition::S => Mnemonic::CMOVS,
+            Condition::NS => Mnemonic::CMOVNS,
+            Condition::P => Mnemonic::CMOVP,
+            Condition::NP => Mnemonic::CMOVNP,
+            Condition::L => Mnemonic::CMOVL,
+            Condition::GE => Mnemonic::CMOVGE,
+            Condition::LE => Mnemonic::CMOVLE,
+            Condition::G => Mnemonic::CMOVG
+        }
+    }
+}
+
+pub fn jcc_condition(mnemonic: Mnemonic) -> Option<Condition> {
+    Some(match mnemonic {
+        Mnemonic::JO => Condition::O,
+        Mnemonic::JNO => Condition::NO,
+        Mnemonic::JB | Mnemonic::JC | Mnemonic::JNAE => Condition::B,
+        Mnemonic::JAE | Mnemonic::JNB | Mnemonic::JNC => Condition::AE,
+        Mnemonic::JE | Mnemonic::JZ => Condition::E,
+        Mnemonic::JNE | Mnemonic::JNZ => Condition::NE,
+        Mnemonic::JBE | Mnemonic::JNA => Condition::BE,
+        Mnemonic::JA | Mnemonic::JNBE => Condition::A,
+        Mnemonic::JS => Condition::S,
+        Mnemonic::JNS => Condition::NS,
+        Mnemonic::JP | Mnemonic::JPE => Condition::P,
+        Mnemonic::JNP | Mnemonic::JPO => Condition::NP,
+        Mnemonic::JL | Mnemonic::JNGE => Condition::L,
+        Mnemonic::JGE | Mnemonic::JNL => Condition::GE,
+        Mnemonic::JLE | Mnemonic::JNG => Condition::LE,
+        Mnemonic::JG | Mnemonic::JNLE => Condition::G,
+        _ => return None
+    })
+}
+
+pub fn setcc_condition(mnemonic: Mnemonic) -> Option<Condition> {
+    Some(match mnemonic {
+        Mnemonic::SETO => Condition::O,
+        Mnemonic::SETNO => Condition::NO,
+        Mnemonic::SETB | Mnemonic::SETC | Mnemonic::SETNAE => Condition::B,
+        Mnemonic::SETAE | Mnemonic::SETNB | Mnemonic::SETNC => Condition::AE,
+        Mnemonic::SETE | Mnemonic::SETZ => Condition::E,
+        Mnemonic::SETNE | Mnemonic::SETNZ => Condition::NE,
+        Mnemonic::SETBE | Mnemonic::SETNA => Condition::BE,
+        Mnemonic::SETA | Mnemonic::SETNBE => Condition::A,
+        Mnemonic::SETS => Condition::S,
+        Mnemonic::SETNS => Condition::NS,
+        Mnemonic::SETP | Mnemonic::SETPE => Condition::P,
+        Mnemonic::SETNP | Mnemonic::SETPO => Condition::NP,
+        Mnemonic::SETL | Mnemonic::SETNGE => Condition::L,
+        Mnemonic::SETGE | Mnemonic::SETNL => Condition::GE,
+        Mnemonic::SETLE | Mnemonic::SETNG => Condition::LE,
+        Mnemonic::SETG | Mnemonic::SETNLE => Condition::G,
+        _ => return None
+    })
+}
+
+pub fn cmovcc_condition(mnemonic: Mnemonic) -> Option<Condition> {
+    Some(match mnemonic {
+        Mnemonic::CMOVO => Condition::O,
+        Mnemonic::CMOVNO => Condition::NO,
+        Mnemonic::CMOVB | Mnemonic::CMOVC | Mnemonic::CMOVNAE => Condition::B,
+        Mnemonic::CMOVAE | Mnemonic::CMOVNB | Mnemonic::CMOVNC => Condition::AE,
+        Mnemonic::CMOVE | Mnemonic::CMOVZ => Condition::E,
+        Mnemonic::CMOVNE | Mnemonic::CMOVNZ => Condition::NE,
+        Mnemonic::CMOVBE | Mnemonic::CMOVNA => Condition::BE,
+        Mnemonic::CMOVA | Mnemonic::CMOVNBE => Condition::A,
+        Mnemonic::CMOVS => Condition::S,
+        Mnemonic::CMOVNS => Condition::NS,
+        Mnemonic::CMOVP | Mnemonic::CMOVPE => Condition::P,
+        Mnemonic::CMOVNP | Mnemonic::CMOVPO => Condition::NP,
+        Mnemonic::CMOVL | Mnemonic::CMOVNGE => Condition::L,
+        Mnemonic::CMOVGE | Mnemonic::CMOVNL => Condition::GE,
+        Mnemonic::CMOVLE | Mnemonic::CMOVNG => Condition::LE,
+        Mnemonic::CMOVG | Mnemonic::CMOVNLE => Condition::G,
+        _ => return None
+    })
+}
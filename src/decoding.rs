@@ -1,3 +1,5 @@
+use std::error::Error;
+use std::fmt;
 use std::io::{Bytes, Read};
 use std::iter::Peekable;
 use ::{BroadcastMode, Instruction, MaskReg, MergeMode, Mode, Operand, OperandSize, Reg, RegScale, RegType, RoundingMode};
@@ -8,6 +10,72 @@ use ::instruction_def::*;
 pub struct InstructionReader<T: Read> {
     reader: Peekable<Bytes<T>>,
     mode: Mode,
+    displacement_len: usize, // Bytes of displacement read for the current instruction
+    offset: usize, // Bytes read for the current instruction
+    trace: Option<DecodeTrace>,
+}
+
+// The part of an encoded instruction that a field is.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FieldKind {
+    // Prefix - Legacy prefix (LOCK, REP, segment override, operand or address size).
+    Prefix,
+
+    // Rex - REX prefix, in long mode.
+    Rex,
+
+    // Vex - Two or three byte VEX prefix.
+    Vex,
+
+    // Evex - Four byte EVEX prefix.
+    Evex,
+
+    // Opcode - Opcode bytes, including the 0F/0F 38/0F 3A escapes.
+    Opcode,
+
+    // ModRm - ModR/M byte.
+    ModRm,
+
+    // Sib - SIB byte.
+    Sib,
+
+    // Displacement - Displacement of a memory operand, or a moffs address.
+    Displacement,
+
+    // Immediate - Immediate, relative branch offset or far pointer.
+    Immediate,
+}
+
+impl fmt::Display for FieldKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            FieldKind::Prefix => "prefix",
+            FieldKind::Rex => "REX",
+            FieldKind::Vex => "VEX",
+            FieldKind::Evex => "EVEX",
+            FieldKind::Opcode => "opcode",
+            FieldKind::ModRm => "ModR/M",
+            FieldKind::Sib => "SIB",
+            FieldKind::Displacement => "disp",
+            FieldKind::Immediate => "imm",
+        })
+    }
+}
+
+// Where each field of the last instruction was, and the definition it decoded to.
+#[derive(Default)]
+pub struct DecodeTrace {
+    pub fields: Vec<(FieldKind, usize, usize)>, // Offset and length
+    pub def: Option<&'static InstructionDefinition>,
+}
+
+// Decodes the first instruction in `bytes`, recording where each of its fields is.
+pub fn decode_traced(bytes: &[u8], mode: Mode)
+    -> Result<(Instruction, usize, DecodeTrace), InstructionDecodingError> {
+    let mut reader = InstructionReader::new(bytes, mode);
+    reader.trace = Some(DecodeTrace::default());
+    let (instr, len) = reader.read()?;
+    Ok((instr, len, reader.trace.take().expect("The trace was enabled above.")))
 }
 
 impl<T: Read> InstructionReader<T> {
@@ -15,6 +83,9 @@ impl<T: Read> InstructionReader<T> {
         InstructionReader {
             reader: reader.bytes().peekable(),
             mode: mode,
+            displacement_len: 0,
+            offset: 0,
+            trace: None,
         }
     }
 
@@ -22,6 +93,7 @@ impl<T: Read> InstructionReader<T> {
         match self.reader.next() {
             Some(Ok(b)) => {
                 *counter += 1;
+                self.offset += 1;
                 return Ok(b);
             },
             Some(Err(_)) => Err(InstructionDecodingError::ReadError),
@@ -29,6 +101,11 @@ impl<T: Read> InstructionReader<T> {
         }
     }
     
+    fn mark(&mut self, kind: FieldKind, start: usize) {
+        let len = self.offset - start;
+        if let Some(ref mut trace) = self.trace { trace.fields.push((kind, start, len)); }
+    }
+
     #[allow(unused_assignments)] // Compiler wrongly complains about opcode_byte not being read?
     pub fn read(&mut self) -> Result<(Instruction, usize), InstructionDecodingError> {
         let mut buffer: InstructionBuffer = Default::default();
@@ -38,6 +115,9 @@ impl<T: Read> InstructionReader<T> {
         let mut opcode_byte = 0;
 
         let mut bytes_read = 0usize;
+        self.displacement_len = 0;
+        self.offset = 0;
+        if let Some(ref mut trace) = self.trace { *trace = DecodeTrace::default(); }
 
         // Check for end of stream
         if self.reader.peek().is_none() {
@@ -46,6 +126,8 @@ impl<T: Read> InstructionReader<T> {
 
         // Read prefixes
         loop {
+            let start = self.offset;
+            let mut kind = FieldKind::Prefix;
             let b = self.expect_byte(&mut bytes_read)?;
             // TODO This could be written without match
             let lookahead: Option<u8> = match self.reader.peek() { 
@@ -66,8 +148,9 @@ impl<T: Read> InstructionReader<T> {
                 PREFIX_ES => { buffer.prefix2 = Some(Prefix2::ES); },
                 PREFIX_FS => { buffer.prefix2 = Some(Prefix2::FS); },
                 PREFIX_GS => { buffer.prefix2 = Some(Prefix2::GS); },
-                PREFIX_TWO_BYTE_OPCODE => { buffer.is_two_byte_opcode = true; },
+                PREFIX_TWO_BYTE_OPCODE => { buffer.is_two_byte_opcode = true; kind = FieldKind::Opcode; },
                 PREFIX_VEX2 => { // Two-byte VEX prefix
+                    kind = FieldKind::Vex;
                     let data = self.expect_byte(&mut bytes_read)?;
                     buffer.composite_prefix = Some(CompositePrefix::Vex);
                     reg_ext = if data & 0x80 != 0 || self.mode != Mode::Long { 0 } else { 0x8 };
@@ -83,6 +166,7 @@ impl<T: Read> InstructionReader<T> {
                     }
                 },
                 PREFIX_VEX3 => { // Three-byte VEX prefix
+                    kind = FieldKind::Vex;
                     let data1 = self.expect_byte(&mut bytes_read)?;
                     let data2 = self.expect_byte(&mut bytes_read)?;
                     buffer.composite_prefix = Some(CompositePrefix::Vex);
@@ -107,6 +191,7 @@ impl<T: Read> InstructionReader<T> {
                     }
                 },
                 PREFIX_EVEX if self.mode == Mode::Long || lookahead.map_or(false, |l| l & 0xC0 == 0xC0) => {
+                    kind = FieldKind::Evex;
                     let data1 = self.expect_byte(&mut bytes_read)?;
                     let data2 = self.expect_byte(&mut bytes_read)?;
                     let data3 = self.expect_byte(&mut bytes_read)?;
@@ -139,35 +224,61 @@ impl<T: Read> InstructionReader<T> {
                 },
                 b if self.mode == Mode::Long && buffer.composite_prefix.is_none() && b & 0xF0 == 0x40 => { // REX prefix
                     buffer.composite_prefix = Some(CompositePrefix::Rex);
+                    kind = FieldKind::Rex;
                     reg_ext |= if b & 0x4 != 0 { 0x8 } else { 0 };
                     index_ext |= if b & 0x2 != 0 { 0x8 } else { 0 };
                     b_ext |= if b & 0x1 != 0 { 0x8 } else { 0 };
                     buffer.operand_size_64 = b & 0x8 != 0;
                 },
-                b => { opcode_byte = b; break; }, // Not a prefix, move on
+                b => { // Not a prefix, move on
+                    opcode_byte = b;
+                    self.mark(FieldKind::Opcode, start);
+                    break;
+                },
             }
+            self.mark(kind, start);
         }
 
-        let addr_mode = Mode::from_size(InstructionReader::<T>::get_addressing_mode(self.mode, &buffer)).unwrap();
+        let addr_mode = Mode::from_size(InstructionReader::<T>::get_addressing_mode(self.mode, &buffer))
+            .ok_or(InstructionDecodingError::InvalidInstruction)?;
 
         // Read opcode
         if buffer.primary_opcode == 0 {
             buffer.primary_opcode = opcode_byte;
+            // Only used if the opcode turns out to have a register added to it.
+            buffer.opcode_add = Some(opcode_byte & 0x7 | b_ext);
         } else {
             buffer.secondary_opcode = Some(opcode_byte);
         }
 
         if (buffer.primary_opcode == 0x38 || buffer.primary_opcode == 0x3A) && buffer.secondary_opcode.is_none() {
+            let start = self.offset;
             buffer.secondary_opcode = Some(self.expect_byte(&mut bytes_read)?);
+            self.mark(FieldKind::Opcode, start);
+        }
+
+        // The byte after the opcode is part of it only if an instruction matches with it.
+        if buffer.secondary_opcode.is_none() && may_have_secondary_opcode(&buffer) {
+            if let Some(&Ok(next)) = self.reader.peek() {
+                buffer.secondary_opcode = Some(next);
+                if find_instruction_def_by_opcode(&buffer, self.mode).is_ok() {
+                    let start = self.offset;
+                    self.expect_byte(&mut bytes_read)?;
+                    self.mark(FieldKind::Opcode, start);
+                } else {
+                    buffer.secondary_opcode = None;
+                }
+            }
         }
 
         // Find the matching instruction definition
         let def_res = find_instruction_def_by_opcode(&buffer, self.mode);
             
         // Read a ModR/M if we found a valid def which needs one or if we need one to disambiguate.
-        if def_res.map(|def| InstructionReader::<T>::has_mod_rm(def)).unwrap_or(false) ||
+        if def_res.map(|def| def.needs_mod_rm()).unwrap_or(false) ||
             matches!(def_res, Err(FindInstructionDefByOpcodeError::NeedModRm)) {
             let mod_rm = self.expect_byte(&mut bytes_read)?;
+            self.mark(FieldKind::ModRm, self.offset - 1);
             buffer.mod_rm_mod = Some(mod_rm >> 6);
             buffer.mod_rm_reg = Some((mod_rm >> 3) & 0x7 | reg_ext);
             buffer.mod_rm_rm = Some(mod_rm & 0x7);
@@ -175,12 +286,15 @@ impl<T: Read> InstructionReader<T> {
             // SIB
             if InstructionReader::<T>::has_sib(addr_mode, &buffer) {
                 let sib = self.expect_byte(&mut bytes_read)?;
+                self.mark(FieldKind::Sib, self.offset - 1);
                 buffer.sib_scale = Some(sib >> 6);
                 buffer.sib_index = Some((sib >> 3) & 0x7 | index_ext);
                 buffer.sib_base = Some(sib & 0x7 | b_ext);
             } else {
-                buffer.mod_rm_reg = buffer.mod_rm_reg.map(|reg| reg | b_ext);
-                buffer.mod_rm_rm = buffer.mod_rm_rm.map(|rm| rm | index_ext);
+                // REX.B/VEX.B extend the r/m field. EVEX.X adds a fifth bit for registers 16-31.
+                let rm_ext = if buffer.composite_prefix == Some(CompositePrefix::Evex) &&
+                    buffer.mod_rm_mod == Some(0b11) { index_ext << 1 } else { 0 };
+                buffer.mod_rm_rm = buffer.mod_rm_rm.map(|rm| rm | b_ext | rm_ext);
             }
         }
         
@@ -188,6 +302,7 @@ impl<T: Read> InstructionReader<T> {
         // we read.
         let def = def_res.or_else(|_| find_instruction_def_by_opcode(&buffer, self.mode)
             .map_err(|_| InstructionDecodingError::UnknownOpcode))?;
+        if let Some(ref mut trace) = self.trace { trace.def = Some(def); }
 
         // Build operands (reading immediates as appropriate)
         // TODO Could re-write this without vec
@@ -198,6 +313,18 @@ impl<T: Read> InstructionReader<T> {
             )).collect();
         let mut operands_iter = operand_results?.into_iter();
 
+        let rounding_mode = if def.allow_rounding && buffer.vex_b.unwrap_or(false) &&
+            buffer.mod_rm_mod.map_or(false, |m| m == 0b11) {
+            Some(RoundingMode::from_code(
+                buffer.vex_l.map_or(0, |l| if l { 2 } else { 0 }) +
+                buffer.vector_len.map_or(0, |l| if l { 1 } else { 0 })
+            ).ok_or(InstructionDecodingError::InvalidInstruction)?)
+        } else { None };
+        let mask = if def.allow_mask && buffer.mask_reg != Some(0) {
+            buffer.mask_reg.map(|r| MaskReg::from_code(r)
+                .ok_or(InstructionDecodingError::InvalidInstruction)).transpose()?
+        } else { None };
+
         let instruction = Instruction {
             mnemonic: def.mnemonic,
             operand1: operands_iter.next(),
@@ -205,30 +332,23 @@ impl<T: Read> InstructionReader<T> {
             operand3: operands_iter.next(),
             operand4: operands_iter.next(),
             lock: buffer.prefix1 == Some(Prefix1::Lock),
-            rounding_mode: if def.allow_rounding && buffer.vex_b.unwrap_or(false) &&
-                buffer.mod_rm_mod.map_or(false, |m| m == 0b11) {  
-                Some(RoundingMode::from_code(
-                    buffer.vex_l.map_or(0, |l| if l { 2 } else { 0 }) +
-                    buffer.vector_len.map_or(0, |l| if l { 1 } else { 0 })
-                ).unwrap())
-            } else { None },
+            rounding_mode,
             merge_mode: if def.allow_merge_mode && 
                 buffer.merge_mode != Some(MergeMode::Merge) { buffer.merge_mode } else { None },
             sae: def.allow_sae && buffer.vex_b.unwrap_or(false) &&
                 buffer.mod_rm_mod.map_or(false, |r| r == 0b11),
-            mask: if def.allow_mask && buffer.mask_reg != Some(0) {
-                buffer.mask_reg.map(|r| MaskReg::from_code(r).unwrap())
-            } else { None },
-            broadcast: InstructionReader::<T>::get_broadcast(def, &buffer),
+            mask,
+            broadcast: InstructionReader::<T>::get_broadcast(def, &buffer)?,
         };
 
-        Ok((instruction, bytes_read))
+        Ok((instruction, bytes_read + self.displacement_len))
     }
 
     fn read_operand(&mut self, op_def: &OperandDefinition, buffer: &InstructionBuffer, bytes_read: &mut usize)
         -> Result<Operand, InstructionDecodingError> {
 
-        let size = InstructionReader::<T>::get_operand_size(op_def, buffer);
+        let start = self.offset;
+        let size = InstructionReader::<T>::get_operand_size(op_def, buffer)?;
         let addr_size = InstructionReader::<T>::get_address_size(self.mode, buffer);
 
         // We can assume that we have a ModR/M byte if we've gotten to this point as it
@@ -237,10 +357,15 @@ impl<T: Read> InstructionReader<T> {
             OperandEncoding::ModRmReg =>
                 if let OperandType::Reg(reg_type) = op_def.op_type {
                     Ok(Operand::Direct(Reg::from_code_reg_type(
-                        buffer.mod_rm_reg.unwrap(), reg_type, size, buffer.has_rex())
+                        buffer.mod_rm_reg.ok_or(InstructionDecodingError::InvalidInstruction)?,
+                        reg_type, size, buffer.has_rex())
                         .ok_or(InstructionDecodingError::InvalidInstruction)?))
                 } else { Err(InstructionDecodingError::InvalidOperand) },
 
+            // Only a register can be encoded where the operand has to be one.
+            OperandEncoding::ModRmRm if matches!(op_def.op_type, OperandType::Reg(_)) &&
+                buffer.mod_rm_mod != Some(0b11) => Err(InstructionDecodingError::InvalidOperand),
+
             OperandEncoding::ModRmRm => { // TODO Handle MIB
                 let reg_type = if let OperandType::Reg(reg_type) = op_def.op_type { reg_type }
                     else if let OperandType::Set(set) = op_def.op_type {
@@ -254,7 +379,8 @@ impl<T: Read> InstructionReader<T> {
             OperandEncoding::Vex =>
                 if let OperandType::Reg(reg_type) = op_def.op_type {
                     Ok(Operand::Direct(Reg::from_code_reg_type(
-                        buffer.vex_operand.unwrap(), reg_type, size, buffer.has_rex())
+                        buffer.vex_operand.ok_or(InstructionDecodingError::InvalidInstruction)?,
+                        reg_type, size, buffer.has_rex())
                         .ok_or(InstructionDecodingError::InvalidInstruction)?))
                 } else { Err(InstructionDecodingError::InvalidOperand) },
 
@@ -272,6 +398,9 @@ impl<T: Read> InstructionReader<T> {
                         OperandSize::Dword => 
                             (0..4).fold(Ok(0), |acc, n| acc.and_then(|a| self.expect_byte(bytes_read).map(
                                 |b| a | ((b as u32) << (8*n) )))).map(|b| Operand::Literal32(b)),
+                        OperandSize::Qword =>
+                            (0..8).try_fold(0, |a, n| self.expect_byte(bytes_read).map(
+                                |b| a | ((b as u64) << (8*n) ))).map(Operand::Literal64),
                         OperandSize::Far16 => { // 16:16
                             let addr = (0..2).fold(Ok(0), |acc, n| acc.and_then(|a|
                                 self.expect_byte(bytes_read).map(|b| a | ((b as u16) << (8*n) ))))?;
@@ -288,18 +417,21 @@ impl<T: Read> InstructionReader<T> {
                         },
                         _ => Err(InstructionDecodingError::NotImplemented)
                     },
-                    OperandType::Rel(_) => 
-                        Ok(Operand::Offset(
-                            (0..op_def.size.bits() >> 3).fold(Ok(0), |acc, n| acc.and_then(|a|
-                                self.expect_byte(bytes_read).map(|b| a | ((b as u64) << (8*n) ))))?,
-                            None, None)),
+                    OperandType::Rel(_) => {
+                        let bits = op_def.size.bits();
+                        let rel = (0..bits >> 3).fold(Ok(0), |acc, n| acc.and_then(|a|
+                            self.expect_byte(bytes_read).map(|b| a | ((b as u64) << (8*n) ))))?;
+                        // Relative offsets are signed, so sign-extend them to match the encoder.
+                        let shift = 64 - bits.clamp(8, 64);
+                        Ok(Operand::Offset((((rel << shift) as i64) >> shift) as u64, None, None))
+                    },
                     _ => Err(InstructionDecodingError::InvalidInstruction) 
                 },
 
             OperandEncoding::OpcodeAddend =>
                 if let OperandType::Reg(reg_type) = op_def.op_type {
                     Ok(Operand::Direct(Reg::from_code_reg_type(
-                        buffer.primary_opcode & 0x7, reg_type, size, buffer.has_rex())
+                        buffer.opcode_add.unwrap_or(0), reg_type, size, buffer.has_rex())
                         .ok_or(InstructionDecodingError::InvalidInstruction)?))
                 } else { Err(InstructionDecodingError::InvalidOperand) },
 
@@ -329,42 +461,50 @@ impl<T: Read> InstructionReader<T> {
                 }
         };
 
+        if op_def.encoding == OperandEncoding::Imm && self.offset > start {
+            self.mark(FieldKind::Immediate, start);
+        }
         res
     }
 
     fn read_disp8(&mut self) -> Result<u8, InstructionDecodingError> {
         let mut bytes_read = 0usize;
-        self.expect_byte(&mut bytes_read)
+        let start = self.offset;
+        let disp = self.expect_byte(&mut bytes_read);
+        self.displacement_len += bytes_read;
+        self.mark(FieldKind::Displacement, start);
+        disp
     }
 
     fn read_disp16(&mut self) -> Result<u16, InstructionDecodingError> {
         let mut bytes_read = 0usize;
-        (0..2).fold(Ok(0), |acc, n| acc.and_then(|a| self.expect_byte(&mut bytes_read).map(
-            |b| a | ((b as u16) << (8*n) ))))
+        let start = self.offset;
+        let disp = (0..2).fold(Ok(0), |acc, n| acc.and_then(|a| self.expect_byte(&mut bytes_read).map(
+            |b| a | ((b as u16) << (8*n) ))));
+        self.displacement_len += bytes_read;
+        self.mark(FieldKind::Displacement, start);
+        disp
     }
 
     fn read_disp32(&mut self) -> Result<u32, InstructionDecodingError> {
         let mut bytes_read = 0usize;
-        (0..4).fold(Ok(0), |acc, n| acc.and_then(|a| self.expect_byte(&mut bytes_read).map(
-            |b| a | ((b as u32) << (8*n) ))))
+        let start = self.offset;
+        let disp = (0..4).fold(Ok(0), |acc, n| acc.and_then(|a| self.expect_byte(&mut bytes_read).map(
+            |b| a | ((b as u32) << (8*n) ))));
+        self.displacement_len += bytes_read;
+        self.mark(FieldKind::Displacement, start);
+        disp
     }
 
     #[allow(dead_code)]
     fn read_disp64(&mut self) -> Result<u64, InstructionDecodingError> {
         let mut bytes_read = 0usize;
-        (0..8).fold(Ok(0), |acc, n| acc.and_then(|a| self.expect_byte(&mut bytes_read).map(
-            |b| a | ((b as u64) << (8*n) ))))
-    }
-
-    fn has_mod_rm(def: &InstructionDefinition) -> bool {
-        def.opcode_ext.is_some() ||
-        def.operands.iter().any(|o| o.as_ref().map_or(false, |op| match op.encoding {
-            OperandEncoding::ModRmReg |
-            OperandEncoding::ModRmRm |
-            OperandEncoding::Mib
-                => true,
-            _ => false
-        }))
+        let start = self.offset;
+        let disp = (0..8).fold(Ok(0), |acc, n| acc.and_then(|a| self.expect_byte(&mut bytes_read).map(
+            |b| a | ((b as u64) << (8*n) ))));
+        self.displacement_len += bytes_read;
+        self.mark(FieldKind::Displacement, start);
+        disp
     }
 
     fn get_address_size(mode: Mode, buffer: &InstructionBuffer) -> OperandSize {
@@ -389,7 +529,7 @@ impl<T: Read> InstructionReader<T> {
         Ok(match addr_size {
             OperandSize::Word => {
                 let mode = buffer.mod_rm_mod.ok_or(InstructionDecodingError::InvalidInstruction)?;
-                let size = InstructionReader::<T>::get_operand_size(op_def, buffer);
+                let size = InstructionReader::<T>::get_operand_size(op_def, buffer)?;
                 let segment = buffer.get_segment_reg();
 
                 if mode == 0b11 { 
@@ -397,13 +537,15 @@ impl<T: Read> InstructionReader<T> {
                         .map(|r| Operand::Direct(r)); 
                 }
 
+                // disp8 and disp16 are sign-extended, except the absolute address of [disp16].
                 let disp = if mode == 0 && rm != 0b110 || mode == 3 { None } // No displacement
+                    else if mode == 0 { Some(self.read_disp16()? as u64) }
                     else { // 8/16-bit displacement
-                        Some(if mode == 1 { self.read_disp8()? as u64 } 
-                            else { self.read_disp16()? as u64 })
+                        Some(if mode == 1 { self.read_disp8()? as i8 as u64 } 
+                            else { self.read_disp16()? as i16 as u64 })
                     };
 
-                let (reg1, reg2) = match rm {
+                let (reg1, reg2) = match rm & 0b111 {
                     0 => (Some(Reg::BX), Some(Reg::SI)),
                     1 => (Some(Reg::BX), Some(Reg::DI)),
                     2 => (Some(Reg::BP), Some(Reg::SI)),
@@ -412,7 +554,7 @@ impl<T: Read> InstructionReader<T> {
                     5 => (Some(Reg::DI), None),
                     6 => if mode == 0 { (None, None) } else { (Some(Reg::BP), None) },
                     7 => (Some(Reg::BX), None),
-                    _ => unreachable!()
+                    _ => return Err(InstructionDecodingError::InvalidInstruction)
                 };
 
                 match (reg1, reg2, disp) {
@@ -425,51 +567,60 @@ impl<T: Read> InstructionReader<T> {
                     (Some(r1), Some(r2), Some(disp)) =>
                         Operand::IndirectScaledIndexedDisplaced(r1, r2, RegScale::One, disp, 
                             Some(size), segment),
-                    _ => unreachable!()
+                    _ => return Err(InstructionDecodingError::InvalidInstruction)
                 }
             },
             addr_size @ OperandSize::Dword | addr_size @ OperandSize::Qword => {
-                let size = Some(InstructionReader::<T>::get_operand_size(op_def, buffer));
+                let size = Some(InstructionReader::<T>::get_operand_size(op_def, buffer)?);
                 let segment = buffer.get_segment_reg();
                 match buffer.mod_rm_mod.ok_or(InstructionDecodingError::InvalidInstruction)? & 0x7 {
                     0b00 => {
-                        match rm {
+                        match rm & 0b111 {
                             0b000 | 0b001 | 0b010 | 0b011 | 0b110 | 0b111 => // [RM]
                                 Operand::Indirect(Reg::from_code_general_sized(rm,
                                     InstructionReader::<T>::has_rex(buffer),
                                     addr_size).ok_or(InstructionDecodingError::InvalidInstruction)?,
                                     size, segment),
                             0b100 => self.sib_helper(buffer, op_def, addr_size)?, // [SIB]
-                            0b101 => if addr_size == OperandSize::Dword { Operand::Memory(self.read_disp32()? as u64, size, segment) }
-                                        else { Operand::Offset(self.read_disp32()? as u64, size, segment) },
-                            _ => unreachable!()
+                            // [disp32], which is relative to the instruction pointer in long mode.
+                            0b101 => {
+                                let disp = self.read_disp32()?;
+                                match (self.mode, addr_size) {
+                                    (Mode::Long, OperandSize::Qword) => Operand::IndirectDisplaced(
+                                        Reg::RIP, disp as i32 as u64, size, segment),
+                                    (Mode::Long, _) => Operand::IndirectDisplaced(
+                                        Reg::EIP, disp as i32 as u64, size, segment),
+                                    _ => Operand::Memory(disp as u64, size, segment)
+                                }
+                            },
+                            _ => return Err(InstructionDecodingError::InvalidInstruction)
                         }
                     },
                     0b01 => {
-                        match rm {
+                        match rm & 0b111 {
                             0b000 | 0b001 | 0b010 | 0b011 | 0b101 | 0b110 | 0b111 => // [RM + disp8]
                                 Operand::IndirectDisplaced(Reg::from_code_general_sized(rm, InstructionReader::<T>::has_rex(buffer),
                                     addr_size).ok_or(InstructionDecodingError::InvalidInstruction)?,
-                                    self.read_disp8()? as u64, size, segment),
+                                    self.read_disp8()? as i8 as u64, size, segment),
                             0b100 => self.sib_helper(buffer, op_def, addr_size)?, // [SIB + disp8]
-                            _ => unreachable!()
+                            _ => return Err(InstructionDecodingError::InvalidInstruction)
                         }
                     },
                     0b10 => {
-                        match rm {
+                        match rm & 0b111 {
                             0b000 | 0b001 | 0b010 | 0b011 | 0b101 | 0b110 | 0b111 => // [RM + disp32]
                                 Operand::IndirectDisplaced(Reg::from_code_general_sized(rm, InstructionReader::<T>::has_rex(buffer),
                                     addr_size).ok_or(InstructionDecodingError::InvalidInstruction)?,
-                                    self.read_disp32()? as u64, size, segment),
+                                    self.read_disp32()? as i32 as u64, size, segment),
                             0b100 => self.sib_helper(buffer, op_def, addr_size)?, // [SIB + disp32]
-                            _ => unreachable!()
+                            _ => return Err(InstructionDecodingError::InvalidInstruction)
                         }
                     },
                     0b11 => Operand::Direct(conv_proc(rm).ok_or(InstructionDecodingError::InvalidInstruction)?),
-                    _ => panic!("Invalid mod_rm_mod value.") // Should be statically impossible
+                    _ => return Err(InstructionDecodingError::InvalidInstruction)
                 }
             },
-            _ => unreachable!()
+            _ => return Err(InstructionDecodingError::InvalidInstruction)
         })
     }
 
@@ -485,7 +636,7 @@ impl<T: Read> InstructionReader<T> {
             .ok_or(InstructionDecodingError::InvalidInstruction)?;
         let mode = buffer.mod_rm_mod.ok_or(InstructionDecodingError::InvalidInstruction)?;
         let segment = buffer.get_segment_reg();
-        let size = Some(InstructionReader::<T>::get_operand_size(op_def, buffer));
+        let size = Some(InstructionReader::<T>::get_operand_size(op_def, buffer)?);
         
         Ok(match mode {
             0b00 => {
@@ -504,14 +655,15 @@ impl<T: Read> InstructionReader<T> {
                 }
             },
             0b01 | 0b10 => {
-                let disp = if mode == 0b01 { self.read_disp8()? as u64 } else { self.read_disp32()? as u64 };
+                let disp = if mode == 0b01 { self.read_disp8()? as i8 as u64 }
+                    else { self.read_disp32()? as i32 as u64 };
                 if index_code == 0b100 { // [base + disp8/32]
                     Operand::IndirectDisplaced(base, disp, size, segment)
                 } else { // [base + index*s + disp8/32]
                     Operand::IndirectScaledIndexedDisplaced(base, index, scale, disp, size, segment)
                 }
             },
-            _ => unreachable!()
+            _ => return Err(InstructionDecodingError::InvalidInstruction)
         })
     }
 
@@ -525,7 +677,7 @@ impl<T: Read> InstructionReader<T> {
     }
 
     fn get_operand_type<'a>(op_def: &'a OperandDefinition, buffer: &InstructionBuffer) 
-        -> &'a OperandType {
+        -> Result<&'a OperandType, InstructionDecodingError> {
         let has_broadcast = match op_def.op_type {
             OperandType::Bcst(_) => true,
             OperandType::Set(items) => items.iter().any(|t| matches!(*t, OperandType::Bcst(_))),
@@ -542,27 +694,27 @@ impl<T: Read> InstructionReader<T> {
                     OperandType::Mem(_)
                         => !has_broadcast || !buffer.vex_b.unwrap_or(false),
                     _ => true
-                }).expect("Bad instruction definition.")
+                }).ok_or(InstructionDecodingError::InvalidOperand)
             },
-            _ => &op_def.op_type
+            _ => Ok(&op_def.op_type)
         }
     }
 
     fn get_operand_size(op_def: &OperandDefinition, buffer: &InstructionBuffer)
-        -> OperandSize {
-        let op_type = InstructionReader::<T>::get_operand_type(op_def, buffer);
+        -> Result<OperandSize, InstructionDecodingError> {
+        let op_type = InstructionReader::<T>::get_operand_type(op_def, buffer)?;
         let s = match *op_type {
             OperandType::Mem(Some(s)) |
             OperandType::Bcst(s) => s,
             _ => op_def.size
         };
 
-        match s {
+        Ok(match s {
             OperandSize::Far16 => OperandSize::Dword,
             OperandSize::Far32 => OperandSize::Fword,
             OperandSize::Far64 => OperandSize::Tbyte,
             _ => s
-        }
+        })
     }
 
     pub fn has_rex(buffer: &InstructionBuffer) -> bool {
@@ -570,17 +722,19 @@ impl<T: Read> InstructionReader<T> {
     }
 
     fn get_broadcast(def: &InstructionDefinition, buffer: &InstructionBuffer)
-        -> Option<BroadcastMode> {
-        buffer.vex_b.map_or(None, |b| if b {
-            def.operands.iter().filter_map(|o| o.as_ref().and_then(|op |
-                  InstructionReader::<T>::get_broadcast_helper(&op.op_type, op.size))).next()
-        } else { None })
+        -> Result<Option<BroadcastMode>, InstructionDecodingError> {
+        if !buffer.vex_b.unwrap_or(false) { return Ok(None); }
+        def.operands.iter().filter_map(|o| o.as_ref().and_then(|op |
+              InstructionReader::<T>::get_broadcast_helper(&op.op_type, op.size))).next()
+            .transpose()
     }
 
-    fn get_broadcast_helper(op_type: &OperandType, op_size: OperandSize) -> Option<BroadcastMode> {
+    fn get_broadcast_helper(op_type: &OperandType, op_size: OperandSize)
+        -> Option<Result<BroadcastMode, InstructionDecodingError>> {
         if let OperandType::Bcst(s) = *op_type {
-            Some(BroadcastMode::from_multiplier((op_size.bits() / s.bits()) as u8)
-                .expect("Bad instruction definition."))
+            Some(op_size.bits().checked_div(s.bits())
+                .and_then(|multiplier| BroadcastMode::from_multiplier(multiplier as u8))
+                .ok_or(InstructionDecodingError::InvalidOperand))
         } else if let OperandType::Set(set) = *op_type {
             set.iter().filter_map(
                |i| InstructionReader::<T>::get_broadcast_helper(i, op_size)).next()
@@ -612,3 +766,19 @@ pub enum InstructionDecodingError {
     // NotImplemented - Indicates that the suport required to decode is not implemented.
     NotImplemented
 }
+
+impl fmt::Display for InstructionDecodingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            InstructionDecodingError::EndOfStream => "end of stream",
+            InstructionDecodingError::PartialInstruction => "stream ended in the middle of an instruction",
+            InstructionDecodingError::ReadError => "failed to read from the underlying stream",
+            InstructionDecodingError::InvalidInstruction => "invalid instruction",
+            InstructionDecodingError::InvalidOperand => "invalid operand",
+            InstructionDecodingError::UnknownOpcode => "unknown opcode",
+            InstructionDecodingError::NotImplemented => "decoding not implemented for instruction"
+        })
+    }
+}
+
+impl Error for InstructionDecodingError { }
@@ -0,0 +1,128 @@
+use std::io::Write;
+use byteorder::{LittleEndian, WriteBytesExt};
+use ::{FixupKind, Label, Mode, ObjectError, ObjectWriter, Section};
+use ::object::align_to;
+
+pub const MZ_MAGIC: [u8; 2] = [b'M', b'Z'];
+pub const MZ_HEADER_SIZE: u64 = 0x1C;
+pub const MZ_PAGE_SIZE: u64 = 512;
+pub const PARAGRAPH_SIZE: u64 = 16;
+
+const SEGMENT_LIMIT: u64 = 0x1_0000;
+
+// Where the sections of an MZ executable are placed, following the small memory model: .text has
+// a code segment of its own, and .rodata, .data, .bss and the stack share the data segment.
+struct MzLayout {
+    data_segment: u64, // In paragraphs from the start of the image
+    offsets: Vec<(Section, u64)>, // From the start of the section's segment
+    stack_end: u64,
+}
+
+impl MzLayout {
+    fn segment(&self, section: Section) -> u64 {
+        if section == Section::Text { 0 } else { self.data_segment }
+    }
+
+    fn offset(&self, section: Section) -> u64 {
+        self.offsets.iter().find(|o| o.0 == section).map(|o| o.1)
+            .expect("Every section is laid out.")
+    }
+
+    // Offset of a section from the start of the image.
+    fn image_offset(&self, section: Section) -> u64 {
+        self.segment(section) * PARAGRAPH_SIZE + self.offset(section)
+    }
+}
+
+impl ObjectWriter {
+    // Writes a DOS MZ executable that starts at `entry`, with a stack of `stack_size` bytes after
+    // .bss. Labels resolve to offsets in their segment, and the segment words of far pointers
+    // (MemoryAndSegment16 operands, and `write_far_address`) are added to the relocation table so
+    // DOS can adjust them to the segment the program is loaded at.
+    pub fn write_mz<W: Write>(&self, writer: &mut W, entry: Label, stack_size: u16)
+        -> Result<usize, ObjectError> {
+        if self.get_mode() != Mode::Real {
+            return Err(ObjectError::UnsupportedMode(self.get_mode()));
+        }
+        let size = |section| self.get_section(section).get_inner_writer_ref().len() as u64;
+        if size(Section::Text) > SEGMENT_LIMIT {
+            return Err(ObjectError::TooLarge(size(Section::Text)));
+        }
+
+        let mut offsets = vec![(Section::Text, 0)];
+        let mut offset = 0;
+        for section in [Section::Rodata, Section::Data, Section::Bss].iter() {
+            offset = align_to(offset, section.alignment());
+            offsets.push((*section, offset));
+            offset += size(*section);
+        }
+        let stack_end = align_to(offset, 2) + stack_size as u64;
+        if stack_end > SEGMENT_LIMIT { return Err(ObjectError::TooLarge(stack_end)); }
+        let layout = MzLayout {
+            data_segment: align_to(size(Section::Text), PARAGRAPH_SIZE) / PARAGRAPH_SIZE,
+            offsets,
+            stack_end,
+        };
+
+        // Resolve the fixups, collecting the segment relocations as (segment, offset) pairs.
+        let mut image = Vec::new();
+        let mut relocations = Vec::new();
+        for section in [Section::Text, Section::Rodata, Section::Data].iter() {
+            let (segment, base) = (layout.segment(*section), layout.offset(*section));
+            let writer = self.get_section(*section);
+            let fixups = writer.get_fixups().map_err(|_| ObjectError::Unflushed)?;
+            let mut bytes = writer.get_inner_writer_ref().clone();
+            for fixup in fixups {
+                let (target_section, target) = self.get_label_location(fixup.label)
+                    .ok_or(ObjectError::UndefinedLabel(fixup.label))?;
+                let result = if fixup.kind == FixupKind::Segment {
+                    relocations.push((segment, base + fixup.offset));
+                    fixup.apply(&mut bytes, layout.segment(target_section) * PARAGRAPH_SIZE, 0)
+                } else {
+                    fixup.apply(&mut bytes, layout.offset(target_section) + target, base)
+                };
+                result.map_err(|_| ObjectError::OutOfRange(fixup.label))?;
+            }
+            for field in writer.get_segment_fields() {
+                relocations.push((segment, base + field));
+            }
+            image.resize(layout.image_offset(*section) as usize, 0);
+            image.extend_from_slice(&bytes);
+        }
+
+        let (entry_section, entry_offset) = self.get_label_location(entry)
+            .ok_or(ObjectError::UndefinedLabel(entry))?;
+        let header_size = align_to(MZ_HEADER_SIZE + 4 * relocations.len() as u64, PARAGRAPH_SIZE);
+        let file_size = header_size + image.len() as u64;
+        let memory_size = layout.data_segment * PARAGRAPH_SIZE + layout.stack_end;
+        let extra_paragraphs = align_to(memory_size.saturating_sub(image.len() as u64),
+            PARAGRAPH_SIZE) / PARAGRAPH_SIZE;
+
+        let mut header = Vec::new();
+        header.extend_from_slice(&MZ_MAGIC);
+        header.write_u16::<LittleEndian>((file_size % MZ_PAGE_SIZE) as u16).unwrap();
+        header.write_u16::<LittleEndian>((align_to(file_size, MZ_PAGE_SIZE) / MZ_PAGE_SIZE) as u16)
+            .unwrap();
+        header.write_u16::<LittleEndian>(relocations.len() as u16).unwrap();
+        header.write_u16::<LittleEndian>((header_size / PARAGRAPH_SIZE) as u16).unwrap();
+        header.write_u16::<LittleEndian>(extra_paragraphs as u16).unwrap(); // Minimum allocation
+        header.write_u16::<LittleEndian>(0xFFFF).unwrap(); // Maximum allocation
+        header.write_u16::<LittleEndian>(layout.data_segment as u16).unwrap(); // SS
+        header.write_u16::<LittleEndian>(layout.stack_end as u16).unwrap(); // SP (0 for 64K)
+        header.write_u16::<LittleEndian>(0).unwrap(); // Checksum
+        header.write_u16::<LittleEndian>((layout.offset(entry_section) + entry_offset) as u16)
+            .unwrap(); // IP
+        header.write_u16::<LittleEndian>(layout.segment(entry_section) as u16).unwrap(); // CS
+        header.write_u16::<LittleEndian>(MZ_HEADER_SIZE as u16).unwrap(); // Relocation table
+        header.write_u16::<LittleEndian>(0).unwrap(); // Overlay number
+        for &(segment, offset) in relocations.iter() {
+            header.write_u16::<LittleEndian>(offset as u16).unwrap();
+            header.write_u16::<LittleEndian>(segment as u16).unwrap();
+        }
+        header.resize(header_size as usize, 0);
+
+        writer.write_all(&header).and_then(|_| writer.write_all(&image))
+            .map_err(|_| ObjectError::WriteFailed)?;
+        Ok(file_size as usize)
+    }
+}
@@ -0,0 +1,655 @@
+use std::io::Write;
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+use ::{build_frame_section, FixupKind, FrameFormat, Label, Mode, OperandSize};
+use ::object::{align_to, Binding, ObjectError, ObjectWriter, Relocation, RelocationTarget, Section, SECTIONS};
+
+pub const ELF_MAGIC: [u8; 4] = [0x7F, b'E', b'L', b'F'];
+pub const ELFCLASS32: u8 = 1;
+pub const ELFCLASS64: u8 = 2;
+pub const ELFDATA2LSB: u8 = 1;
+pub const EV_CURRENT: u8 = 1;
+
+pub const ET_REL: u16 = 1;
+pub const ET_EXEC: u16 = 2;
+pub const EM_386: u16 = 3;
+pub const EM_X86_64: u16 = 62;
+
+pub const PT_LOAD: u32 = 1;
+pub const PT_GNU_STACK: u32 = 0x6474_E551;
+pub const PF_X: u32 = 0x1;
+pub const PF_W: u32 = 0x2;
+pub const PF_R: u32 = 0x4;
+
+pub const SHT_PROGBITS: u32 = 1;
+pub const SHT_SYMTAB: u32 = 2;
+pub const SHT_STRTAB: u32 = 3;
+pub const SHT_RELA: u32 = 4;
+pub const SHT_NOBITS: u32 = 8;
+pub const SHT_REL: u32 = 9;
+pub const SHT_DYNSYM: u32 = 11;
+pub const SHT_X86_64_UNWIND: u32 = 0x7000_0001;
+
+pub const SHF_WRITE: u64 = 0x1;
+pub const SHF_ALLOC: u64 = 0x2;
+pub const SHF_EXECINSTR: u64 = 0x4;
+pub const SHF_INFO_LINK: u64 = 0x40;
+
+pub const STB_LOCAL: u8 = 0;
+pub const STB_GLOBAL: u8 = 1;
+pub const STB_WEAK: u8 = 2;
+pub const STT_NOTYPE: u8 = 0;
+pub const STT_OBJECT: u8 = 1;
+pub const STT_FUNC: u8 = 2;
+pub const STT_SECTION: u8 = 3;
+
+// Where executables are loaded, as chosen by the default GNU linker scripts.
+pub const EXECUTABLE_BASE_32: u64 = 0x0804_8000;
+pub const EXECUTABLE_BASE_64: u64 = 0x0040_0000;
+pub const PAGE_SIZE: u64 = 0x1000;
+
+pub const R_X86_64_64: u32 = 1;
+pub const R_X86_64_PC32: u32 = 2;
+pub const R_X86_64_PLT32: u32 = 4;
+pub const R_X86_64_GOTPCREL: u32 = 9;
+pub const R_X86_64_32: u32 = 10;
+pub const R_X86_64_32S: u32 = 11;
+pub const R_X86_64_16: u32 = 12;
+pub const R_X86_64_PC16: u32 = 13;
+pub const R_386_32: u32 = 1;
+pub const R_386_PC32: u32 = 2;
+pub const R_386_16: u32 = 20;
+pub const R_386_PC16: u32 = 21;
+
+// Builds ELF structures, whose field sizes depend on the class (ELF32 or ELF64).
+pub struct ElfBuffer {
+    pub bytes: Vec<u8>,
+    pub is_64: bool,
+}
+
+impl ElfBuffer {
+    pub fn new(is_64: bool) -> ElfBuffer {
+        ElfBuffer { bytes: Vec::new(), is_64 }
+    }
+
+    pub fn size(&self) -> u64 { self.bytes.len() as u64 }
+
+    pub fn u8(&mut self, val: u8) { self.bytes.push(val); }
+
+    pub fn u16(&mut self, val: u16) { self.bytes.write_u16::<LittleEndian>(val).unwrap(); }
+
+    pub fn u32(&mut self, val: u32) { self.bytes.write_u32::<LittleEndian>(val).unwrap(); }
+
+    pub fn u64(&mut self, val: u64) { self.bytes.write_u64::<LittleEndian>(val).unwrap(); }
+
+    // An address, offset or other class-sized field (Elf32_Addr or Elf64_Xword).
+    pub fn word(&mut self, val: u64) {
+        if self.is_64 { self.u64(val) } else { self.u32(val as u32) }
+    }
+
+    pub fn align(&mut self, alignment: u64) {
+        while !self.size().is_multiple_of(alignment) { self.bytes.push(0); }
+    }
+
+    pub fn header_size(&self) -> u64 { if self.is_64 { 64 } else { 52 } }
+
+    pub fn program_header_size(&self) -> u64 { if self.is_64 { 56 } else { 32 } }
+
+    pub fn section_header_size(&self) -> u64 { if self.is_64 { 64 } else { 40 } }
+
+    pub fn symbol_size(&self) -> u64 { if self.is_64 { 24 } else { 16 } }
+
+    // Relocations have explicit addends (RELA) in ELF64, and implicit ones (REL) in ELF32.
+    pub fn relocation_size(&self) -> u64 { if self.is_64 { 24 } else { 8 } }
+
+    pub fn machine(&self) -> u16 { if self.is_64 { EM_X86_64 } else { EM_386 } }
+
+    pub fn header(&mut self, header: &ElfHeader) {
+        let (is_64, ehsize) = (self.is_64, self.header_size() as u16);
+        let (phentsize, shentsize) =
+            (self.program_header_size() as u16, self.section_header_size() as u16);
+        self.bytes.extend_from_slice(&ELF_MAGIC);
+        self.u8(if is_64 { ELFCLASS64 } else { ELFCLASS32 });
+        self.u8(ELFDATA2LSB);
+        self.u8(EV_CURRENT);
+        self.bytes.extend_from_slice(&[0; 9]); // OS ABI (System V), ABI version and padding
+        self.u16(header.file_type);
+        let machine = self.machine();
+        self.u16(machine);
+        self.u32(EV_CURRENT as u32);
+        self.word(header.entry);
+        self.word(header.program_header_offset);
+        self.word(header.section_header_offset);
+        self.u32(0); // Flags
+        self.u16(ehsize);
+        self.u16(if header.program_header_count > 0 { phentsize } else { 0 });
+        self.u16(header.program_header_count);
+        self.u16(if header.section_header_count > 0 { shentsize } else { 0 });
+        self.u16(header.section_header_count);
+        self.u16(header.string_table_index);
+    }
+
+    pub fn program_header(&mut self, header: &ProgramHeader) {
+        self.u32(header.segment_type);
+        if self.is_64 { self.u32(header.flags); }
+        self.word(header.offset);
+        self.word(header.address);
+        self.word(header.address); // Physical address
+        self.word(header.file_size);
+        self.word(header.memory_size);
+        if !self.is_64 { self.u32(header.flags); }
+        self.word(header.alignment);
+    }
+
+    pub fn section_header(&mut self, header: &SectionHeader) {
+        self.u32(header.name);
+        self.u32(header.section_type);
+        self.word(header.flags);
+        self.word(header.address);
+        self.word(header.offset);
+        self.word(header.size);
+        self.u32(header.link);
+        self.u32(header.info);
+        self.word(header.alignment);
+        self.word(header.entry_size);
+    }
+
+    pub fn symbol(&mut self, name: u32, value: u64, info: u8, section_index: u16) {
+        self.u32(name);
+        if self.is_64 {
+            self.u8(info);
+            self.u8(0); // Visibility
+            self.u16(section_index);
+            self.u64(value);
+            self.u64(0); // Size
+        } else {
+            self.u32(value as u32);
+            self.u32(0); // Size
+            self.u8(info);
+            self.u8(0); // Visibility
+            self.u16(section_index);
+        }
+    }
+
+    pub fn relocation(&mut self, offset: u64, symbol: u32, relocation_type: u32, addend: i64) {
+        if self.is_64 {
+            self.u64(offset);
+            self.u64((symbol as u64) << 32 | relocation_type as u64);
+            self.u64(addend as u64);
+        } else {
+            self.u32(offset as u32);
+            self.u32(symbol << 8 | relocation_type);
+        }
+    }
+}
+
+pub struct ElfHeader {
+    pub file_type: u16,
+    pub entry: u64,
+    pub program_header_offset: u64,
+    pub program_header_count: u16,
+    pub section_header_offset: u64,
+    pub section_header_count: u16,
+    pub string_table_index: u16,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ProgramHeader {
+    pub segment_type: u32,
+    pub flags: u32,
+    pub offset: u64,
+    pub address: u64,
+    pub file_size: u64,
+    pub memory_size: u64,
+    pub alignment: u64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SectionHeader {
+    pub name: u32,
+    pub section_type: u32,
+    pub flags: u64,
+    pub address: u64,
+    pub offset: u64,
+    pub size: u64,
+    pub link: u32,
+    pub info: u32,
+    pub alignment: u64,
+    pub entry_size: u64,
+}
+
+// A string table (.strtab/.shstrtab), starting with the empty string.
+pub struct StringTable {
+    pub bytes: Vec<u8>,
+}
+
+impl StringTable {
+    pub fn new() -> StringTable { StringTable { bytes: vec![0] } }
+
+    pub fn add(&mut self, s: &str) -> u32 {
+        let offset = self.bytes.len() as u32;
+        self.bytes.extend_from_slice(s.as_bytes());
+        self.bytes.push(0);
+        offset
+    }
+}
+
+impl Default for StringTable {
+    fn default() -> StringTable { StringTable::new() }
+}
+
+pub fn section_flags(section: Section) -> u64 {
+    SHF_ALLOC |
+        if section.is_writable() { SHF_WRITE } else { 0 } |
+        if section.is_executable() { SHF_EXECINSTR } else { 0 }
+}
+
+// The st_info of a symbol, from its binding and where it was placed.
+pub fn symbol_info(binding: Binding, location: Option<(Section, u64)>) -> u8 {
+    let binding = match binding {
+        Binding::Local => STB_LOCAL,
+        Binding::Global => STB_GLOBAL,
+        Binding::Weak => STB_WEAK
+    };
+    let symbol_type = match location {
+        Some((Section::Text, _)) => STT_FUNC,
+        Some(_) => STT_OBJECT,
+        None => STT_NOTYPE
+    };
+    binding << 4 | symbol_type
+}
+
+impl ObjectWriter {
+    // Writes a relocatable ELF object: ELF64 (x86-64) in long mode, ELF32 (i386) otherwise.
+    pub fn write_elf<W: Write>(&self, writer: &mut W) -> Result<usize, ObjectError> {
+        let mut elf = ElfBuffer::new(self.get_mode() == Mode::Long);
+        let (contents, relocations) = self.resolve(!elf.is_64)?;
+        let mut section_names = StringTable::new();
+        let mut names = StringTable::new();
+        let mut headers = vec![SectionHeader::default()];
+
+        // Header, filled in once the section headers have been placed.
+        elf.bytes.resize(elf.header_size() as usize, 0);
+
+        // Sections, in the order of SECTIONS, so section i has index i + 1.
+        for &(section, ref bytes) in contents.iter() {
+            elf.align(section.alignment());
+            headers.push(SectionHeader {
+                name: section_names.add(section.name()),
+                section_type: if section.has_contents() { SHT_PROGBITS } else { SHT_NOBITS },
+                flags: section_flags(section),
+                offset: elf.size(),
+                size: bytes.len() as u64,
+                alignment: section.alignment(),
+                ..Default::default()
+            });
+            if section.has_contents() { elf.bytes.extend_from_slice(bytes); }
+        }
+
+        // Marks the stack as non-executable, otherwise the linker assumes it has to be.
+        headers.push(SectionHeader {
+            name: section_names.add(".note.GNU-stack"),
+            section_type: SHT_PROGBITS,
+            offset: elf.size(),
+            alignment: 1,
+            ..Default::default()
+        });
+
+        // .eh_frame for the functions with frame info, referring to them PC-relatively.
+        let mut frame_relocations = None;
+        if !self.get_frame_info().is_empty() {
+            let text_offset = |label| match self.get_label_location(label) {
+                Some((Section::Text, offset)) => Some(offset),
+                _ => None
+            };
+            let (mut bytes, fixups) = build_frame_section(self.get_frame_info(), self.get_mode(),
+                FrameFormat::EhFrame, text_offset)?;
+            let mut table = ElfBuffer::new(elf.is_64);
+            let text = 1 + SECTIONS.iter().position(|s| *s == Section::Text)
+                .expect("Every section is in the section headers.");
+            for fixup in fixups {
+                let target = text_offset(fixup.label)
+                    .ok_or(ObjectError::UndefinedLabel(fixup.label))?;
+                let addend = target as i64 + fixup.addend;
+                if elf.is_64 {
+                    table.relocation(fixup.offset, text as u32, R_X86_64_PC32, addend);
+                } else {
+                    LittleEndian::write_u32(&mut bytes[fixup.offset as usize..], addend as u32);
+                    table.relocation(fixup.offset, text as u32, R_386_PC32, 0);
+                }
+            }
+            elf.align(8);
+            headers.push(SectionHeader {
+                name: section_names.add(".eh_frame"),
+                section_type: if elf.is_64 { SHT_X86_64_UNWIND } else { SHT_PROGBITS },
+                flags: SHF_ALLOC,
+                offset: elf.size(),
+                size: bytes.len() as u64,
+                alignment: 8,
+                ..Default::default()
+            });
+            elf.bytes.extend_from_slice(&bytes);
+            frame_relocations = Some((headers.len() - 1, table));
+        }
+
+        // Symbols - null, one per section, local symbols and then the others.
+        let symbols = self.get_symbols();
+        let mut order: Vec<usize> = (0..symbols.len()).collect();
+        order.sort_by_key(|i| symbols[*i].binding != Binding::Local);
+        let first_global = 1 + SECTIONS.len() + order.iter()
+            .filter(|i| symbols[**i].binding == Binding::Local).count();
+        let symbol_index = |i: usize| 1 + SECTIONS.len() + order.iter().position(|o| *o == i)
+            .expect("Every symbol is in the symbol table.");
+        let section_index = |section: Section| 1 + SECTIONS.iter().position(|s| *s == section)
+            .expect("Every section is in the section headers.");
+
+        let mut symtab = ElfBuffer::new(elf.is_64);
+        symtab.symbol(0, 0, 0, 0);
+        for section in SECTIONS.iter() {
+            symtab.symbol(0, 0, STB_LOCAL << 4 | STT_SECTION, section_index(*section) as u16);
+        }
+        for i in order.iter() {
+            let symbol = &symbols[*i];
+            let location = self.get_label_location(symbol.label);
+            if location.is_none() && symbol.binding == Binding::Local {
+                return Err(ObjectError::UndefinedLabel(symbol.label));
+            }
+            let name = names.add(&symbol.name);
+            symtab.symbol(name, location.map_or(0, |l| l.1), symbol_info(symbol.binding, location),
+                location.map_or(0, |l| section_index(l.0) as u16));
+        }
+
+        // Relocation sections refer to the symbol table, which comes right after them.
+        let relocation_sections: Vec<Section> = SECTIONS.iter().cloned()
+            .filter(|s| relocations.iter().any(|r| r.section == *s)).collect();
+        let symtab_index = headers.len() + relocation_sections.len() +
+            frame_relocations.iter().count();
+        for section in relocation_sections {
+            let mut table = ElfBuffer::new(elf.is_64);
+            for relocation in relocations.iter().filter(|r| r.section == section) {
+                let (symbol, is_symbol) = match relocation.target {
+                    RelocationTarget::Symbol(i) => (symbol_index(i), true),
+                    RelocationTarget::Section(s) => (section_index(s), false)
+                };
+                let relocation_type = get_relocation_type(relocation, elf.is_64, is_symbol)?;
+                table.relocation(relocation.offset, symbol as u32, relocation_type,
+                    relocation.addend);
+            }
+            elf.align(8);
+            let name = if elf.is_64 { format!(".rela{}", section.name()) }
+                else { format!(".rel{}", section.name()) };
+            headers.push(SectionHeader {
+                name: section_names.add(&name),
+                section_type: if elf.is_64 { SHT_RELA } else { SHT_REL },
+                flags: SHF_INFO_LINK,
+                offset: elf.size(),
+                size: table.size(),
+                link: symtab_index as u32,
+                info: section_index(section) as u32,
+                alignment: 8,
+                entry_size: elf.relocation_size(),
+                ..Default::default()
+            });
+            elf.bytes.extend_from_slice(&table.bytes);
+        }
+        if let Some((eh_frame, table)) = frame_relocations {
+            elf.align(8);
+            headers.push(SectionHeader {
+                name: section_names.add(if elf.is_64 { ".rela.eh_frame" } else { ".rel.eh_frame" }),
+                section_type: if elf.is_64 { SHT_RELA } else { SHT_REL },
+                flags: SHF_INFO_LINK,
+                offset: elf.size(),
+                size: table.size(),
+                link: symtab_index as u32,
+                info: eh_frame as u32,
+                alignment: 8,
+                entry_size: elf.relocation_size(),
+                ..Default::default()
+            });
+            elf.bytes.extend_from_slice(&table.bytes);
+        }
+
+        elf.align(8);
+        headers.push(SectionHeader {
+            name: section_names.add(".symtab"),
+            section_type: SHT_SYMTAB,
+            offset: elf.size(),
+            size: symtab.size(),
+            link: symtab_index as u32 + 1,
+            info: first_global as u32,
+            alignment: 8,
+            entry_size: elf.symbol_size(),
+            ..Default::default()
+        });
+        elf.bytes.extend_from_slice(&symtab.bytes);
+
+        headers.push(SectionHeader {
+            name: section_names.add(".strtab"),
+            section_type: SHT_STRTAB,
+            offset: elf.size(),
+            size: names.bytes.len() as u64,
+            alignment: 1,
+            ..Default::default()
+        });
+        elf.bytes.extend_from_slice(&names.bytes);
+
+        let shstrtab_name = section_names.add(".shstrtab");
+        headers.push(SectionHeader {
+            name: shstrtab_name,
+            section_type: SHT_STRTAB,
+            offset: elf.size(),
+            size: section_names.bytes.len() as u64,
+            alignment: 1,
+            ..Default::default()
+        });
+        elf.bytes.extend_from_slice(&section_names.bytes);
+
+        elf.align(8);
+        let section_header_offset = elf.size();
+        for header in headers.iter() { elf.section_header(header); }
+
+        let mut header = ElfBuffer::new(elf.is_64);
+        header.header(&ElfHeader {
+            file_type: ET_REL,
+            entry: 0,
+            program_header_offset: 0,
+            program_header_count: 0,
+            section_header_offset,
+            section_header_count: headers.len() as u16,
+            string_table_index: headers.len() as u16 - 1,
+        });
+        elf.bytes[..header.bytes.len()].copy_from_slice(&header.bytes);
+
+        writer.write_all(&elf.bytes).map_err(|_| ObjectError::WriteFailed)?;
+        Ok(elf.bytes.len())
+    }
+}
+
+impl ObjectWriter {
+    // Writes a statically linked ELF executable that starts at `entry`: ELF64 in long mode and
+    // ELF32 in protected mode. Every label has to be resolvable, as there's no dynamic linking.
+    //
+    // The headers, .text and .rodata share a read-only executable segment, followed by a
+    // read-write segment holding .data and .bss. The symbols are kept in a symbol table.
+    pub fn write_executable<W: Write>(&self, writer: &mut W, entry: Label)
+        -> Result<usize, ObjectError> {
+        let mut elf = match self.get_mode() {
+            Mode::Long => ElfBuffer::new(true),
+            Mode::Protected => ElfBuffer::new(false),
+            mode => return Err(ObjectError::UnsupportedMode(mode))
+        };
+        let base = if elf.is_64 { EXECUTABLE_BASE_64 } else { EXECUTABLE_BASE_32 };
+        let size = |section| self.get_section(section).get_inner_writer_ref().len() as u64;
+        let has_data = size(Section::Data) + size(Section::Bss) > 0;
+        let program_header_count = if has_data { 3 } else { 2 };
+
+        // Lay out the sections. Segments are mapped a page at a time, so the file offset of each
+        // section is also its offset from the base address.
+        let mut offsets = Vec::new();
+        let mut offset = elf.header_size() + program_header_count * elf.program_header_size();
+        for section in [Section::Text, Section::Rodata].iter() {
+            offset = align_to(offset, section.alignment());
+            offsets.push((*section, offset));
+            offset += size(*section);
+        }
+        let code_end = offset;
+        let data_start = align_to(offset, PAGE_SIZE);
+        offsets.push((Section::Data, data_start));
+        let data_end = data_start + size(Section::Data);
+        let bss_start = align_to(data_end, Section::Bss.alignment());
+        offsets.push((Section::Bss, bss_start));
+
+        let addresses: Vec<(Section, u64)> = offsets.iter().map(|o| (o.0, base + o.1)).collect();
+        let contents = self.link(&addresses)?;
+        let entry = self.get_label_address(entry, &addresses)
+            .ok_or(ObjectError::UndefinedLabel(entry))?;
+
+        // Header, filled in once the section headers have been placed.
+        elf.bytes.resize(elf.header_size() as usize, 0);
+        elf.program_header(&ProgramHeader {
+            segment_type: PT_LOAD,
+            flags: PF_R | PF_X,
+            offset: 0,
+            address: base,
+            file_size: code_end,
+            memory_size: code_end,
+            alignment: PAGE_SIZE,
+        });
+        if has_data {
+            elf.program_header(&ProgramHeader {
+                segment_type: PT_LOAD,
+                flags: PF_R | PF_W,
+                offset: data_start,
+                address: base + data_start,
+                file_size: data_end - data_start,
+                memory_size: bss_start + size(Section::Bss) - data_start,
+                alignment: PAGE_SIZE,
+            });
+        }
+        elf.program_header(&ProgramHeader {
+            segment_type: PT_GNU_STACK,
+            flags: PF_R | PF_W,
+            ..Default::default()
+        });
+
+        let mut section_names = StringTable::new();
+        let mut headers = vec![SectionHeader::default()];
+        let bytes = |section| &contents.iter().find(|c| c.0 == section)
+            .expect("Every section is linked.").1;
+        for &(section, offset) in offsets.iter().filter(|o| o.0.has_contents()) {
+            elf.bytes.resize(offset as usize, 0);
+            elf.bytes.extend_from_slice(bytes(section));
+        }
+        for &(section, ref bytes) in contents.iter() {
+            let offset = offsets.iter().find(|o| o.0 == section).map(|o| o.1)
+                .expect("Every section is laid out.");
+            headers.push(SectionHeader {
+                name: section_names.add(section.name()),
+                section_type: if section.has_contents() { SHT_PROGBITS } else { SHT_NOBITS },
+                flags: section_flags(section),
+                address: base + offset,
+                offset,
+                size: bytes.len() as u64,
+                alignment: section.alignment(),
+                ..Default::default()
+            });
+        }
+
+        // Symbols, with their final addresses. Undefined (weak) symbols are left out.
+        let section_index = |section: Section| 1 + SECTIONS.iter().position(|s| *s == section)
+            .expect("Every section is in the section headers.");
+        let mut symbols: Vec<_> = self.get_symbols().iter()
+            .filter_map(|s| self.get_label_location(s.label).map(|l| (s, l))).collect();
+        symbols.sort_by_key(|s| s.0.binding != Binding::Local);
+        let mut names = StringTable::new();
+        let mut symtab = ElfBuffer::new(elf.is_64);
+        symtab.symbol(0, 0, 0, 0);
+        for &(symbol, location) in symbols.iter() {
+            let name = names.add(&symbol.name);
+            let address = self.get_label_address(symbol.label, &addresses)
+                .expect("The symbol is placed.");
+            symtab.symbol(name, address, symbol_info(symbol.binding, Some(location)),
+                section_index(location.0) as u16);
+        }
+        let first_global = 1 + symbols.iter().filter(|s| s.0.binding == Binding::Local).count();
+
+        elf.align(8);
+        headers.push(SectionHeader {
+            name: section_names.add(".symtab"),
+            section_type: SHT_SYMTAB,
+            offset: elf.size(),
+            size: symtab.size(),
+            link: headers.len() as u32 + 1,
+            info: first_global as u32,
+            alignment: 8,
+            entry_size: elf.symbol_size(),
+            ..Default::default()
+        });
+        elf.bytes.extend_from_slice(&symtab.bytes);
+
+        headers.push(SectionHeader {
+            name: section_names.add(".strtab"),
+            section_type: SHT_STRTAB,
+            offset: elf.size(),
+            size: names.bytes.len() as u64,
+            alignment: 1,
+            ..Default::default()
+        });
+        elf.bytes.extend_from_slice(&names.bytes);
+
+        let shstrtab_name = section_names.add(".shstrtab");
+        headers.push(SectionHeader {
+            name: shstrtab_name,
+            section_type: SHT_STRTAB,
+            offset: elf.size(),
+            size: section_names.bytes.len() as u64,
+            alignment: 1,
+            ..Default::default()
+        });
+        elf.bytes.extend_from_slice(&section_names.bytes);
+
+        elf.align(8);
+        let section_header_offset = elf.size();
+        for header in headers.iter() { elf.section_header(header); }
+
+        let mut header = ElfBuffer::new(elf.is_64);
+        header.header(&ElfHeader {
+            file_type: ET_EXEC,
+            entry,
+            program_header_offset: elf.header_size(),
+            program_header_count: program_header_count as u16,
+            section_header_offset,
+            section_header_count: headers.len() as u16,
+            string_table_index: headers.len() as u16 - 1,
+        });
+        elf.bytes[..header.bytes.len()].copy_from_slice(&header.bytes);
+
+        writer.write_all(&elf.bytes).map_err(|_| ObjectError::WriteFailed)?;
+        Ok(elf.bytes.len())
+    }
+}
+
+fn get_relocation_type(relocation: &Relocation, is_64: bool, is_symbol: bool)
+    -> Result<u32, ObjectError> {
+    let relative = relocation.kind == FixupKind::Branch || relocation.kind == FixupKind::Relative;
+    let relocation_type = match (is_64, relative, relocation.size) {
+        _ if relocation.got => match (is_64, relocation.kind, relocation.size) {
+            (true, FixupKind::Relative, OperandSize::Dword) => Some(R_X86_64_GOTPCREL),
+            _ => None
+        },
+        // Calls and jumps to symbols go through the PLT, in case the symbol is in a shared object.
+        (true, true, OperandSize::Dword) if is_symbol && relocation.kind == FixupKind::Branch =>
+            Some(R_X86_64_PLT32),
+        (true, true, OperandSize::Dword) => Some(R_X86_64_PC32),
+        (true, true, OperandSize::Word) => Some(R_X86_64_PC16),
+        (true, false, OperandSize::Qword) => Some(R_X86_64_64),
+        (true, false, OperandSize::Dword) => Some(
+            if relocation.kind == FixupKind::SignExtended { R_X86_64_32S } else { R_X86_64_32 }),
+        (true, false, OperandSize::Word) => Some(R_X86_64_16),
+        (false, true, OperandSize::Dword) => Some(R_386_PC32),
+        (false, true, OperandSize::Word) => Some(R_386_PC16),
+        (false, false, OperandSize::Dword) => Some(R_386_32),
+        (false, false, OperandSize::Word) => Some(R_386_16),
+        _ => None
+    };
+    relocation_type.ok_or(ObjectError::UnsupportedRelocation(relocation.kind, relocation.size))
+}
@@ -0,0 +1,353 @@
+use std::error::Error;
+use std::fmt;
+use byteorder::{ByteOrder, LittleEndian};
+use ::{Instruction, InstructionDecodingError, InstructionReader, Mode};
+use ::elf::*;
+
+pub const SHN_LORESERVE: u16 = 0xFF00;
+
+// A section header of a parsed ELF file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ElfSection {
+    pub index: usize,
+    pub name: String,
+    pub section_type: u32,
+    pub flags: u64,
+    pub address: u64,
+    pub offset: u64,
+    pub size: u64,
+    pub link: u32,
+    pub info: u32,
+    pub entry_size: u64,
+}
+
+impl ElfSection {
+    pub fn is_executable(&self) -> bool { self.flags & SHF_EXECINSTR != 0 }
+}
+
+// A symbol from .symtab or .dynsym.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ElfSymbol {
+    pub name: String,
+    pub value: u64,
+    pub size: u64,
+    pub info: u8,
+    pub section_index: u16,
+}
+
+impl ElfSymbol {
+    pub fn get_binding(&self) -> u8 { self.info >> 4 }
+
+    pub fn get_type(&self) -> u8 { self.info & 0xF }
+}
+
+// An ELF32 or ELF64 file for x86 or x86-64, with its sections and symbols.
+pub struct ElfFile<'a> {
+    bytes: &'a [u8],
+    is_64: bool,
+    mode: Mode,
+    file_type: u16,
+    entry: u64,
+    sections: Vec<ElfSection>,
+    symbols: Vec<ElfSymbol>,
+}
+
+// Reads class-sized fields, failing on truncated files instead of panicking.
+struct FieldReader<'a> {
+    bytes: &'a [u8],
+    is_64: bool,
+}
+
+impl<'a> FieldReader<'a> {
+    fn slice(&self, at: u64, len: u64) -> Result<&'a [u8], ElfError> {
+        let end = at.checked_add(len).ok_or(ElfError::Truncated)?;
+        self.bytes.get(at as usize..end as usize).ok_or(ElfError::Truncated)
+    }
+
+    fn u8(&self, at: u64) -> Result<u8, ElfError> { self.slice(at, 1).map(|b| b[0]) }
+
+    fn u16(&self, at: u64) -> Result<u16, ElfError> {
+        self.slice(at, 2).map(LittleEndian::read_u16)
+    }
+
+    fn u32(&self, at: u64) -> Result<u32, ElfError> {
+        self.slice(at, 4).map(LittleEndian::read_u32)
+    }
+
+    fn word(&self, at: u64) -> Result<u64, ElfError> {
+        if self.is_64 { self.slice(at, 8).map(LittleEndian::read_u64) }
+        else { self.u32(at).map(|v| v as u64) }
+    }
+
+    // The offset of entry `index` of a table. Entries past the end of the file fail, so adding the
+    // offsets of their fields can't overflow.
+    fn entry(&self, table: u64, index: u64, size: u64) -> Result<u64, ElfError> {
+        index.checked_mul(size).and_then(|offset| table.checked_add(offset))
+            .filter(|&at| at <= self.bytes.len() as u64).ok_or(ElfError::Truncated)
+    }
+
+    // A string of a string table.
+    fn table_string(&self, table: u64, index: u32) -> Result<String, ElfError> {
+        self.string(table.checked_add(index as u64).ok_or(ElfError::Truncated)?)
+    }
+
+    fn string(&self, at: u64) -> Result<String, ElfError> {
+        let bytes = self.bytes.get(at as usize..).ok_or(ElfError::Truncated)?;
+        let len = bytes.iter().position(|b| *b == 0).ok_or(ElfError::Truncated)?;
+        Ok(String::from_utf8_lossy(&bytes[..len]).into_owned())
+    }
+}
+
+impl<'a> ElfFile<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<ElfFile<'a>, ElfError> {
+        if bytes.len() < 16 || bytes[..4] != ELF_MAGIC { return Err(ElfError::NotElf); }
+        let is_64 = match bytes[4] {
+            ELFCLASS32 => false,
+            ELFCLASS64 => true,
+            class => return Err(ElfError::UnsupportedClass(class))
+        };
+        if bytes[5] != ELFDATA2LSB { return Err(ElfError::UnsupportedEncoding(bytes[5])); }
+
+        let reader = FieldReader { bytes, is_64 };
+        let w = if is_64 { 8 } else { 4 };
+        let file_type = reader.u16(0x10)?;
+        // The mode follows the machine; x32 objects are ELF32 but contain long mode code.
+        let mode = match reader.u16(0x12)? {
+            EM_386 => Mode::Protected,
+            EM_X86_64 => Mode::Long,
+            machine => return Err(ElfError::UnsupportedMachine(machine))
+        };
+        let entry = reader.word(0x18)?;
+        let section_header_offset = reader.word(0x18 + 2 * w)?;
+        let section_header_size = reader.u16(0x22 + 3 * w)? as u64;
+        let section_count = reader.u16(0x24 + 3 * w)? as u64;
+        let string_table_index = reader.u16(0x26 + 3 * w)? as usize;
+
+        let mut sections = Vec::new();
+        for i in 0..section_count {
+            let at = reader.entry(section_header_offset, i, section_header_size)?;
+            let section = ElfSection {
+                index: i as usize,
+                name: String::new(),
+                section_type: reader.u32(at + 4)?,
+                flags: reader.word(at + 8)?,
+                address: reader.word(at + 8 + w)?,
+                offset: reader.word(at + 8 + 2 * w)?,
+                size: reader.word(at + 8 + 3 * w)?,
+                link: reader.u32(at + 8 + 4 * w)?,
+                info: reader.u32(at + 12 + 4 * w)?,
+                entry_size: reader.word(at + 16 + 5 * w)?,
+            };
+            if section.address.checked_add(section.size).is_none() {
+                return Err(ElfError::InvalidSection(i as usize));
+            }
+            sections.push(section);
+        }
+        if let Some(names) = sections.get(string_table_index).map(|s| s.offset) {
+            for section in sections.iter_mut() {
+                let at = reader.entry(section_header_offset, section.index as u64,
+                    section_header_size)?;
+                section.name = reader.table_string(names, reader.u32(at)?)?;
+            }
+        }
+
+        let mut symbols = Vec::new();
+        for table in sections.iter().filter(|s| s.section_type == SHT_SYMTAB ||
+            s.section_type == SHT_DYNSYM) {
+            let strings = sections.get(table.link as usize).ok_or(ElfError::Truncated)?.offset;
+            let size = if is_64 { 24 } else { 16 };
+            for i in 1..table.size / size {
+                let at = reader.entry(table.offset, i, size)?;
+                let (value, symbol_size, info, section_index) = if is_64 {
+                    (reader.word(at + 8)?, reader.word(at + 16)?, reader.u8(at + 4)?,
+                        reader.u16(at + 6)?)
+                } else {
+                    (reader.word(at + 4)?, reader.word(at + 8)?, reader.u8(at + 12)?,
+                        reader.u16(at + 14)?)
+                };
+                symbols.push(ElfSymbol {
+                    name: reader.table_string(strings, reader.u32(at)?)?,
+                    value,
+                    size: symbol_size,
+                    info,
+                    section_index,
+                });
+            }
+        }
+
+        Ok(ElfFile { bytes, is_64, mode, file_type, entry, sections, symbols })
+    }
+
+    pub fn is_64(&self) -> bool { self.is_64 }
+
+    pub fn get_mode(&self) -> Mode { self.mode }
+
+    // ET_REL, ET_EXEC or ET_DYN.
+    pub fn get_file_type(&self) -> u16 { self.file_type }
+
+    pub fn get_entry(&self) -> u64 { self.entry }
+
+    pub fn get_sections(&self) -> &[ElfSection] { &self.sections }
+
+    pub fn get_section(&self, name: &str) -> Option<&ElfSection> {
+        self.sections.iter().find(|s| s.name == name)
+    }
+
+    // The symbols of .symtab followed by those of .dynsym, without the null symbols.
+    pub fn get_symbols(&self) -> &[ElfSymbol] { &self.symbols }
+
+    pub fn get_symbol(&self, name: &str) -> Option<&ElfSymbol> {
+        self.symbols.iter().find(|s| s.name == name)
+    }
+
+    // The contents of a section, or None for .bss-like sections and sections past the end of
+    // the file.
+    pub fn get_section_data(&self, section: &ElfSection) -> Option<&'a [u8]> {
+        if section.section_type == SHT_NOBITS { return None; }
+        let end = section.offset.checked_add(section.size)?;
+        self.bytes.get(section.offset as usize..end as usize)
+    }
+
+    // The address of a symbol. Symbols of relocatable objects hold offsets into their section,
+    // which is at address 0.
+    fn get_symbol_address(&self, symbol: &ElfSymbol, section: &ElfSection) -> u64 {
+        if self.file_type == ET_REL { section.address.wrapping_add(symbol.value) }
+        else { symbol.value }
+    }
+
+    // Splits the executable sections at their function (and untyped) symbols. Code that comes
+    // before the first symbol of its section, i.e. in stripped files, has no symbol.
+    fn get_code_ranges(&self) -> Vec<CodeRange<'_>> {
+        let mut ranges = Vec::new();
+        for section in self.sections.iter().filter(|s| s.is_executable()) {
+            let mut starts: Vec<(u64, Option<&ElfSymbol>)> = self.symbols.iter()
+                .filter(|s| s.section_index as usize == section.index &&
+                    s.section_index < SHN_LORESERVE && !s.name.is_empty() &&
+                    (s.get_type() == STT_FUNC || s.get_type() == STT_NOTYPE))
+                .map(|s| (self.get_symbol_address(s, section), Some(s)))
+                .filter(|s| s.0 >= section.address && s.0 < section.address + section.size)
+                .collect();
+            starts.sort_by_key(|s| s.0);
+            starts.dedup_by_key(|s| s.0);
+            if starts.first().is_none_or(|s| s.0 != section.address) {
+                starts.insert(0, (section.address, None));
+            }
+
+            for (i, &(start, symbol)) in starts.iter().enumerate() {
+                let next = starts.get(i + 1).map_or(section.address + section.size, |s| s.0);
+                let end = match symbol {
+                    Some(symbol) if symbol.size > 0 =>
+                        start.checked_add(symbol.size).map_or(next, |end| next.min(end)),
+                    _ => next
+                };
+                ranges.push(CodeRange { section, symbol, start, end });
+            }
+        }
+        ranges
+    }
+
+    // Decodes the executable sections, starting at each symbol.
+    pub fn disassemble(&self) -> ElfDisassembly<'_> {
+        ElfDisassembly { file: self, ranges: self.get_code_ranges(), range: 0, address: None }
+    }
+
+    // Decodes the code of a single function.
+    pub fn disassemble_symbol(&self, name: &str) -> Option<ElfDisassembly<'_>> {
+        let ranges: Vec<_> = self.get_code_ranges().into_iter()
+            .filter(|r| r.symbol.is_some_and(|s| s.name == name)).collect();
+        if ranges.is_empty() { return None; }
+        Some(ElfDisassembly { file: self, ranges, range: 0, address: None })
+    }
+}
+
+struct CodeRange<'a> {
+    section: &'a ElfSection,
+    symbol: Option<&'a ElfSymbol>,
+    start: u64,
+    end: u64,
+}
+
+// A decoded instruction, or the byte that couldn't be decoded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ElfInstruction<'a> {
+    pub symbol: Option<&'a ElfSymbol>,
+    pub section: &'a ElfSection,
+    pub address: u64,
+    pub bytes: &'a [u8],
+    pub instruction: Result<Instruction, InstructionDecodingError>,
+}
+
+pub struct ElfDisassembly<'a> {
+    file: &'a ElfFile<'a>,
+    ranges: Vec<CodeRange<'a>>,
+    range: usize,
+    address: Option<u64>,
+}
+
+impl<'a> Iterator for ElfDisassembly<'a> {
+    type Item = ElfInstruction<'a>;
+
+    fn next(&mut self) -> Option<ElfInstruction<'a>> {
+        loop {
+            let range = self.ranges.get(self.range)?;
+            let address = self.address.unwrap_or(range.start);
+            let data = self.file.get_section_data(range.section);
+            if address >= range.end || data.is_none() {
+                self.range += 1;
+                self.address = None;
+                continue;
+            }
+
+            let offset = (address - range.section.address) as usize;
+            let code = &data.unwrap()[offset..(range.end - range.section.address) as usize];
+            let instruction = InstructionReader::new(code, self.file.mode).read();
+            let len = instruction.as_ref().map_or(1, |i| i.1);
+            self.address = Some(address + len as u64);
+            return Some(ElfInstruction {
+                symbol: range.symbol,
+                section: range.section,
+                address,
+                bytes: &code[..len],
+                instruction: instruction.map(|i| i.0),
+            });
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ElfError {
+    // NotElf - The file doesn't start with the ELF magic number.
+    NotElf,
+
+    // UnsupportedClass - EI_CLASS is neither ELFCLASS32 nor ELFCLASS64.
+    UnsupportedClass(u8),
+
+    // UnsupportedEncoding - The file isn't little endian.
+    UnsupportedEncoding(u8),
+
+    // UnsupportedMachine - e_machine is neither EM_386 nor EM_X86_64.
+    UnsupportedMachine(u16),
+
+    // Truncated - A header, table or string extends past the end of the file.
+    Truncated,
+
+    // InvalidSection - The section at the index ends past the end of the address space.
+    InvalidSection(usize),
+}
+
+impl fmt::Display for ElfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ElfError::NotElf => f.write_str("not an ELF file"),
+            ElfError::UnsupportedClass(class) => write!(f, "unsupported ELF class {}", class),
+            ElfError::UnsupportedEncoding(encoding) =>
+                write!(f, "unsupported data encoding {}", encoding),
+            ElfError::UnsupportedMachine(machine) => write!(f, "unsupported machine {}", machine),
+            ElfError::Truncated => f.write_str("the file is truncated"),
+            ElfError::InvalidSection(index) =>
+                write!(f, "section {} ends past the end of the address space", index),
+        }
+    }
+}
+
+impl Error for ElfError { }
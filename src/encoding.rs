@@ -1,11 +1,32 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
 use std::io::Write;
-use ::{Instruction, Mnemonic, Mode, Operand, OperandSize, Reg, RegScale};
-use ::instruction_buffer::{ImmediateValue, InstructionBuffer};
+use ::{Instruction, Mnemonic, Mode, Operand, OperandSize, Reg, RegScale, SegmentReg};
+use ::instruction_buffer::{ImmediateValue, InstructionBuffer, Prefix1};
+use ::branch_align::{add_padding_prefixes, branch_padding, is_fused, is_jump, HeldInstruction,
+    CACHE_LINE_SIZE, MAX_HELD};
+use ::cfi::{FrameInfo, FrameRecorder};
+use ::label::{Fixup, FixupKind, Label};
+use ::listing::ListingEntry;
+use ::patch::nop_padding;
 use ::instruction_def::*;
 
+const OP_INT3: u8 = 0xCC;
+
 pub struct InstructionWriter<T: Write> {
     writer: T,
     mode: Mode,
+    offset: u64,
+    labels: HashMap<Label, u64>,
+    fixups: Vec<Fixup>,
+    segment_fields: Vec<u64>,
+    listing: Option<Vec<ListingEntry>>,
+    source: Option<(usize, String)>,
+    frame: Option<FrameRecorder>,
+    held: Option<Vec<HeldInstruction>>,
+    line_crossings: Vec<(u64, usize)>,
+    flush_failed: bool,
 }
 
 impl<T: Write> InstructionWriter<T> {
@@ -13,61 +34,353 @@ impl<T: Write> InstructionWriter<T> {
         InstructionWriter {
             writer: writer,
             mode: mode,
+            offset: 0,
+            labels: HashMap::new(),
+            fixups: Vec::new(),
+            segment_fields: Vec::new(),
+            listing: None,
+            source: None,
+            frame: None,
+            held: None,
+            line_crossings: Vec::new(),
+            flush_failed: false,
         }
     }
 
+    // The code written so far. Instructions held back by branch alignment aren't in it until
+    // `flush`, see `get_fixups`.
     pub fn get_inner_writer_ref(&self) -> &T { &self.writer }
 
+    pub fn get_inner_writer_mut(&mut self) -> &mut T { &mut self.writer }
+
+    pub fn get_mode(&self) -> Mode { self.mode }
+
+    // Changes the mode of the instructions that follow, i.e. after a bootloader enables protected
+    // mode. Labels and fixups are kept, so code in either mode can refer to the other.
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.flush_held();
+        self.mode = mode;
+    }
+
+    // The number of bytes written so far, including instructions held back by branch alignment.
+    pub fn get_offset(&self) -> u64 {
+        self.offset + self.held.as_ref().map_or(0, |held| held.iter()
+            .map(|instr| instr.bytes.len() as u64).sum())
+    }
+
+    // Places the label at the current offset.
+    pub fn set_label(&mut self, label: Label) {
+        self.flush_held();
+        self.labels.insert(label, self.offset);
+    }
+
+    pub fn get_label_offset(&self, label: Label) -> Option<u64> {
+        self.labels.get(&label).cloned()
+    }
+
+    // The fields that refer to labels, which have been written as zero. Fails while instructions
+    // are held back by branch alignment, as their fixups (and bytes) are missing until `flush`.
+    pub fn get_fixups(&self) -> Result<&[Fixup], InstructionEncodingError> {
+        if self.is_holding() { return Err(InstructionEncodingError::Unflushed); }
+        Ok(&self.fixups)
+    }
+
+    // Whether instructions are held back by branch alignment, waiting for a jump or `flush`.
+    pub fn is_holding(&self) -> bool { self.held.as_ref().is_some_and(|held| !held.is_empty()) }
+
+    // Offsets of the segment words of far pointer operands (i.e. JMP 0x1000:0x20), which have to
+    // be relocated if the code is loaded at a segment chosen at run time.
+    pub fn get_segment_fields(&self) -> &[u64] { &self.segment_fields }
+
+    // Records everything written from now on, with its offset and bytes, for
+    // `write_assembly_listing`.
+    pub fn enable_listing(&mut self) {
+        self.flush_held();
+        if self.listing.is_none() { self.listing = Some(Vec::new()); }
+    }
+
+    pub fn get_listing(&self) -> &[ListingEntry] {
+        self.listing.as_ref().map_or(&[], |l| &l[..])
+    }
+
+    // The source line the next instruction or data was assembled from, for the listing.
+    pub fn set_source(&mut self, line: usize, text: &str) {
+        self.flush_held();
+        self.source = Some((line, text.to_owned()));
+    }
+
+    // Places `start` and records the call frame information of the function starting there as its
+    // prolog is written, until `end_frame`.
+    pub fn begin_frame(&mut self, start: Label) {
+        self.set_label(start);
+        self.frame = Some(FrameRecorder::new(start, self.mode));
+    }
+
+    // Places `end` and returns the call frame information of the function since `begin_frame`.
+    pub fn end_frame(&mut self, end: Label) -> Option<FrameInfo> {
+        self.set_label(end);
+        self.frame.take().map(|frame| FrameInfo { end, ..frame.info })
+    }
+
+    // Pads jumps, calls and returns so that, together with a CMP, TEST, ADD, SUB, AND, INC or DEC
+    // fused with a Jcc, they don't cross or end at a 32 byte boundary, working around Intel's JCC
+    // erratum. The instructions before a jump are padded with segment prefixes that don't change
+    // them, or NOPs if they can't be. They're held back until the next jump, label, data or mode
+    // switch, or a call to `flush`, which has to be done before reading the code. Also records
+    // instructions that cross 64 byte cache lines from now on.
+    pub fn enable_branch_alignment(&mut self) {
+        if self.held.is_none() { self.held = Some(Vec::new()); }
+    }
+
+    // Writes the instructions held back by branch alignment, i.e. before reading the code.
+    pub fn flush(&mut self) -> Result<(), InstructionEncodingError> {
+        if self.flush_failed { return Err(InstructionEncodingError::WriteFailed); }
+        let held = match self.held {
+            Some(ref mut held) => ::std::mem::take(held),
+            None => return Ok(())
+        };
+        for instr in held { self.emit(instr)?; }
+        Ok(())
+    }
+
+    // Flushes where errors can't be returned, so they're returned by the next write instead.
+    fn flush_held(&mut self) {
+        if self.flush().is_err() { self.flush_failed = true; }
+    }
+
+    // The offsets and lengths of instructions that cross 64 byte cache lines, with branch
+    // alignment enabled.
+    pub fn get_line_crossings(&self) -> &[(u64, usize)] { &self.line_crossings }
+
+    fn add_listing_entry(&mut self, bytes: &[u8], instruction: Option<Instruction>) {
+        let source = self.source.take();
+        if let Some(ref mut listing) = self.listing {
+            listing.push(ListingEntry { offset: self.offset, bytes: bytes.to_vec(), instruction, source });
+        }
+    }
+
+    // Writes an instruction and returns its length. With branch alignment, prefixes added to it
+    // later to pad a jump aren't included.
     pub fn write(&mut self, instr: &Instruction) -> Result<usize, InstructionEncodingError> {
-        instr.encode(&mut self.writer, self.mode)
+        if self.flush_failed { return Err(InstructionEncodingError::WriteFailed); }
+        if self.listing.is_none() && self.held.is_none() {
+            let layout = instr.encode_layout(&mut self.writer, self.mode)?;
+            let len = layout.len;
+            self.record(instr, layout);
+            return Ok(len);
+        }
+        let mut bytes = Vec::new();
+        let layout = instr.encode_layout(&mut bytes, self.mode)?;
+        let len = layout.len;
+        let instr = HeldInstruction { instr: *instr, bytes, layout };
+        if self.held.is_some() { self.write_aligned(instr)?; } else { self.emit(instr)?; }
+        Ok(len)
+    }
+
+    // Writes an encoded instruction.
+    fn emit(&mut self, instr: HeldInstruction) -> Result<(), InstructionEncodingError> {
+        self.writer.write_all(&instr.bytes).map_err(|_| InstructionEncodingError::WriteFailed)?;
+        self.add_listing_entry(&instr.bytes, Some(instr.instr));
+        self.record(&instr.instr, instr.layout);
+        Ok(())
+    }
+
+    // Records the fixups and call frame information of an instruction that's been written.
+    fn record(&mut self, instr: &Instruction, layout: InstructionLayout) {
+        for fixup in layout.fixups {
+            self.fixups.push(Fixup { offset: fixup.offset + self.offset, ..fixup });
+        }
+        if let Some(segment) = layout.segment {
+            self.segment_fields.push(segment.offset as u64 + self.offset);
+        }
+        let end = self.offset + layout.len as u64;
+        if self.held.is_some() && self.offset / CACHE_LINE_SIZE != (end - 1) / CACHE_LINE_SIZE {
+            self.line_crossings.push((self.offset, layout.len));
+        }
+        self.offset = end;
+        if let Some(ref mut frame) = self.frame {
+            let after = Label::new();
+            if frame.record(instr, after) { self.labels.insert(after, self.offset); }
+        }
+    }
+
+    // Holds back instructions until a jump, then pads the ones before it if it would cross or end
+    // at a boundary.
+    fn write_aligned(&mut self, instr: HeldInstruction) -> Result<(), InstructionEncodingError> {
+        let mut held = self.held.take().unwrap_or_default();
+        if !is_jump(&instr.instr) {
+            held.push(instr);
+            let oldest = if held.len() > MAX_HELD { Some(held.remove(0)) } else { None };
+            self.held = Some(held);
+            return oldest.map_or(Ok(()), |oldest| self.emit(oldest));
+        }
+
+        let fused = held.last().is_some_and(|last| is_fused(&last.instr, &instr.instr));
+        let pair = if fused { held.pop() } else { None };
+        let start = self.offset + held.iter().map(|instr| instr.bytes.len() as u64).sum::<u64>();
+        let len = pair.as_ref().map_or(0, |first| first.bytes.len()) + instr.bytes.len();
+        let nops = add_padding_prefixes(&mut held, branch_padding(start, len), self.mode);
+        self.held = Some(Vec::new());
+        for instr in held { self.emit(instr)?; }
+        self.write_raw(&nop_padding(nops, self.mode))?;
+        if let Some(first) = pair { self.emit(first)?; }
+        self.emit(instr)
     }
 
     pub fn write0(&mut self, mnemonic: Mnemonic) -> Result<usize, InstructionEncodingError> {
-        Instruction {
-            mnemonic: mnemonic,
+        self.write(&Instruction {
+            mnemonic,
             .. Default::default()
-        } .encode(&mut self.writer, self.mode)
+        })
     }
 
     pub fn write1(&mut self, mnemonic: Mnemonic, operand1: Operand) -> Result<usize, InstructionEncodingError> {
-        Instruction {
-            mnemonic: mnemonic,
+        self.write(&Instruction {
+            mnemonic,
             operand1: Some(operand1),
             .. Default::default()
-        } .encode(&mut self.writer, self.mode)
+        })
     }
 
     pub fn write2(&mut self, mnemonic: Mnemonic, operand1: Operand, operand2: Operand) -> Result<usize, InstructionEncodingError> {
-        Instruction {
-            mnemonic: mnemonic,
+        self.write(&Instruction {
+            mnemonic,
             operand1: Some(operand1),
             operand2: Some(operand2),
             .. Default::default()
-        } .encode(&mut self.writer, self.mode)
+        })
     }
 
     pub fn write3(&mut self, mnemonic: Mnemonic, operand1: Operand, operand2: Operand, operand3: Operand) -> Result<usize, InstructionEncodingError> {
-        Instruction {
-            mnemonic: mnemonic,
+        self.write(&Instruction {
+            mnemonic,
             operand1: Some(operand1),
             operand2: Some(operand2),
             operand3: Some(operand3),
             .. Default::default()
-        } .encode(&mut self.writer, self.mode)
+        })
     }
 
     pub fn write4(&mut self, mnemonic: Mnemonic, operand1: Operand, operand2: Operand, operand3: Operand, operand4: Operand) -> Result<usize, InstructionEncodingError> {
-        Instruction {
-            mnemonic: mnemonic,
+        self.write(&Instruction {
+            mnemonic,
             operand1: Some(operand1),
             operand2: Some(operand2),
             operand3: Some(operand3),
             operand4: Some(operand4),
             .. Default::default()
-        } .encode(&mut self.writer, self.mode)
+        })
+    }
+
+    // Writes raw data, i.e. strings or tables between functions.
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> Result<usize, InstructionEncodingError> {
+        self.flush()?;
+        self.write_raw(bytes)
+    }
+
+    fn write_raw(&mut self, bytes: &[u8]) -> Result<usize, InstructionEncodingError> {
+        self.writer.write_all(bytes).map_err(|_| InstructionEncodingError::WriteFailed)?;
+        self.add_listing_entry(bytes, None);
+        self.offset += bytes.len() as u64;
+        Ok(bytes.len())
+    }
+
+    // Writes the address of a label as data, i.e. for jump tables.
+    pub fn write_address(&mut self, label: Label, size: OperandSize)
+        -> Result<usize, InstructionEncodingError> {
+        self.flush()?;
+        let len = match size {
+            OperandSize::Word | OperandSize::Dword | OperandSize::Qword => size.bits() as usize / 8,
+            _ => return Err(InstructionEncodingError::MismatchedSize)
+        };
+        self.fixups.push(Fixup {
+            offset: self.offset,
+            size,
+            label,
+            kind: FixupKind::Absolute,
+            addend: 0
+        });
+        self.write_bytes(&[0; 8][..len])
+    }
+
+    // Writes a 16:16 far pointer to a label as data: its offset followed by its segment.
+    pub fn write_far_address(&mut self, label: Label) -> Result<usize, InstructionEncodingError> {
+        self.flush()?;
+        self.write_address(label, OperandSize::Word)?;
+        self.fixups.push(Fixup {
+            offset: self.offset,
+            size: OperandSize::Word,
+            label,
+            kind: FixupKind::Segment,
+            addend: 0
+        });
+        self.write_bytes(&[0; 2]).map(|len| len + 2)
+    }
+
+    // Pads to a multiple of `alignment` bytes, i.e. to align loops or functions, and returns the
+    // number of bytes written. An alignment of 0, like 1, writes nothing.
+    pub fn align(&mut self, alignment: u64, fill: Fill)
+        -> Result<usize, InstructionEncodingError> {
+        self.flush()?;
+        let len = self.offset.checked_rem(alignment).map_or(0, |rem| (alignment - rem) % alignment);
+        let bytes = match fill {
+            Fill::Nop => nop_padding(len as usize, self.mode),
+            Fill::Int3 => vec![OP_INT3; len as usize],
+            Fill::Byte(byte) => vec![byte; len as usize],
+        };
+        self.write_bytes(&bytes)
+    }
+
+    // Writes a 5-byte NOP that Patcher::patch_jump can turn into a JMP with a single store,
+    // padding first with NOPs so it doesn't straddle 8 byte alignment. Returns its offset. The
+    // alignment is of the offset, so the code has to be loaded at an 8 byte aligned address (a
+    // JitBuffer is page aligned), or patch_jump rejects the site.
+    pub fn write_patch_site(&mut self) -> Result<u64, InstructionEncodingError> {
+        self.flush()?;
+        let misalignment = self.offset % 8;
+        if misalignment > 3 {
+            self.write_bytes(&nop_padding(8 - misalignment as usize, self.mode))?;
+        }
+        let offset = self.offset;
+        self.write_bytes(&nop_padding(5, self.mode))?;
+        Ok(offset)
     }
 }
 
+// What InstructionWriter::align pads with.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Fill {
+    // Nop - As few NOPs as possible, for padding that's executed.
+    Nop,
+
+    // Int3 - Breakpoints, for padding between functions that should never be executed.
+    Int3,
+
+    // Byte - Any byte, i.e. zeros in data.
+    Byte(u8),
+}
+
+// Where the displacement and immediates of an encoded instruction are.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct InstructionLayout {
+    pub len: usize,
+    pub displacement: Option<EncodedField>,
+    pub immediate: Option<EncodedField>,
+    pub immediate2: Option<EncodedField>,
+
+    // The segment word of a far pointer operand, at the end of its immediate.
+    pub segment: Option<EncodedField>,
+
+    // Fields holding label operands, with offsets from the start of the instruction.
+    pub fixups: Vec<Fixup>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct EncodedField {
+    pub offset: usize, // From the start of the instruction
+    pub size: usize,   // In bytes
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum InstructionEncodingError {
     InvalidMnemonic,
@@ -76,10 +389,29 @@ pub enum InstructionEncodingError {
     MismatchedSize,
     AmbiguousSize,
     MismatchedEncoding,
-    InvalidAddressing
+    InvalidAddressing,
+    Unflushed
+}
+
+impl fmt::Display for InstructionEncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            InstructionEncodingError::InvalidMnemonic => "invalid mnemonic",
+            InstructionEncodingError::NoEncoding => "no encoding matches the operands",
+            InstructionEncodingError::WriteFailed => "failed to write to the underlying stream",
+            InstructionEncodingError::MismatchedSize => "operand sizes don't match",
+            InstructionEncodingError::AmbiguousSize => "operand size is ambiguous",
+            InstructionEncodingError::MismatchedEncoding => "operand doesn't match its encoding",
+            InstructionEncodingError::InvalidAddressing => "invalid addressing mode",
+            InstructionEncodingError::Unflushed =>
+                "instructions are held back by branch alignment until flushed"
+        })
+    }
 }
 
-pub fn encode<W>(writer: &mut W, def: &InstructionDefinition, instr: &Instruction, mode: Mode) -> Result<usize, InstructionEncodingError>
+impl Error for InstructionEncodingError { }
+
+pub fn encode<W>(writer: &mut W, def: &InstructionDefinition, instr: &Instruction, mode: Mode) -> Result<InstructionLayout, InstructionEncodingError>
     where W : Write {
     let mut buffer: InstructionBuffer = Default::default(); 
 
@@ -87,8 +419,10 @@ pub fn encode<W>(writer: &mut W, def: &InstructionDefinition, instr: &Instructio
 
     buffer.fwait = def.fwait;
     buffer.operand_size_prefix = get_operand_size_prefix(def.operand_size_prefix, mode);
-    buffer.address_size_prefix = def.address_size_prefix.unwrap_or_else(
-        || get_address_size_prefix(addr_size, mode));
+    buffer.address_size_prefix = match def.address_size_prefix {
+        Some(prefix) => prefix,
+        None => get_address_size_prefix(addr_size, mode)?
+    };
 
     match def.f2_prefix {
         PrefixBehavior::Always => buffer.f2_prefix = true,
@@ -102,6 +436,9 @@ pub fn encode<W>(writer: &mut W, def: &InstructionDefinition, instr: &Instructio
         PrefixBehavior::Never => buffer.f3_prefix = false,
     }
 
+    if instr.lock { buffer.prefix1 = Some(Prefix1::Lock); }
+    if let Some(segment) = get_segment_override(instr) { buffer.set_segment_override(segment); }
+
     buffer.operand_size_64 = get_op_size_64(def);
     if def.opcode_ext.is_some() { buffer.mod_rm_reg = def.opcode_ext; }
     if def.fixed_mod_rm_mod.is_some() { buffer.mod_rm_mod = def.fixed_mod_rm_mod; }
@@ -152,7 +489,48 @@ pub fn encode<W>(writer: &mut W, def: &InstructionDefinition, instr: &Instructio
         }
     }
 
-    buffer.write(writer, mode)
+    let mut layout = buffer.write(writer, mode)?;
+    layout.fixups = get_label_fixups(def, instr, &layout, buffer.operand_size_64, mode);
+    if instr.operands().iter().any(|op| matches!(**op,
+        Some(Operand::MemoryAndSegment16(..)) | Some(Operand::MemoryAndSegment32(..)))) {
+        layout.segment = layout.immediate
+            .map(|imm| EncodedField { offset: imm.offset + imm.size - 2, size: 2 });
+    }
+    Ok(layout)
+}
+
+// Finds the fields holding label operands, which are encoded as zero until the label's address is
+// known.
+fn get_label_fixups(def: &InstructionDefinition, instr: &Instruction, layout: &InstructionLayout,
+    operand_size_64: bool, mode: Mode) -> Vec<Fixup> {
+    let mut fixups = Vec::new();
+    let mut immediates = layout.immediate.into_iter().chain(layout.immediate2);
+
+    for (maybe_op_def, op) in def.operands.iter().zip(instr.operands().iter()) {
+        let op_def = match *maybe_op_def { Some(ref op_def) => op_def, None => continue };
+        let is_immediate = (op_def.encoding == OperandEncoding::Imm ||
+            op_def.encoding == OperandEncoding::Offset) && !matches!(op_def.op_type, OperandType::Fixed(_));
+        let immediate = if is_immediate { immediates.next() } else { None };
+
+        let (label, field, kind) = match (**op, immediate, layout.displacement) {
+            (Some(Operand::Label(label)), Some(field), _) => (label, field,
+                if let OperandType::Rel(_) = op_def.op_type { FixupKind::Branch }
+                else if operand_size_64 && field.size == 4 { FixupKind::SignExtended }
+                else { FixupKind::Absolute }),
+            (Some(Operand::LabelMemory(label, ..)), _, Some(field)) => (label, field,
+                if mode == Mode::Long { FixupKind::Relative } else { FixupKind::Absolute }),
+            _ => continue
+        };
+        let relative = kind == FixupKind::Branch || kind == FixupKind::Relative;
+        fixups.push(Fixup {
+            offset: field.offset as u64,
+            size: OperandSize::from_bits(field.size as u32 * 8).unwrap_or(OperandSize::Unsized),
+            label,
+            kind,
+            addend: if relative { field.offset as i64 - layout.len as i64 } else { 0 }
+        });
+    }
+    fixups
 }
 
 fn get_operand_size_prefix(behavior: OperandSizePrefixBehavior, mode: Mode) 
@@ -165,6 +543,11 @@ fn get_operand_size_prefix(behavior: OperandSizePrefixBehavior, mode: Mode)
     }
 }
 
+// The segment override of the first memory operand that has one.
+fn get_segment_override(instr: &Instruction) -> Option<SegmentReg> {
+    instr.operands().iter().filter_map(|op| op.and_then(|op| op.segment_reg())).next()
+}
+
 fn get_addr_size(def: &InstructionDefinition, instr: &Instruction, mode: Mode)
     -> Result<OperandSize, InstructionEncodingError> {
     match check_unique::<_, OperandSize, InstructionEncodingError>(def.operands.iter().zip(instr.operands().iter()).filter_map(
@@ -215,23 +598,16 @@ fn check_unique<I, J, E>(mut iter: I, default_error: E) -> Result<Option<J>, E>
     } else { Ok(None) }
 }
 
-fn get_address_size_prefix(addr_size: OperandSize, mode: Mode) -> bool {
-    match mode {
-        Mode::Long => match addr_size {
-            OperandSize::Qword => false,
-            OperandSize::Dword => true,
-            _ => unreachable!()
-        },
-        Mode::Protected => match addr_size {
-            OperandSize::Dword => false,
-            OperandSize::Word => true,
-            _ => unreachable!()
-        },
-        Mode::Real => match addr_size {
-            OperandSize::Word => false,
-            OperandSize::Dword => true,
-            _ => unreachable!()
-        },
+fn get_address_size_prefix(addr_size: OperandSize, mode: Mode)
+    -> Result<bool, InstructionEncodingError> {
+    match (mode, addr_size) {
+        (Mode::Long, OperandSize::Qword) |
+        (Mode::Protected, OperandSize::Dword) |
+        (Mode::Real, OperandSize::Word) => Ok(false),
+        (Mode::Long, OperandSize::Dword) |
+        (Mode::Protected, OperandSize::Word) |
+        (Mode::Real, OperandSize::Dword) => Ok(true),
+        _ => Err(InstructionEncodingError::InvalidAddressing)
     }
 }
 
@@ -247,35 +623,37 @@ pub fn encode_operand(buffer: &mut InstructionBuffer, def: &OperandDefinition, o
     match def.encoding {
         OperandEncoding::ModRmReg => { 
             if let Some(Operand::Direct(reg)) = *op {
-                buffer.mod_rm_reg = Some(reg.get_reg_code());
+                buffer.mod_rm_reg = Some(reg_code(reg)?);
 
                 if reg.needs_rex() && buffer.composite_prefix.is_none() {
                     buffer.composite_prefix = Some(::instruction_buffer::CompositePrefix::Rex);
                 }
-            } else { panic!("Internal error."); }
+            } else { return Err(InstructionEncodingError::MismatchedEncoding); }
         }
         OperandEncoding::Mib |
-        OperandEncoding::ModRmRm => { encode_rm(buffer, &op.expect("Internal error."), mode)?; },
+        OperandEncoding::ModRmRm => {
+            encode_rm(buffer, &op.ok_or(InstructionEncodingError::MismatchedEncoding)?, mode)?;
+        },
         OperandEncoding::Vex => {
             if let Some(Operand::Direct(reg)) = *op {
-                buffer.vex_operand = Some(reg.get_reg_code());
-            } else { panic!("Internal error."); }
+                buffer.vex_operand = Some(reg_code(reg)?);
+            } else { return Err(InstructionEncodingError::MismatchedEncoding); }
         },
         OperandEncoding::Offset |
         OperandEncoding::Imm => {
-            match op.expect("Internal error.") {
+            match op.ok_or(InstructionEncodingError::MismatchedEncoding)? {
                 Operand::Literal8(val) => 
-                    { buffer.add_immediate(ImmediateValue::Literal8(val)); },
+                    { buffer.add_immediate(ImmediateValue::Literal8(val))?; },
                 Operand::Literal16(val) =>
-                    { buffer.add_immediate(ImmediateValue::Literal16(val)); },
+                    { buffer.add_immediate(ImmediateValue::Literal16(val))?; },
                 Operand::Literal32(val) =>
-                    { buffer.add_immediate(ImmediateValue::Literal32(val)); },
+                    { buffer.add_immediate(ImmediateValue::Literal32(val))?; },
                 Operand::Literal64(val) =>
-                    { buffer.add_immediate(ImmediateValue::Literal64(val)); },
+                    { buffer.add_immediate(ImmediateValue::Literal64(val))?; },
                 Operand::MemoryAndSegment16(seg, addr) =>
-                    { buffer.add_immediate(ImmediateValue::MemoryAndSegment16(seg, addr)); },
+                    { buffer.add_immediate(ImmediateValue::MemoryAndSegment16(seg, addr))?; },
                 Operand::MemoryAndSegment32(seg, addr) =>
-                    { buffer.add_immediate(ImmediateValue::MemoryAndSegment32(seg, addr)); },
+                    { buffer.add_immediate(ImmediateValue::MemoryAndSegment32(seg, addr))?; },
                 Operand::Offset(offset, ..) => { 
                     let imm_size = if let OperandType::Rel(enc_size) = def.op_type { enc_size }
                         else { addr_size };
@@ -284,18 +662,29 @@ pub fn encode_operand(buffer: &mut InstructionBuffer, def: &OperandDefinition, o
                         OperandSize::Word => { ImmediateValue::Literal16(offset as u16) },
                         OperandSize::Dword => { ImmediateValue::Literal32(offset as u32) },
                         OperandSize::Qword => { ImmediateValue::Literal64(offset) },
-                        _ => panic!("Internal error")
-                    });
+                        _ => return Err(InstructionEncodingError::MismatchedSize)
+                    })?;
                 },
                 Operand::Direct(reg) => 
-                    { buffer.add_immediate(ImmediateValue::Literal8(reg.get_reg_code() << 4)); }
-                _ => panic!("Invalid immediate operand: {:?}.", op)
+                    { buffer.add_immediate(ImmediateValue::Literal8(reg_code(reg)? << 4))?; }
+                Operand::Label(_) => {
+                    // Filled in once the address of the label is known.
+                    let imm_size = if let OperandType::Rel(enc_size) = def.op_type { enc_size }
+                        else { def.size };
+                    buffer.add_immediate(match imm_size {
+                        OperandSize::Word => ImmediateValue::Literal16(0),
+                        OperandSize::Dword => ImmediateValue::Literal32(0),
+                        OperandSize::Qword => ImmediateValue::Literal64(0),
+                        _ => return Err(InstructionEncodingError::MismatchedSize)
+                    })?;
+                },
+                _ => return Err(InstructionEncodingError::MismatchedEncoding)
             }
         },
         OperandEncoding::OpcodeAddend => {
             if let Some(Operand::Direct(reg)) = *op {
-                buffer.opcode_add = Some(reg.get_reg_code());
-            } else { panic!("Internal error."); }
+                buffer.opcode_add = Some(reg_code(reg)?);
+            } else { return Err(InstructionEncodingError::MismatchedEncoding); }
         },
         OperandEncoding::Fixed => {}
     }
@@ -303,12 +692,16 @@ pub fn encode_operand(buffer: &mut InstructionBuffer, def: &OperandDefinition, o
     Ok(())
 }
 
+fn reg_code(reg: Reg) -> Result<u8, InstructionEncodingError> {
+    reg.reg_code().ok_or(InstructionEncodingError::MismatchedEncoding)
+}
+
 fn encode_rm(buffer: &mut InstructionBuffer, op: &Operand, mode: Mode)
     -> Result<(), InstructionEncodingError> {
     match *op {
         Operand::Direct(reg) => {
             buffer.mod_rm_mod = Some(0b11);
-            buffer.mod_rm_rm = Some(reg.get_reg_code());
+            buffer.mod_rm_rm = Some(reg_code(reg)?);
             Ok(())
         },
         Operand::Indirect(base, ..) => {
@@ -329,16 +722,29 @@ fn encode_rm(buffer: &mut InstructionBuffer, op: &Operand, mode: Mode)
         Operand::Memory(addr, ..) | Operand::Offset(addr, ..) => { // TODO Should offset panic or be here?
             encode_indirect(buffer, None, None, None, addr, mode)
         },
+        Operand::LabelMemory(..) => {
+            // The displacement is filled in once the address of the label is known.
+            let base = if mode == Mode::Long { Some(Reg::RIP) } else { None };
+            encode_indirect(buffer, base, None, None, 0, mode)
+        },
+        Operand::Label(..) |
         Operand::Literal8(..) |
         Operand::Literal16(..) |
         Operand::Literal32(..) |
         Operand::Literal64(..) |
         Operand::MemoryAndSegment16(..) |
-        Operand::MemoryAndSegment32(..) => panic!("Invalid addressing.")
+        Operand::MemoryAndSegment32(..) => Err(InstructionEncodingError::InvalidAddressing)
     }
 }
 
 fn encode_indirect(buffer: &mut InstructionBuffer, base: Option<Reg>, index: Option<Reg>, scale: Option<RegScale>, displacement: u64, mode: Mode) -> Result<(), InstructionEncodingError> {
+    // Only general purpose registers (and the instruction pointer as a base) can address memory.
+    let is_ip = |reg: Reg| reg == Reg::RIP || reg == Reg::EIP;
+    if base.is_some_and(|b| !b.is_general() && !is_ip(b)) ||
+        index.is_some_and(|i| !i.is_general()) {
+        return Err(InstructionEncodingError::InvalidAddressing);
+    }
+
     match mode {
         Mode::Real => {
             encode_indirect_16(buffer, base, index, displacement).or_else(|_| {
@@ -377,12 +783,12 @@ fn encode_indirect_16(buffer: &mut InstructionBuffer, reg1: Option<Reg>, reg2: O
 
     buffer.mod_rm_rm = Some(rm);
 
-    if (displacement == 0) && !(reg1 == Some(Reg::BP) && reg2.is_none()) {
-        buffer.mod_rm_mod = Some(0);
-    } else if (rm == 6) && reg1.is_none() && reg2.is_none() {
+    if (rm == 6) && reg1.is_none() && reg2.is_none() {
         buffer.mod_rm_mod = Some(0);
         buffer.displacement = Some(ImmediateValue::Literal16(displacement as u16));
-    } else if displacement < 128 as u64 {
+    } else if (displacement == 0) && !(reg1 == Some(Reg::BP) && reg2.is_none()) {
+        buffer.mod_rm_mod = Some(0);
+    } else if (-128..128).contains(&(displacement as i64)) {
         buffer.mod_rm_mod = Some(1);
         buffer.displacement = Some(ImmediateValue::Literal8(displacement as u8));
     } else {
@@ -396,7 +802,7 @@ fn encode_indirect_16(buffer: &mut InstructionBuffer, reg1: Option<Reg>, reg2: O
 fn encode_indirect_32(buffer: &mut InstructionBuffer, base: Option<Reg>, index: Option<Reg>, scale: Option<RegScale>, displacement: u64, mode: Mode) -> Result<(), InstructionEncodingError> {
     fn disp_helper(buffer: &mut InstructionBuffer, disp: u64) {
         if disp == 0 { buffer.mod_rm_mod = Some(0); }
-        else if disp <= 128 as u64 {
+        else if (-128..128).contains(&(disp as i64)) {
             buffer.mod_rm_mod = Some(1);
             buffer.displacement = Some(ImmediateValue::Literal8(disp as u8));
         }
@@ -411,18 +817,17 @@ fn encode_indirect_32(buffer: &mut InstructionBuffer, base: Option<Reg>, index:
     if real_scale != RegScale::One && index.is_none() { return Err(InstructionEncodingError::InvalidAddressing); }
 
     match base {
-        Some(Reg::EAX) | Some(Reg::EBX) | Some(Reg::ECX) |
-        Some(Reg::EDX) | Some(Reg::ESI) | Some(Reg::EDI) => {
+        Some(base_reg @ (Reg::EAX | Reg::EBX | Reg::ECX | Reg::EDX | Reg::ESI | Reg::EDI)) => {
             match index {
                 Some(index_reg) if index_reg != Reg::ESP => {
                     buffer.mod_rm_rm = Some(4); // Force SIB
-                    buffer.sib_base = Some(base.unwrap().get_reg_code());
+                    buffer.sib_base = Some(base_reg.get_reg_code());
                     buffer.sib_index = Some(index_reg.get_reg_code());
                     buffer.sib_scale = Some(real_scale.get_sib_code());
                     disp_helper(buffer, displacement);
                 },
                 None => {
-                    buffer.mod_rm_rm = Some(base.unwrap().get_reg_code());
+                    buffer.mod_rm_rm = Some(base_reg.get_reg_code());
                     disp_helper(buffer, displacement);
                 },
                 _ => return Err(InstructionEncodingError::InvalidAddressing)
@@ -433,7 +838,7 @@ fn encode_indirect_32(buffer: &mut InstructionBuffer, base: Option<Reg>, index:
                 Some(index_reg) if index_reg != Reg::ESP => {
                     // Mode 0 for EBP is reserved for index*scale + disp32, so we'll encode it with
                     // mode 1 or 2.
-                    if displacement < 128 as u64 {
+                    if (-128..128).contains(&(displacement as i64)) {
                         buffer.mod_rm_mod = Some(1); 
                         buffer.displacement = Some(ImmediateValue::Literal8(displacement as u8));
                     }
@@ -450,7 +855,7 @@ fn encode_indirect_32(buffer: &mut InstructionBuffer, base: Option<Reg>, index:
                     // Mode 0 for EBP means displacement (or EIP+displacement in long mode), so use
                     // mode 1 or 2.
                     buffer.mod_rm_rm = base.map(|b| b.get_reg_code());
-                    if displacement < 128 as u64 {
+                    if (-128..128).contains(&(displacement as i64)) {
                         buffer.mod_rm_mod = Some(1);
                         buffer.displacement = Some(ImmediateValue::Literal8(displacement as u8));
                     }
@@ -462,11 +867,11 @@ fn encode_indirect_32(buffer: &mut InstructionBuffer, base: Option<Reg>, index:
                 _ => return Err(InstructionEncodingError::InvalidAddressing)
             }
         },
-        Some(Reg::ESP) => {
+        Some(base_reg @ Reg::ESP) => {
             buffer.mod_rm_rm = Some(4); // Force SIB
             match index {
                 Some(index_reg) if index_reg != Reg::ESP => {
-                    buffer.sib_base = Some(base.unwrap().get_reg_code());
+                    buffer.sib_base = Some(base_reg.get_reg_code());
                     buffer.sib_index = Some(index_reg.get_reg_code());
                     buffer.sib_scale = Some(real_scale.get_sib_code());
                     disp_helper(buffer, displacement);
@@ -515,7 +920,7 @@ fn encode_indirect_32(buffer: &mut InstructionBuffer, base: Option<Reg>, index:
 fn encode_indirect_64(buffer: &mut InstructionBuffer, base: Option<Reg>, index: Option<Reg>, scale: Option<RegScale>, displacement: u64) -> Result<(), InstructionEncodingError> {
     fn disp_helper(buffer: &mut InstructionBuffer, disp: u64) {
         if disp == 0 { buffer.mod_rm_mod = Some(0); }
-        else if disp <= 128 as u64 {
+        else if (-128..128).contains(&(disp as i64)) {
             buffer.mod_rm_mod = Some(1);
             buffer.displacement = Some(ImmediateValue::Literal8(disp as u8));
         }
@@ -530,18 +935,17 @@ fn encode_indirect_64(buffer: &mut InstructionBuffer, base: Option<Reg>, index:
     if real_scale != RegScale::One && index.is_none() { return Err(InstructionEncodingError::InvalidAddressing); }
 
     match base {
-        Some(Reg::RAX) | Some(Reg::RBX) | Some(Reg::RCX) |
-        Some(Reg::RDX) | Some(Reg::RSI) | Some(Reg::RDI) => {
+        Some(base_reg @ (Reg::RAX | Reg::RBX | Reg::RCX | Reg::RDX | Reg::RSI | Reg::RDI)) => {
             match index {
                 Some(index_reg) if index_reg != Reg::RSP => {
                     buffer.mod_rm_rm = Some(4); // Force SIB
-                    buffer.sib_base = Some(base.unwrap().get_reg_code());
+                    buffer.sib_base = Some(base_reg.get_reg_code());
                     buffer.sib_index = Some(index_reg.get_reg_code());
                     buffer.sib_scale = Some(real_scale.get_sib_code());
                     disp_helper(buffer, displacement);
                 },
                 None => {
-                    buffer.mod_rm_rm = Some(base.unwrap().get_reg_code());
+                    buffer.mod_rm_rm = Some(base_reg.get_reg_code());
                     disp_helper(buffer, displacement);
                 },
                 _ => return Err(InstructionEncodingError::InvalidAddressing)
@@ -552,7 +956,7 @@ fn encode_indirect_64(buffer: &mut InstructionBuffer, base: Option<Reg>, index:
                 Some(index_reg) if index_reg != Reg::RSP => {
                     // Mode 0 for EBP is reserved for index*scale + disp32, so we'll encode it with
                     // mode 1 or 2.
-                    if displacement < 128 as u64 {
+                    if (-128..128).contains(&(displacement as i64)) {
                         buffer.mod_rm_mod = Some(1); 
                         buffer.displacement = Some(ImmediateValue::Literal8(displacement as u8));
                     }
@@ -569,7 +973,7 @@ fn encode_indirect_64(buffer: &mut InstructionBuffer, base: Option<Reg>, index:
                     // Mode 0 for EBP means displacement (or EIP+displacement in long mode), so use
                     // mode 1 or 2.
                     buffer.mod_rm_rm = base.map(|b| b.get_reg_code());
-                    if displacement < 128 as u64 {
+                    if (-128..128).contains(&(displacement as i64)) {
                         buffer.mod_rm_mod = Some(1);
                         buffer.displacement = Some(ImmediateValue::Literal8(displacement as u8));
                     }
@@ -581,11 +985,11 @@ fn encode_indirect_64(buffer: &mut InstructionBuffer, base: Option<Reg>, index:
                 _ => return Err(InstructionEncodingError::InvalidAddressing)
             }
         },
-        Some(Reg::RSP) => {
+        Some(base_reg @ Reg::RSP) => {
             buffer.mod_rm_rm = Some(4); // Force SIB
             match index {
                 Some(index_reg) if index_reg != Reg::RSP => {
-                    buffer.sib_base = Some(base.unwrap().get_reg_code());
+                    buffer.sib_base = Some(base_reg.get_reg_code());
                     buffer.sib_index = Some(index_reg.get_reg_code());
                     buffer.sib_scale = Some(real_scale.get_sib_code());
                     disp_helper(buffer, displacement);
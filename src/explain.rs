@@ -0,0 +1,321 @@
+use std::error::Error;
+use std::fmt;
+use ::{Instruction, InstructionDecodingError, InstructionEncodingError, Mode, Operand, Reg};
+use ::decoding::{decode_traced, FieldKind};
+use ::instruction_def::{InstructionDefinition, OperandEncoding, OperandSizePrefixBehavior, OperandType,
+    PrefixBehavior};
+
+// A field of an encoded instruction and what it means, i.e. a REX prefix with "W=1 (64-bit
+// operand size)", "R=0", "X=0" and "B=0".
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExplainedField {
+    pub kind: FieldKind,
+    pub offset: usize, // From the start of the instruction
+    pub bytes: Vec<u8>,
+    pub notes: Vec<String>,
+}
+
+// An instruction broken down into its fields, in the order they're encoded.
+#[derive(Clone, Debug)]
+pub struct Explanation {
+    pub instruction: Instruction,
+    pub definition: &'static InstructionDefinition,
+    pub fields: Vec<ExplainedField>,
+}
+
+impl Explanation {
+    pub fn len(&self) -> usize { self.fields.iter().map(|f| f.bytes.len()).sum() }
+
+    pub fn is_empty(&self) -> bool { self.fields.is_empty() }
+}
+
+// One field per line: the bytes, the kind of field and the notes, i.e.
+//   48           REX     W=1 (64-bit operand size), R=0, X=0, B=0
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}  ; {}", self.instruction, self.definition)?;
+        for field in self.fields.iter() {
+            let hex: Vec<_> = field.bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            writeln!(f, "  {:<24} {:<8} {}", hex.join(" "), field.kind, field.notes.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum ExplainError {
+    // Encoding - The instruction couldn't be encoded.
+    Encoding(InstructionEncodingError),
+
+    // Decoding - The bytes don't start with a valid instruction.
+    Decoding(InstructionDecodingError),
+}
+
+impl fmt::Display for ExplainError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ExplainError::Encoding(ref e) => write!(f, "{}", e),
+            ExplainError::Decoding(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for ExplainError { }
+
+// Breaks down the first instruction in `bytes`, annotating the prefixes, the REX/VEX/EVEX bits,
+// the opcode, the ModR/M and SIB fields (with the registers they select), the displacement and
+// the immediates.
+pub fn explain(bytes: &[u8], mode: Mode) -> Result<Explanation, ExplainError> {
+    let (instr, _, trace) = decode_traced(bytes, mode).map_err(ExplainError::Decoding)?;
+    let def = trace.def.expect("A decoded instruction has a definition.");
+    let address_override = trace.fields.iter()
+        .any(|&(kind, offset, _)| kind == FieldKind::Prefix && bytes[offset] == 0x67);
+    let context = Context { instr: &instr, def, mode, address_override };
+
+    // The escape and opcode bytes are read one at a time, but are explained together.
+    let mut fields: Vec<(FieldKind, usize, usize)> = Vec::new();
+    for &(kind, offset, len) in trace.fields.iter() {
+        match fields.last_mut() {
+            Some(last) if last.0 == FieldKind::Opcode && kind == FieldKind::Opcode => last.2 += len,
+            _ => fields.push((kind, offset, len))
+        }
+    }
+
+    let mut immediates = def.operands.iter().enumerate()
+        .filter(|&(_, op)| op.as_ref().is_some_and(|op| op.encoding == OperandEncoding::Imm))
+        .map(|(i, _)| i);
+    let explained = fields.into_iter().map(|(kind, offset, len)| {
+        let field = &bytes[offset..offset + len];
+        let notes = match kind {
+            FieldKind::Prefix => vec![context.prefix(field[0])],
+            FieldKind::Rex => context.rex(field[0]),
+            FieldKind::Vex => context.vex(field),
+            FieldKind::Evex => context.evex(field),
+            FieldKind::Opcode => context.opcode(field),
+            FieldKind::ModRm => context.mod_rm(field[0]),
+            FieldKind::Sib => context.sib(field[0], bytes[offset - 1]),
+            FieldKind::Displacement => vec![format!("disp{} = {}", len * 8, signed(field))],
+            FieldKind::Immediate => context.immediate(field, immediates.next()),
+        };
+        ExplainedField { kind, offset, bytes: field.to_vec(), notes }
+    }).collect();
+
+    Ok(Explanation { instruction: instr, definition: def, fields: explained })
+}
+
+pub fn explain_encoding(instr: &Instruction, mode: Mode) -> Result<Explanation, ExplainError> {
+    let mut bytes = Vec::new();
+    instr.encode(&mut bytes, mode).map_err(ExplainError::Encoding)?;
+    explain(&bytes, mode)
+}
+
+struct Context<'a> {
+    instr: &'a Instruction,
+    def: &'static InstructionDefinition,
+    mode: Mode,
+    address_override: bool, // Whether there's a 0x67 prefix
+}
+
+impl<'a> Context<'a> {
+    fn prefix(&self, byte: u8) -> String {
+        let mandatory = |behavior| if behavior == PrefixBehavior::Always { " (mandatory)" } else { "" };
+        match byte {
+            0xF0 => "LOCK".to_owned(),
+            0xF2 => format!("REPNE{}", mandatory(self.def.f2_prefix)),
+            0xF3 => format!("REP{}", mandatory(self.def.f3_prefix)),
+            0x66 if self.def.operand_size_prefix == OperandSizePrefixBehavior::Always =>
+                "operand size (mandatory)".to_owned(),
+            0x66 => format!("operand size override ({}-bit operands)",
+                if self.mode == Mode::Real { 32 } else { 16 }),
+            0x67 => format!("address size override ({}-bit addressing)", match self.mode {
+                Mode::Real => 32, Mode::Protected => 16, Mode::Long => 32 }),
+            0x2E => "CS segment override".to_owned(),
+            0x36 => "SS segment override".to_owned(),
+            0x3E => "DS segment override".to_owned(),
+            0x26 => "ES segment override".to_owned(),
+            0x64 => "FS segment override".to_owned(),
+            0x65 => "GS segment override".to_owned(),
+            _ => format!("{:02X}", byte)
+        }
+    }
+
+    fn rex(&self, byte: u8) -> Vec<String> {
+        vec![
+            format!("W={}{}", bit(byte, 3), if byte & 0x8 != 0 { " (64-bit operand size)" } else { "" }),
+            format!("R={}", bit(byte, 2)),
+            format!("X={}", bit(byte, 1)),
+            format!("B={}", bit(byte, 0)),
+        ]
+    }
+
+    // The fields that are stored inverted are shown with a bar, as in the Intel manual.
+    fn vex(&self, bytes: &[u8]) -> Vec<String> {
+        let mut notes = Vec::new();
+        let last = bytes[bytes.len() - 1];
+        notes.push(format!("R\u{305}={}", bit(bytes[1], 7)));
+        if bytes[0] == 0xC4 {
+            notes.push(format!("X\u{305}={}", bit(bytes[1], 6)));
+            notes.push(format!("B\u{305}={}", bit(bytes[1], 5)));
+            notes.push(format!("mmmmm={:05b} ({})", bytes[1] & 0x1F, opcode_map(bytes[1] & 0x1F)));
+            notes.push(format!("W={}", bit(last, 7)));
+        }
+        notes.push(self.vvvv(last));
+        notes.push(format!("L={} ({}-bit)", bit(last, 2), if last & 0x4 != 0 { 256 } else { 128 }));
+        notes.push(pp(last));
+        notes
+    }
+
+    fn evex(&self, bytes: &[u8]) -> Vec<String> {
+        let (p0, p1, p2) = (bytes[1], bytes[2], bytes[3]);
+        vec![
+            format!("R\u{305}={}", bit(p0, 7)),
+            format!("X\u{305}={}", bit(p0, 6)),
+            format!("B\u{305}={}", bit(p0, 5)),
+            format!("R\u{305}'={}", bit(p0, 4)),
+            format!("mm={:02b} ({})", p0 & 0x3, opcode_map(p0 & 0x3)),
+            format!("W={}", bit(p1, 7)),
+            self.vvvv(p1),
+            pp(p1),
+            format!("z={}{}", bit(p2, 7), if p2 & 0x80 != 0 { " (zeroing)" } else { " (merging)" }),
+            format!("L'L={:02b}{}", (p2 >> 5) & 0x3, if self.instr.rounding_mode.is_some() {
+                " (rounding)".to_owned()
+            } else {
+                format!(" ({}-bit)", 128 << ((p2 >> 5) & 0x3))
+            }),
+            format!("b={}{}", bit(p2, 4), if p2 & 0x10 == 0 { "" }
+                else if self.instr.broadcast.is_some() { " (broadcast)" }
+                else { " (rounding/SAE)" }),
+            format!("V\u{305}'={}", bit(p2, 3)),
+            format!("aaa={:03b}{}", p2 & 0x7, if p2 & 0x7 == 0 { " (no mask)".to_owned() }
+                else { format!(" (k{})", p2 & 0x7) }),
+        ]
+    }
+
+    fn vvvv(&self, byte: u8) -> String {
+        let reg = self.register(OperandEncoding::Vex)
+            .map_or("unused".to_owned(), |r| Operand::Direct(r).to_string());
+        format!("v\u{305}v\u{305}v\u{305}v\u{305}={:04b} ({})", (byte >> 3) & 0xF, reg)
+    }
+
+    fn opcode(&self, bytes: &[u8]) -> Vec<String> {
+        let mut notes = vec![self.def.to_string()];
+        if let Some(reg) = self.register(OperandEncoding::OpcodeAddend) {
+            notes.push(format!("+{} ({})", bytes[bytes.len() - 1] & 0x7, Operand::Direct(reg)));
+        }
+        notes
+    }
+
+    fn mod_rm(&self, byte: u8) -> Vec<String> {
+        let (mod_, reg, rm) = (byte >> 6, (byte >> 3) & 0x7, byte & 0x7);
+        let reg_note = match self.def.opcode_ext {
+            Some(ext) => format!("opcode extension /{}", ext),
+            None => self.register(OperandEncoding::ModRmReg)
+                .map_or("unused".to_owned(), |r| Operand::Direct(r).to_string())
+        };
+        let rm_note = if mod_ == 0b11 {
+            self.register(OperandEncoding::ModRmRm)
+                .map_or("unused".to_owned(), |r| Operand::Direct(r).to_string())
+        } else if self.address_bits() == 16 {
+            let base = ["bx+si", "bx+di", "bp+si", "bp+di", "si", "di", "bp", "bx"][rm as usize];
+            match (mod_, rm) {
+                (0b00, 0b110) => "disp16".to_owned(),
+                (0b00, _) => format!("[{}]", base),
+                (0b01, _) => format!("[{}+disp8]", base),
+                _ => format!("[{}+disp16]", base),
+            }
+        } else {
+            let disp = match mod_ { 0b00 => "", 0b01 => "+disp8", _ => "+disp32" };
+            match (mod_, rm) {
+                (_, 0b100) => "SIB follows".to_owned(),
+                (0b00, 0b101) if self.mode == Mode::Long => "[rip+disp32]".to_owned(),
+                (0b00, 0b101) => "[disp32]".to_owned(),
+                _ => format!("[{}{}]", self.memory_registers().0
+                    .map_or("?".to_owned(), |r| Operand::Direct(r).to_string()), disp)
+            }
+        };
+        vec![
+            format!("mod={:02b}{}", mod_, if mod_ == 0b11 { " (register)" } else { " (memory)" }),
+            format!("reg={:03b} ({})", reg, reg_note),
+            format!("rm={:03b} ({})", rm, rm_note),
+        ]
+    }
+
+    fn sib(&self, byte: u8, mod_rm: u8) -> Vec<String> {
+        let (scale, index, base) = (byte >> 6, (byte >> 3) & 0x7, byte & 0x7);
+        let (base_reg, index_reg) = self.memory_registers();
+        let name = |reg: Option<Reg>| reg.map_or("none".to_owned(), |r| Operand::Direct(r).to_string());
+        vec![
+            format!("scale={:02b} (*{})", scale, 1 << scale),
+            format!("index={:03b} ({})", index, name(index_reg)),
+            format!("base={:03b} ({})", base, if base == 0b101 && mod_rm >> 6 == 0 {
+                "none, disp32".to_owned()
+            } else { name(base_reg) }),
+        ]
+    }
+
+    fn immediate(&self, bytes: &[u8], index: Option<usize>) -> Vec<String> {
+        let bits = bytes.len() * 8;
+        let op_type = index.and_then(|i| self.def.operands[i].as_ref()).map(|op| &op.op_type);
+        let operand = index.and_then(|i| *self.instr.operands()[i]);
+        let text = operand.map_or(String::new(), |op| op.to_string());
+        vec![match (op_type, operand) {
+            (Some(&OperandType::Rel(_)), _) => format!("rel{} = {}", bits, signed(bytes)),
+            (Some(&OperandType::Reg(_)), _) => format!("is4 = {}", text),
+            (_, Some(Operand::MemoryAndSegment16(..))) |
+            (_, Some(Operand::MemoryAndSegment32(..))) => format!("ptr16:{} = {}", bits - 16, text),
+            _ => format!("imm{} = 0x{:x}", bits, unsigned(bytes))
+        }]
+    }
+
+    // The register an operand with the given encoding selects.
+    fn register(&self, encoding: OperandEncoding) -> Option<Reg> {
+        let index = self.def.operands.iter()
+            .position(|op| op.as_ref().is_some_and(|op| op.encoding == encoding))?;
+        match *self.instr.operands()[index] {
+            Some(Operand::Direct(reg)) => Some(reg),
+            _ => None
+        }
+    }
+
+    // The base and index registers of the memory operand.
+    fn memory_registers(&self) -> (Option<Reg>, Option<Reg>) {
+        self.instr.operands().iter().filter_map(|op| match **op {
+            Some(Operand::Indirect(base, ..)) |
+            Some(Operand::IndirectDisplaced(base, ..)) => Some((Some(base), None)),
+            Some(Operand::IndirectScaledIndexed(base, index, ..)) |
+            Some(Operand::IndirectScaledIndexedDisplaced(base, index, ..)) =>
+                Some((Some(base), Some(index))),
+            Some(Operand::IndirectScaledDisplaced(index, ..)) => Some((None, Some(index))),
+            Some(Operand::Memory(..)) => Some((None, None)),
+            _ => None
+        }).next().unwrap_or((None, None))
+    }
+
+    fn address_bits(&self) -> u32 {
+        match (self.mode, self.address_override) {
+            (Mode::Real, false) | (Mode::Protected, true) => 16,
+            (Mode::Long, false) => 64,
+            _ => 32
+        }
+    }
+}
+
+fn bit(byte: u8, bit: u8) -> u8 { (byte >> bit) & 1 }
+
+fn pp(byte: u8) -> String {
+    format!("pp={:02b} ({})", byte & 0x3, ["none", "66", "F3", "F2"][(byte & 0x3) as usize])
+}
+
+fn opcode_map(map: u8) -> &'static str {
+    match map { 1 => "0F", 2 => "0F 38", 3 => "0F 3A", _ => "reserved" }
+}
+
+fn unsigned(bytes: &[u8]) -> u64 {
+    bytes.iter().rev().fold(0, |value, b| value << 8 | *b as u64)
+}
+
+fn signed(bytes: &[u8]) -> String {
+    let shift = 64 - 8 * bytes.len() as u32;
+    let value = ((unsigned(bytes) << shift) as i64) >> shift;
+    if value < 0 { format!("-0x{:x}", value.unsigned_abs()) } else { format!("0x{:x}", value) }
+}
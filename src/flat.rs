@@ -0,0 +1,77 @@
+use std::io::Write;
+use ::{FixupKind, InstructionWriter, Label, ObjectError};
+
+// A flat binary image, i.e. a boot sector or a DOS .COM program: the code exactly as it's loaded,
+// without headers or relocations. Labels resolve to addresses relative to the origin.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FlatBinary {
+    // The address the image is loaded at (ORG).
+    pub origin: u64,
+
+    // The size the image is padded to with zeros, including the signature.
+    pub size: Option<u64>,
+
+    // The largest the image can be, if it isn't padded to a size.
+    pub max_size: Option<u64>,
+
+    // Bytes placed at the very end of the image.
+    pub signature: Vec<u8>,
+}
+
+impl FlatBinary {
+    pub fn new(origin: u64) -> FlatBinary {
+        FlatBinary { origin, size: None, max_size: None, signature: Vec::new() }
+    }
+
+    // A 512 byte boot sector loaded at 0000:7C00, ending with the 0x55 0xAA boot signature.
+    pub fn boot_sector() -> FlatBinary {
+        FlatBinary {
+            origin: 0x7C00, size: Some(512), max_size: None, signature: vec![0x55, 0xAA]
+        }
+    }
+
+    // The address a label is at once the image is loaded.
+    pub fn get_label_address(&self, code: &InstructionWriter<Vec<u8>>, label: Label)
+        -> Option<u64> {
+        code.get_label_offset(label).map(|offset| self.origin + offset)
+    }
+
+    // A DOS .COM program, loaded at offset 0x100 of its segment after the PSP. It can be up to
+    // 0xFF00 bytes long, with the stack at the end of the segment.
+    pub fn com() -> FlatBinary {
+        FlatBinary { origin: 0x100, size: None, max_size: Some(0xFF00), signature: Vec::new() }
+    }
+
+    // Resolves every fixup of the code and builds the image.
+    pub fn build(&self, code: &InstructionWriter<Vec<u8>>) -> Result<Vec<u8>, ObjectError> {
+        let fixups = code.get_fixups().map_err(|_| ObjectError::Unflushed)?;
+        let mut bytes = code.get_inner_writer_ref().clone();
+        for fixup in fixups {
+            // The segment the image is loaded at isn't known.
+            if fixup.kind == FixupKind::Segment {
+                return Err(ObjectError::UnsupportedRelocation(fixup.kind, fixup.size));
+            }
+            let target = self.get_label_address(code, fixup.label)
+                .ok_or(ObjectError::UndefinedLabel(fixup.label))?;
+            fixup.apply(&mut bytes, target, self.origin)
+                .map_err(|_| ObjectError::OutOfRange(fixup.label))?;
+        }
+
+        let len = (bytes.len() + self.signature.len()) as u64;
+        if let Some(size) = self.size.or(self.max_size) {
+            if len > size { return Err(ObjectError::TooLarge(len)); }
+        }
+        if let Some(size) = self.size {
+            bytes.resize((size as usize) - self.signature.len(), 0);
+        }
+        bytes.extend_from_slice(&self.signature);
+        Ok(bytes)
+    }
+
+    pub fn write<W: Write>(&self, code: &InstructionWriter<Vec<u8>>, writer: &mut W)
+        -> Result<usize, ObjectError> {
+        let bytes = self.build(code)?;
+        writer.write_all(&bytes).map_err(|_| ObjectError::WriteFailed)?;
+        Ok(bytes.len())
+    }
+}
@@ -0,0 +1,131 @@
+use ::{Instruction, Label, Mnemonic, Operand};
+use ::condition::{jcc_condition, Condition};
+
+// Describes how an instruction affects the flow of execution.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FlowKind {
+    // Fallthrough - Execution continues with the next instruction.
+    Fallthrough,
+
+    // Jump - Unconditional direct jump (relative or absolute far).
+    Jump(BranchTarget),
+
+    // ConditionalJump - Direct jump taken only when the condition holds (Jcc, JCXZ, LOOP).
+    ConditionalJump(BranchCondition, BranchTarget),
+
+    // Call - Direct call (relative or absolute far).
+    Call(BranchTarget),
+
+    // IndirectJump - Jump through a register or memory operand.
+    IndirectJump(BranchTarget),
+
+    // IndirectCall - Call through a register or memory operand.
+    IndirectCall(BranchTarget),
+
+    // Return - Return to the caller (RET, IRET, SYSRET, SYSEXIT).
+    Return,
+
+    // Interrupt - Transfers control to the system (INT, SYSCALL, SYSENTER, UD2).
+    Interrupt,
+}
+
+impl FlowKind {
+    pub fn target(&self) -> Option<BranchTarget> {
+        match *self {
+            FlowKind::Jump(target) |
+            FlowKind::ConditionalJump(_, target) |
+            FlowKind::Call(target) |
+            FlowKind::IndirectJump(target) |
+            FlowKind::IndirectCall(target) => Some(target),
+            FlowKind::Fallthrough |
+            FlowKind::Return |
+            FlowKind::Interrupt => None
+        }
+    }
+
+    pub fn is_branch(&self) -> bool {
+        *self != FlowKind::Fallthrough
+    }
+
+    pub fn is_indirect(&self) -> bool {
+        matches!(*self, FlowKind::IndirectJump(_) | FlowKind::IndirectCall(_))
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BranchTarget {
+    // Relative - Displacement from the end of the instruction.
+    Relative(i64),
+
+    // Far - Absolute segment:offset pointer (from MemoryAndSegment16/32).
+    Far(u16, u32),
+
+    // Indirect - Target is read from a register or memory operand.
+    Indirect(Operand),
+
+    // Label - Target is a label that hasn't been laid out yet.
+    Label(Label),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BranchCondition {
+    // Flags - Jcc, taken when the condition code holds.
+    Flags(Condition),
+
+    // CounterZero - JCXZ/JECXZ/JRCXZ, taken when the counter register is zero.
+    CounterZero,
+
+    // Loop - LOOP/LOOPE/LOOPNE, decrements the counter and is taken when it is non-zero (and the
+    // optional condition holds).
+    Loop(Option<Condition>),
+}
+
+pub fn flow_kind(instr: &Instruction) -> FlowKind {
+    if let Some(cond) = jcc_condition(instr.mnemonic) {
+        return conditional(BranchCondition::Flags(cond), instr);
+    }
+
+    match instr.mnemonic {
+        Mnemonic::JMP => match branch_target(instr) {
+            Some(target @ BranchTarget::Indirect(_)) => FlowKind::IndirectJump(target),
+            Some(target) => FlowKind::Jump(target),
+            None => FlowKind::Fallthrough
+        },
+        Mnemonic::CALL => match branch_target(instr) {
+            Some(target @ BranchTarget::Indirect(_)) => FlowKind::IndirectCall(target),
+            Some(target) => FlowKind::Call(target),
+            None => FlowKind::Fallthrough
+        },
+        Mnemonic::JCXZ | Mnemonic::JECXZ | Mnemonic::JRCXZ =>
+            conditional(BranchCondition::CounterZero, instr),
+        Mnemonic::LOOP => conditional(BranchCondition::Loop(None), instr),
+        Mnemonic::LOOPE => conditional(BranchCondition::Loop(Some(Condition::E)), instr),
+        Mnemonic::LOOPNE => conditional(BranchCondition::Loop(Some(Condition::NE)), instr),
+        Mnemonic::RET | Mnemonic::IRET | Mnemonic::IRETD | Mnemonic::IRETQ |
+        Mnemonic::SYSRET | Mnemonic::SYSEXIT => FlowKind::Return,
+        Mnemonic::INT | Mnemonic::INTO | Mnemonic::SYSCALL | Mnemonic::SYSENTER |
+        Mnemonic::UD0 | Mnemonic::UD1 | Mnemonic::UD2 => FlowKind::Interrupt,
+        _ => FlowKind::Fallthrough
+    }
+}
+
+fn conditional(cond: BranchCondition, instr: &Instruction) -> FlowKind {
+    match branch_target(instr) {
+        Some(target) => FlowKind::ConditionalJump(cond, target),
+        None => FlowKind::Fallthrough
+    }
+}
+
+fn branch_target(instr: &Instruction) -> Option<BranchTarget> {
+    instr.operand1.map(|op| match op {
+        Operand::Offset(offset, ..) => BranchTarget::Relative(offset as i64),
+        Operand::Literal8(val) => BranchTarget::Relative(val as i8 as i64),
+        Operand::Literal16(val) => BranchTarget::Relative(val as i16 as i64),
+        Operand::Literal32(val) => BranchTarget::Relative(val as i32 as i64),
+        Operand::Literal64(val) => BranchTarget::Relative(val as i64),
+        Operand::MemoryAndSegment16(seg, addr) => BranchTarget::Far(seg, addr as u32),
+        Operand::MemoryAndSegment32(seg, addr) => BranchTarget::Far(seg, addr),
+        Operand::Label(label) => BranchTarget::Label(label),
+        _ => BranchTarget::Indirect(op)
+    })
+}
@@ -1,8 +1,12 @@
+use std::fmt;
 use std::io::Write;
 use ::{InstructionEncodingError, Mnemonic, Mode};
-use ::encoding::{encode};
-use ::instruction_def::{find_instruction_def};
+use ::encoding::{encode, InstructionLayout};
+use ::explain::{explain_encoding, ExplainError, Explanation};
+use ::flow::{flow_kind, FlowKind};
+use ::instruction_def::{find_instruction_def, InstructionDefinition};
 use ::operand::{Operand, OperandSize};
+use ::validation::{validate, ValidationError};
 
 #[derive(Copy, Clone, Debug)]
 pub struct Instruction {
@@ -73,9 +77,35 @@ impl Instruction {
 
     pub fn encode<W>(&self, writer: &mut W, mode: Mode) -> Result<usize, InstructionEncodingError> 
         where W: Write {
+        self.encode_layout(writer, mode).map(|layout| layout.len)
+    }
+
+    // Encodes the instruction, returning where its displacement, immediates and label fixups are.
+    pub fn encode_layout<W>(&self, writer: &mut W, mode: Mode)
+        -> Result<InstructionLayout, InstructionEncodingError> where W: Write {
         let enc = find_instruction_def(&self, mode)?;
         encode(writer, enc, &self, mode)
     }
+
+    // The form of the instruction that encoding it in the given mode would use.
+    pub fn definition(&self, mode: Mode)
+        -> Result<&'static InstructionDefinition, InstructionEncodingError> {
+        find_instruction_def(self, mode)
+    }
+
+    // Encodes the instruction and breaks the bytes down field by field, as `explain` does.
+    pub fn explain_encoding(&self, mode: Mode) -> Result<Explanation, ExplainError> {
+        explain_encoding(self, mode)
+    }
+
+    pub fn flow_kind(&self) -> FlowKind {
+        flow_kind(self)
+    }
+
+    // Checks if the instruction can be encoded in the given mode, with a detailed error if not.
+    pub fn validate(&self, mode: Mode) -> Result<(), ValidationError> {
+        validate(self, mode)
+    }
 }
 
 impl Default for Instruction {
@@ -115,6 +145,35 @@ impl PartialEq for Instruction {
 
 impl Eq for Instruction { }
 
+// Formats the instruction in Intel syntax, i.e. "add dword ptr [ebp+0x8], eax". AVX-512 masks and
+// broadcasts follow their operand, and embedded rounding comes last.
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.lock { f.write_str("lock ")?; }
+        write!(f, "{}", format!("{:?}", self.mnemonic).to_lowercase())?;
+        for (i, op) in self.operands().iter().filter_map(|op| op.as_ref()).enumerate() {
+            write!(f, "{}{}", if i == 0 { " " } else { ", " }, op)?;
+            if i == 0 {
+                if let Some(mask) = self.mask.filter(|m| *m != MaskReg::K0) {
+                    write!(f, "{{k{}}}", mask.get_reg_code())?;
+                }
+                if self.merge_mode == Some(MergeMode::Zero) { f.write_str("{z}")?; }
+            }
+            if let Some(broadcast) = self.broadcast.filter(|_| op.is_memory()) {
+                write!(f, "{{1to{}}}", broadcast.get_multiplier())?;
+            }
+        }
+        match self.rounding_mode {
+            Some(RoundingMode::Nearest) => f.write_str(", {rn-sae}"),
+            Some(RoundingMode::Down) => f.write_str(", {rd-sae}"),
+            Some(RoundingMode::Up) => f.write_str(", {ru-sae}"),
+            Some(RoundingMode::Zero) => f.write_str(", {rz-sae}"),
+            None if self.sae => f.write_str(", {sae}"),
+            None => Ok(())
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum RoundingMode {
     Nearest,
@@ -183,6 +242,12 @@ pub enum SegmentReg {
     SS
 }
 
+impl fmt::Display for SegmentReg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&format!("{:?}", self).to_lowercase())
+    }
+}
+
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum Reg {
     AL, AH, AX, EAX, RAX,
@@ -252,16 +317,62 @@ pub enum Reg {
     BND0, BND1, BND2, BND3
 }
 
+// Every register, in declaration order.
+static ALL_REGS: [Reg; 234] = [
+    Reg::AL, Reg::AH, Reg::AX, Reg::EAX, Reg::RAX, Reg::BL, Reg::BH, Reg::BX, Reg::EBX, Reg::RBX,
+    Reg::CL, Reg::CH, Reg::CX, Reg::ECX, Reg::RCX, Reg::DL, Reg::DH, Reg::DX, Reg::EDX, Reg::RDX,
+    Reg::BP, Reg::EBP, Reg::RBP, Reg::BPL, Reg::SP, Reg::ESP, Reg::RSP, Reg::SPL, Reg::SI, Reg::ESI,
+    Reg::RSI, Reg::SIL, Reg::DI, Reg::EDI, Reg::RDI, Reg::DIL, Reg::IP, Reg::EIP, Reg::RIP, Reg::R8,
+    Reg::R8D, Reg::R8W, Reg::R8B, Reg::R9, Reg::R9D, Reg::R9W, Reg::R9B, Reg::R10, Reg::R10D,
+    Reg::R10W, Reg::R10B, Reg::R11, Reg::R11D, Reg::R11W, Reg::R11B, Reg::R12, Reg::R12D, Reg::R12W,
+    Reg::R12B, Reg::R13, Reg::R13D, Reg::R13W, Reg::R13B, Reg::R14, Reg::R14D, Reg::R14W, Reg::R14B,
+    Reg::R15, Reg::R15D, Reg::R15W, Reg::R15B, Reg::FLAGS, Reg::EFLAGS, Reg::RFLAGS, Reg::CS,
+    Reg::DS, Reg::ES, Reg::FS, Reg::GS, Reg::SS, Reg::ST, Reg::ST0, Reg::ST1, Reg::ST2, Reg::ST3,
+    Reg::ST4, Reg::ST5, Reg::ST6, Reg::ST7, Reg::MM0, Reg::MM1, Reg::MM2, Reg::MM3, Reg::MM4,
+    Reg::MM5, Reg::MM6, Reg::MM7, Reg::CR0, Reg::CR1, Reg::CR2, Reg::CR3, Reg::CR4, Reg::CR5,
+    Reg::CR6, Reg::CR7, Reg::CR8, Reg::DR0, Reg::DR1, Reg::DR2, Reg::DR3, Reg::DR4, Reg::DR5,
+    Reg::DR6, Reg::DR7, Reg::TR3, Reg::TR4, Reg::TR5, Reg::TR6, Reg::TR7, Reg::XMM0, Reg::YMM0,
+    Reg::ZMM0, Reg::XMM1, Reg::YMM1, Reg::ZMM1, Reg::XMM2, Reg::YMM2, Reg::ZMM2, Reg::XMM3,
+    Reg::YMM3, Reg::ZMM3, Reg::XMM4, Reg::YMM4, Reg::ZMM4, Reg::XMM5, Reg::YMM5, Reg::ZMM5,
+    Reg::XMM6, Reg::YMM6, Reg::ZMM6, Reg::XMM7, Reg::YMM7, Reg::ZMM7, Reg::XMM8, Reg::YMM8,
+    Reg::ZMM8, Reg::XMM9, Reg::YMM9, Reg::ZMM9, Reg::XMM10, Reg::YMM10, Reg::ZMM10, Reg::XMM11,
+    Reg::YMM11, Reg::ZMM11, Reg::XMM12, Reg::YMM12, Reg::ZMM12, Reg::XMM13, Reg::YMM13, Reg::ZMM13,
+    Reg::XMM14, Reg::YMM14, Reg::ZMM14, Reg::XMM15, Reg::YMM15, Reg::ZMM15, Reg::XMM16, Reg::YMM16,
+    Reg::ZMM16, Reg::XMM17, Reg::YMM17, Reg::ZMM17, Reg::XMM18, Reg::YMM18, Reg::ZMM18, Reg::XMM19,
+    Reg::YMM19, Reg::ZMM19, Reg::XMM20, Reg::YMM20, Reg::ZMM20, Reg::XMM21, Reg::YMM21, Reg::ZMM21,
+    Reg::XMM22, Reg::YMM22, Reg::ZMM22, Reg::XMM23, Reg::YMM23, Reg::ZMM23, Reg::XMM24, Reg::YMM24,
+    Reg::ZMM24, Reg::XMM25, Reg::YMM25, Reg::ZMM25, Reg::XMM26, Reg::YMM26, Reg::ZMM26, Reg::XMM27,
+    Reg::YMM27, Reg::ZMM27, Reg::XMM28, Reg::YMM28, Reg::ZMM28, Reg::XMM29, Reg::YMM29, Reg::ZMM29,
+    Reg::XMM30, Reg::YMM30, Reg::ZMM30, Reg::XMM31, Reg::YMM31, Reg::ZMM31, Reg::GDTR, Reg::LDTR,
+    Reg::IDTR, Reg::TR, Reg::XCR, Reg::MSR, Reg::PMC, Reg::K0, Reg::K1, Reg::K2, Reg::K3, Reg::K4,
+    Reg::K5, Reg::K6, Reg::K7, Reg::BND0, Reg::BND1, Reg::BND2, Reg::BND3,
+];
+
 impl Reg {
+    pub fn all() -> &'static [Reg] { &ALL_REGS }
+
+    // Parses an uppercase register name, i.e. "EAX" or "XMM15". FPU stack registers are "ST0".
+    pub fn parse(val: &str) -> Result<Reg, ()> {
+        ALL_REGS.iter().find(|r| format!("{:?}", r) == val).cloned().ok_or(())
+    }
+
     pub fn get_reg_type(&self) -> RegType {
-        if self.is_general() { RegType::General }
-        else if self.is_mmx() { RegType::Mmx }
-        else if self.is_avx() { RegType::Avx }
-        else if self.is_fpu() { RegType::Fpu }
-        else if self.is_mask() { RegType::Mask }
-        else if self.is_segment() { RegType::Segment }
-        else if self.is_bounds() { RegType::Bound }
-        else { panic!("Unknown register type for {:?}.", self); }
+        self.reg_type().unwrap_or_else(|| panic!("Unknown register type for {:?}.", self))
+    }
+
+    // Like get_reg_type, but returns None for registers that can't be encoded as an operand
+    // (i.e. RIP, RFLAGS or GDTR).
+    pub fn reg_type(&self) -> Option<RegType> {
+        if self.is_general() { Some(RegType::General) }
+        else if self.is_mmx() { Some(RegType::Mmx) }
+        else if self.is_avx() { Some(RegType::Avx) }
+        else if self.is_fpu() { Some(RegType::Fpu) }
+        else if self.is_mask() { Some(RegType::Mask) }
+        else if self.is_segment() { Some(RegType::Segment) }
+        else if self.is_bounds() { Some(RegType::Bound) }
+        else if self.is_control() { Some(RegType::Control) }
+        else if self.is_debug() { Some(RegType::Debug) }
+        else { None }
     }
 
     pub fn is_64_only(&self) -> bool {
@@ -553,41 +664,46 @@ impl Reg {
     }
 
     pub fn get_reg_code(&self) -> u8 {
+        self.reg_code().unwrap_or_else(|| panic!("Invalid register: {:?}.", self))
+    }
+
+    // Like get_reg_code, but returns None for registers that have no encoding (i.e. RIP or GDTR).
+    pub fn reg_code(&self) -> Option<u8> {
         match *self {
             // TODO Handle SPL, BPL, SIL, DIL
-            Reg::AL               | Reg::AX   | Reg::EAX  | Reg::RAX | Reg::ST0 | Reg::MM0 | Reg::XMM0  | Reg::YMM0  | Reg::ZMM0  | Reg::ES | Reg::CR0  | Reg::K0 | Reg::BND0 | Reg::DR0 => 0,
-            Reg::CL               | Reg::CX   | Reg::ECX  | Reg::RCX | Reg::ST1 | Reg::MM1 | Reg::XMM1  | Reg::YMM1  | Reg::ZMM1  | Reg::CS | Reg::CR1  | Reg::K1 | Reg::BND1 | Reg::DR1 => 1,
-            Reg::DL               | Reg::DX   | Reg::EDX  | Reg::RDX | Reg::ST2 | Reg::MM2 | Reg::XMM2  | Reg::YMM2  | Reg::ZMM2  | Reg::SS | Reg::CR2  | Reg::K2 | Reg::BND2 | Reg::DR2 => 2,
-            Reg::BL               | Reg::BX   | Reg::EBX  | Reg::RBX | Reg::ST3 | Reg::MM3 | Reg::XMM3  | Reg::YMM3  | Reg::ZMM3  | Reg::DS | Reg::CR3  | Reg::K3 | Reg::BND3 | Reg::DR3 => 3,
-            Reg::AH   | Reg::SPL  | Reg::SP   | Reg::ESP  | Reg::RSP | Reg::ST4 | Reg::MM4 | Reg::XMM4  | Reg::YMM4  | Reg::ZMM4  | Reg::FS | Reg::CR4  | Reg::K4             | Reg::DR4 => 4,
-            Reg::CH   | Reg::BPL  | Reg::BP   | Reg::EBP  | Reg::RBP | Reg::ST5 | Reg::MM5 | Reg::XMM5  | Reg::YMM5  | Reg::ZMM5  | Reg::GS             | Reg::K5             | Reg::DR5 => 5,
-            Reg::DH   | Reg::SIL  | Reg::SI   | Reg::ESI  | Reg::RSI | Reg::ST6 | Reg::MM6 | Reg::XMM6  | Reg::YMM6  | Reg::ZMM6                        | Reg::K6             | Reg::DR6 => 6,
-            Reg::BH   | Reg::DIL  | Reg::DI   | Reg::EDI  | Reg::RDI | Reg::ST7 | Reg::MM7 | Reg::XMM7  | Reg::YMM7  | Reg::ZMM7                        | Reg::K7             | Reg::DR7 => 7,
-            Reg::R8B              | Reg::R8W  | Reg::R8D  | Reg::R8                        | Reg::XMM8  | Reg::YMM8  | Reg::ZMM8            | Reg::CR8                                   => 8,
-            Reg::R9B              | Reg::R9W  | Reg::R9D  | Reg::R9                        | Reg::XMM9  | Reg::YMM9  | Reg::ZMM9                                                         => 9,
-            Reg::R10B             | Reg::R10W | Reg::R10D | Reg::R10                       | Reg::XMM10 | Reg::YMM10 | Reg::ZMM10                                                        => 10,
-            Reg::R11B             | Reg::R11W | Reg::R11D | Reg::R11                       | Reg::XMM11 | Reg::YMM11 | Reg::ZMM11                                                        => 11,
-            Reg::R12B             | Reg::R12W | Reg::R12D | Reg::R12                       | Reg::XMM12 | Reg::YMM12 | Reg::ZMM12                                                        => 12,
-            Reg::R13B             | Reg::R13W | Reg::R13D | Reg::R13                       | Reg::XMM13 | Reg::YMM13 | Reg::ZMM13                                                        => 13,
-            Reg::R14B             | Reg::R14W | Reg::R14D | Reg::R14                       | Reg::XMM14 | Reg::YMM14 | Reg::ZMM14                                                        => 14,
-            Reg::R15B             | Reg::R15W | Reg::R15D | Reg::R15                       | Reg::XMM15 | Reg::YMM15 | Reg::ZMM15                                                        => 15,
-                                                                                             Reg::XMM16 | Reg::YMM16 | Reg::ZMM16                                                        => 16,
-                                                                                             Reg::XMM17 | Reg::YMM17 | Reg::ZMM17                                                        => 17,
-                                                                                             Reg::XMM18 | Reg::YMM18 | Reg::ZMM18                                                        => 18,
-                                                                                             Reg::XMM19 | Reg::YMM19 | Reg::ZMM19                                                        => 19,
-                                                                                             Reg::XMM20 | Reg::YMM20 | Reg::ZMM20                                                        => 20,
-                                                                                             Reg::XMM21 | Reg::YMM21 | Reg::ZMM21                                                        => 21,
-                                                                                             Reg::XMM22 | Reg::YMM22 | Reg::ZMM22                                                        => 22,
-                                                                                             Reg::XMM23 | Reg::YMM23 | Reg::ZMM23                                                        => 23,
-                                                                                             Reg::XMM24 | Reg::YMM24 | Reg::ZMM24                                                        => 24,
-                                                                                             Reg::XMM25 | Reg::YMM25 | Reg::ZMM25                                                        => 25,
-                                                                                             Reg::XMM26 | Reg::YMM26 | Reg::ZMM26                                                        => 26,
-                                                                                             Reg::XMM27 | Reg::YMM27 | Reg::ZMM27                                                        => 27,
-                                                                                             Reg::XMM28 | Reg::YMM28 | Reg::ZMM28                                                        => 28,
-                                                                                             Reg::XMM29 | Reg::YMM29 | Reg::ZMM29                                                        => 29,
-                                                                                             Reg::XMM30 | Reg::YMM30 | Reg::ZMM30                                                        => 30,
-                                                                                             Reg::XMM31 | Reg::YMM31 | Reg::ZMM31                                                        => 31,
-            _ => panic!("Invalid register: {:?}.", self)
+            Reg::AL               | Reg::AX   | Reg::EAX  | Reg::RAX | Reg::ST0 | Reg::MM0 | Reg::XMM0  | Reg::YMM0  | Reg::ZMM0  | Reg::ES | Reg::CR0  | Reg::K0 | Reg::BND0 | Reg::DR0 => Some(0),
+            Reg::CL               | Reg::CX   | Reg::ECX  | Reg::RCX | Reg::ST1 | Reg::MM1 | Reg::XMM1  | Reg::YMM1  | Reg::ZMM1  | Reg::CS | Reg::CR1  | Reg::K1 | Reg::BND1 | Reg::DR1 => Some(1),
+            Reg::DL               | Reg::DX   | Reg::EDX  | Reg::RDX | Reg::ST2 | Reg::MM2 | Reg::XMM2  | Reg::YMM2  | Reg::ZMM2  | Reg::SS | Reg::CR2  | Reg::K2 | Reg::BND2 | Reg::DR2 => Some(2),
+            Reg::BL               | Reg::BX   | Reg::EBX  | Reg::RBX | Reg::ST3 | Reg::MM3 | Reg::XMM3  | Reg::YMM3  | Reg::ZMM3  | Reg::DS | Reg::CR3  | Reg::K3 | Reg::BND3 | Reg::DR3 => Some(3),
+            Reg::AH   | Reg::SPL  | Reg::SP   | Reg::ESP  | Reg::RSP | Reg::ST4 | Reg::MM4 | Reg::XMM4  | Reg::YMM4  | Reg::ZMM4  | Reg::FS | Reg::CR4  | Reg::K4             | Reg::DR4 => Some(4),
+            Reg::CH   | Reg::BPL  | Reg::BP   | Reg::EBP  | Reg::RBP | Reg::ST5 | Reg::MM5 | Reg::XMM5  | Reg::YMM5  | Reg::ZMM5  | Reg::GS             | Reg::K5             | Reg::DR5 => Some(5),
+            Reg::DH   | Reg::SIL  | Reg::SI   | Reg::ESI  | Reg::RSI | Reg::ST6 | Reg::MM6 | Reg::XMM6  | Reg::YMM6  | Reg::ZMM6                        | Reg::K6             | Reg::DR6 => Some(6),
+            Reg::BH   | Reg::DIL  | Reg::DI   | Reg::EDI  | Reg::RDI | Reg::ST7 | Reg::MM7 | Reg::XMM7  | Reg::YMM7  | Reg::ZMM7                        | Reg::K7             | Reg::DR7 => Some(7),
+            Reg::R8B              | Reg::R8W  | Reg::R8D  | Reg::R8                        | Reg::XMM8  | Reg::YMM8  | Reg::ZMM8            | Reg::CR8                                   => Some(8),
+            Reg::R9B              | Reg::R9W  | Reg::R9D  | Reg::R9                        | Reg::XMM9  | Reg::YMM9  | Reg::ZMM9                                                         => Some(9),
+            Reg::R10B             | Reg::R10W | Reg::R10D | Reg::R10                       | Reg::XMM10 | Reg::YMM10 | Reg::ZMM10                                                        => Some(10),
+            Reg::R11B             | Reg::R11W | Reg::R11D | Reg::R11                       | Reg::XMM11 | Reg::YMM11 | Reg::ZMM11                                                        => Some(11),
+            Reg::R12B             | Reg::R12W | Reg::R12D | Reg::R12                       | Reg::XMM12 | Reg::YMM12 | Reg::ZMM12                                                        => Some(12),
+            Reg::R13B             | Reg::R13W | Reg::R13D | Reg::R13                       | Reg::XMM13 | Reg::YMM13 | Reg::ZMM13                                                        => Some(13),
+            Reg::R14B             | Reg::R14W | Reg::R14D | Reg::R14                       | Reg::XMM14 | Reg::YMM14 | Reg::ZMM14                                                        => Some(14),
+            Reg::R15B             | Reg::R15W | Reg::R15D | Reg::R15                       | Reg::XMM15 | Reg::YMM15 | Reg::ZMM15                                                        => Some(15),
+                                                                                             Reg::XMM16 | Reg::YMM16 | Reg::ZMM16                                                        => Some(16),
+                                                                                             Reg::XMM17 | Reg::YMM17 | Reg::ZMM17                                                        => Some(17),
+                                                                                             Reg::XMM18 | Reg::YMM18 | Reg::ZMM18                                                        => Some(18),
+                                                                                             Reg::XMM19 | Reg::YMM19 | Reg::ZMM19                                                        => Some(19),
+                                                                                             Reg::XMM20 | Reg::YMM20 | Reg::ZMM20                                                        => Some(20),
+                                                                                             Reg::XMM21 | Reg::YMM21 | Reg::ZMM21                                                        => Some(21),
+                                                                                             Reg::XMM22 | Reg::YMM22 | Reg::ZMM22                                                        => Some(22),
+                                                                                             Reg::XMM23 | Reg::YMM23 | Reg::ZMM23                                                        => Some(23),
+                                                                                             Reg::XMM24 | Reg::YMM24 | Reg::ZMM24                                                        => Some(24),
+                                                                                             Reg::XMM25 | Reg::YMM25 | Reg::ZMM25                                                        => Some(25),
+                                                                                             Reg::XMM26 | Reg::YMM26 | Reg::ZMM26                                                        => Some(26),
+                                                                                             Reg::XMM27 | Reg::YMM27 | Reg::ZMM27                                                        => Some(27),
+                                                                                             Reg::XMM28 | Reg::YMM28 | Reg::ZMM28                                                        => Some(28),
+                                                                                             Reg::XMM29 | Reg::YMM29 | Reg::ZMM29                                                        => Some(29),
+                                                                                             Reg::XMM30 | Reg::YMM30 | Reg::ZMM30                                                        => Some(30),
+                                                                                             Reg::XMM31 | Reg::YMM31 | Reg::ZMM31                                                        => Some(31),
+            _ => None
         }
     }
 
@@ -598,6 +714,91 @@ impl Reg {
         }
     }
 
+    // AH, CH, DH and BH share their encodings with SPL, BPL, SIL and DIL, so they can't be used
+    // in an instruction that has a REX prefix.
+    pub fn is_high_byte(&self) -> bool {
+        matches!(*self, Reg::AH | Reg::CH | Reg::DH | Reg::BH)
+    }
+
+    pub fn is_rex_compatible(&self) -> bool { !self.is_high_byte() }
+
+    // Gets the largest register this register is a part of (i.e. AL -> RAX, XMM3 -> ZMM3).
+    pub fn full_register(&self) -> Reg {
+        match *self {
+            Reg::AH => Reg::RAX,
+            Reg::CH => Reg::RCX,
+            Reg::DH => Reg::RDX,
+            Reg::BH => Reg::RBX,
+            Reg::FLAGS | Reg::EFLAGS => Reg::RFLAGS,
+            Reg::IP | Reg::EIP => Reg::RIP,
+            Reg::ST => Reg::ST0,
+            r if r.is_general() => Reg::from_code_general_64(r.get_reg_code()).unwrap(),
+            r if r.is_avx() => Reg::from_code_zmm(r.get_reg_code()).unwrap(),
+            r => r
+        }
+    }
+
+    // Gets the register of the given size that aliases the same storage (i.e. EAX -> AX/AL/RAX).
+    // Byte sized general registers resolve to the low byte.
+    pub fn with_size(&self, size: OperandSize) -> Option<Reg> {
+        if self.size() == size && !self.is_high_byte() { return Some(*self); }
+        let full = self.full_register();
+        match full {
+            Reg::RFLAGS => match size {
+                OperandSize::Word => Some(Reg::FLAGS),
+                OperandSize::Dword => Some(Reg::EFLAGS),
+                OperandSize::Qword => Some(Reg::RFLAGS),
+                _ => None
+            },
+            Reg::RIP => match size {
+                OperandSize::Word => Some(Reg::IP),
+                OperandSize::Dword => Some(Reg::EIP),
+                OperandSize::Qword => Some(Reg::RIP),
+                _ => None
+            },
+            r if r.is_general() => Reg::from_code_general_sized(r.get_reg_code(), true, size),
+            r if r.is_avx() => Reg::from_code_avx(r.get_reg_code(), size),
+            r if r.size() == size => Some(r),
+            _ => None
+        }
+    }
+
+    // Gets the high byte register (AH, CH, DH, BH) that is a part of this register, if any.
+    pub fn high_byte(&self) -> Option<Reg> {
+        match self.full_register() {
+            Reg::RAX if *self != Reg::AL => Some(Reg::AH),
+            Reg::RCX if *self != Reg::CL => Some(Reg::CH),
+            Reg::RDX if *self != Reg::DL => Some(Reg::DH),
+            Reg::RBX if *self != Reg::BL => Some(Reg::BH),
+            _ => None
+        }
+    }
+
+    // Gets all the smaller registers contained in this register, from largest to smallest
+    // (i.e. RAX -> EAX, AX, AL, AH).
+    pub fn sub_registers(&self) -> Vec<Reg> {
+        let sizes = [OperandSize::Zmmword, OperandSize::Ymmword, OperandSize::Xmmword,
+            OperandSize::Qword, OperandSize::Dword, OperandSize::Word, OperandSize::Byte];
+        let size = self.size();
+        let mut regs: Vec<Reg> = sizes.iter()
+            .filter(|s| s.bits() < size.bits())
+            .filter_map(|s| self.with_size(*s))
+            .filter(|r| r != self)
+            .collect();
+        if let Some(high) = self.high_byte() {
+            if high.size().bits() < size.bits() { regs.push(high); }
+        }
+        regs
+    }
+
+    // Checks if writing one register can change the value of the other.
+    pub fn overlaps(&self, other: Reg) -> bool {
+        if *self == other { return true; }
+        if self.full_register() != other.full_register() { return false; }
+        // The low and high byte registers are disjoint.
+        !(self.is_8_bit() && other.is_8_bit())
+    }
+
     pub fn from_code_general_sized(code: u8, has_rex: bool, size: OperandSize) -> Option<Reg> {
         match size {
             OperandSize::Byte => Reg::from_code_general_8(code, has_rex),
@@ -936,6 +1137,17 @@ impl Reg {
     }
 }
 
+// Register names are lowercase, with the FPU stack registers written as st(i).
+impl fmt::Display for Reg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_fpu() && *self != Reg::ST {
+            write!(f, "st({})", self.get_reg_code())
+        } else {
+            f.write_str(&format!("{:?}", self).to_lowercase())
+        }
+    }
+}
+
 // AVX Merge Mode
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum MergeMode {
@@ -1,7 +1,7 @@
 use std::io::{ Write };
 use std::io::Result as IoResult;
 use byteorder::{ LittleEndian, WriteBytesExt };
-use ::{ InstructionEncodingError, Mode, SegmentReg };
+use ::{ EncodedField, InstructionEncodingError, InstructionLayout, Mode, SegmentReg };
 use ::instruction::MergeMode;
 
 pub const PREFIX_LOCK: u8 = 0xF0;
@@ -59,14 +59,15 @@ pub struct InstructionBuffer {
 }
 
 impl InstructionBuffer {
-    pub fn write<W>(&self, writer: &mut W, mode: Mode) -> Result<usize, InstructionEncodingError> 
-        where W: Write {
+    pub fn write<W>(&self, writer: &mut W, mode: Mode)
+        -> Result<InstructionLayout, InstructionEncodingError> where W: Write {
         self.write_inner(writer, mode).map_err(|_| InstructionEncodingError::WriteFailed)
     }
     
-    fn write_inner<W>(&self, writer: &mut W, mode: Mode) -> IoResult<usize> 
+    fn write_inner<W>(&self, writer: &mut W, mode: Mode) -> IoResult<InstructionLayout> 
         where W: Write {
         let mut bytes_written: usize = 0;
+        let mut layout = InstructionLayout::default();
 
         // TODO Return error if certain vex bits are double used (i.e. b)
         // TODO Support vector sib (See Intel x86 manual - AVX)
@@ -117,7 +118,9 @@ impl InstructionBuffer {
 
         // Primary opcode
         if !(emit_vex || emit_evex) || !(self.primary_opcode == 0x38 || self.primary_opcode == 0x3A) {
-            writer.write_all(&[self.primary_opcode + self.opcode_add.unwrap_or(0)])?; bytes_written += 1;
+            // The fourth bit of an opcode addend register goes in REX.B.
+            writer.write_all(&[self.primary_opcode + (self.opcode_add.unwrap_or(0) & 0x7)])?;
+            bytes_written += 1;
         }
 
         // Secondary opcode
@@ -131,16 +134,23 @@ impl InstructionBuffer {
 
         // Immediate values
         if let Some(ref v) = self.displacement {
-            bytes_written += InstructionBuffer::write_immediate(writer, &v)?;
+            let size = InstructionBuffer::write_immediate(writer, v)?;
+            layout.displacement = Some(EncodedField { offset: bytes_written, size });
+            bytes_written += size;
         }
         if let Some(ref v) = self.immediate {
-            bytes_written += InstructionBuffer::write_immediate(writer, &v)?;
+            let size = InstructionBuffer::write_immediate(writer, v)?;
+            layout.immediate = Some(EncodedField { offset: bytes_written, size });
+            bytes_written += size;
         }
         if let Some(ref v) = self.immediate2 {
-            bytes_written += InstructionBuffer::write_immediate(writer, &v)?;
+            let size = InstructionBuffer::write_immediate(writer, v)?;
+            layout.immediate2 = Some(EncodedField { offset: bytes_written, size });
+            bytes_written += size;
         }
 
-        Ok(bytes_written)
+        layout.len = bytes_written;
+        Ok(layout)
     }
 
     fn write_immediate<W>(writer: &mut W, val: &ImmediateValue) -> IoResult<usize> 
@@ -179,8 +189,8 @@ impl InstructionBuffer {
             if self.operand_size_64 { 1 << 3 } else { 0 } |
             self.mod_rm_reg.map(|reg| (reg & 0x8) >> 1).unwrap_or(0) |
             self.sib_index.map(|idx| (idx & 0x8) >> 2).unwrap_or(0) |
-            self.mod_rm_rm.map(|rm| (rm & 0x8) >> 3)
-                .or(self.sib_base.map(|b| b & 0x8)).unwrap_or(0);
+            self.sib_base.or(self.mod_rm_rm).or(self.opcode_add).map(|b| (b & 0x8) >> 3)
+                .unwrap_or(0);
         writer.write(&[rex_byte])
     }
 
@@ -188,7 +198,7 @@ impl InstructionBuffer {
         where W: Write {
         let vex_r = self.mod_rm_reg.map(|r| (!r & 0x8) >> 3).unwrap_or(0);
         let vex_x = self.sib_index.map(|s| (!s & 0x8) >> 3);
-        let vex_b = self.mod_rm_rm.or(self.sib_base).map(|r| (!r & 0x8) >> 3);
+        let vex_b = self.sib_base.or(self.mod_rm_rm).map(|r| (!r & 0x8) >> 3);
         let vex_we = if self.vex_e.unwrap_or(self.operand_size_64) { 1 } else { 0 };
         let pp = if self.operand_size_prefix { 1 }
                  else if self.f3_prefix { 2 }
@@ -232,7 +242,7 @@ impl InstructionBuffer {
         let vex_r2 = if mode == Mode::Long { self.mod_rm_reg.map(|r| if r & 0x10 == 0 { 1 } else { 0 }).unwrap_or(0) } else { 1 };
         let vex_x = if mode == Mode::Long { self.sib_index.map(|s| if s & 0x8 == 0 { 1 } else { 0 })
             .or(self.mod_rm_rm.map(|r| if r & 0x10 == 0 { 1 } else { 0 })).unwrap_or(1) } else { 1 };
-        let vex_b = if mode == Mode::Long { self.mod_rm_rm.or(self.sib_base).map(|r| if r & 0x8 == 0 { 1 } else { 0 }).unwrap_or(1) } else { 1 };
+        let vex_b = if mode == Mode::Long { self.sib_base.or(self.mod_rm_rm).map(|r| if r & 0x8 == 0 { 1 } else { 0 }).unwrap_or(1) } else { 1 };
         let vex_b2 = if self.vex_b.unwrap_or(false) { 1 } else { 0 };
         let vex_v = vex_operand.map(|s| (s & 0x10) >> 4).unwrap_or(1);
         let vex_v4 = vex_operand.map(|s| s & 0xF).unwrap_or(0xF);
@@ -273,14 +283,16 @@ impl InstructionBuffer {
         writer.write(&[PREFIX_EVEX, b2, b3, b4])
     }
 
-    pub fn add_immediate(&mut self, val: ImmediateValue) {
+    pub fn add_immediate(&mut self, val: ImmediateValue) -> Result<(), InstructionEncodingError> {
         if self.immediate.is_none() {
             self.immediate = Some(val);
         } else if self.immediate2.is_none() {
             self.immediate2 = Some(val);
         } else {
-            panic!("Too many immediate values.");
+            // No instruction has more than two immediates.
+            return Err(InstructionEncodingError::MismatchedEncoding);
         }
+        Ok(())
     }
 
     fn has_mod_rm(&self) -> bool { self.mod_rm_mod.is_some() || self.mod_rm_rm.is_some() || self.mod_rm_reg.is_some() }
@@ -336,7 +348,8 @@ impl InstructionBuffer {
         self.mod_rm_reg.map(|reg| reg & 0x8 != 0).unwrap_or(false) ||
         self.sib_index.map(|inx| inx & 0x8 != 0).unwrap_or(false) ||
         self.mod_rm_rm.map(|rm| rm & 0x8 != 0).unwrap_or(false) ||
-        self.sib_base.map(|b| b & 0x8 != 0).unwrap_or(false)
+        self.sib_base.map(|b| b & 0x8 != 0).unwrap_or(false) ||
+        self.opcode_add.map(|r| r & 0x8 != 0).unwrap_or(false)
     }
 
     fn should_emit_vex(&self) -> bool {
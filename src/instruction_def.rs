@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::sync::RwLock;
 use ::{Instruction, InstructionEncodingError, Mnemonic, Mode, Operand, OperandSize, Reg, RegType};
 use ::instruction_buffer::InstructionBuffer;
@@ -45,6 +46,17 @@ lazy_static! {
         }
         lock
     };
+
+    // Two-byte opcodes that some instructions follow with a fixed byte where others have a
+    // ModR/M byte (i.e. ENDBR64, F3 0F 1E FA), besides the 0F 38 and 0F 3A maps.
+    static ref SECONDARY_OPCODES: HashSet<u8> = INSTR_DEFS.iter()
+        .filter(|def| def.two_byte_opcode && def.secondary_opcode.is_some() &&
+            def.primary_opcode != 0x38 && def.primary_opcode != 0x3A)
+        .map(|def| def.primary_opcode).collect();
+}
+
+pub fn may_have_secondary_opcode(buffer: &InstructionBuffer) -> bool {
+    buffer.is_two_byte_opcode && SECONDARY_OPCODES.contains(&buffer.primary_opcode)
 }
 
 pub fn load_instructions(map: &mut HashMap<Mnemonic, Vec<&'static InstructionDefinition>>) {
@@ -58,12 +70,28 @@ pub fn load_instructions(map: &mut HashMap<Mnemonic, Vec<&'static InstructionDef
     }
 }
 
+pub fn get_instruction_defs(mnemonic: Mnemonic) -> Vec<&'static InstructionDefinition> {
+    INSTR_MNEMONIC_MAP.read().unwrap().get(&mnemonic).cloned().unwrap_or_default()
+}
+
+// Every mnemonic with at least one definition, in alphabetical order.
+pub fn get_mnemonics() -> Vec<Mnemonic> {
+    let mut mnemonics: Vec<_> = INSTR_MNEMONIC_MAP.read().unwrap().keys().cloned().collect();
+    mnemonics.sort_by_key(|m| format!("{:?}", m));
+    mnemonics
+}
+
 pub fn find_instruction_def(instr: &Instruction, mode: Mode) 
     -> Result<&'static InstructionDefinition, InstructionEncodingError> {
     INSTR_MNEMONIC_MAP.read().unwrap().get(&instr.mnemonic)
         .ok_or(InstructionEncodingError::NoEncoding)
         .and_then(|list| {
-            let matches = list.iter().filter(|enc| enc.matches_instruction(instr, mode));
+            let candidates: Vec<_> = list.iter()
+                .filter(|enc| enc.matches_instruction(instr, mode)).collect();
+            // Offsets match both relative branch targets and memory operands (i.e. JMP rel32 and
+            // JMP m32), in which case the branch is meant.
+            let relative = candidates.iter().any(|enc| enc.is_relative());
+            let matches = candidates.into_iter().filter(|enc| !relative || enc.is_relative());
             let mut sizes = None;
             let mut best = None;
             
@@ -163,8 +191,10 @@ pub fn find_instruction_def_by_opcode(buffer: &InstructionBuffer, mode: Mode)
     let first = matches.next();
     if let Some(f) = first {
         let next = matches.next();
-        if next.is_some() { // If multiple matches exist...
-            if buffer.mod_rm_mod.is_none() { // Need a ModR/M byte
+        if let Some(n) = next { // If multiple matches exist...
+            // Aliases (i.e. JE/JZ) share an opcode, so a ModR/M byte only helps if one of the
+            // candidates actually has one.
+            if buffer.mod_rm_mod.is_none() && (f.needs_mod_rm() || n.needs_mod_rm()) {
                 Err(FindInstructionDefByOpcodeError::NeedModRm)
             } else {
                 Ok(f)
@@ -199,7 +229,13 @@ fn compare_sizes(a: &[Option<OperandSize>; 4], b: &[Option<OperandSize>; 4]) ->
 fn get_op_sizes(def: &InstructionDefinition, instr: &Instruction) -> [Option<OperandSize>; 4] {
     let ops = instr.operands();
     let mut iter = def.operands.iter().zip(ops.iter())
-        .map(|(def, op)| def.as_ref().map(|d| op.map_or(d.size, |o| d.get_real_size(&o))));
+        .map(|(def, op)| def.as_ref().map(|d| match d.op_type {
+            // Relative operands don't make a match ambiguous, the shortest form that fits is used.
+            // The same goes for labels, which are always sized to fit an address.
+            OperandType::Rel(_) => OperandSize::Unsized,
+            _ if matches!(*op, Some(Operand::Label(_))) => OperandSize::Unsized,
+            _ => op.map_or(d.size, |o| d.get_real_size(&o))
+        }));
     [iter.next().unwrap_or(None),
      iter.next().unwrap_or(None),
      iter.next().unwrap_or(None),
@@ -211,6 +247,28 @@ fn compare_def_length(a: &InstructionDefinition, b: &InstructionDefinition) -> b
 }
 
 impl InstructionDefinition {
+    pub fn needs_mod_rm(&self) -> bool {
+        self.opcode_ext.is_some() ||
+        self.operands.iter().flatten().any(|op| matches!(op.encoding,
+            OperandEncoding::ModRmReg | OperandEncoding::ModRmRm | OperandEncoding::Mib))
+    }
+
+    pub fn is_relative(&self) -> bool {
+        self.operands.iter().flatten().any(|op| matches!(op.op_type, OperandType::Rel(_)))
+    }
+
+    pub fn is_evex(&self) -> bool {
+        matches!(self.composite_prefix, Some(CompositePrefix::Evex { .. }))
+    }
+
+    pub fn is_valid_in(&self, mode: Mode) -> bool {
+        match mode {
+            Mode::Real => self.valid_16,
+            Mode::Protected => self.valid_32,
+            Mode::Long => self.valid_64
+        }
+    }
+
     fn matches_instruction(&self, instr: &Instruction, mode: Mode) -> bool {
         self.mnemonic == instr.mnemonic &&
         // (self.allow_lock || instr.lock) &&
@@ -222,11 +280,27 @@ impl InstructionDefinition {
                 d.matches_operand(op, self, instr)
             } else { op.is_none() }
         )) &&
-        match mode {
-            Mode::Real => self.valid_16,
-            Mode::Protected => self.valid_32,
-            Mode::Long => self.valid_64
-        }
+        self.is_valid_in(mode) &&
+        self.fits_mode(instr, mode)
+    }
+
+    // Branches to labels and label addresses have to be the size of the instruction pointer, as
+    // the shorter forms could truncate the address once it's known (i.e. JMP rel16 in protected
+    // mode). Literal operands keep the size they're given.
+    fn fits_mode(&self, instr: &Instruction, mode: Mode) -> bool {
+        self.operands.iter().zip(instr.operands().iter()).all(|(def, op)| {
+            let (def, is_label) = match (def, **op) {
+                (Some(def), Some(op)) => (def, matches!(op, Operand::Label(_))),
+                _ => return true
+            };
+            match def.op_type {
+                OperandType::Rel(size) if is_label => size == OperandSize::Dword ||
+                    (mode == Mode::Real && size == OperandSize::Word),
+                OperandType::Imm if is_label => def.size == mode.pointer_size() ||
+                    (mode == Mode::Long && def.size == OperandSize::Dword),
+                _ => true
+            }
+        })
     }
 
     // This isn't intended to be an exact byte length of the instruction, as it's only used to
@@ -246,6 +320,78 @@ impl InstructionDefinition {
     }
 }
 
+impl InstructionDefinition {
+    // The opcode column of the Intel manual, i.e. "REX.W 81 /0 id" or "VEX.256 66 0F 58 /r".
+    pub fn describe_opcode(&self) -> String {
+        let mut parts = Vec::new();
+        match self.composite_prefix {
+            Some(CompositePrefix::Rex { size_64: Some(true) }) => parts.push("REX.W".to_owned()),
+            Some(CompositePrefix::Vex { vector_size, we, .. }) =>
+                parts.push(describe_vex("VEX", vector_size, we)),
+            Some(CompositePrefix::Evex { vector_size, we, .. }) =>
+                parts.push(describe_vex("EVEX", vector_size, we)),
+            _ => {}
+        }
+        if self.fwait { parts.push("9B".to_owned()); }
+        if self.operand_size_prefix == OperandSizePrefixBehavior::Always {
+            parts.push("66".to_owned());
+        }
+        if self.f2_prefix == PrefixBehavior::Always { parts.push("F2".to_owned()); }
+        if self.f3_prefix == PrefixBehavior::Always { parts.push("F3".to_owned()); }
+        if self.two_byte_opcode { parts.push("0F".to_owned()); }
+
+        let addend = self.operands.iter().flatten()
+            .any(|op| op.encoding == OperandEncoding::OpcodeAddend);
+        parts.push(format!("{:02X}{}", self.primary_opcode, if addend { "+r" } else { "" }));
+        if let Some(secondary) = self.secondary_opcode { parts.push(format!("{:02X}", secondary)); }
+        if let Some(ext) = self.opcode_ext {
+            parts.push(format!("/{}", ext));
+        } else if self.operands.iter().flatten().any(|op| op.encoding == OperandEncoding::ModRmReg) {
+            parts.push("/r".to_owned());
+        }
+        for op in self.operands.iter().flatten() {
+            let code = match (op.encoding, &op.op_type) {
+                (OperandEncoding::Imm, &OperandType::Rel(size)) => match size {
+                    OperandSize::Byte => "cb",
+                    OperandSize::Word => "cw",
+                    _ => "cd"
+                },
+                (OperandEncoding::Imm, _) => match op.size {
+                    OperandSize::Byte => "ib",
+                    OperandSize::Word => "iw",
+                    OperandSize::Qword => "io",
+                    _ => "id"
+                },
+                _ => continue
+            };
+            parts.push(code.to_owned());
+        }
+        parts.join(" ")
+    }
+}
+
+fn describe_vex(name: &str, vector_size: Option<OperandSize>, we: Option<bool>) -> String {
+    let mut text = name.to_owned();
+    if let Some(size) = vector_size { text.push_str(&format!(".{}", size.bits())); }
+    match we {
+        Some(true) => text.push_str(".W1"),
+        Some(false) => text.push_str(".W0"),
+        None => {}
+    }
+    text
+}
+
+// Formats the definition in the style of the Intel manual, i.e. "ADD r/m32, imm8".
+impl fmt::Display for InstructionDefinition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.mnemonic)?;
+        for (i, op) in self.operands.iter().flatten().enumerate() {
+            write!(f, "{}{}", if i == 0 { " " } else { ", " }, op)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum OperandSizePrefixBehavior {
     Always,
@@ -345,7 +491,7 @@ impl OperandDefinition {
                         sz == OperandSize::Unsized ||
                         s == OperandSize::Unsized)
                 },
-                _ => unimplemented!()
+                _ => false
             })
         } else { Some(&self.op_type) }
     }
@@ -361,19 +507,23 @@ impl OperandDefinition {
         match *op_type {
             OperandType::Reg(reg_type)
                 => if let Some(Operand::Direct(reg)) = *op { 
-                    reg.get_reg_type() == reg_type && size_helper(def_size, op)
+                    reg.reg_type() == Some(reg_type) && size_helper(def_size, op)
                 } else { false },
             OperandType::Mem(s) => op.map(|o| o.is_memory()).unwrap_or(false) &&
                 size_helper(s.unwrap_or(def_size), op),
-            OperandType::Imm => op.map(|o| o.is_literal() || o.is_offset() || o.is_far())
-                .unwrap_or(false) && size_helper(def_size, op),
-            OperandType::Offset => unimplemented!(),
+            OperandType::Imm => op.is_some_and(|o| o.is_literal() || o.is_offset() || o.is_far() ||
+                matches!(o, Operand::Label(_))) && size_helper(def_size, op),
+            OperandType::Offset => match *op {
+                Some(Operand::Offset(..)) => size_helper(def_size, op),
+                _ => false
+            },
             OperandType::Rel(op_size) => match *op {
                 Some(Operand::Offset(o, ..)) => op_size.is_valid_literal(o),
                 Some(Operand::Literal8(_)) => true,
                 Some(Operand::Literal16(_)) => op_size.bits() >= 16,
                 Some(Operand::Literal32(_)) => op_size.bits() >= 32,
                 Some(Operand::Literal64(_)) => op_size.bits() >= 64,
+                Some(Operand::Label(_)) => true,
                 _ => false
             },
             OperandType::Mib => op.map(|o| o.is_scaled_indexed()).unwrap_or(false), // TODO Size?
@@ -421,6 +571,48 @@ impl OperandDefinition {
     }
 }
 
+impl fmt::Display for OperandDefinition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&describe_operand_type(&self.op_type, self.size))
+    }
+}
+
+fn describe_operand_type(op_type: &OperandType, size: OperandSize) -> String {
+    fn sized(name: &str, size: OperandSize) -> String {
+        if size.bits() == 0 { name.to_owned() } else { format!("{}{}", name, size.bits()) }
+    }
+
+    match *op_type {
+        OperandType::Reg(RegType::General) => sized("r", size),
+        OperandType::Reg(RegType::Avx) => match size {
+            OperandSize::Ymmword => "ymm",
+            OperandSize::Zmmword => "zmm",
+            _ => "xmm"
+        }.to_owned(),
+        OperandType::Reg(RegType::Mmx) => "mm".to_owned(),
+        OperandType::Reg(RegType::Fpu) => "st(i)".to_owned(),
+        OperandType::Reg(RegType::Bound) => "bnd".to_owned(),
+        OperandType::Reg(RegType::Mask) => "k".to_owned(),
+        OperandType::Reg(RegType::Segment) => "sreg".to_owned(),
+        OperandType::Reg(RegType::Control) => "cr".to_owned(),
+        OperandType::Reg(RegType::Debug) => "dr".to_owned(),
+        OperandType::Mem(s) => sized("m", s.unwrap_or(size)),
+        OperandType::Imm => sized("imm", size),
+        OperandType::Offset => sized("moffs", size),
+        OperandType::Rel(s) => sized("rel", s),
+        OperandType::Mib => "mib".to_owned(),
+        OperandType::Bcst(s) => format!("{}bcst", sized("m", s)),
+        OperandType::Fixed(FixedOperand::Reg(reg)) => format!("{:?}", reg),
+        OperandType::Fixed(FixedOperand::Constant(val)) => val.to_string(),
+        OperandType::Set(&[OperandType::Reg(RegType::General), OperandType::Mem(_)]) =>
+            sized("r/m", size),
+        OperandType::Set(types) => types.iter()
+            .map(|t| describe_operand_type(t, size))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum OperandType {
     Reg(RegType),
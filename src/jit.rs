@@ -0,0 +1,293 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+use std::mem;
+use std::ptr;
+use std::slice;
+use libc;
+use ::{build_frame_section, FixupKind, FrameFormat, FrameInfo, InstructionWriter, JitListener,
+    Label, Mode, ObjectError, OperandSize};
+
+// Executable memory for code generated at run time. The buffer is mapped read+write while code is
+// written to it and read+execute once it's finalized, never both (W^X). Writing to an executable
+// buffer fails until it's made writable again. The mapping grows as needed while writable, which
+// moves it, so addresses handed out before growing are invalid. It's unmapped on drop.
+pub struct JitBuffer {
+    ptr: *mut u8,
+    capacity: usize,
+    len: usize,
+    executable: bool,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum JitError {
+    // UndefinedLabel - A label is referenced but never placed.
+    UndefinedLabel(Label),
+
+    // UnsupportedRelocation - The fixup can't be resolved in memory (i.e. a real mode segment).
+    UnsupportedRelocation(FixupKind, OperandSize),
+
+    // OutOfRange - The address of a label doesn't fit in the field referring to it.
+    OutOfRange(Label),
+
+    // MapFailed - mmap or mprotect failed with the given OS error code.
+    MapFailed(i32),
+
+    // NotExecutable - The code has to be finalized first.
+    NotExecutable,
+
+    // WriteFailed - Writing held back instructions, or a listener recording a function, failed.
+    WriteFailed,
+
+    // InvalidUnwindInfo - The frame info of the function starting at the label can't be encoded.
+    InvalidUnwindInfo(Label),
+
+    // UnsupportedMode - There's no frame info for code in the mode (real mode).
+    UnsupportedMode(Mode),
+
+    // InvalidOffset - There's no code at the offset.
+    InvalidOffset(u64),
+
+    // NotFunctionPointer - The type asked for isn't the size of a function pointer.
+    NotFunctionPointer,
+}
+
+impl fmt::Display for JitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            JitError::UndefinedLabel(label) =>
+                write!(f, "label {} is referenced but never placed", label.get_id()),
+            JitError::UnsupportedRelocation(kind, size) =>
+                write!(f, "a {:?} {:?} fixup can't be resolved in memory", size, kind),
+            JitError::OutOfRange(label) =>
+                write!(f, "label {} is out of range", label.get_id()),
+            JitError::MapFailed(code) =>
+                write!(f, "failed to map memory: {}", io::Error::from_raw_os_error(code)),
+            JitError::NotExecutable => f.write_str("the code hasn't been finalized"),
+            JitError::WriteFailed => f.write_str("failed to record the function"),
+            JitError::InvalidUnwindInfo(label) =>
+                write!(f, "invalid frame info for the function at label {}", label.get_id()),
+            JitError::UnsupportedMode(mode) =>
+                write!(f, "no frame info for {:?} mode code", mode),
+            JitError::InvalidOffset(offset) => write!(f, "no code at offset {}", offset),
+            JitError::NotFunctionPointer => f.write_str("the type isn't a function pointer"),
+        }
+    }
+}
+
+impl Error for JitError { }
+
+fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+fn last_error() -> JitError {
+    JitError::MapFailed(io::Error::last_os_error().raw_os_error().unwrap_or(0))
+}
+
+// Rounds up to a whole number of pages, at least one.
+fn round_to_pages(size: usize) -> usize {
+    let page = page_size();
+    size.max(1).div_ceil(page) * page
+}
+
+fn map(capacity: usize) -> Result<*mut u8, JitError> {
+    let ptr = unsafe {
+        libc::mmap(ptr::null_mut(), capacity, libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANON, -1, 0)
+    };
+    if ptr == libc::MAP_FAILED { Err(last_error()) } else { Ok(ptr as *mut u8) }
+}
+
+impl JitBuffer {
+    // A writable buffer of one page.
+    pub fn new() -> Result<JitBuffer, JitError> {
+        JitBuffer::with_capacity(0)
+    }
+
+    // A writable buffer of at least `capacity` bytes, rounded up to whole pages.
+    pub fn with_capacity(capacity: usize) -> Result<JitBuffer, JitError> {
+        let capacity = round_to_pages(capacity);
+        Ok(JitBuffer { ptr: map(capacity)?, capacity, len: 0, executable: false })
+    }
+
+    // The number of bytes written.
+    pub fn len(&self) -> usize { self.len }
+
+    pub fn is_empty(&self) -> bool { self.len == 0 }
+
+    // The size of the mapping.
+    pub fn capacity(&self) -> usize { self.capacity }
+
+    pub fn is_executable(&self) -> bool { self.executable }
+
+    // The address the code starts at. It changes when the buffer grows.
+    pub fn get_address(&self) -> u64 { self.ptr as u64 }
+
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    // The code for patching in place, see Patcher, or None while it's executable.
+    pub fn as_mut_slice(&mut self) -> Option<&mut [u8]> {
+        if self.executable { return None; }
+        Some(unsafe { slice::from_raw_parts_mut(self.ptr, self.len) })
+    }
+
+    // Maps a larger region and moves the code to it.
+    fn grow(&mut self, needed: usize) -> Result<(), JitError> {
+        let capacity = round_to_pages(needed.max(self.capacity * 2));
+        let ptr = map(capacity)?;
+        unsafe {
+            ptr::copy_nonoverlapping(self.ptr, ptr, self.len);
+            libc::munmap(self.ptr as *mut libc::c_void, self.capacity);
+        }
+        self.ptr = ptr;
+        self.capacity = capacity;
+        Ok(())
+    }
+
+    fn protect(&mut self, executable: bool) -> Result<(), JitError> {
+        let prot = if executable { libc::PROT_READ | libc::PROT_EXEC }
+            else { libc::PROT_READ | libc::PROT_WRITE };
+        if unsafe { libc::mprotect(self.ptr as *mut libc::c_void, self.capacity, prot) } != 0 {
+            return Err(last_error());
+        }
+        self.executable = executable;
+        Ok(())
+    }
+
+    // Makes the code executable and read-only. Labels have to be resolved first, see
+    // InstructionWriter::finalize.
+    pub fn make_executable(&mut self) -> Result<(), JitError> { self.protect(true) }
+
+    // Makes the code writable again (and not executable), i.e. to append to or patch it.
+    pub fn make_writable(&mut self) -> Result<(), JitError> { self.protect(false) }
+
+    /// A function pointer to the code at `offset`, once the buffer is executable.
+    ///
+    /// # Safety
+    ///
+    /// F has to be a function pointer type whose signature and calling convention match the
+    /// code, i.e. `extern "C" fn(u64) -> u64`. Only its size is checked. The pointer must not be
+    /// called once the buffer is made writable or dropped.
+    pub unsafe fn get_function<F: Copy>(&self, offset: u64) -> Result<F, JitError> {
+        if mem::size_of::<F>() != mem::size_of::<usize>() {
+            return Err(JitError::NotFunctionPointer);
+        }
+        if !self.executable { return Err(JitError::NotExecutable); }
+        if offset >= self.len as u64 { return Err(JitError::InvalidOffset(offset)); }
+        let address = self.ptr.add(offset as usize);
+        Ok(mem::transmute_copy(&address))
+    }
+}
+
+impl Write for JitBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.executable {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied,
+                "the JIT buffer is executable"));
+        }
+        let needed = self.len + buf.len();
+        if needed > self.capacity {
+            self.grow(needed).map_err(|e| io::Error::other(e.to_string()))?;
+        }
+        unsafe { ptr::copy_nonoverlapping(buf.as_ptr(), self.ptr.add(self.len), buf.len()); }
+        self.len = needed;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> { Ok(()) }
+}
+
+impl Drop for JitBuffer {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr as *mut libc::c_void, self.capacity); }
+    }
+}
+
+impl fmt::Debug for JitBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("JitBuffer").field("address", &self.ptr).field("len", &self.len)
+            .field("capacity", &self.capacity).field("executable", &self.executable).finish()
+    }
+}
+
+impl InstructionWriter<JitBuffer> {
+    // The address a label is at in memory, which changes when the buffer grows.
+    pub fn get_label_address(&self, label: Label) -> Option<u64> {
+        let base = self.get_inner_writer_ref().get_address();
+        self.get_label_offset(label).map(|offset| base + offset)
+    }
+
+    // Fills in every field referring to a label with its address in the buffer and makes the code
+    // executable. Call it again after making the buffer writable and appending to it, since the
+    // buffer may have moved.
+    pub fn finalize(&mut self) -> Result<(), JitError> {
+        self.flush().map_err(|_| JitError::WriteFailed)?;
+        let base = self.get_inner_writer_ref().get_address();
+        let fixups = self.get_fixups().map_err(|_| JitError::WriteFailed)?;
+        let mut resolved = Vec::with_capacity(fixups.len());
+        for fixup in fixups {
+            if fixup.kind == FixupKind::Segment {
+                return Err(JitError::UnsupportedRelocation(fixup.kind, fixup.size));
+            }
+            let target = self.get_label_address(fixup.label)
+                .ok_or(JitError::UndefinedLabel(fixup.label))?;
+            resolved.push((*fixup, target));
+        }
+
+        let buffer = self.get_inner_writer_mut();
+        if buffer.executable { buffer.make_writable()?; }
+        let code = unsafe { slice::from_raw_parts_mut(buffer.ptr, buffer.len) };
+        for (fixup, target) in resolved {
+            fixup.apply(code, target, base).map_err(|_| JitError::OutOfRange(fixup.label))?;
+        }
+        buffer.make_executable()
+    }
+
+    /// A function pointer to the code at a label, once finalized.
+    ///
+    /// # Safety
+    ///
+    /// The same as JitBuffer::get_function: F has to be a function pointer type matching the
+    /// code, and the pointer must not be called once the buffer is made writable or dropped.
+    pub unsafe fn get_function<F: Copy>(&self, label: Label) -> Result<F, JitError> {
+        let offset = self.get_label_offset(label).ok_or(JitError::UndefinedLabel(label))?;
+        self.get_inner_writer_ref().get_function(offset)
+    }
+
+    // Tells a listener (i.e. a PerfMap or JitDump) about the finalized function between two
+    // labels, so profilers can name it.
+    pub fn report_function<L: JitListener>(&self, listener: &mut L, name: &str, start: Label,
+        end: Label) -> Result<(), JitError> {
+        let buffer = self.get_inner_writer_ref();
+        if !buffer.executable { return Err(JitError::NotExecutable); }
+        let start_offset = self.get_label_offset(start).ok_or(JitError::UndefinedLabel(start))?;
+        let end_offset = self.get_label_offset(end).ok_or(JitError::UndefinedLabel(end))?;
+        let code = buffer.as_slice().get(start_offset as usize..end_offset as usize)
+            .ok_or(JitError::OutOfRange(end))?;
+        listener.code_loaded(name, buffer.get_address() + start_offset, code)
+            .map_err(|_| JitError::WriteFailed)
+    }
+
+    // Builds .eh_frame contents for functions in the buffer, with their current addresses and a
+    // zero terminator, ready for __register_frame. They have to be built again if the buffer moves.
+    pub fn build_eh_frame(&self, frames: &[FrameInfo]) -> Result<Vec<u8>, JitError> {
+        let offset = |label| self.get_label_offset(label);
+        let (mut bytes, fixups) = build_frame_section(frames, self.get_mode(),
+            FrameFormat::EhFrameAbsolute, offset).map_err(|e| match e {
+                ObjectError::UndefinedLabel(label) => JitError::UndefinedLabel(label),
+                ObjectError::UnsupportedMode(mode) => JitError::UnsupportedMode(mode),
+                ObjectError::InvalidUnwindInfo(label) => JitError::InvalidUnwindInfo(label),
+                _ => unreachable!("Building frame info only fails on labels and the mode.")
+            })?;
+        bytes.extend_from_slice(&[0; 4]);
+        for fixup in fixups {
+            let target = self.get_label_address(fixup.label)
+                .ok_or(JitError::UndefinedLabel(fixup.label))?;
+            fixup.apply(&mut bytes, target, 0).map_err(|_| JitError::OutOfRange(fixup.label))?;
+        }
+        Ok(bytes)
+    }
+}
@@ -0,0 +1,106 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use byteorder::{ByteOrder, LittleEndian};
+use ::{InstructionEncodingError, OperandSize};
+
+static NEXT_LABEL: AtomicUsize = AtomicUsize::new(0);
+
+// A location operands can refer to before its address is known, i.e. a forward branch target or
+// a symbol defined in another object. Labels are unique within the process, so labels from
+// different writers can be mixed freely.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Label(usize);
+
+impl Label {
+    pub fn new() -> Label {
+        Label(NEXT_LABEL.fetch_add(1, Ordering::Relaxed))
+    }
+
+    pub fn get_id(&self) -> usize { self.0 }
+}
+
+impl Default for Label {
+    fn default() -> Label { Label::new() }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FixupKind {
+    // Branch - Relative branch target, measured from the end of the instruction.
+    Branch,
+
+    // Relative - RIP-relative memory operand, measured from the end of the instruction.
+    Relative,
+
+    // Absolute - Address of the label.
+    Absolute,
+
+    // SignExtended - Address of the label in a 32-bit field the processor sign-extends to 64 bits
+    // (i.e. MOV r/m64, imm32).
+    SignExtended,
+
+    // Segment - Paragraph (address / 16) of the segment holding the label, for real mode far
+    // pointers. Only formats with segment relocations (MZ executables) support it.
+    Segment,
+}
+
+// A field in the emitted code that has to be filled in with the address of a label.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Fixup {
+    // Offset of the field from the start of the code.
+    pub offset: u64,
+    pub size: OperandSize,
+    pub label: Label,
+    pub kind: FixupKind,
+
+    // Added to the address of the label. For relative fixups this includes the distance from the
+    // field to the end of the instruction, like an ELF addend.
+    pub addend: i64,
+}
+
+impl Fixup {
+    pub fn is_relative(&self) -> bool {
+        self.kind == FixupKind::Branch || self.kind == FixupKind::Relative
+    }
+
+    // The value the field holds once the label is at `target`, with the code starting at `base`.
+    // For segment fixups `target` is the address of the segment holding the label.
+    pub fn value(&self, target: u64, base: u64) -> i64 {
+        let value = (target as i64).wrapping_add(self.addend);
+        if self.kind == FixupKind::Segment {
+            value >> 4
+        } else if self.is_relative() {
+            value.wrapping_sub(base.wrapping_add(self.offset) as i64)
+        } else { value }
+    }
+
+    // Writes the resolved value into `code`, which starts at address `base`.
+    pub fn apply(&self, code: &mut [u8], target: u64, base: u64)
+        -> Result<(), InstructionEncodingError> {
+        let value = self.value(target, base);
+        let start = self.offset as usize;
+        let bytes = (self.size.bits() / 8) as usize;
+        let field = code.get_mut(start..start + bytes)
+            .ok_or(InstructionEncodingError::MismatchedEncoding)?;
+
+        if !fits(value, self.size, self.is_relative() || self.kind == FixupKind::SignExtended) {
+            return Err(InstructionEncodingError::MismatchedSize);
+        }
+        match self.size {
+            OperandSize::Byte => field[0] = value as u8,
+            OperandSize::Word => LittleEndian::write_u16(field, value as u16),
+            OperandSize::Dword => LittleEndian::write_u32(field, value as u32),
+            OperandSize::Qword => LittleEndian::write_u64(field, value as u64),
+            _ => return Err(InstructionEncodingError::MismatchedSize)
+        }
+        Ok(())
+    }
+}
+
+// Checks if the value can be stored in a field of the given size. Absolute addresses can be
+// either sign or zero extended.
+fn fits(value: i64, size: OperandSize, signed: bool) -> bool {
+    let bits = size.bits();
+    if bits >= 64 { return true; }
+    let min = -(1i64 << (bits - 1));
+    let max = if signed { (1i64 << (bits - 1)) - 1 } else { (1i64 << bits) - 1 };
+    value >= min && value <= max
+}
@@ -2,22 +2,65 @@ extern crate arrayvec;
 extern crate byteorder;
 #[macro_use] extern crate lazy_static;
 #[macro_use] extern crate matches;
+#[cfg(unix)] extern crate libc;
 
+mod abi;
+mod branch_align;
+mod cfi;
+mod coff;
+mod condition;
 mod decoding;
+mod dos;
+mod elf;
+mod elf_file;
 mod encoding;
+mod explain;
+mod flat;
+mod flow;
 mod instruction;
 mod instruction_buffer;
 mod instruction_def;
 mod instruction_defs;
+#[cfg(unix)] mod jit;
+mod label;
+mod listing;
+mod mitigate;
 mod mnemonic;
+mod object;
 mod operand;
+mod parser;
+mod patch;
+mod relocate;
+mod signature;
+#[cfg(unix)] mod perf;
+mod validation;
 #[cfg(test)] mod test;
 
-pub use self::decoding::{InstructionDecodingError, InstructionReader};
-pub use self::encoding::{InstructionEncodingError, InstructionWriter};
+pub use self::abi::{AbiError, ArgumentLocation, ArgumentType, CallingConvention, Frame};
+pub use self::cfi::{build_frame_section, CfiOp, FrameFormat, FrameInfo};
+pub use self::coff::{UnwindInfo, UnwindOp};
+pub use self::condition::Condition;
+pub use self::decoding::{FieldKind, InstructionDecodingError, InstructionReader};
+pub use self::elf_file::{ElfDisassembly, ElfError, ElfFile, ElfInstruction, ElfSection, ElfSymbol};
+pub use self::encoding::{EncodedField, Fill, InstructionEncodingError, InstructionLayout, InstructionWriter};
+pub use self::explain::{explain, ExplainError, ExplainedField, Explanation};
+pub use self::flat::FlatBinary;
+pub use self::flow::{BranchCondition, BranchTarget, FlowKind};
+pub use self::instruction_def::{get_instruction_defs, get_mnemonics, InstructionDefinition};
 pub use self::instruction::{ Instruction, Reg, RegScale, SegmentReg, MergeMode, MaskReg, BroadcastMode, RoundingMode, RegType };
+#[cfg(unix)] pub use self::jit::{JitBuffer, JitError};
+pub use self::label::{Fixup, FixupKind, Label};
+pub use self::listing::{write_assembly_listing, Listing, ListingEntry};
 pub use self::operand::{Operand, OperandSize};
+pub use self::mitigate::{mitigate, write_items, CodeItem, MitigationError, Mitigations};
 pub use self::mnemonic::Mnemonic;
+#[cfg(unix)] pub use self::perf::{JitDump, JitListener, PerfMap};
+pub use self::parser::{assemble, parse_instruction, parse_number, AsmError, ParseError};
+pub use self::patch::{nop_padding, Patcher, PatchError};
+pub use self::relocate::{find_instruction_boundary, relocate, RelocationError};
+pub use self::signature::Signature;
+pub use self::object::{Binding, ObjectError, ObjectWriter, Relocation, RelocationTarget, Resolved, Section, Symbol};
+pub use self::validation::{ValidationError, ValidationReason};
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Mode {
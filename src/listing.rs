@@ -0,0 +1,183 @@
+use std::io::{self, Write};
+use ::{ElfFile, Instruction, InstructionDecodingError, InstructionReader, Mode, Operand, Reg};
+use ::elf::{ET_REL, STT_FUNC, STT_NOTYPE, STT_OBJECT};
+use ::flow::BranchTarget;
+use ::relocate::wrap;
+
+// How many bytes objdump shows on each line before wrapping.
+pub const LISTING_BYTES_PER_LINE: usize = 7;
+
+// Renders decoded code like `objdump -d -M intel`: the address, the raw bytes (wrapping long
+// instructions onto continuation lines), the instruction and, for direct branches, the target
+// with the nearest preceding symbol, i.e. "call 0x401020 <helper+0x4>".
+#[derive(Clone, Debug, Default)]
+pub struct Listing {
+    symbols: Vec<(u64, String)>, // Sorted by address
+}
+
+impl Listing {
+    pub fn new() -> Listing { Listing { symbols: Vec::new() } }
+
+    // A listing with the named functions and objects of an ELF file.
+    pub fn from_elf(file: &ElfFile) -> Listing {
+        let mut listing = Listing::new();
+        for symbol in file.get_symbols().iter().filter(|s| !s.name.is_empty() &&
+            s.section_index != 0 &&
+            [STT_FUNC, STT_NOTYPE, STT_OBJECT].contains(&s.get_type())) {
+            let address = match file.get_sections().get(symbol.section_index as usize) {
+                Some(section) if file.get_file_type() == ET_REL =>
+                    section.address + symbol.value,
+                _ => symbol.value
+            };
+            listing.add_symbol(address, &symbol.name);
+        }
+        listing
+    }
+
+    // Names an address. The first name given to an address is kept.
+    pub fn add_symbol(&mut self, address: u64, name: &str) {
+        let at = self.symbols.partition_point(|s| s.0 <= address);
+        if at == 0 || self.symbols[at - 1].0 != address {
+            self.symbols.insert(at, (address, name.to_owned()));
+        }
+    }
+
+    // The nearest symbol at or before the address, with the offset from it.
+    pub fn get_symbol(&self, address: u64) -> Option<(&str, u64)> {
+        let at = self.symbols.partition_point(|s| s.0 <= address);
+        self.symbols[..at].last().map(|s| (&s.1[..], address - s.0))
+    }
+
+    // "<name>" or "<name+0x10>" for the address, if it has a symbol.
+    pub fn annotate(&self, address: u64) -> Option<String> {
+        self.get_symbol(address).map(|(name, offset)| if offset == 0 {
+            format!("<{}>", name)
+        } else {
+            format!("<{}+0x{:x}>", name, offset)
+        })
+    }
+
+    // The instruction text, with the absolute target of relative branches, and of RIP-relative
+    // operands after a "#", i.e. "mov rax, qword ptr [rip+0x10] # 0x1017". Targets wrap around
+    // at the size of the instruction pointer in the mode.
+    pub fn format_instruction(&self, address: u64, len: usize, instr: &Instruction, mode: Mode)
+        -> String {
+        let next = address.wrapping_add(len as u64);
+        let relative = instr.operands().iter().filter_map(|op| match **op {
+            Some(Operand::IndirectDisplaced(Reg::RIP, disp, ..)) => Some(next.wrapping_add(disp)),
+            Some(Operand::IndirectDisplaced(Reg::EIP, disp, ..)) =>
+                Some(next.wrapping_add(disp) & 0xFFFF_FFFF),
+            _ => None
+        }).next();
+        if let Some(target) = relative {
+            return match self.annotate(target) {
+                Some(symbol) => format!("{} # 0x{:x} {}", instr, target, symbol),
+                None => format!("{} # 0x{:x}", instr, target)
+            };
+        }
+        match instr.flow_kind().target() {
+            Some(BranchTarget::Relative(offset)) => {
+                let target = wrap(next.wrapping_add(offset as u64), mode);
+                let mut shown = *instr;
+                shown.operand1 = Some(Operand::Literal64(target));
+                match self.annotate(target) {
+                    Some(symbol) => format!("{} {}", shown, symbol),
+                    None => shown.to_string()
+                }
+            },
+            _ => instr.to_string()
+        }
+    }
+
+    // Writes one instruction, or "(bad)" for bytes that couldn't be decoded.
+    pub fn write_instruction<W: Write>(&self, writer: &mut W, address: u64, bytes: &[u8],
+        instr: &Result<Instruction, InstructionDecodingError>, mode: Mode) -> io::Result<()> {
+        if let Some((_, name)) = self.symbols.iter().find(|s| s.0 == address) {
+            writeln!(writer, "\n{:016x} <{}>:", address, name)?;
+        }
+        let text = match *instr {
+            Ok(ref instr) => self.format_instruction(address, bytes.len(), instr, mode),
+            Err(_) => "(bad)".to_owned()
+        };
+        for (i, chunk) in bytes.chunks(LISTING_BYTES_PER_LINE).enumerate() {
+            let hex: String = chunk.iter().map(|b| format!("{:02x} ", b)).collect();
+            let line_address = wrap(address.wrapping_add((i * LISTING_BYTES_PER_LINE) as u64),
+                mode);
+            if i == 0 {
+                writeln!(writer, "{:>8x}:\t{:<width$}\t{}", line_address, hex, text,
+                    width = 3 * LISTING_BYTES_PER_LINE)?;
+            } else {
+                writeln!(writer, "{:>8x}:\t{}", line_address, hex.trim_end())?;
+            }
+        }
+        Ok(())
+    }
+
+    // Decodes and writes code that is loaded at `address`. Bytes that can't be decoded are shown
+    // one at a time, so decoding picks up again after them.
+    pub fn write_code<W: Write>(&self, writer: &mut W, code: &[u8], address: u64, mode: Mode)
+        -> io::Result<()> {
+        let mut offset = 0;
+        while offset < code.len() {
+            let instr = InstructionReader::new(&code[offset..], mode).read();
+            let len = instr.as_ref().map_or(1, |i| i.1);
+            self.write_instruction(writer, wrap(address.wrapping_add(offset as u64), mode),
+                &code[offset..offset + len], &instr.map(|i| i.0), mode)?;
+            offset += len;
+        }
+        Ok(())
+    }
+
+    // Writes the executable sections of an ELF file, like `objdump -d`.
+    pub fn write_elf<W: Write>(&self, writer: &mut W, file: &ElfFile) -> io::Result<()> {
+        let mut section = None;
+        for record in file.disassemble() {
+            if section != Some(record.section.index) {
+                writeln!(writer, "\nDisassembly of section {}:", record.section.name)?;
+                section = Some(record.section.index);
+            }
+            self.write_instruction(writer, record.address, record.bytes, &record.instruction,
+                file.get_mode())?;
+        }
+        Ok(())
+    }
+}
+
+// An instruction or data written through an InstructionWriter with the listing enabled. The
+// bytes of fields that refer to labels are still zero, as they are before linking.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListingEntry {
+    pub offset: u64,
+    pub bytes: Vec<u8>,
+    pub instruction: Option<Instruction>, // None for data
+    pub source: Option<(usize, String)>, // Line number and text
+}
+
+// How many bytes the assembler listing shows on each line before wrapping.
+pub const ASSEMBLY_BYTES_PER_LINE: usize = 8;
+
+// Writes an assembler listing in the style of `nasm -l`: the source line number, the offset, the
+// bytes and the source text (or the instruction, for code that wasn't assembled from text).
+pub fn write_assembly_listing<W: Write>(writer: &mut W, entries: &[ListingEntry])
+    -> io::Result<()> {
+    for entry in entries {
+        let line = entry.source.as_ref().map_or(String::new(), |s| s.0.to_string());
+        let text = match (&entry.source, &entry.instruction) {
+            (Some((_, text)), _) => text.clone(),
+            (None, Some(instr)) => instr.to_string(),
+            (None, None) => format!("db {}", entry.bytes.iter().map(|b| format!("0x{:02x}", b))
+                .collect::<Vec<_>>().join(", "))
+        };
+        let mut chunks = entry.bytes.chunks(ASSEMBLY_BYTES_PER_LINE);
+        let first: String = chunks.next().unwrap_or(&[]).iter().map(|b| format!("{:02X}", b))
+            .collect();
+        writeln!(writer, "{:>6} {:08X} {:<width$} {}", line, entry.offset, first, text,
+            width = 2 * ASSEMBLY_BYTES_PER_LINE)?;
+        for (i, chunk) in chunks.enumerate() {
+            let hex: String = chunk.iter().map(|b| format!("{:02X}", b)).collect();
+            writeln!(writer, "{:>6} {:08X} {}", line,
+                entry.offset + ((i + 1) * ASSEMBLY_BYTES_PER_LINE) as u64, hex)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,197 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::io::Write;
+use ::{Instruction, InstructionEncodingError, InstructionWriter, Label, Mnemonic, Mode, Operand,
+    OperandSize, Reg};
+use ::flow::{BranchTarget, FlowKind};
+
+// An instruction, or a label placed before the instructions that follow it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CodeItem {
+    Instruction(Instruction),
+    Label(Label),
+}
+
+// Writes the instructions and places the labels.
+pub fn write_items<T: Write>(writer: &mut InstructionWriter<T>, items: &[CodeItem])
+    -> Result<(), InstructionEncodingError> {
+    for item in items {
+        match *item {
+            CodeItem::Instruction(ref instr) => { writer.write(instr)?; },
+            CodeItem::Label(label) => writer.set_label(label),
+        }
+    }
+    Ok(())
+}
+
+// Which speculative execution mitigations `mitigate` applies.
+#[derive(Clone, Debug, Default)]
+pub struct Mitigations {
+    // Indirect CALL and JMP through a register go through a retpoline thunk, which keeps the
+    // processor from speculating into a predicted target.
+    pub retpoline: bool,
+
+    // LFENCE at both successors of conditional branches, so nothing after them runs
+    // speculatively before the condition is known.
+    pub lfence: bool,
+
+    // RET jumps to a return thunk, which keeps the processor from speculating into a target
+    // predicted by the return stack buffer.
+    pub return_thunk: bool,
+
+    // ENDBR64 (ENDBR32 in protected mode) at the labels code can branch to indirectly, for
+    // indirect branch tracking: the labels whose address is taken (i.e. LEA or MOV of the label)
+    // and `indirect_targets`, i.e. exported functions and jump table entries.
+    pub endbranch: bool,
+    pub indirect_targets: Vec<Label>,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MitigationError {
+    // Unmitigated - The item at the index can't be rewritten, i.e. an indirect branch through
+    // memory, which has to be loaded into a register first, or a RET that pops arguments.
+    Unmitigated(usize),
+
+    // UnsupportedMode - The mitigations need 32 or 64-bit code.
+    UnsupportedMode(Mode),
+}
+
+impl fmt::Display for MitigationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MitigationError::Unmitigated(index) =>
+                write!(f, "the instruction at index {} can't be mitigated", index),
+            MitigationError::UnsupportedMode(mode) =>
+                write!(f, "mitigations aren't supported in {:?} mode", mode),
+        }
+    }
+}
+
+impl Error for MitigationError { }
+
+// The labels branched to indirectly: ones whose address is taken, and the given ones.
+fn indirect_targets(items: &[CodeItem], targets: &[Label]) -> HashSet<Label> {
+    let mut labels: HashSet<Label> = targets.iter().cloned().collect();
+    for item in items {
+        let instr = match *item {
+            CodeItem::Instruction(ref instr) => instr,
+            CodeItem::Label(_) => continue
+        };
+        let direct = match instr.flow_kind().target() {
+            Some(BranchTarget::Label(label)) => Some(label),
+            _ => None
+        };
+        for op in [instr.operand1, instr.operand2, instr.operand3, instr.operand4].iter() {
+            match *op {
+                Some(Operand::Label(label)) if Some(label) != direct => { labels.insert(label); },
+                Some(Operand::LabelMemory(label, ..)) if instr.mnemonic == Mnemonic::LEA => {
+                    labels.insert(label);
+                },
+                _ => {}
+            }
+        }
+    }
+    labels
+}
+
+fn instr(mnemonic: Mnemonic, operands: &[Operand]) -> CodeItem {
+    CodeItem::Instruction(Instruction {
+        mnemonic,
+        operand1: operands.first().cloned(),
+        operand2: operands.get(1).cloned(),
+        .. Default::default()
+    })
+}
+
+// Writes a retpoline: the CALL pushes the address of a speculation trap and predicts a return to
+// it, then `set_up` replaces it with the real target (the register, or the return address under
+// it) and returns there.
+fn retpoline(items: &mut Vec<CodeItem>, entry: Label, target: Option<Reg>, mode: Mode) {
+    let (sp, size) = if mode == Mode::Long { (Reg::RSP, OperandSize::Qword) }
+        else { (Reg::ESP, OperandSize::Dword) };
+    let (trap, set_up) = (Label::new(), Label::new());
+    items.push(CodeItem::Label(entry));
+    items.push(instr(Mnemonic::CALL, &[Operand::Label(set_up)]));
+    items.push(CodeItem::Label(trap));
+    items.push(instr(Mnemonic::PAUSE, &[]));
+    items.push(instr(Mnemonic::LFENCE, &[]));
+    items.push(instr(Mnemonic::JMP, &[Operand::Label(trap)]));
+    items.push(CodeItem::Label(set_up));
+    match target {
+        Some(reg) => items.push(instr(Mnemonic::MOV, &[Operand::Indirect(sp, Some(size), None),
+            Operand::Direct(reg)])),
+        None => items.push(instr(Mnemonic::LEA, &[Operand::Direct(sp),
+            Operand::IndirectDisplaced(sp, size.bits() as u64 / 8, None, None)])),
+    }
+    items.push(instr(Mnemonic::RET, &[]));
+}
+
+// Rewrites code to mitigate speculative execution attacks. Thunks that are needed are added at
+// the end, after a label each.
+pub fn mitigate(items: &[CodeItem], mitigations: &Mitigations, mode: Mode)
+    -> Result<Vec<CodeItem>, MitigationError> {
+    if mode == Mode::Real { return Err(MitigationError::UnsupportedMode(mode)); }
+    let endbr = if mode == Mode::Long { Mnemonic::ENDBR64 } else { Mnemonic::ENDBR32 };
+    let indirect = if mitigations.endbranch {
+        indirect_targets(items, &mitigations.indirect_targets)
+    } else {
+        HashSet::new()
+    };
+    let branch_targets: HashSet<Label> = items.iter().filter_map(|item| match *item {
+        CodeItem::Instruction(ref instr) => match instr.flow_kind() {
+            FlowKind::ConditionalJump(_, BranchTarget::Label(label)) => Some(label),
+            _ => None
+        },
+        CodeItem::Label(_) => None
+    }).collect();
+
+    let mut code = Vec::with_capacity(items.len());
+    let mut thunks: Vec<(Option<Reg>, Label)> = Vec::new();
+    let mut thunk = |target: Option<Reg>| {
+        if let Some(&(_, label)) = thunks.iter().find(|t| t.0 == target) { return label; }
+        let label = Label::new();
+        thunks.push((target, label));
+        label
+    };
+    let word = if mode == Mode::Long { OperandSize::Qword } else { OperandSize::Dword };
+    for (i, item) in items.iter().enumerate() {
+        code.push(*item);
+        let current = match *item {
+            CodeItem::Instruction(current) => current,
+            CodeItem::Label(_) => {
+                // Once, after the last of the labels at the position.
+                if matches!(items.get(i + 1), Some(&CodeItem::Label(_))) { continue; }
+                let placed = |labels: &HashSet<Label>| items[..i + 1].iter().rev()
+                    .take_while(|item| matches!(**item, CodeItem::Label(_)))
+                    .any(|item| matches!(*item, CodeItem::Label(l) if labels.contains(&l)));
+                if placed(&indirect) { code.push(instr(endbr, &[])); }
+                if mitigations.lfence && placed(&branch_targets) {
+                    code.push(instr(Mnemonic::LFENCE, &[]));
+                }
+                continue;
+            }
+        };
+        match current.flow_kind() {
+            FlowKind::IndirectJump(_) | FlowKind::IndirectCall(_) if mitigations.retpoline => {
+                let target = match current.operand1 {
+                    Some(Operand::Direct(reg)) if reg.size() == word => reg,
+                    _ => return Err(MitigationError::Unmitigated(i))
+                };
+                code.pop();
+                code.push(instr(current.mnemonic, &[Operand::Label(thunk(Some(target)))]));
+            },
+            FlowKind::Return if mitigations.return_thunk && current.mnemonic == Mnemonic::RET => {
+                if current.operand1.is_some() { return Err(MitigationError::Unmitigated(i)); }
+                code.pop();
+                code.push(instr(Mnemonic::JMP, &[Operand::Label(thunk(None))]));
+            },
+            FlowKind::ConditionalJump(..) if mitigations.lfence => {
+                code.push(instr(Mnemonic::LFENCE, &[]));
+            },
+            _ => {}
+        }
+    }
+    for (target, label) in thunks { retpoline(&mut code, label, target, mode); }
+    Ok(code)
+}
@@ -61,6 +61,7 @@ pub enum Mnemonic {
     CLFLUSH,
     CLFLUSHOPT,
     CLI,
+    CLRSSBSY,
     CLTS,
     CLWB,
     CMC,
@@ -91,6 +92,9 @@ pub enum Mnemonic {
     CMOVO,
     CMOVP,
     CMOVPE,
+    CMOVPO,
+    CMOVS,
+    CMOVZ,
     CMP,
     CMPPD,
     CMPPS,
@@ -143,6 +147,8 @@ pub enum Mnemonic {
     DPPD,
     DPPS,
     EMMS,
+    ENDBR32,
+    ENDBR64,
     ENTER,
     EXTRACTPS,
     F2XM1,
@@ -251,6 +257,8 @@ pub enum Mnemonic {
     IMUL,
     IN,
     INC,
+    INCSSPD,
+    INCSSPQ,
     INS,
     INSB,
     INSD,
@@ -603,6 +611,8 @@ pub enum Mnemonic {
     RDPMC,
     RDRAND,
     RDSEED,
+    RDSSPD,
+    RDSSPQ,
     RDTSC,
     RDTSCP,
     RET,
@@ -616,10 +626,12 @@ pub enum Mnemonic {
     RSM,
     RSQRTPS,
     RSQRTSS,
+    RSTORSSP,
     SAHF,
     SAL,
     SAR,
     SARX,
+    SAVEPREVSSP,
     SBB,
     SCAS,
     SCASB,
@@ -646,6 +658,17 @@ pub enum Mnemonic {
     SETNGE,
     SETNL,
     SETNLE,
+    SETNO,
+    SETNP,
+    SETNS,
+    SETNZ,
+    SETO,
+    SETP,
+    SETPE,
+    SETPO,
+    SETS,
+    SETSSBSY,
+    SETZ,
     SFENCE,
     SGDT,
     SHA1MSG1,
@@ -1282,6 +1305,10 @@ pub enum Mnemonic {
     WRGSBASE,
     WRMSR,
     WRPKRU,
+    WRSSD,
+    WRSSQ,
+    WRUSSD,
+    WRUSSQ,
     XABORT,
     XACQUIRE,
     XADD,
@@ -1375,6 +1402,7 @@ impl Mnemonic {
             "CLFLUSH" => Ok(Mnemonic::CLFLUSH),
             "CLFLUSHOPT" => Ok(Mnemonic::CLFLUSHOPT),
             "CLI" => Ok(Mnemonic::CLI),
+            "CLRSSBSY" => Ok(Mnemonic::CLRSSBSY),
             "CLTS" => Ok(Mnemonic::CLTS),
             "CLWB" => Ok(Mnemonic::CLWB),
             "CMC" => Ok(Mnemonic::CMC),
@@ -1405,6 +1433,9 @@ impl Mnemonic {
             "CMOVO" => Ok(Mnemonic::CMOVO),
             "CMOVP" => Ok(Mnemonic::CMOVP),
             "CMOVPE" => Ok(Mnemonic::CMOVPE),
+            "CMOVPO" => Ok(Mnemonic::CMOVPO),
+            "CMOVS" => Ok(Mnemonic::CMOVS),
+            "CMOVZ" => Ok(Mnemonic::CMOVZ),
             "CMP" => Ok(Mnemonic::CMP),
             "CMPPD" => Ok(Mnemonic::CMPPD),
             "CMPPS" => Ok(Mnemonic::CMPPS),
@@ -1457,6 +1488,8 @@ impl Mnemonic {
             "DPPD" => Ok(Mnemonic::DPPD),
             "DPPS" => Ok(Mnemonic::DPPS),
             "EMMS" => Ok(Mnemonic::EMMS),
+            "ENDBR32" => Ok(Mnemonic::ENDBR32),
+            "ENDBR64" => Ok(Mnemonic::ENDBR64),
             "ENTER" => Ok(Mnemonic::ENTER),
             "EXTRACTPS" => Ok(Mnemonic::EXTRACTPS),
             "F2XM1" => Ok(Mnemonic::F2XM1),
@@ -1565,6 +1598,8 @@ impl Mnemonic {
             "IMUL" => Ok(Mnemonic::IMUL),
             "IN" => Ok(Mnemonic::IN),
             "INC" => Ok(Mnemonic::INC),
+            "INCSSPD" => Ok(Mnemonic::INCSSPD),
+            "INCSSPQ" => Ok(Mnemonic::INCSSPQ),
             "INS" => Ok(Mnemonic::INS),
             "INSB" => Ok(Mnemonic::INSB),
             "INSD" => Ok(Mnemonic::INSD),
@@ -1917,6 +1952,8 @@ impl Mnemonic {
             "RDPMC" => Ok(Mnemonic::RDPMC),
             "RDRAND" => Ok(Mnemonic::RDRAND),
             "RDSEED" => Ok(Mnemonic::RDSEED),
+            "RDSSPD" => Ok(Mnemonic::RDSSPD),
+            "RDSSPQ" => Ok(Mnemonic::RDSSPQ),
             "RDTSC" => Ok(Mnemonic::RDTSC),
             "RDTSCP" => Ok(Mnemonic::RDTSCP),
             "RET" => Ok(Mnemonic::RET),
@@ -1930,10 +1967,12 @@ impl Mnemonic {
             "RSM" => Ok(Mnemonic::RSM),
             "RSQRTPS" => Ok(Mnemonic::RSQRTPS),
             "RSQRTSS" => Ok(Mnemonic::RSQRTSS),
+            "RSTORSSP" => Ok(Mnemonic::RSTORSSP),
             "SAHF" => Ok(Mnemonic::SAHF),
             "SAL" => Ok(Mnemonic::SAL),
             "SAR" => Ok(Mnemonic::SAR),
             "SARX" => Ok(Mnemonic::SARX),
+            "SAVEPREVSSP" => Ok(Mnemonic::SAVEPREVSSP),
             "SBB" => Ok(Mnemonic::SBB),
             "SCAS" => Ok(Mnemonic::SCAS),
             "SCASB" => Ok(Mnemonic::SCASB),
@@ -1960,6 +1999,17 @@ impl Mnemonic {
             "SETNGE" => Ok(Mnemonic::SETNGE),
             "SETNL" => Ok(Mnemonic::SETNL),
             "SETNLE" => Ok(Mnemonic::SETNLE),
+            "SETNO" => Ok(Mnemonic::SETNO),
+            "SETNP" => Ok(Mnemonic::SETNP),
+            "SETNS" => Ok(Mnemonic::SETNS),
+            "SETNZ" => Ok(Mnemonic::SETNZ),
+            "SETO" => Ok(Mnemonic::SETO),
+            "SETP" => Ok(Mnemonic::SETP),
+            "SETPE" => Ok(Mnemonic::SETPE),
+            "SETPO" => Ok(Mnemonic::SETPO),
+            "SETS" => Ok(Mnemonic::SETS),
+            "SETSSBSY" => Ok(Mnemonic::SETSSBSY),
+            "SETZ" => Ok(Mnemonic::SETZ),
             "SFENCE" => Ok(Mnemonic::SFENCE),
             "SGDT" => Ok(Mnemonic::SGDT),
             "SHA1MSG1" => Ok(Mnemonic::SHA1MSG1),
@@ -2596,6 +2646,10 @@ impl Mnemonic {
             "WRGSBASE" => Ok(Mnemonic::WRGSBASE),
             "WRMSR" => Ok(Mnemonic::WRMSR),
             "WRPKRU" => Ok(Mnemonic::WRPKRU),
+            "WRSSD" => Ok(Mnemonic::WRSSD),
+            "WRSSQ" => Ok(Mnemonic::WRSSQ),
+            "WRUSSD" => Ok(Mnemonic::WRUSSD),
+            "WRUSSQ" => Ok(Mnemonic::WRUSSQ),
             "XABORT" => Ok(Mnemonic::XABORT),
             "XACQUIRE" => Ok(Mnemonic::XACQUIRE),
             "XADD" => Ok(Mnemonic::XADD),
@@ -0,0 +1,338 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use ::{Fixup, FixupKind, FrameInfo, InstructionEncodingError, InstructionWriter, Label, Mode,
+    OperandSize, UnwindInfo};
+
+// The sections of an object file. Each is written through its own InstructionWriter.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Section {
+    Text,
+    Data,
+    Bss,
+    Rodata,
+}
+
+pub const SECTIONS: [Section; 4] = [Section::Text, Section::Data, Section::Bss, Section::Rodata];
+
+impl Section {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Section::Text => ".text",
+            Section::Data => ".data",
+            Section::Bss => ".bss",
+            Section::Rodata => ".rodata",
+        }
+    }
+
+    pub fn is_executable(&self) -> bool { *self == Section::Text }
+
+    pub fn is_writable(&self) -> bool { *self == Section::Data || *self == Section::Bss }
+
+    // .bss only has a size, anything written to it is discarded.
+    pub fn has_contents(&self) -> bool { *self != Section::Bss }
+
+    pub fn alignment(&self) -> u64 {
+        match *self {
+            Section::Text => 16,
+            Section::Data | Section::Bss | Section::Rodata => 8,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Binding {
+    // Local - Only visible within the object.
+    Local,
+
+    // Global - Visible to other objects, and used to resolve their undefined symbols.
+    Global,
+
+    // Weak - Like global, but may be overridden by a global symbol of the same name.
+    Weak,
+}
+
+// A label with a name that's visible to the linker.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub label: Label,
+    pub binding: Binding,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RelocationTarget {
+    // Symbol - Index into ObjectWriter::get_symbols.
+    Symbol(usize),
+
+    // Section - Start of a section, used for labels that aren't visible to the linker.
+    Section(Section),
+}
+
+// A fixup that has to be left to the linker.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Relocation {
+    // The label the fixup referred to.
+    pub label: Label,
+    pub section: Section,
+    pub offset: u64,
+    pub size: OperandSize,
+    pub kind: FixupKind,
+    pub target: RelocationTarget,
+    pub addend: i64,
+
+    // The field refers to the GOT entry of the target rather than the target itself.
+    pub got: bool,
+}
+
+// The contents of each section, and the relocations left for the linker.
+pub type Resolved = (Vec<(Section, Vec<u8>)>, Vec<Relocation>);
+
+// Collects code and data for an object file. Labels placed in a section become symbols when they
+// are declared with `symbol`, and references to labels that can't be resolved until link time
+// (i.e. undefined symbols, or labels in other sections) become relocations.
+pub struct ObjectWriter {
+    mode: Mode,
+    sections: Vec<(Section, InstructionWriter<Vec<u8>>)>,
+    symbols: Vec<Symbol>,
+    got_entries: HashMap<Label, Label>,
+    unwind_info: Vec<UnwindInfo>,
+    frame_info: Vec<FrameInfo>,
+}
+
+impl ObjectWriter {
+    pub fn new(mode: Mode) -> ObjectWriter {
+        ObjectWriter {
+            mode,
+            sections: SECTIONS.iter().map(|s| (*s, InstructionWriter::new(Vec::new(), mode)))
+                .collect(),
+            symbols: Vec::new(),
+            got_entries: HashMap::new(),
+            unwind_info: Vec::new(),
+            frame_info: Vec::new(),
+        }
+    }
+
+    pub fn get_mode(&self) -> Mode { self.mode }
+
+    // The writer for a section. Space in .bss is reserved by writing zeros to it.
+    pub fn section(&mut self, section: Section) -> &mut InstructionWriter<Vec<u8>> {
+        &mut self.sections.iter_mut().find(|s| s.0 == section)
+            .expect("All sections are created up front.").1
+    }
+
+    pub fn get_section(&self, section: Section) -> &InstructionWriter<Vec<u8>> {
+        &self.sections.iter().find(|s| s.0 == section)
+            .expect("All sections are created up front.").1
+    }
+
+    // Writes the instructions held back by branch alignment in every section, which has to be
+    // done before the object is written.
+    pub fn flush(&mut self) -> Result<(), InstructionEncodingError> {
+        self.sections.iter_mut().try_for_each(|s| s.1.flush())
+    }
+
+    // Declares a symbol, returning its label. The symbol is defined if its label is placed in one
+    // of the sections, and undefined (external) otherwise. Declaring a name again changes its
+    // binding and returns the same label.
+    pub fn symbol(&mut self, name: &str, binding: Binding) -> Label {
+        if let Some(symbol) = self.symbols.iter_mut().find(|s| s.name == name) {
+            symbol.binding = binding;
+            return symbol.label;
+        }
+        let label = Label::new();
+        self.symbols.push(Symbol { name: name.to_string(), label, binding });
+        label
+    }
+
+    pub fn get_symbols(&self) -> &[Symbol] { &self.symbols }
+
+    // A label for the GOT entry of a symbol, for RIP-relative loads of its address
+    // (R_X86_64_GOTPCREL).
+    pub fn got_entry(&mut self, symbol: Label) -> Label {
+        if let Some((entry, _)) = self.got_entries.iter().find(|e| *e.1 == symbol) {
+            return *entry;
+        }
+        let entry = Label::new();
+        self.got_entries.insert(entry, symbol);
+        entry
+    }
+
+    // Describes the prolog of a function in .text, so that it can be unwound through (i.e. for
+    // exceptions). Only written to COFF objects, as .pdata/.xdata records.
+    pub fn add_unwind_info(&mut self, info: UnwindInfo) { self.unwind_info.push(info); }
+
+    pub fn get_unwind_info(&self) -> &[UnwindInfo] { &self.unwind_info }
+
+    // Describes the frame of a function in .text (see InstructionWriter::begin_frame), so that it
+    // can be unwound through. Only written to ELF objects, as .eh_frame.
+    pub fn add_frame_info(&mut self, info: FrameInfo) { self.frame_info.push(info); }
+
+    pub fn get_frame_info(&self) -> &[FrameInfo] { &self.frame_info }
+
+    // Finds the section a label was placed in, and its offset in that section.
+    pub fn get_label_location(&self, label: Label) -> Option<(Section, u64)> {
+        self.sections.iter().filter_map(|s| s.1.get_label_offset(label).map(|o| (s.0, o))).next()
+    }
+
+    // Resolves the fixups that don't need the linker, returning the contents of each section and
+    // the remaining relocations. With `implicit_addends` (REL rather than RELA relocations) the
+    // addends are stored in the fields themselves.
+    pub fn resolve(&self, implicit_addends: bool) -> Result<Resolved, ObjectError> {
+        let mut contents = Vec::new();
+        let mut relocations = Vec::new();
+
+        for &(section, ref writer) in self.sections.iter() {
+            let fixups = writer.get_fixups().map_err(|_| ObjectError::Unflushed)?;
+            let mut bytes = writer.get_inner_writer_ref().clone();
+            for fixup in fixups {
+                if let Some(relocation) = self.resolve_fixup(section, fixup, &mut bytes)? {
+                    if implicit_addends {
+                        let field = Fixup { label: fixup.label, kind: FixupKind::Absolute,
+                            addend: relocation.addend, ..*fixup };
+                        field.apply(&mut bytes, 0, 0)
+                            .map_err(|_| ObjectError::OutOfRange(fixup.label))?;
+                    }
+                    relocations.push(relocation);
+                }
+            }
+            contents.push((section, bytes));
+        }
+        Ok((contents, relocations))
+    }
+
+    // Places each section at an absolute address and resolves every fixup, for output formats
+    // without relocations. Undefined weak symbols resolve to zero.
+    pub fn link(&self, addresses: &[(Section, u64)])
+        -> Result<Vec<(Section, Vec<u8>)>, ObjectError> {
+        let address = |section: Section| addresses.iter().find(|a| a.0 == section).map(|a| a.1)
+            .expect("Every section has an address.");
+        let mut contents = Vec::new();
+
+        for &(section, ref writer) in self.sections.iter() {
+            let fixups = writer.get_fixups().map_err(|_| ObjectError::Unflushed)?;
+            let mut bytes = writer.get_inner_writer_ref().clone();
+            for fixup in fixups {
+                if self.got_entries.contains_key(&fixup.label) || fixup.kind == FixupKind::Segment {
+                    return Err(ObjectError::UnsupportedRelocation(fixup.kind, fixup.size));
+                }
+                let target = self.get_label_address(fixup.label, addresses)
+                    .ok_or(ObjectError::UndefinedLabel(fixup.label))?;
+                fixup.apply(&mut bytes, target, address(section))
+                    .map_err(|_| ObjectError::OutOfRange(fixup.label))?;
+            }
+            contents.push((section, bytes));
+        }
+        Ok(contents)
+    }
+
+    // The address of a label once the sections are placed, as in `link`.
+    pub fn get_label_address(&self, label: Label, addresses: &[(Section, u64)]) -> Option<u64> {
+        match self.get_label_location(label) {
+            Some((section, offset)) =>
+                addresses.iter().find(|a| a.0 == section).map(|a| a.1 + offset),
+            None => self.symbols.iter().find(|s| s.label == label && s.binding == Binding::Weak)
+                .map(|_| 0)
+        }
+    }
+
+    fn resolve_fixup(&self, section: Section, fixup: &Fixup, bytes: &mut [u8])
+        -> Result<Option<Relocation>, ObjectError> {
+        if fixup.kind == FixupKind::Segment {
+            return Err(ObjectError::UnsupportedRelocation(fixup.kind, fixup.size));
+        }
+        let got = self.got_entries.get(&fixup.label);
+        let label = got.cloned().unwrap_or(fixup.label);
+        let symbol = self.symbols.iter().position(|s| s.label == label);
+        let location = self.get_label_location(label);
+        let relocation = |target, addend| Ok(Some(Relocation {
+            label: fixup.label,
+            section,
+            offset: fixup.offset,
+            size: fixup.size,
+            kind: fixup.kind,
+            target,
+            addend,
+            got: got.is_some()
+        }));
+
+        // Global and weak symbols may be replaced at link time, so references to them are always
+        // left to the linker.
+        let is_local = symbol.is_none_or(|s| self.symbols[s].binding == Binding::Local);
+        match location {
+            Some((target_section, offset)) if is_local && got.is_none() => {
+                if target_section == section && fixup.is_relative() {
+                    fixup.apply(bytes, offset, 0).map_err(|_| ObjectError::OutOfRange(label))?;
+                    Ok(None)
+                } else {
+                    relocation(RelocationTarget::Section(target_section),
+                        fixup.addend + offset as i64)
+                }
+            },
+            _ => match symbol {
+                Some(index) if location.is_some() || !is_local =>
+                    relocation(RelocationTarget::Symbol(index), fixup.addend),
+                _ => Err(ObjectError::UndefinedLabel(label))
+            }
+        }
+    }
+}
+
+// Rounds up to a multiple of the alignment, for laying out sections and segments.
+pub fn align_to(value: u64, alignment: u64) -> u64 {
+    value.div_ceil(alignment) * alignment
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ObjectError {
+    // UndefinedLabel - A label that isn't a global symbol is referenced but never placed.
+    UndefinedLabel(Label),
+
+    // UnsupportedRelocation - The format has no relocation for the fixup (i.e. a GOT reference
+    // outside of long mode).
+    UnsupportedRelocation(FixupKind, OperandSize),
+
+    // OutOfRange - The distance to a label doesn't fit in the field referring to it.
+    OutOfRange(Label),
+
+    // UnsupportedMode - The format can't hold code for the mode (i.e. a 16-bit ELF executable).
+    UnsupportedMode(Mode),
+
+    // InvalidUnwindInfo - The unwind info of the function starting at the label can't be encoded,
+    // i.e. its prolog is too long or a stack allocation isn't a multiple of 8.
+    InvalidUnwindInfo(Label),
+
+    // TooLarge - The contents (of the given size) don't fit in the image.
+    TooLarge(u64),
+
+    // WriteFailed - The underlying stream returned an error.
+    WriteFailed,
+
+    // Unflushed - Instructions of a section are still held back by branch alignment (see
+    // InstructionWriter::flush).
+    Unflushed,
+}
+
+impl fmt::Display for ObjectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ObjectError::UndefinedLabel(label) =>
+                write!(f, "label {} is referenced but never placed", label.get_id()),
+            ObjectError::UnsupportedRelocation(kind, size) =>
+                write!(f, "no relocation for a {:?} {:?} fixup", size, kind),
+            ObjectError::OutOfRange(label) =>
+                write!(f, "label {} is out of range", label.get_id()),
+            ObjectError::UnsupportedMode(mode) =>
+                write!(f, "the format doesn't support {:?} mode code", mode),
+            ObjectError::InvalidUnwindInfo(label) =>
+                write!(f, "invalid unwind info for the function at label {}", label.get_id()),
+            ObjectError::TooLarge(size) =>
+                write!(f, "{} bytes don't fit in the image", size),
+            ObjectError::WriteFailed => f.write_str("failed to write to the underlying stream"),
+            ObjectError::Unflushed =>
+                f.write_str("instructions are held back by branch alignment until flushed")
+        }
+    }
+}
+
+impl Error for ObjectError { }
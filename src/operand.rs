@@ -1,4 +1,6 @@
+use std::fmt;
 use ::instruction::{Reg, RegScale, SegmentReg};
+use ::label::Label;
 
 #[derive(PartialEq, Eq, Clone, Debug, Copy)]
 pub enum Operand {
@@ -16,6 +18,8 @@ pub enum Operand {
    Literal64(u64),
    MemoryAndSegment16(u16, u16),
    MemoryAndSegment32(u16, u32),
+   Label(Label), // Branch target or immediate holding the address of the label
+   LabelMemory(Label, Option<OperandSize>, Option<SegmentReg>), // RIP-relative in long mode
 }
 
 impl Operand {
@@ -28,14 +32,16 @@ impl Operand {
             Operand::IndirectScaledIndexedDisplaced(_, _, _, _, size, _) |
             Operand::IndirectScaledDisplaced(_, _, _, size, _) |
             Operand::Memory(_, size, _) |
-            Operand::Offset(_, size, _)
+            Operand::Offset(_, size, _) |
+            Operand::LabelMemory(_, size, _)
                 => size,
             Operand::Literal8(_) => Some(OperandSize::Byte),
             Operand::Literal16(_) => Some(OperandSize::Word),
             Operand::Literal32(_) => Some(OperandSize::Dword),
             Operand::Literal64(_) => Some(OperandSize::Qword),
             Operand::MemoryAndSegment16(..) |
-            Operand::MemoryAndSegment32(..)
+            Operand::MemoryAndSegment32(..) |
+            Operand::Label(_)
                 => None // TODO?
         }
     }
@@ -56,12 +62,41 @@ impl Operand {
            Operand::IndirectScaledIndexedDisplaced(_, _, _, _, _, seg) |
            Operand::IndirectScaledDisplaced(_, _, _, _, seg) |
            Operand::Memory(_, _, seg) |
-           Operand::Offset(_, _, seg)
+           Operand::Offset(_, _, seg) |
+           Operand::LabelMemory(_, _, seg)
                 => seg,
             _ => None
         }
     }
 
+    pub fn base_reg(&self) -> Option<Reg> {
+        match *self {
+            Operand::Indirect(reg, ..) |
+            Operand::IndirectDisplaced(reg, ..) |
+            Operand::IndirectScaledIndexed(reg, ..) |
+            Operand::IndirectScaledIndexedDisplaced(reg, ..) => Some(reg),
+            _ => None
+        }
+    }
+
+    pub fn index_reg(&self) -> Option<Reg> {
+        match *self {
+            Operand::IndirectScaledIndexed(_, reg, ..) |
+            Operand::IndirectScaledIndexedDisplaced(_, reg, ..) |
+            Operand::IndirectScaledDisplaced(reg, ..) => Some(reg),
+            _ => None
+        }
+    }
+
+    pub fn scale(&self) -> Option<RegScale> {
+        match *self {
+            Operand::IndirectScaledIndexed(_, _, scale, ..) |
+            Operand::IndirectScaledIndexedDisplaced(_, _, scale, ..) |
+            Operand::IndirectScaledDisplaced(_, scale, ..) => Some(scale),
+            _ => None
+        }
+    }
+
     pub fn is_direct(&self) -> bool {
         match *self {
             Operand::Direct(..) => true,
@@ -84,7 +119,8 @@ impl Operand {
             Operand::IndirectScaledIndexedDisplaced(..) |
             Operand::IndirectScaledDisplaced(..) |
             Operand::Memory(..) |
-            Operand::Offset(..) => true,
+            Operand::Offset(..) |
+            Operand::LabelMemory(..) => true,
             _ => false
         }
     }
@@ -95,10 +131,19 @@ impl Operand {
             Operand::Offset(..) |
             Operand::MemoryAndSegment16(..) => true,
             Operand::MemoryAndSegment32(..) => true,
+            Operand::LabelMemory(..) => true,
             _ => false
         }
     }
 
+    pub fn label(&self) -> Option<Label> {
+        match *self {
+            Operand::Label(label) |
+            Operand::LabelMemory(label, ..) => Some(label),
+            _ => None
+        }
+    }
+
     pub fn is_far(&self) -> bool {
         match *self {
             Operand::MemoryAndSegment16(..) => true,
@@ -178,6 +223,67 @@ impl Operand {
     }
 }
 
+// Formats the operand in Intel syntax. Displacements and relative branch offsets (Offset without a
+// size) are shown as signed numbers, and labels as ".L<id>".
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Operand::Direct(reg) => return write!(f, "{}", reg),
+            Operand::Literal8(val) => return write!(f, "0x{:x}", val),
+            Operand::Literal16(val) => return write!(f, "0x{:x}", val),
+            Operand::Literal32(val) => return write!(f, "0x{:x}", val),
+            Operand::Literal64(val) => return write!(f, "0x{:x}", val),
+            Operand::MemoryAndSegment16(seg, addr) => return write!(f, "0x{:x}:0x{:x}", seg, addr),
+            Operand::MemoryAndSegment32(seg, addr) => return write!(f, "0x{:x}:0x{:x}", seg, addr),
+            Operand::Label(label) => return write!(f, ".L{}", label.get_id()),
+            Operand::Offset(offset, None, _) => return if (offset as i64) < 0 {
+                write!(f, "-0x{:x}", (offset as i64).wrapping_neg())
+            } else {
+                write!(f, "0x{:x}", offset)
+            },
+            _ => {}
+        }
+
+        if let Some(size) = self.size().and_then(|s| s.ptr_name()) {
+            write!(f, "{} ptr ", size)?;
+        }
+        if let Some(seg) = self.segment_reg() { write!(f, "{}:", seg)?; }
+        f.write_str("[")?;
+        match *self {
+            Operand::Memory(addr, ..) | Operand::Offset(addr, ..) => write!(f, "0x{:x}", addr)?,
+            Operand::LabelMemory(label, ..) => write!(f, ".L{}", label.get_id())?,
+            _ => {
+                let base = self.base_reg();
+                if let Some(base) = base { write!(f, "{}", base)?; }
+                if let Some(index) = self.index_reg() {
+                    if base.is_some() { f.write_str("+")?; }
+                    write!(f, "{}", index)?;
+                    match self.scale() {
+                        Some(RegScale::Two) => f.write_str("*2")?,
+                        Some(RegScale::Four) => f.write_str("*4")?,
+                        Some(RegScale::Eight) => f.write_str("*8")?,
+                        _ => {}
+                    }
+                }
+                match *self {
+                    Operand::IndirectDisplaced(_, disp, ..) |
+                    Operand::IndirectScaledIndexedDisplaced(_, _, _, disp, ..) |
+                    Operand::IndirectScaledDisplaced(_, _, disp, ..) => {
+                        // disp32 is sign-extended, so 0xFFFFFFF8 and -8 are the same displacement.
+                        let signed = if (0x8000_0000..=0xFFFF_FFFF).contains(&disp) {
+                            disp as u32 as i32 as i64
+                        } else { disp as i64 };
+                        if signed < 0 { write!(f, "-0x{:x}", signed.wrapping_neg())?; }
+                        else { write!(f, "+0x{:x}", disp)?; }
+                    },
+                    _ => {}
+                }
+            }
+        }
+        f.write_str("]")
+    }
+}
+
 #[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub enum OperandSize {
     // Order here is important because of derive(Ord)
@@ -215,6 +321,22 @@ impl OperandSize {
         }
     }
 
+    // The size keyword of memory operands in Intel syntax, i.e. "dword" in "dword ptr [eax]".
+    pub fn ptr_name(&self) -> Option<&'static str> {
+        Some(match *self {
+            OperandSize::Unsized => return None,
+            OperandSize::Byte => "byte",
+            OperandSize::Word => "word",
+            OperandSize::Dword | OperandSize::Far16 => "dword",
+            OperandSize::Fword | OperandSize::Far32 => "fword",
+            OperandSize::Qword => "qword",
+            OperandSize::Tbyte | OperandSize::Far64 => "tbyte",
+            OperandSize::Xmmword => "xmmword",
+            OperandSize::Ymmword => "ymmword",
+            OperandSize::Zmmword => "zmmword",
+        })
+    }
+
     pub fn from_bits(bits: u32) -> Option<OperandSize> {
         Some(match bits {
             8 => OperandSize::Byte,
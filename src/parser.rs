@@ -0,0 +1,558 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use ::{Binding, BroadcastMode, Instruction, InstructionEncodingError, Label, MaskReg, MergeMode,
+    Mnemonic, Mode, ObjectWriter, Operand, OperandSize, Reg, RegScale, RoundingMode, Section,
+    SegmentReg};
+
+// The most bytes a res directive can reserve, as they're written out as zeros (256 MiB).
+const MAX_RESERVED: u64 = 1 << 28;
+
+// An operand before its size is known. Numbers are tried as immediates of each size that holds
+// them, as the instruction definitions only match literals of the exact size.
+#[derive(Clone, Copy, Debug)]
+enum ParsedOperand {
+    Operand(Operand),
+    Number(u64),
+}
+
+// The EVEX decorations of an instruction, written after its operands.
+#[derive(Clone, Copy, Debug, Default)]
+struct Decorations {
+    mask: Option<MaskReg>,
+    merge_mode: Option<MergeMode>,
+    broadcast: Option<BroadcastMode>,
+    rounding_mode: Option<RoundingMode>,
+    sae: bool,
+}
+
+// Parses a single instruction in Intel syntax, i.e. "add dword ptr [ebp+8], 0x10". Immediates get
+// the smallest size the instruction has a form for in `mode`, so "add eax, 1" uses the imm8 form.
+// Numeric branch targets are relative to the end of the instruction. AVX-512 masking, broadcast
+// and rounding are written as they're displayed: "vaddps zmm1{k1}{z}, zmm2, [rax]{1to16}" and
+// "vaddps zmm1, zmm2, zmm3, {rn-sae}".
+pub fn parse_instruction(text: &str, mode: Mode) -> Result<Instruction, ParseError> {
+    let mut labels = |name: &str| Err(ParseError::UnexpectedLabel(name.to_owned()));
+    parse_line_instruction(text, mode, &mut labels)
+}
+
+fn parse_line_instruction<F>(text: &str, mode: Mode, labels: &mut F)
+    -> Result<Instruction, ParseError> where F: FnMut(&str) -> Result<Label, ParseError> {
+    let (mut word, mut rest) = split_word(text);
+    let lock = word.eq_ignore_ascii_case("lock");
+    if lock { let next = split_word(rest); word = next.0; rest = next.1; }
+    let mnemonic = Mnemonic::parse(&word.to_uppercase())
+        .map_err(|_| ParseError::UnknownMnemonic(word.to_owned()))?;
+
+    let mut operands = Vec::new();
+    let mut decorations = Decorations::default();
+    for op in split_operands(rest) {
+        let text = parse_decorations(op, &mut decorations)?;
+        // Rounding is written as an operand of its own.
+        if text.is_empty() && op.starts_with('{') { continue; }
+        operands.push(parse_operand(text, mode, labels)?);
+    }
+    if operands.len() > 4 { return Err(ParseError::InvalidOperand(rest.trim().to_owned())); }
+
+    // The size of the register or sized memory operands, which unsigned immediates have to match.
+    let size = operands.iter().filter_map(|op| match *op {
+        ParsedOperand::Operand(op) => op.size(),
+        ParsedOperand::Number(_) => None
+    }).find(|s| *s != OperandSize::Unsized);
+
+    let mut candidates = vec![Vec::new()];
+    for op in operands.iter() {
+        let choices = match *op {
+            // The FPU forms with the top of the stack as a fixed operand take it as ST.
+            ParsedOperand::Operand(Operand::Direct(Reg::ST0)) =>
+                vec![Operand::Direct(Reg::ST0), Operand::Direct(Reg::ST)],
+            ParsedOperand::Operand(op) => vec![op],
+            ParsedOperand::Number(value) => literal_choices(value, size, mode)
+        };
+        candidates = candidates.into_iter().flat_map(|prefix: Vec<Operand>| choices.iter()
+            .map(move |choice| { let mut ops = prefix.clone(); ops.push(*choice); ops }))
+            .collect();
+    }
+
+    let mut error = InstructionEncodingError::NoEncoding;
+    for ops in candidates {
+        let instr = Instruction {
+            mnemonic,
+            operand1: ops.first().cloned(),
+            operand2: ops.get(1).cloned(),
+            operand3: ops.get(2).cloned(),
+            operand4: ops.get(3).cloned(),
+            lock,
+            mask: decorations.mask,
+            merge_mode: decorations.merge_mode,
+            broadcast: decorations.broadcast,
+            rounding_mode: decorations.rounding_mode,
+            sae: decorations.sae,
+        };
+        match instr.encode(&mut Vec::new(), mode) {
+            Ok(_) => return Ok(instr),
+            // Keep the most specific error, rather than the one for the last candidate.
+            Err(e) => if error == InstructionEncodingError::NoEncoding { error = e; }
+        }
+    }
+    Err(ParseError::Encoding(error))
+}
+
+// Sign-extended sizes come first, so "push 0x80" doesn't use the imm8 form (which would push
+// -128). Zero-extended values only fit the size of the other operands, i.e. "mov al, 0xFF".
+fn literal_choices(value: u64, size: Option<OperandSize>, mode: Mode) -> Vec<Operand> {
+    let default = if mode == Mode::Real { 16 } else { 32 };
+    let mut bits = vec![8, default, 16, 32];
+    bits.dedup();
+    let signed = value as i64;
+    let mut choices: Vec<u32> = bits.iter().cloned().filter(|&b| {
+        let shift = 64 - b;
+        (signed << shift) >> shift == signed
+    }).collect();
+    choices.extend(bits.iter().cloned().filter(|&b| value >> b == 0 &&
+        size.is_none_or(|s| s.bits() == b)));
+    choices.push(64);
+    choices.iter().map(|&b| match b {
+        8 => Operand::Literal8(value as u8),
+        16 => Operand::Literal16(value as u16),
+        32 => Operand::Literal32(value as u32),
+        _ => Operand::Literal64(value)
+    }).collect()
+}
+
+// Removes the decorations in braces from the end of an operand, i.e. "{k1}{z}", and returns the
+// rest of it.
+fn parse_decorations<'a>(text: &'a str, decorations: &mut Decorations)
+    -> Result<&'a str, ParseError> {
+    let invalid = || ParseError::InvalidOperand(text.to_owned());
+    let mut rest = text.trim();
+    while rest.ends_with('}') {
+        let at = rest.rfind('{').ok_or_else(invalid)?;
+        let decoration = rest[at + 1..rest.len() - 1].trim().to_lowercase();
+        match &decoration[..] {
+            "z" => decorations.merge_mode = Some(MergeMode::Zero),
+            "sae" => decorations.sae = true,
+            "rn-sae" => decorations.rounding_mode = Some(RoundingMode::Nearest),
+            "rd-sae" => decorations.rounding_mode = Some(RoundingMode::Down),
+            "ru-sae" => decorations.rounding_mode = Some(RoundingMode::Up),
+            "rz-sae" => decorations.rounding_mode = Some(RoundingMode::Zero),
+            _ => {
+                // K0 can't be used as a mask, as it means no masking.
+                let mask = decoration.strip_prefix('k').and_then(|n| n.parse().ok())
+                    .and_then(MaskReg::from_code).filter(|m| *m != MaskReg::K0);
+                let broadcast = decoration.strip_prefix("1to").and_then(|n| n.parse().ok())
+                    .and_then(BroadcastMode::from_multiplier);
+                match (mask, broadcast) {
+                    (Some(mask), _) => decorations.mask = Some(mask),
+                    (_, Some(broadcast)) => decorations.broadcast = Some(broadcast),
+                    _ => return Err(invalid())
+                }
+            }
+        }
+        rest = rest[..at].trim_end();
+    }
+    Ok(rest)
+}
+
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim();
+    match text.find(char::is_whitespace) {
+        Some(at) => (&text[..at], text[at..].trim()),
+        None => (text, "")
+    }
+}
+
+// Splits on commas outside of brackets and quotes.
+fn split_operands(text: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut quote, mut start) = (0, None, 0);
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' | '\'' if quote.is_none() => quote = Some(c),
+            _ if quote == Some(c) => quote = None,
+            '[' | '(' if quote.is_none() => depth += 1,
+            ']' | ')' if quote.is_none() => depth -= 1,
+            ',' if quote.is_none() && depth == 0 => {
+                parts.push(text[start..i].trim());
+                start = i + 1;
+            },
+            _ => {}
+        }
+    }
+    if !text[start..].trim().is_empty() || !parts.is_empty() { parts.push(text[start..].trim()); }
+    parts
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || "_.$@".contains(c)) &&
+        chars.all(|c| c.is_ascii_alphanumeric() || "_.$@".contains(c))
+}
+
+// Decimal, 0x/0b prefixed, h suffixed ("0FFh") and character ('A') numbers, optionally negative.
+pub fn parse_number(text: &str) -> Option<u64> {
+    let text = text.trim();
+    if let Some(rest) = text.strip_prefix('-') { return parse_number(rest).map(u64::wrapping_neg); }
+    let lower = text.to_lowercase();
+    let bytes = text.as_bytes();
+    if bytes.len() == 3 && bytes[0] == b'\'' && bytes[2] == b'\'' { return Some(bytes[1] as u64); }
+    if let Some(hex) = lower.strip_prefix("0x") { return u64::from_str_radix(hex, 16).ok(); }
+    if let Some(bin) = lower.strip_prefix("0b") { return u64::from_str_radix(bin, 2).ok(); }
+    if let Some(hex) = lower.strip_suffix('h') {
+        if hex.starts_with(|c: char| c.is_ascii_digit()) { return u64::from_str_radix(hex, 16).ok(); }
+    }
+    lower.parse().ok()
+}
+
+fn parse_register(text: &str) -> Option<Reg> {
+    let upper: String = text.trim().to_uppercase().chars().filter(|c| *c != '(' && *c != ')')
+        .collect();
+    Reg::parse(&upper).ok()
+}
+
+fn parse_segment(text: &str) -> Option<SegmentReg> {
+    Some(match &text.trim().to_lowercase()[..] {
+        "cs" => SegmentReg::CS,
+        "ds" => SegmentReg::DS,
+        "es" => SegmentReg::ES,
+        "fs" => SegmentReg::FS,
+        "gs" => SegmentReg::GS,
+        "ss" => SegmentReg::SS,
+        _ => return None
+    })
+}
+
+fn parse_size(text: &str) -> Option<OperandSize> {
+    Some(match &text.to_lowercase()[..] {
+        "byte" => OperandSize::Byte,
+        "word" => OperandSize::Word,
+        "dword" => OperandSize::Dword,
+        "fword" => OperandSize::Fword,
+        "qword" => OperandSize::Qword,
+        "tbyte" | "tword" => OperandSize::Tbyte,
+        "xmmword" => OperandSize::Xmmword,
+        "ymmword" => OperandSize::Ymmword,
+        "zmmword" => OperandSize::Zmmword,
+        _ => return None
+    })
+}
+
+fn parse_operand<F>(text: &str, mode: Mode, labels: &mut F) -> Result<ParsedOperand, ParseError>
+    where F: FnMut(&str) -> Result<Label, ParseError> {
+    let invalid = || ParseError::InvalidOperand(text.to_owned());
+    let mut rest = text.trim();
+
+    // "dword ptr", "dword" or nothing
+    let (word, after) = split_word(rest);
+    let size = parse_size(word);
+    if size.is_some() {
+        rest = after;
+        let (word, after) = split_word(rest);
+        if word.eq_ignore_ascii_case("ptr") { rest = after; }
+    }
+
+    // "fs:[...]"
+    let mut segment = None;
+    if let Some(at) = rest.find(':') {
+        if rest[at + 1..].trim_start().starts_with('[') {
+            segment = Some(parse_segment(&rest[..at]).ok_or_else(invalid)?);
+            rest = rest[at + 1..].trim_start();
+        }
+    }
+
+    if rest.starts_with('[') && rest.ends_with(']') {
+        return parse_memory(&rest[1..rest.len() - 1], size, segment, labels)
+            .map(ParsedOperand::Operand).ok_or_else(invalid);
+    }
+    if size.is_some() || segment.is_some() { return Err(invalid()); }
+
+    if let Some(reg) = parse_register(rest) { return Ok(ParsedOperand::Operand(Operand::Direct(reg))); }
+    if let Some(value) = parse_number(rest) { return Ok(ParsedOperand::Number(value)); }
+    // Far pointers, i.e. "0x08:0x7C00"
+    if let Some(at) = rest.find(':') {
+        if let (Some(seg), Some(addr)) = (parse_number(&rest[..at]), parse_number(&rest[at + 1..])) {
+            return Ok(ParsedOperand::Operand(if mode == Mode::Real {
+                Operand::MemoryAndSegment16(seg as u16, addr as u16)
+            } else {
+                Operand::MemoryAndSegment32(seg as u16, addr as u32)
+            }));
+        }
+    }
+    if is_identifier(rest) { return labels(rest).map(|l| ParsedOperand::Operand(Operand::Label(l))); }
+    Err(invalid())
+}
+
+// The inside of a memory reference: a base register, an index register with an optional scale,
+// and a displacement, or a label (optionally relative to RIP).
+fn parse_memory<F>(text: &str, size: Option<OperandSize>, segment: Option<SegmentReg>,
+    labels: &mut F) -> Option<Operand> where F: FnMut(&str) -> Result<Label, ParseError> {
+    let (mut base, mut index, mut scale, mut disp, mut label) = (None, None, RegScale::One, 0u64, None);
+    let mut has_disp = false;
+    let mut terms = Vec::new();
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        if (c == '+' || c == '-') && i > 0 {
+            terms.push(&text[start..i]);
+            start = i;
+        }
+    }
+    terms.push(&text[start..]);
+
+    for term in terms {
+        let (negative, term) = match term.trim() {
+            t if t.starts_with('+') => (false, t[1..].trim()),
+            t if t.starts_with('-') => (true, t[1..].trim()),
+            t => (false, t)
+        };
+        if let Some(at) = term.find('*') {
+            let (a, b) = (term[..at].trim(), term[at + 1..].trim());
+            let (reg, factor) = match (parse_register(a), parse_register(b)) {
+                (Some(reg), None) => (reg, parse_number(b)?),
+                (None, Some(reg)) => (reg, parse_number(a)?),
+                _ => return None
+            };
+            if negative || index.is_some() { return None; }
+            index = Some(reg);
+            scale = match factor { 1 => RegScale::One, 2 => RegScale::Two, 4 => RegScale::Four,
+                8 => RegScale::Eight, _ => return None };
+        } else if let Some(reg) = parse_register(term) {
+            if negative { return None; }
+            if base.is_none() { base = Some(reg); }
+            else if index.is_none() { index = Some(reg); }
+            else { return None; }
+        } else if let Some(value) = parse_number(term) {
+            disp = if negative { disp.wrapping_sub(value) } else { disp.wrapping_add(value) };
+            has_disp = true;
+        } else if is_identifier(term) && !negative && label.is_none() {
+            label = Some(labels(term).ok()?);
+        } else {
+            return None;
+        }
+    }
+
+    if let Some(label) = label {
+        // The label's address is the whole displacement; RIP may be named explicitly.
+        return match (base, index, has_disp) {
+            (None, None, false) | (Some(Reg::RIP), None, false) =>
+                Some(Operand::LabelMemory(label, size, segment)),
+            _ => None
+        };
+    }
+    Some(match (base, index, has_disp) {
+        (None, None, _) => Operand::Memory(disp, size, segment),
+        (Some(base), None, false) => Operand::Indirect(base, size, segment),
+        (Some(base), None, true) => Operand::IndirectDisplaced(base, disp, size, segment),
+        (Some(base), Some(index), false) =>
+            Operand::IndirectScaledIndexed(base, index, scale, size, segment),
+        (Some(base), Some(index), true) =>
+            Operand::IndirectScaledIndexedDisplaced(base, index, scale, disp, size, segment),
+        (None, Some(index), _) => Operand::IndirectScaledDisplaced(index, scale, disp, size, segment),
+    })
+}
+
+// Assembles a source file in Intel syntax into an object. Each line may start with labels
+// ("name:"), followed by an instruction or a directive:
+//   bits 16|32|64                     - Mode of the instructions that follow
+//   section .text|.data|.rodata|.bss  - Section that the following lines go to
+//   global name, ... / extern name    - Global (exported or imported) symbols
+//   db/dw/dd/dq value, ...            - Data; db takes strings, dw/dd/dq take labels
+//   resb/resw/resd/resq count         - Zeroed space, i.e. in .bss
+// Comments start with ';'. Labels that aren't global are local symbols. Every line is recorded
+// in the section's listing.
+pub fn assemble(source: &str, mode: Mode) -> Result<ObjectWriter, AsmError> {
+    let mut obj = ObjectWriter::new(mode);
+    for section in ::object::SECTIONS.iter() { obj.section(*section).enable_listing(); }
+    let mut labels: HashMap<String, (Label, usize, bool)> = HashMap::new(); // Line of first use, defined
+    let mut section = Section::Text;
+    let mut mode = mode;
+
+    for (i, full_line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        let error = |error| AsmError { line: line_number, error };
+        let mut line = strip_comment(full_line).trim();
+
+        // Labels
+        while let Some(at) = line.find(':') {
+            let name = line[..at].trim();
+            if !is_identifier(name) || parse_segment(name).is_some() { break; }
+            let label = use_label(&mut obj, &mut labels, name, line_number);
+            let entry = labels.get_mut(name).expect("use_label adds the label.");
+            if entry.2 { return Err(error(ParseError::DuplicateLabel(name.to_owned()))); }
+            entry.2 = true;
+            obj.section(section).set_label(label);
+            line = line[at + 1..].trim();
+        }
+        if line.is_empty() { continue; }
+
+        // NASM also accepts directives in brackets, i.e. "[bits 32]".
+        if line.starts_with('[') && line.ends_with(']') { line = &line[1..line.len() - 1]; }
+        let (word, rest) = split_word(line);
+        let args = split_operands(rest);
+        let directive = word.to_lowercase();
+        match &directive[..] {
+            "bits" => {
+                mode = match rest {
+                    "16" => Mode::Real,
+                    "32" => Mode::Protected,
+                    "64" => Mode::Long,
+                    _ => return Err(error(ParseError::InvalidDirective(line.to_owned())))
+                };
+                continue;
+            },
+            "section" | "segment" | ".text" | ".data" | ".rodata" | ".bss" => {
+                let name = if directive.starts_with('.') { &directive[..] } else { rest };
+                section = *::object::SECTIONS.iter().find(|s| s.name() == name)
+                    .ok_or_else(|| error(ParseError::InvalidDirective(line.to_owned())))?;
+                continue;
+            },
+            "global" | "extern" => {
+                for name in args.iter() {
+                    if !is_identifier(name) {
+                        return Err(error(ParseError::InvalidDirective(line.to_owned())));
+                    }
+                    let label = obj.symbol(name, Binding::Global);
+                    labels.entry(name.to_string()).or_insert((label, line_number, false));
+                }
+                continue;
+            },
+            _ => {}
+        }
+
+        let writer_mode = obj.section(section).get_mode();
+        if writer_mode != mode { obj.section(section).set_mode(mode); }
+        obj.section(section).set_source(line_number, full_line.trim());
+
+        let data_size = match &directive[..] {
+            "db" | "resb" => Some(OperandSize::Byte),
+            "dw" | "resw" => Some(OperandSize::Word),
+            "dd" | "resd" => Some(OperandSize::Dword),
+            "dq" | "resq" => Some(OperandSize::Qword),
+            _ => None
+        };
+        if let Some(size) = data_size {
+            let len = size.bits() as usize / 8;
+            if directive.starts_with("res") {
+                let reserved = parse_number(rest).filter(|_| args.len() == 1)
+                    .and_then(|count| count.checked_mul(len as u64))
+                    .filter(|&reserved| reserved <= MAX_RESERVED)
+                    .ok_or_else(|| error(ParseError::InvalidDirective(line.to_owned())))?;
+                obj.section(section).write_bytes(&vec![0; reserved as usize])
+                    .map_err(|e| error(ParseError::Encoding(e)))?;
+                continue;
+            }
+            let mut bytes = Vec::new();
+            for arg in args.iter() {
+                if arg.len() >= 2 && arg.starts_with('"') && arg.ends_with('"') && len == 1 {
+                    bytes.extend_from_slice(&arg.as_bytes()[1..arg.len() - 1]);
+                } else if let Some(value) = parse_number(arg) {
+                    bytes.extend((0..len).map(|b| (value >> (8 * b)) as u8));
+                } else if is_identifier(arg) && len > 1 {
+                    let label = use_label(&mut obj, &mut labels, arg, line_number);
+                    let writer = obj.section(section);
+                    writer.write_bytes(&bytes).map_err(|e| error(ParseError::Encoding(e)))?;
+                    bytes.clear();
+                    writer.write_address(label, size).map_err(|e| error(ParseError::Encoding(e)))?;
+                } else {
+                    return Err(error(ParseError::InvalidDirective(line.to_owned())));
+                }
+            }
+            if !bytes.is_empty() || args.is_empty() {
+                obj.section(section).write_bytes(&bytes).map_err(|e| error(ParseError::Encoding(e)))?;
+            }
+            continue;
+        }
+
+        let instr = {
+            let mut resolve = |name: &str| Ok(use_label(&mut obj, &mut labels, name, line_number));
+            parse_line_instruction(line, mode, &mut resolve).map_err(error)?
+        };
+        obj.section(section).write(&instr).map_err(|e| error(ParseError::Encoding(e)))?;
+    }
+
+    // Labels that are used but never placed have to come from another object.
+    let mut undefined: Vec<_> = labels.iter()
+        .filter(|&(name, entry)| !entry.2 && obj.get_symbols().iter()
+            .any(|s| s.name == *name && s.binding == Binding::Local))
+        .map(|(name, entry)| (entry.1, name.clone())).collect();
+    undefined.sort();
+    if let Some((line, name)) = undefined.into_iter().next() {
+        return Err(AsmError { line, error: ParseError::UndefinedLabel(name) });
+    }
+    Ok(obj)
+}
+
+fn use_label(obj: &mut ObjectWriter, labels: &mut HashMap<String, (Label, usize, bool)>, name: &str,
+    line: usize) -> Label {
+    if let Some(entry) = labels.get(name) { return entry.0; }
+    let label = obj.symbol(name, Binding::Local);
+    labels.insert(name.to_owned(), (label, line, false));
+    label
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' | '\'' if quote.is_none() => quote = Some(c),
+            _ if quote == Some(c) => quote = None,
+            ';' if quote.is_none() => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    // UnknownMnemonic - The line doesn't start with an instruction or directive.
+    UnknownMnemonic(String),
+
+    // InvalidOperand - Not a register, number, memory reference, far pointer or label.
+    InvalidOperand(String),
+
+    // InvalidDirective - A directive with missing or invalid arguments.
+    InvalidDirective(String),
+
+    // UnexpectedLabel - A label in a lone instruction, which has nowhere to be defined.
+    UnexpectedLabel(String),
+
+    // DuplicateLabel - A label is placed twice.
+    DuplicateLabel(String),
+
+    // UndefinedLabel - A label is used but neither placed nor declared global/extern.
+    UndefinedLabel(String),
+
+    // Encoding - No form of the instruction matches the operands.
+    Encoding(InstructionEncodingError),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::UnknownMnemonic(ref m) => write!(f, "unknown instruction '{}'", m),
+            ParseError::InvalidOperand(ref op) => write!(f, "invalid operand '{}'", op),
+            ParseError::InvalidDirective(ref d) => write!(f, "invalid directive '{}'", d),
+            ParseError::UnexpectedLabel(ref l) => write!(f, "unexpected label '{}'", l),
+            ParseError::DuplicateLabel(ref l) => write!(f, "label '{}' is already defined", l),
+            ParseError::UndefinedLabel(ref l) => write!(f, "label '{}' is never defined", l),
+            ParseError::Encoding(ref e) => write!(f, "{}", e)
+        }
+    }
+}
+
+impl Error for ParseError { }
+
+// A ParseError on a line of the source.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub error: ParseError,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
+    }
+}
+
+impl Error for AsmError { }
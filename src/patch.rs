@@ -0,0 +1,230 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use byteorder::{ByteOrder, LittleEndian};
+use ::{Instruction, InstructionDecodingError, InstructionEncodingError, Label, Mnemonic, Mode};
+use ::decoding::{decode_traced, DecodeTrace, FieldKind};
+use ::instruction_def::OperandType;
+use ::relocate::{displacement, fits, near_branch, write_relative};
+
+const PREFIX_OP_SIZE: u8 = 0x66;
+
+// The recommended NOPs of each length: NOP r/m with the shortest addressing of the right length.
+const NOPS: [&[u8]; 9] = [
+    &[0x90],
+    &[0x66, 0x90],
+    &[0x0F, 0x1F, 0x00],
+    &[0x0F, 0x1F, 0x40, 0x00],
+    &[0x0F, 0x1F, 0x44, 0x00, 0x00],
+    &[0x66, 0x0F, 0x1F, 0x44, 0x00, 0x00],
+    &[0x0F, 0x1F, 0x80, 0x00, 0x00, 0x00, 0x00],
+    &[0x0F, 0x1F, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00],
+    &[0x66, 0x0F, 0x1F, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00],
+];
+
+// The same with 16-bit addressing, which has no SIB byte and at most a 16-bit displacement.
+const NOPS_16: [&[u8]; 6] = [
+    &[0x90],
+    &[0x66, 0x90],
+    &[0x0F, 0x1F, 0x00],
+    &[0x0F, 0x1F, 0x40, 0x00],
+    &[0x0F, 0x1F, 0x80, 0x00, 0x00],
+    &[0x66, 0x0F, 0x1F, 0x80, 0x00, 0x00],
+];
+
+// The longest an instruction can be.
+const MAX_INSTRUCTION: usize = 15;
+
+// Returns `len` bytes of NOPs, in as few instructions as possible. Longer NOPs than the table has
+// repeat the operand size prefix, up to the 15 byte limit on instructions.
+pub fn nop_padding(len: usize, mode: Mode) -> Vec<u8> {
+    let table: &[&[u8]] = if mode == Mode::Real { &NOPS_16 } else { &NOPS };
+    let mut bytes = Vec::with_capacity(len);
+    while bytes.len() < len {
+        let size = (len - bytes.len()).min(MAX_INSTRUCTION);
+        let nop = table[size.min(table.len()) - 1];
+        bytes.resize(bytes.len() + size - nop.len(), PREFIX_OP_SIZE);
+        bytes.extend_from_slice(nop);
+    }
+    bytes
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum PatchError {
+    // Decoding - There's no valid instruction at the offset.
+    Decoding(InstructionDecodingError),
+
+    // Encoding - The replacement couldn't be encoded.
+    Encoding(InstructionEncodingError),
+
+    // UnresolvedLabel - The replacement refers to a label, which has no address when patching.
+    UnresolvedLabel(Label),
+
+    // TooLarge - The replacement needs the first number of bytes, but there are only the second.
+    TooLarge(usize, usize),
+
+    // OutOfRange - The target or value doesn't fit in the field of the instruction.
+    OutOfRange,
+
+    // NoField - The instruction has no relative branch target or immediate to change.
+    NoField,
+
+    // InvalidPatchSite - A patch site has to be a 5 byte instruction within an aligned 8 bytes of
+    // the code, so it can be written with a single store.
+    InvalidPatchSite,
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PatchError::Decoding(ref e) => write!(f, "{}", e),
+            PatchError::Encoding(ref e) => write!(f, "{}", e),
+            PatchError::UnresolvedLabel(label) =>
+                write!(f, "label {} can't be resolved when patching", label.get_id()),
+            PatchError::TooLarge(needed, available) =>
+                write!(f, "the replacement needs {} bytes but only {} are available", needed,
+                    available),
+            PatchError::OutOfRange => f.write_str("the value doesn't fit in the instruction"),
+            PatchError::NoField => f.write_str("the instruction has no field to change"),
+            PatchError::InvalidPatchSite =>
+                f.write_str("not a 5 byte instruction within an aligned 8 bytes"),
+        }
+    }
+}
+
+impl Error for PatchError { }
+
+// Changes code that has already been written, i.e. the bytes of an InstructionWriter<Vec<u8>> or
+// of a writable JitBuffer. `base` is the address the code runs at, for relative branches.
+pub struct Patcher<'a> {
+    code: &'a mut [u8],
+    base: u64,
+    mode: Mode,
+}
+
+impl<'a> Patcher<'a> {
+    pub fn new(code: &'a mut [u8], base: u64, mode: Mode) -> Patcher<'a> {
+        Patcher { code, base, mode }
+    }
+
+    pub fn get_code(&self) -> &[u8] { self.code }
+
+    fn decode_traced(&self, offset: usize)
+        -> Result<(Instruction, usize, DecodeTrace), PatchError> {
+        let bytes = self.code.get(offset..)
+            .ok_or(PatchError::Decoding(InstructionDecodingError::EndOfStream))?;
+        decode_traced(bytes, self.mode).map_err(PatchError::Decoding)
+    }
+
+    // The instruction at the offset and its length.
+    pub fn decode(&self, offset: usize) -> Result<(Instruction, usize), PatchError> {
+        self.decode_traced(offset).map(|(instr, len, _)| (instr, len))
+    }
+
+    // Overwrites `len` bytes at the offset with NOPs, i.e. to remove instructions.
+    pub fn write_nops(&mut self, offset: usize, len: usize) -> Result<(), PatchError> {
+        let available = self.code.len().saturating_sub(offset);
+        if len > available { return Err(PatchError::TooLarge(len, available)); }
+        self.code[offset..offset + len].copy_from_slice(&nop_padding(len, self.mode));
+        Ok(())
+    }
+
+    // Overwrites the instruction at the offset with one that's no larger, padding the rest of it
+    // with NOPs, and returns the length of the replacement. Relative branch offsets in the
+    // replacement are from its own end, see retarget to keep the target of a branch.
+    pub fn replace(&mut self, offset: usize, instr: &Instruction) -> Result<usize, PatchError> {
+        let (_, available) = self.decode(offset)?;
+        let mut bytes = Vec::new();
+        let layout = instr.encode_layout(&mut bytes, self.mode).map_err(PatchError::Encoding)?;
+        if let Some(fixup) = layout.fixups.first() {
+            return Err(PatchError::UnresolvedLabel(fixup.label));
+        }
+        if bytes.len() > available { return Err(PatchError::TooLarge(bytes.len(), available)); }
+        self.code[offset..offset + bytes.len()].copy_from_slice(&bytes);
+        self.write_nops(offset + bytes.len(), available - bytes.len())?;
+        Ok(bytes.len())
+    }
+
+    // The field of the relative branch target, or of the immediate, of the instruction at the
+    // offset, and the end of the instruction.
+    fn field(&self, offset: usize, branch: bool) -> Result<(usize, usize, usize), PatchError> {
+        let (_, len, trace) = self.decode_traced(offset)?;
+        let is_branch = trace.def.is_some_and(|def| def.operands.iter()
+            .any(|op| op.as_ref().is_some_and(|op| matches!(op.op_type, OperandType::Rel(_)))));
+        if is_branch != branch { return Err(PatchError::NoField); }
+        trace.fields.iter().rev().find(|f| f.0 == FieldKind::Immediate)
+            .map(|&(_, start, size)| (offset + start, size, offset + len))
+            .ok_or(PatchError::NoField)
+    }
+
+    // Points the relative branch at the offset (JMP, Jcc, CALL, LOOP...) at `target`, which has
+    // to be in reach of its rel8, rel16 or rel32 field.
+    pub fn retarget(&mut self, offset: usize, target: u64) -> Result<(), PatchError> {
+        let (start, size, end) = self.field(offset, true)?;
+        let value = displacement(target, self.base.wrapping_add(end as u64), self.mode);
+        if write_relative(&mut self.code[start..], (0, size), value) { Ok(()) }
+        else { Err(PatchError::OutOfRange) }
+    }
+
+    // Changes the immediate of the instruction at the offset (the last one, for instructions with
+    // two). The value has to fit in the field as it's encoded, and is written as is, so sign
+    // extended immediates (i.e. ADD EAX, imm8) read 0xFF as -1.
+    pub fn set_immediate(&mut self, offset: usize, value: u64) -> Result<(), PatchError> {
+        let (start, size, _) = self.field(offset, false)?;
+        let unsigned = size >= 8 || value >> (size * 8) == 0;
+        if !unsigned && !fits(value as i64, size) { return Err(PatchError::OutOfRange); }
+        LittleEndian::write_uint(&mut self.code[start..start + size],
+            value & (u64::MAX >> (64 - size * 8)), size);
+        Ok(())
+    }
+
+    // The offset of the aligned 8 bytes holding the patch site at the offset.
+    fn patch_word(&self, offset: usize) -> Result<usize, PatchError> {
+        let misalignment = (self.code.as_ptr() as usize).wrapping_add(offset) % 8;
+        let len = self.decode(offset)?.1;
+        if len != 5 || misalignment > 3 || offset < misalignment ||
+            offset - misalignment + 8 > self.code.len() {
+            return Err(PatchError::InvalidPatchSite);
+        }
+        Ok(offset - misalignment)
+    }
+
+    // Writes the 8 bytes at the offset with a single aligned store, so the patch site is never
+    // half written.
+    fn store_word(&mut self, word: usize, bytes: [u8; 8]) {
+        // The caller checked the alignment, and the store is within `code`.
+        let atomic = unsafe { &*(self.code.as_mut_ptr().add(word) as *const AtomicU64) };
+        atomic.store(u64::from_ne_bytes(bytes), Ordering::SeqCst);
+    }
+
+    // Replaces the patch site at the offset (see InstructionWriter::write_patch_site) with a near
+    // JMP to `target`, in place, i.e. to divert code to a hook. This doesn't make it safe to patch
+    // code that's running: a JitBuffer has to be made writable, so not executable, first.
+    pub fn patch_jump(&mut self, offset: usize, target: u64) -> Result<(), PatchError> {
+        let word = self.patch_word(offset)?;
+        let (jump, field, size) = near_branch(Mnemonic::JMP, self.mode)
+            .map_err(PatchError::Encoding)?;
+        let end = self.base.wrapping_add((offset + jump.len()) as u64);
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&self.code[word..word + 8]);
+        let site = offset - word;
+        bytes[site..site + jump.len()].copy_from_slice(&jump);
+        let value = displacement(target, end, self.mode);
+        if !write_relative(&mut bytes[site..], (field, size), value) {
+            return Err(PatchError::OutOfRange);
+        }
+        // A real mode JMP rel16 is followed by the rest of the site, which is never reached.
+        self.store_word(word, bytes);
+        Ok(())
+    }
+
+    // Turns the patch site at the offset back into a NOP.
+    pub fn clear_patch_site(&mut self, offset: usize) -> Result<(), PatchError> {
+        let word = self.patch_word(offset)?;
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&self.code[word..word + 8]);
+        bytes[offset - word..offset - word + 5].copy_from_slice(&nop_padding(5, self.mode));
+        self.store_word(word, bytes);
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+use std::ptr;
+use byteorder::{LittleEndian, WriteBytesExt};
+use libc;
+use ::Mode;
+use ::elf::{EM_386, EM_X86_64};
+
+// Told about functions once they're in executable memory, so tools like perf can name them.
+pub trait JitListener {
+    fn code_loaded(&mut self, name: &str, address: u64, code: &[u8]) -> io::Result<()>;
+}
+
+// A perf map: one "START SIZE name" line per function, in hex. perf reads /tmp/perf-<pid>.map to
+// name samples in anonymous memory.
+pub struct PerfMap<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> PerfMap<W> {
+    pub fn new(writer: W) -> PerfMap<W> { PerfMap { writer } }
+
+    pub fn get_ref(&self) -> &W { &self.writer }
+}
+
+impl PerfMap<File> {
+    // Appends to /tmp/perf-<pid>.map, creating it if needed.
+    pub fn create() -> io::Result<PerfMap<File>> {
+        let path = format!("/tmp/perf-{}.map", process::id());
+        OpenOptions::new().create(true).append(true).open(path).map(PerfMap::new)
+    }
+}
+
+impl<W: Write> JitListener for PerfMap<W> {
+    fn code_loaded(&mut self, name: &str, address: u64, code: &[u8]) -> io::Result<()> {
+        writeln!(self.writer, "{:x} {:x} {}", address, code.len(), name)?;
+        self.writer.flush()
+    }
+}
+
+const JITDUMP_MAGIC: u32 = 0x4A69_5444;
+const JITDUMP_VERSION: u32 = 1;
+const JITDUMP_HEADER_SIZE: u32 = 40;
+const JIT_CODE_LOAD: u32 = 0;
+const JIT_CODE_CLOSE: u32 = 3;
+
+// The jitdump format `perf inject --jit` merges into a recording, which keeps a copy of the code
+// so it can be annotated after the process exits. Record with `perf record -k mono`, since the
+// timestamps are CLOCK_MONOTONIC.
+pub struct JitDump<W: Write> {
+    writer: W,
+    pid: u32,
+    code_index: u64,
+    // perf finds the dump through an executable mapping of the file in the recording.
+    marker: Option<(*mut libc::c_void, usize)>,
+}
+
+impl<W: Write> JitDump<W> {
+    // Writes the file header, for code of the given mode.
+    pub fn new(mut writer: W, mode: Mode) -> io::Result<JitDump<W>> {
+        let pid = process::id();
+        let machine = if mode == Mode::Long { EM_X86_64 } else { EM_386 };
+        writer.write_u32::<LittleEndian>(JITDUMP_MAGIC)?;
+        writer.write_u32::<LittleEndian>(JITDUMP_VERSION)?;
+        writer.write_u32::<LittleEndian>(JITDUMP_HEADER_SIZE)?;
+        writer.write_u32::<LittleEndian>(machine as u32)?;
+        writer.write_u32::<LittleEndian>(0)?;
+        writer.write_u32::<LittleEndian>(pid)?;
+        writer.write_u64::<LittleEndian>(timestamp())?;
+        writer.write_u64::<LittleEndian>(0)?;
+        Ok(JitDump { writer, pid, code_index: 0, marker: None })
+    }
+
+    pub fn get_ref(&self) -> &W { &self.writer }
+
+    // Writes the record marking the end of the dump.
+    pub fn close(&mut self) -> io::Result<()> {
+        self.record_header(JIT_CODE_CLOSE, 16)?;
+        self.writer.flush()
+    }
+
+    fn record_header(&mut self, id: u32, size: u32) -> io::Result<()> {
+        self.writer.write_u32::<LittleEndian>(id)?;
+        self.writer.write_u32::<LittleEndian>(size)?;
+        self.writer.write_u64::<LittleEndian>(timestamp())
+    }
+}
+
+impl JitDump<File> {
+    // Creates <dir>/jit-<pid>.dump and maps it so perf record notices it.
+    pub fn create<P: AsRef<Path>>(dir: P, mode: Mode) -> io::Result<JitDump<File>> {
+        use std::os::unix::io::AsRawFd;
+        let path = dir.as_ref().join(format!("jit-{}.dump", process::id()));
+        let file = OpenOptions::new().create(true).truncate(true).read(true).write(true)
+            .open(path)?;
+        let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize };
+        let marker = unsafe {
+            libc::mmap(ptr::null_mut(), size, libc::PROT_READ | libc::PROT_EXEC,
+                libc::MAP_PRIVATE, file.as_raw_fd(), 0)
+        };
+        if marker == libc::MAP_FAILED { return Err(io::Error::last_os_error()); }
+        let mut dump = JitDump::new(file, mode)?;
+        dump.marker = Some((marker, size));
+        Ok(dump)
+    }
+}
+
+impl<W: Write> JitListener for JitDump<W> {
+    fn code_loaded(&mut self, name: &str, address: u64, code: &[u8]) -> io::Result<()> {
+        let size = 16 + 40 + name.len() + 1 + code.len();
+        self.record_header(JIT_CODE_LOAD, size as u32)?;
+        self.writer.write_u32::<LittleEndian>(self.pid)?;
+        self.writer.write_u32::<LittleEndian>(thread_id())?;
+        self.writer.write_u64::<LittleEndian>(address)?;
+        self.writer.write_u64::<LittleEndian>(address)?;
+        self.writer.write_u64::<LittleEndian>(code.len() as u64)?;
+        self.writer.write_u64::<LittleEndian>(self.code_index)?;
+        self.writer.write_all(name.as_bytes())?;
+        self.writer.write_u8(0)?;
+        self.writer.write_all(code)?;
+        self.code_index += 1;
+        self.writer.flush()
+    }
+}
+
+impl<W: Write> Drop for JitDump<W> {
+    fn drop(&mut self) {
+        if let Some((marker, size)) = self.marker {
+            unsafe { libc::munmap(marker, size); }
+        }
+    }
+}
+
+fn timestamp() -> u64 {
+    let mut time = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut time); }
+    time.tv_sec as u64 * 1_000_000_000 + time.tv_nsec as u64
+}
+
+#[cfg(target_os = "linux")]
+fn thread_id() -> u32 {
+    unsafe { libc::syscall(libc::SYS_gettid) as u32 }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn thread_id() -> u32 { process::id() }
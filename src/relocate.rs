@@ -0,0 +1,226 @@
+use std::error::Error;
+use std::fmt;
+use byteorder::{ByteOrder, LittleEndian};
+use ::{Instruction, InstructionDecodingError, InstructionEncodingError, Label, Mnemonic, Mode,
+    Operand};
+use ::condition::jcc_condition;
+use ::decoding::{decode_traced, FieldKind};
+use ::instruction_def::OperandType;
+
+const JMP_SHORT: u8 = 0xEB;
+const PREFIX_ADDR_SIZE: u8 = 0x67;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RelocationError {
+    // Decoding - The bytes at the offset aren't a whole, valid instruction.
+    Decoding(usize, InstructionDecodingError),
+
+    // OutOfRange - The target of the instruction at the offset can't be reached from its new
+    // address, i.e. a RIP-relative operand more than 2GB away.
+    OutOfRange(usize),
+
+    // Unrelocatable - The instruction at the offset can't be moved, i.e. it branches into the
+    // middle of another instruction being moved, or uses 32-bit RIP-relative addressing.
+    Unrelocatable(usize),
+}
+
+impl fmt::Display for RelocationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RelocationError::Decoding(offset, ref e) => write!(f, "{} at offset {}", e, offset),
+            RelocationError::OutOfRange(offset) =>
+                write!(f, "the target of the instruction at offset {} is out of range", offset),
+            RelocationError::Unrelocatable(offset) =>
+                write!(f, "the instruction at offset {} can't be relocated", offset),
+        }
+    }
+}
+
+impl Error for RelocationError { }
+
+// A decoded instruction and the field (offset from the start of the instruction and size) holding
+// its target relative to the end of the instruction, if any.
+struct Relocated {
+    offset: usize,
+    len: usize,
+    mnemonic: Mnemonic,
+    field: Option<(usize, usize)>,
+    is_branch: bool,
+    target: u64,
+
+    // The near JMP or Jcc a short branch that doesn't reach is widened to, with its field.
+    near: Option<(Vec<u8>, usize, usize)>,
+}
+
+// Addresses wrap around at the size of the instruction pointer.
+pub fn wrap(address: u64, mode: Mode) -> u64 {
+    match mode {
+        Mode::Real => address & 0xFFFF,
+        Mode::Protected => address & 0xFFFF_FFFF,
+        Mode::Long => address,
+    }
+}
+
+// The displacement from `end` to `target`, as the processor computes it.
+pub fn displacement(target: u64, end: u64, mode: Mode) -> i64 {
+    let value = target.wrapping_sub(end);
+    match mode {
+        Mode::Real => value as i16 as i64,
+        Mode::Protected => value as i32 as i64,
+        Mode::Long => value as i64,
+    }
+}
+
+pub fn fits(value: i64, size: usize) -> bool {
+    let bits = size as u32 * 8;
+    bits >= 64 || (value >= -(1i64 << (bits - 1)) && value < (1i64 << (bits - 1)))
+}
+
+fn decode(bytes: &[u8], offset: usize, ip: u64, mode: Mode)
+    -> Result<Relocated, RelocationError> {
+    let (instr, len, trace) = decode_traced(&bytes[offset..], mode)
+        .map_err(|e| RelocationError::Decoding(offset, e))?;
+    let field_of = |kind| trace.fields.iter().rev().find(|f| f.0 == kind)
+        .map(|&(_, start, size)| (start, size));
+    let is_branch = trace.def.is_some_and(|def| def.operands.iter()
+        .any(|op| op.as_ref().is_some_and(|op| matches!(op.op_type, OperandType::Rel(_)))));
+    let rip_relative = mode == Mode::Long && field_of(FieldKind::Displacement).is_some() &&
+        field_of(FieldKind::ModRm).is_some_and(|(m, _)| bytes[offset + m] & 0xC7 == 0x05);
+    if rip_relative && trace.fields.iter().any(|f| f.0 == FieldKind::Prefix &&
+        bytes[offset + f.1] == PREFIX_ADDR_SIZE) {
+        return Err(RelocationError::Unrelocatable(offset));
+    }
+
+    let field = if is_branch { field_of(FieldKind::Immediate) }
+        else if rip_relative { field_of(FieldKind::Displacement) }
+        else { None };
+    let end = ip.wrapping_add((offset + len) as u64);
+    let target = field.map_or(0, |(start, size)| {
+        let value = LittleEndian::read_int(&bytes[offset + start..], size);
+        wrap(end.wrapping_add(value as u64), mode)
+    });
+    Ok(Relocated { offset, len, mnemonic: instr.mnemonic, field, is_branch, target,
+        near: None })
+}
+
+// Encodes a near JMP or Jcc, returning the bytes and the offset and size of its rel16/rel32
+// field.
+pub fn near_branch(mnemonic: Mnemonic, mode: Mode)
+    -> Result<(Vec<u8>, usize, usize), InstructionEncodingError> {
+    let mut bytes = Vec::new();
+    let layout = Instruction::new1(mnemonic, Operand::Label(Label::new()))
+        .encode_layout(&mut bytes, mode)?;
+    let field = layout.immediate.ok_or(InstructionEncodingError::NoEncoding)?;
+    Ok((bytes, field.offset, field.size))
+}
+
+pub fn write_relative(code: &mut [u8], field: (usize, usize), value: i64) -> bool {
+    if !fits(value, field.1) { return false; }
+    LittleEndian::write_int(&mut code[field.0..field.0 + field.1], value, field.1);
+    true
+}
+
+// The length of the whole instructions at the start of `bytes` that cover at least `len` bytes,
+// i.e. how much of a function has to be moved to make room for a jump to a hook.
+pub fn find_instruction_boundary(bytes: &[u8], len: usize, mode: Mode)
+    -> Result<usize, RelocationError> {
+    let mut offset = 0;
+    while offset < len {
+        let instr_len = decode_traced(&bytes[offset.min(bytes.len())..], mode)
+            .map_err(|e| RelocationError::Decoding(offset, e))?.1;
+        offset += instr_len;
+    }
+    Ok(offset)
+}
+
+// Rewrites whole instructions at `old_ip` to run at `new_ip`. Relative branches and RIP-relative
+// operands are adjusted to keep their targets, except for branches to the instructions being
+// moved, which follow them. Short branches that don't reach any more are widened: JMP and Jcc to
+// their rel32 (rel16 in real mode) forms, JCXZ and LOOP by branching over a near JMP to the
+// target. In real and protected mode addresses are offsets in the code segment.
+pub fn relocate(bytes: &[u8], old_ip: u64, new_ip: u64, mode: Mode)
+    -> Result<Vec<u8>, RelocationError> {
+    let mut instrs = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let instr = decode(bytes, offset, old_ip, mode)?;
+        offset += instr.len;
+        instrs.push(instr);
+    }
+
+    // JCXZ and LOOP have no near form, so they branch over a short JMP to a near JMP instead.
+    let has_near_form = |mnemonic| mnemonic == Mnemonic::JMP || jcc_condition(mnemonic).is_some();
+    let size = |instr: &Relocated| match instr.near {
+        None => instr.len,
+        Some((ref near, ..)) if has_near_form(instr.mnemonic) => near.len(),
+        Some((ref near, ..)) => instr.len + 2 + near.len()
+    };
+
+    // Branches to the moved instructions go to their new addresses.
+    let old_end = old_ip.wrapping_add(bytes.len() as u64);
+    let is_moved = |target: u64| target.wrapping_sub(old_ip) < old_end.wrapping_sub(old_ip);
+    let new_target = |instrs: &[Relocated], offsets: &[u64], instr: &Relocated| {
+        if !instr.is_branch || !is_moved(instr.target) { return Ok(instr.target); }
+        let index = instrs.iter().position(|i| old_ip.wrapping_add(i.offset as u64) == instr.target)
+            .ok_or(RelocationError::Unrelocatable(instr.offset))?;
+        Ok(wrap(new_ip.wrapping_add(offsets[index]), mode))
+    };
+
+    // Widening a branch moves the ones after it, so repeat until every short branch reaches.
+    let offsets = loop {
+        let offsets: Vec<u64> = instrs.iter().scan(0, |offset, instr| {
+            *offset += size(instr) as u64;
+            Some(*offset - size(instr) as u64)
+        }).collect();
+        let mut widened = false;
+        for i in 0..instrs.len() {
+            let instr = &instrs[i];
+            let short = instr.is_branch && instr.near.is_none() &&
+                instr.field.is_some_and(|f| f.1 == 1);
+            if !short { continue; }
+            let end = new_ip.wrapping_add(offsets[i] + instr.len as u64);
+            if !fits(displacement(new_target(&instrs, &offsets, instr)?, end, mode), 1) {
+                let mnemonic = if has_near_form(instr.mnemonic) { instr.mnemonic }
+                    else { Mnemonic::JMP };
+                let near = near_branch(mnemonic, mode)
+                    .map_err(|_| RelocationError::Unrelocatable(instr.offset))?;
+                instrs[i].near = Some(near);
+                widened = true;
+            }
+        }
+        if !widened { break offsets; }
+    };
+
+    let mut code = Vec::new();
+    for (instr, offset) in instrs.iter().zip(offsets.iter()) {
+        let address = new_ip.wrapping_add(*offset);
+        let target = new_target(&instrs, &offsets, instr)?;
+        let original = &bytes[instr.offset..instr.offset + instr.len];
+        let start = code.len();
+        let out_of_range = RelocationError::OutOfRange(instr.offset);
+        match (instr.field, &instr.near) {
+            (None, _) => code.extend_from_slice(original),
+            (Some(field), &None) => {
+                code.extend_from_slice(original);
+                let end = address.wrapping_add(instr.len as u64);
+                let value = displacement(target, end, mode);
+                if !write_relative(&mut code[start..], field, value) { return Err(out_of_range); }
+            },
+            (Some(field), &Some((ref branch, field_offset, field_size))) => {
+                if !has_near_form(instr.mnemonic) {
+                    code.extend_from_slice(original);
+                    code[start + field.0] = 2;
+                    code.extend_from_slice(&[JMP_SHORT, branch.len() as u8]);
+                }
+                let branch_start = code.len();
+                code.extend_from_slice(branch);
+                let end = new_ip.wrapping_add(offset + size(instr) as u64);
+                let value = displacement(target, end, mode);
+                if !write_relative(&mut code[branch_start..], (field_offset, field_size), value) {
+                    return Err(out_of_range);
+                }
+            }
+        }
+    }
+    Ok(code)
+}
@@ -0,0 +1,127 @@
+use std::fmt;
+use ::{Instruction, InstructionDecodingError, InstructionEncodingError, Mode, Operand, Reg};
+use ::decoding::{decode_traced, FieldKind};
+use ::flow::BranchTarget;
+
+// A byte pattern with wildcards, i.e. to find a function again in another build of a binary.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Signature {
+    pattern: Vec<Option<u8>>,
+}
+
+// Whether a field of the instruction changes when code or data moves: relative branch offsets,
+// far pointers, RIP-relative displacements and absolute addresses (including indexed ones without
+// a base, i.e. jump tables), and optionally all immediates.
+fn is_volatile(instr: &Instruction, kind: FieldKind, immediates: bool) -> bool {
+    let operands = [instr.operand1, instr.operand2, instr.operand3, instr.operand4];
+    // The encoder writes a moffs address (Offset) as an immediate, the decoder as a displacement.
+    let moffs = operands.iter().any(|op| matches!(*op, Some(Operand::Offset(..))));
+    match kind {
+        FieldKind::Displacement => moffs || operands.iter().any(|op| matches!(*op,
+            Some(Operand::Memory(..)) | Some(Operand::LabelMemory(..)) |
+            Some(Operand::IndirectScaledDisplaced(..)) |
+            Some(Operand::IndirectDisplaced(Reg::RIP, ..)) |
+            Some(Operand::IndirectDisplaced(Reg::EIP, ..)))),
+        FieldKind::Immediate => immediates || moffs || matches!(instr.flow_kind().target(),
+            Some(BranchTarget::Relative(_)) | Some(BranchTarget::Label(_)) |
+            Some(BranchTarget::Far(..))),
+        _ => false
+    }
+}
+
+impl Signature {
+    // Encodes the instructions, wildcarding the fields that change when code or data moves (see
+    // is_volatile) and label operands. With `immediates`, all immediates are wildcards too.
+    pub fn from_instructions(instrs: &[Instruction], mode: Mode, immediates: bool)
+        -> Result<Signature, InstructionEncodingError> {
+        let mut pattern = Vec::new();
+        for instr in instrs {
+            let mut bytes = Vec::new();
+            let layout = instr.encode_layout(&mut bytes, mode)?;
+            let start = pattern.len();
+            pattern.extend(bytes.into_iter().map(Some));
+            let fields = [(FieldKind::Displacement, layout.displacement),
+                (FieldKind::Immediate, layout.immediate), (FieldKind::Immediate, layout.immediate2),
+                (FieldKind::Immediate, layout.segment)];
+            for &(kind, field) in fields.iter() {
+                if let Some(field) = field.filter(|_| is_volatile(instr, kind, immediates)) {
+                    Signature::wildcard(&mut pattern, start + field.offset, field.size);
+                }
+            }
+            for fixup in layout.fixups.iter() {
+                let size = fixup.size.bits() as usize / 8;
+                Signature::wildcard(&mut pattern, start + fixup.offset as usize, size);
+            }
+        }
+        Ok(Signature { pattern })
+    }
+
+    // The same for code that's already encoded, which has to be whole instructions.
+    pub fn from_code(code: &[u8], mode: Mode, immediates: bool)
+        -> Result<Signature, InstructionDecodingError> {
+        let mut pattern: Vec<Option<u8>> = code.iter().cloned().map(Some).collect();
+        let mut offset = 0;
+        while offset < code.len() {
+            let (instr, len, trace) = decode_traced(&code[offset..], mode)?;
+            for &(kind, start, size) in trace.fields.iter() {
+                if is_volatile(&instr, kind, immediates) {
+                    Signature::wildcard(&mut pattern, offset + start, size);
+                }
+            }
+            offset += len;
+        }
+        Ok(Signature { pattern })
+    }
+
+    // Parses a pattern like "48 8B 05 ?? ?? ?? ??" ("?" works as a wildcard too).
+    pub fn parse(pattern: &str) -> Option<Signature> {
+        pattern.split_whitespace().map(|byte| match byte {
+            "?" | "??" => Some(None),
+            _ if byte.len() == 2 && byte.bytes().all(|b| b.is_ascii_hexdigit()) =>
+                u8::from_str_radix(byte, 16).ok().map(Some),
+            _ => None
+        }).collect::<Option<Vec<_>>>().map(|pattern| Signature { pattern })
+    }
+
+    fn wildcard(pattern: &mut [Option<u8>], offset: usize, size: usize) {
+        for byte in pattern[offset..offset + size].iter_mut() { *byte = None; }
+    }
+
+    // The bytes, with None for wildcards.
+    pub fn get_pattern(&self) -> &[Option<u8>] { &self.pattern }
+
+    pub fn len(&self) -> usize { self.pattern.len() }
+
+    pub fn is_empty(&self) -> bool { self.pattern.is_empty() }
+
+    // Whether `bytes` starts with the pattern.
+    pub fn matches(&self, bytes: &[u8]) -> bool {
+        bytes.len() >= self.pattern.len() && self.pattern.iter().zip(bytes)
+            .all(|(pattern, byte)| pattern.is_none_or(|p| p == *byte))
+    }
+
+    // The offset of the first match in `bytes`.
+    pub fn find(&self, bytes: &[u8]) -> Option<usize> {
+        (0..(bytes.len() + 1).saturating_sub(self.pattern.len()))
+            .find(|&offset| self.matches(&bytes[offset..]))
+    }
+
+    // The offsets of all matches in `bytes`, including overlapping ones.
+    pub fn find_all(&self, bytes: &[u8]) -> Vec<usize> {
+        (0..(bytes.len() + 1).saturating_sub(self.pattern.len()))
+            .filter(|&offset| self.matches(&bytes[offset..])).collect()
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, byte) in self.pattern.iter().enumerate() {
+            if i > 0 { f.write_str(" ")?; }
+            match *byte {
+                Some(byte) => write!(f, "{:02X}", byte)?,
+                None => f.write_str("??")?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,210 @@
+use std::io::Cursor;
+use ::*;
+
+fn encode(instrs: &[Instruction], mode: Mode) -> Vec<u8> {
+    let mut buffer = Cursor::new(Vec::new());
+    for instr in instrs { instr.encode(&mut buffer, mode).expect("Encoding failed"); }
+    buffer.into_inner()
+}
+
+#[test]
+fn argument_locations() {
+    use ArgumentLocation::*;
+    use ArgumentType::*;
+    let args = [Integer, Double, Integer, Float, Integer, Integer, Integer, Integer, Integer];
+    assert_eq!(CallingConvention::SysV.get_argument_locations(&args), vec![
+        Register(Reg::RDI), Register(Reg::XMM0), Register(Reg::RSI), Register(Reg::XMM1),
+        Register(Reg::RDX), Register(Reg::RCX), Register(Reg::R8), Register(Reg::R9), Stack(0)]);
+    assert_eq!(CallingConvention::SysV.get_argument_stack_size(&args), 8);
+
+    // Positional, above the shadow space.
+    assert_eq!(CallingConvention::Win64.get_argument_locations(&args[..6]), vec![
+        Register(Reg::RCX), Register(Reg::XMM1), Register(Reg::R8), Register(Reg::XMM3),
+        Stack(32), Stack(40)]);
+    assert_eq!(CallingConvention::Win64.get_argument_stack_size(&[]), 32);
+
+    assert_eq!(CallingConvention::Cdecl.get_argument_locations(&args[..4]), vec![
+        Stack(0), Stack(4), Stack(12), Stack(16)]);
+    assert_eq!(CallingConvention::Fastcall.get_argument_locations(&args[..4]), vec![
+        Register(Reg::ECX), Stack(0), Register(Reg::EDX), Stack(8)]);
+    assert_eq!(CallingConvention::Near16.get_argument_locations(&[Integer, Integer]), vec![
+        Stack(0), Stack(2)]);
+}
+
+#[test]
+fn sysv_frame() {
+    // push rbp; mov rbp, rsp; push rbx; push r12; sub rsp, 16 ... lea rsp, [rbp-16]; pop r12;
+    // pop rbx; pop rbp; ret
+    let frame = Frame::new(CallingConvention::SysV, &[ArgumentType::Integer],
+        &[Reg::EAX, Reg::BL, Reg::R12, Reg::RDI], 8, 0);
+    assert_eq!(frame.get_saved(), vec![Reg::RBX, Reg::R12]);
+    assert_eq!(frame.get_allocation(), 16);
+    assert_eq!(encode(&frame.prologue(), Mode::Long),
+        vec![0x55, 0x48, 0x89, 0xE5, 0x53, 0x41, 0x54, 0x48, 0x83, 0xEC, 0x10]);
+    assert_eq!(encode(&frame.epilogue(), Mode::Long),
+        vec![0x48, 0x8D, 0x65, 0xF0, 0x41, 0x5C, 0x5B, 0x5D, 0xC3]);
+    assert_eq!(encode(&[Instruction::new2(Mnemonic::MOV, frame.local(0, OperandSize::Qword),
+        frame.argument(0).unwrap())], Mode::Long), vec![0x48, 0x89, 0x7D, 0xE8]);
+}
+
+#[test]
+fn win64_frame() {
+    // The saved XMM registers go below the general ones, after the stack pointer is moved.
+    let frame = Frame::new(CallingConvention::Win64, &[], &[Reg::RSI, Reg::XMM6], 0, 32);
+    assert_eq!(frame.get_saved(), vec![Reg::RSI, Reg::XMM6]);
+    assert_eq!(frame.get_allocation(), 56);
+    assert_eq!(encode(&frame.prologue(), Mode::Long),
+        vec![0x55, 0x48, 0x89, 0xE5, 0x56, 0x48, 0x83, 0xEC, 0x38,
+            0xF3, 0x0F, 0x7F, 0x75, 0xE8]);
+    assert_eq!(encode(&frame.epilogue(), Mode::Long),
+        vec![0xF3, 0x0F, 0x6F, 0x75, 0xE8, 0x48, 0x8D, 0x65, 0xF8, 0x5E, 0x5D, 0xC3]);
+}
+
+#[test]
+fn x86_frames() {
+    let args = [ArgumentType::Integer, ArgumentType::Integer];
+    let cdecl = Frame::new(CallingConvention::Cdecl, &args, &[Reg::EAX], 0, 0);
+    assert_eq!(cdecl.argument(1), Some(Operand::IndirectDisplaced(Reg::EBP, 12,
+        Some(OperandSize::Dword), None)));
+    // push ebp; mov ebp, esp; sub esp, 8 ... leave; ret
+    assert_eq!(encode(&cdecl.prologue(), Mode::Protected),
+        vec![0x55, 0x89, 0xE5, 0x83, 0xEC, 0x08]);
+    assert_eq!(encode(&cdecl.epilogue(), Mode::Protected), vec![0xC9, 0xC3]);
+
+    let stdcall = Frame::new(CallingConvention::Stdcall, &args, &[], 0, 0);
+    assert_eq!(encode(&stdcall.epilogue(), Mode::Protected), vec![0xC9, 0xC2, 0x08, 0x00]);
+    let fastcall = Frame::new(CallingConvention::Fastcall, &args, &[], 0, 0);
+    assert_eq!(fastcall.argument(1), Some(Operand::Direct(Reg::EDX)));
+    assert_eq!(encode(&fastcall.epilogue(), Mode::Protected), vec![0xC9, 0xC3]);
+
+    // No padding is needed for 2 byte alignment.
+    let near = Frame::new(CallingConvention::Near16, &args, &[Reg::SI], 2, 0);
+    assert_eq!(encode(&near.prologue(), Mode::Real),
+        vec![0x55, 0x89, 0xE5, 0x56, 0x83, 0xEC, 0x02]);
+    assert_eq!(encode(&near.epilogue(), Mode::Real),
+        vec![0x8D, 0x66, 0xFE, 0x5E, 0x5D, 0xC3]);
+}
+
+#[test]
+fn cdecl_call() {
+    // push dword [ebx+4]; push dword [ebx]; push esi; push 1; call eax; add esp, 16
+    let frame = Frame::new(CallingConvention::Cdecl, &[], &[], 0, 0);
+    let call = frame.call(Operand::Direct(Reg::EAX), &[
+        (ArgumentType::Integer, Operand::Literal8(1)),
+        (ArgumentType::Integer, Operand::Direct(Reg::SI)),
+        (ArgumentType::Double, Operand::Indirect(Reg::EBX, None, None))]).unwrap();
+    assert_eq!(encode(&call, Mode::Protected), vec![0xFF, 0x73, 0x04, 0xFF, 0x33, 0x56,
+        0x68, 0x01, 0x00, 0x00, 0x00, 0xFF, 0xD0, 0x83, 0xC4, 0x10]);
+
+    // The callee removes the arguments, leaving the padding.
+    let frame = Frame::new(CallingConvention::Stdcall, &[], &[], 0, 0);
+    let call = frame.call(Operand::Direct(Reg::EAX),
+        &[(ArgumentType::Float, Operand::Direct(Reg::XMM1))]).unwrap();
+    assert_eq!(encode(&call, Mode::Protected), vec![0x83, 0xEC, 0x0C, 0x83, 0xEC, 0x04,
+        0xF3, 0x0F, 0x11, 0x0C, 0x24, 0xFF, 0xD0, 0x83, 0xC4, 0x0C]);
+}
+
+#[test]
+fn register_cycles() {
+    // The arguments are swapped, and RDX is read before it's overwritten.
+    let frame = Frame::new(CallingConvention::SysV, &[], &[], 0, 0);
+    let call = frame.call(Operand::Direct(Reg::RAX), &[
+        (ArgumentType::Integer, Operand::Direct(Reg::RSI)),
+        (ArgumentType::Integer, Operand::Direct(Reg::RDI)),
+        (ArgumentType::Integer, Operand::Literal8(5)),
+        (ArgumentType::Integer, Operand::IndirectDisplaced(Reg::RDX, 8,
+            Some(OperandSize::Qword), None))]).unwrap();
+    // xchg rdi, rsi; mov edx, 5 would clobber rdx, so mov rcx, [rdx+8] comes first.
+    assert_eq!(encode(&call, Mode::Long), vec![0x48, 0x8B, 0x4A, 0x08, 0xBA, 0x05, 0x00, 0x00,
+        0x00, 0x48, 0x87, 0xF7, 0xFF, 0xD0]);
+
+    let conflicting = frame.call(Operand::Direct(Reg::RDI),
+        &[(ArgumentType::Integer, Operand::Literal8(1))]);
+    assert_eq!(conflicting.err(), Some(AbiError::ConflictingArguments));
+    let float_cycle = frame.call(Operand::Direct(Reg::RAX), &[
+        (ArgumentType::Double, Operand::Direct(Reg::XMM1)),
+        (ArgumentType::Double, Operand::Direct(Reg::XMM0))]);
+    assert_eq!(float_cycle.err(), Some(AbiError::ConflictingArguments));
+}
+
+#[test]
+fn errors() {
+    let frame = Frame::new(CallingConvention::Win64, &[], &[], 0, 0);
+    assert_eq!(frame.call(Operand::Direct(Reg::RAX), &[]).err(),
+        Some(AbiError::OutgoingSpace(32)));
+    let frame = Frame::new(CallingConvention::SysV, &[], &[], 0, 0);
+    let literal_float = frame.call(Operand::Direct(Reg::RAX),
+        &[(ArgumentType::Integer, Operand::Direct(Reg::RDI)),
+            (ArgumentType::Double, Operand::Literal64(0))]);
+    assert_eq!(literal_float.err(), Some(AbiError::InvalidArgument(1)));
+    let frame = Frame::new(CallingConvention::Near16, &[], &[], 0, 0);
+    let float = frame.call(Operand::Direct(Reg::AX),
+        &[(ArgumentType::Float, Operand::Literal16(0))]);
+    assert_eq!(float.err(), Some(AbiError::InvalidArgument(0)));
+}
+
+#[cfg(all(unix, target_arch = "x86_64"))]
+mod jit {
+    use std::cell::RefCell;
+    use ::*;
+
+    thread_local!(static RECORDED: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) });
+
+    type Callee = extern "C" fn(u64, u64, u64, f64, u64, f32, u64, u64, u64, u64) -> u64;
+
+    #[allow(clippy::too_many_arguments)]
+    extern "C" fn callee(a: u64, b: u64, c: u64, d: f64, e: u64, f: f32, g: u64, h: u64,
+        i: u64, j: u64) -> u64 {
+        RECORDED.with(|r| *r.borrow_mut() = vec![a, b, c, d.to_bits(), e, f.to_bits() as u64,
+            g, h, i, j]);
+        a + b
+    }
+
+    #[test]
+    fn sysv_call() {
+        // outer(a, b, p) calls callee(b, a, 3, *p, 1 << 32, 3.0, a, 6, 7, bits of *p), with a
+        // kept in RBX and the float in a local.
+        let args = [ArgumentType::Integer, ArgumentType::Double, ArgumentType::Integer,
+            ArgumentType::Integer, ArgumentType::Double];
+        let types = [ArgumentType::Integer, ArgumentType::Integer, ArgumentType::Integer,
+            ArgumentType::Double, ArgumentType::Integer, ArgumentType::Float,
+            ArgumentType::Integer, ArgumentType::Integer, ArgumentType::Integer,
+            ArgumentType::Integer];
+        let outgoing = CallingConvention::SysV.get_argument_stack_size(&types);
+        assert_eq!(outgoing, 16);
+        let frame = Frame::new(CallingConvention::SysV, &args[..3], &[Reg::RBX], 4, outgoing);
+        let (start, target) = (Label::new(), Label::new());
+        let p = Operand::Indirect(Reg::RDX, Some(OperandSize::Qword), None);
+        let call = frame.call(Operand::LabelMemory(target, Some(OperandSize::Qword), None), &[
+            (ArgumentType::Integer, Operand::Direct(Reg::RSI)),
+            (ArgumentType::Integer, Operand::Direct(Reg::RDI)),
+            (ArgumentType::Integer, Operand::Literal8(3)),
+            (ArgumentType::Double, p),
+            (ArgumentType::Integer, Operand::Literal64(1 << 32)),
+            (ArgumentType::Float, frame.local(0, OperandSize::Dword)),
+            (ArgumentType::Integer, Operand::Direct(Reg::RBX)),
+            (ArgumentType::Integer, Operand::Literal8(6)),
+            (ArgumentType::Integer, Operand::Literal8(7)),
+            (ArgumentType::Integer, p)]).unwrap();
+
+        let mut writer = InstructionWriter::new(JitBuffer::new().unwrap(), Mode::Long);
+        writer.set_label(start);
+        for instr in frame.prologue() { writer.write(&instr).unwrap(); }
+        writer.write2(Mnemonic::MOV, Operand::Direct(Reg::RBX), Operand::Direct(Reg::RDI))
+            .unwrap();
+        writer.write2(Mnemonic::MOV, frame.local(0, OperandSize::Dword),
+            Operand::Literal32(3.0f32.to_bits())).unwrap();
+        for instr in call { writer.write(&instr).unwrap(); }
+        for instr in frame.epilogue() { writer.write(&instr).unwrap(); }
+        writer.set_label(target);
+        writer.write_bytes(&(callee as Callee as usize as u64).to_le_bytes()).unwrap();
+        writer.finalize().unwrap();
+
+        let outer: extern "C" fn(u64, u64, *const f64) -> u64 =
+            unsafe { writer.get_function(start) }.unwrap();
+        let value = 2.5f64;
+        assert_eq!(outer(10, 20, &value), 30);
+        RECORDED.with(|r| assert_eq!(*r.borrow(), vec![20, 10, 3, value.to_bits(), 1 << 32,
+            3.0f32.to_bits() as u64, 10, 6, 7, value.to_bits()]));
+    }
+}
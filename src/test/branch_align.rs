@@ -0,0 +1,182 @@
+use ::*;
+
+fn aligned_writer(mode: Mode, offset: usize) -> InstructionWriter<Vec<u8>> {
+    let mut writer = InstructionWriter::new(Vec::new(), mode);
+    writer.enable_branch_alignment();
+    writer.write_bytes(&vec![0xCC; offset]).unwrap();
+    writer
+}
+
+fn reg(reg: Reg) -> Operand { Operand::Direct(reg) }
+
+#[test]
+fn fused_pair() {
+    // cmp eax, 1; je - 9 bytes at 28 would cross 32, so 4 bytes of NOP go before the cmp.
+    let mut writer = aligned_writer(Mode::Long, 28);
+    let target = Label::new();
+    writer.write2(Mnemonic::CMP, reg(Reg::EAX), Operand::Literal8(1)).unwrap();
+    assert_eq!(writer.get_offset(), 31);
+    writer.write1(Mnemonic::JE, Operand::Label(target)).unwrap();
+    writer.set_label(target);
+    let code = writer.get_inner_writer_ref();
+    assert_eq!(code[28..32], [0x0F, 0x1F, 0x40, 0x00]);
+    assert_eq!(code[32..], [0x83, 0xF8, 0x01, 0x0F, 0x84, 0x00, 0x00, 0x00, 0x00]);
+    assert_eq!(writer.get_label_offset(target), Some(41));
+    assert_eq!(writer.get_fixups().unwrap()[0].offset, 37);
+
+    // mov doesn't fuse, so only the jump is moved. It's padded with prefixes on the mov.
+    let mut writer = aligned_writer(Mode::Long, 25);
+    writer.write2(Mnemonic::MOV, reg(Reg::EAX), Operand::Literal32(1)).unwrap();
+    writer.write1(Mnemonic::JE, Operand::Label(target)).unwrap();
+    assert_eq!(writer.get_inner_writer_ref()[25..32], [0x2E, 0x2E, 0xB8, 0x01, 0x00, 0x00, 0x00]);
+
+    // A jump that ends right at the boundary is moved too.
+    let mut writer = aligned_writer(Mode::Long, 27);
+    writer.write1(Mnemonic::JMP, Operand::Label(target)).unwrap();
+    assert_eq!(writer.get_inner_writer_ref()[27..], [0x0F, 0x1F, 0x44, 0x00, 0x00,
+        0xE9, 0x00, 0x00, 0x00, 0x00]);
+    let mut writer = aligned_writer(Mode::Long, 26);
+    writer.write0(Mnemonic::RET).unwrap();
+    assert_eq!(writer.get_inner_writer_ref().len(), 27);
+}
+
+#[test]
+fn prefix_padding() {
+    // Up to 5 prefixes on each of the instructions before the jump, and NOPs for the rest.
+    let mut writer = aligned_writer(Mode::Long, 22);
+    writer.write2(Mnemonic::MOV, reg(Reg::EAX), reg(Reg::EBX)).unwrap();
+    writer.write2(Mnemonic::MOV, reg(Reg::ECX), reg(Reg::EDX)).unwrap();
+    writer.write2(Mnemonic::MOV, reg(Reg::RSI), Operand::Indirect(Reg::RDI, None, None)).unwrap();
+    writer.write1(Mnemonic::PUSH, reg(Reg::RAX)).unwrap();
+    writer.write1(Mnemonic::JMP, reg(Reg::RAX)).unwrap();
+    writer.write_bytes(&[0xCC]).unwrap();
+    let code = writer.get_inner_writer_ref();
+    // The jmp at 30 needs 2 bytes of padding, which the push gets.
+    assert_eq!(code[29..], [0x2E, 0x2E, 0x50, 0xFF, 0xE0, 0xCC]);
+
+    // cmp [rax+rbx*4+0x1000], rcx; je - 14 bytes at 18 need 14 bytes of padding: 5 prefixes on
+    // each mov, none on the one with an fs: override, and 4 bytes of NOPs.
+    let mut writer = aligned_writer(Mode::Long, 10);
+    writer.write2(Mnemonic::MOV, reg(Reg::EAX), reg(Reg::EBX)).unwrap();
+    let fs = Operand::IndirectDisplaced(Reg::RBP, 8, None, Some(SegmentReg::FS));
+    writer.write2(Mnemonic::MOV, fs, reg(Reg::EAX)).unwrap();
+    writer.write2(Mnemonic::MOV, reg(Reg::ECX), reg(Reg::EDX)).unwrap();
+    writer.write2(Mnemonic::CMP, Operand::IndirectScaledIndexedDisplaced(Reg::RAX, Reg::RBX,
+        RegScale::Four, 0x1000, Some(OperandSize::Qword), None), reg(Reg::RCX)).unwrap();
+    writer.write1(Mnemonic::JE, Operand::Label(Label::new())).unwrap();
+    let code = writer.get_inner_writer_ref();
+    assert_eq!(code.len(), 46);
+    assert_eq!(code[10..17], [0x2E, 0x2E, 0x2E, 0x2E, 0x2E, 0x89, 0xD8]);
+    assert_eq!(code[17..21], [0x64, 0x89, 0x45, 0x08]);
+    assert_eq!(code[21..28], [0x2E, 0x2E, 0x2E, 0x2E, 0x2E, 0x89, 0xD1]);
+    assert_eq!(code[28..32], nop_padding(4, Mode::Long)[..]);
+    assert_eq!(code[32..35], [0x48, 0x39, 0x8C]);
+    assert_eq!(InstructionReader::new(&code[10..], Mode::Long).read().unwrap().1, 7);
+
+    // In protected mode, the prefix is the segment the operand uses anyway.
+    let mut writer = aligned_writer(Mode::Protected, 17);
+    writer.write2(Mnemonic::MOV, reg(Reg::EAX), Operand::IndirectDisplaced(Reg::EBP, 8,
+        Some(OperandSize::Dword), None)).unwrap();
+    writer.write2(Mnemonic::MOV, reg(Reg::EAX), Operand::Indirect(Reg::EBX, None, None)).unwrap();
+    writer.write2(Mnemonic::CMP, reg(Reg::ECX), Operand::Literal32(0x1234_5678)).unwrap();
+    writer.write1(Mnemonic::JE, Operand::Label(Label::new())).unwrap();
+    let code = writer.get_inner_writer_ref();
+    assert_eq!(code[17..32], [0x36, 0x36, 0x36, 0x36, 0x36, 0x8B, 0x45, 0x08,
+        0x3E, 0x3E, 0x3E, 0x3E, 0x3E, 0x8B, 0x03]);
+}
+
+#[test]
+fn held_instructions() {
+    let mut writer = aligned_writer(Mode::Long, 0);
+    writer.write2(Mnemonic::MOV, reg(Reg::EAX), reg(Reg::EBX)).unwrap();
+    assert_eq!(writer.get_offset(), 2);
+    assert!(writer.get_inner_writer_ref().is_empty());
+    writer.flush().unwrap();
+    assert_eq!(writer.get_inner_writer_ref(), &[0x89, 0xD8]);
+
+    // Only the last 8 instructions are held.
+    for _ in 0..10 { writer.write0(Mnemonic::NOP).unwrap(); }
+    assert_eq!(writer.get_inner_writer_ref().len(), 4);
+    writer.set_label(Label::new());
+    assert_eq!(writer.get_inner_writer_ref().len(), 12);
+}
+
+#[test]
+fn line_crossings() {
+    let mut writer = aligned_writer(Mode::Long, 60);
+    writer.write2(Mnemonic::MOV, reg(Reg::EAX), Operand::Literal32(1)).unwrap();
+    writer.write2(Mnemonic::MOV, reg(Reg::EAX), Operand::Literal32(1)).unwrap();
+    writer.write2(Mnemonic::MOV, reg(Reg::EAX), Operand::Literal32(1)).unwrap();
+    writer.flush().unwrap();
+    assert_eq!(writer.get_line_crossings(), &[(60, 5)]);
+}
+
+#[cfg(all(unix, target_arch = "x86_64"))]
+#[test]
+fn aligned_function() {
+    // sum(n) = n + (n - 1) + ... + 1. The test and je at 28 are moved by prefixes on the
+    // RIP-relative load before them.
+    let (start, zero, top, done) = (Label::new(), Label::new(), Label::new(), Label::new());
+    let mut writer = InstructionWriter::new(JitBuffer::new().unwrap(), Mode::Long);
+    writer.enable_branch_alignment();
+    writer.write_bytes(&[0xCC; 20]).unwrap();
+    writer.set_label(start);
+    writer.write2(Mnemonic::MOV, reg(Reg::ECX), reg(Reg::EDI)).unwrap();
+    writer.write2(Mnemonic::MOV, reg(Reg::EAX), Operand::LabelMemory(zero, None, None)).unwrap();
+    writer.write2(Mnemonic::TEST, reg(Reg::ECX), reg(Reg::ECX)).unwrap();
+    writer.write1(Mnemonic::JE, Operand::Label(done)).unwrap();
+    writer.set_label(top);
+    writer.write2(Mnemonic::ADD, reg(Reg::EAX), reg(Reg::ECX)).unwrap();
+    writer.write1(Mnemonic::DEC, reg(Reg::ECX)).unwrap();
+    writer.write1(Mnemonic::JNE, Operand::Label(top)).unwrap();
+    writer.set_label(done);
+    writer.write0(Mnemonic::RET).unwrap();
+    writer.set_label(zero);
+    writer.write_bytes(&[0; 4]).unwrap();
+    writer.finalize().unwrap();
+
+    assert_eq!(writer.get_inner_writer_ref().as_slice()[22..26], [0x2E; 4]);
+    let sum: extern "C" fn(u32) -> u32 = unsafe { writer.get_function(start) }.unwrap();
+    assert_eq!(sum(10), 55);
+}
+
+#[test]
+fn unflushed_code() {
+    // The code ends in a load rather than a jump, so it's held until flushed.
+    let data = Label::new();
+    let mut writer = InstructionWriter::new(Vec::new(), Mode::Protected);
+    writer.enable_branch_alignment();
+    writer.set_label(data);
+    writer.write_bytes(&[1, 2, 3, 4]).unwrap();
+    writer.write2(Mnemonic::MOV, reg(Reg::EAX), Operand::LabelMemory(data, None, None)).unwrap();
+    assert!(writer.is_holding());
+    assert_eq!(writer.get_fixups(), Err(InstructionEncodingError::Unflushed));
+    assert_eq!(FlatBinary::new(0x1000).build(&writer), Err(ObjectError::Unflushed));
+    writer.flush().unwrap();
+    let image = FlatBinary::new(0x1000).build(&writer).unwrap();
+    assert_eq!(image[4..], [0x8B, 0x05, 0x00, 0x10, 0x00, 0x00]);
+
+    let mut obj = ObjectWriter::new(Mode::Long);
+    obj.section(Section::Text).enable_branch_alignment();
+    obj.section(Section::Text).write0(Mnemonic::RET).unwrap();
+    obj.section(Section::Text).write2(Mnemonic::MOV, reg(Reg::EAX), reg(Reg::EBX)).unwrap();
+    assert_eq!(obj.write_elf(&mut Vec::new()), Err(ObjectError::Unflushed));
+    obj.flush().unwrap();
+    let resolved = obj.resolve(false).unwrap();
+    assert_eq!(resolved.0.iter().find(|s| s.0 == Section::Text).unwrap().1,
+        vec![0xC3, 0x89, 0xD8]);
+}
+
+#[test]
+fn mode_switch() {
+    // Instructions held in real mode are written before switching, so they aren't padded with
+    // a CS override, which isn't ignored outside long mode.
+    let mut writer = aligned_writer(Mode::Real, 26);
+    writer.write2(Mnemonic::MOV, reg(Reg::AX), Operand::Indirect(Reg::BX, None, None)).unwrap();
+    writer.set_mode(Mode::Long);
+    assert!(!writer.is_holding());
+    writer.write1(Mnemonic::JMP, Operand::Label(Label::new())).unwrap();
+    let code = writer.get_inner_writer_ref();
+    assert_eq!(code[26..28], [0x8B, 0x07]);
+    assert_eq!(code[28..32], nop_padding(4, Mode::Long)[..]);
+}
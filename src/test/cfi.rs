@@ -0,0 +1,201 @@
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+use std::os::raw::c_void;
+use ::*;
+
+// push rbp; mov rbp, rsp; push rbx; sub rsp, 24; mov eax, 1; add rsp, 24; pop rbx; pop rbp; ret
+fn function<T: ::std::io::Write>(writer: &mut InstructionWriter<T>) -> FrameInfo {
+    let (start, end) = (Label::new(), Label::new());
+    writer.begin_frame(start);
+    writer.write1(Mnemonic::PUSH, Operand::Direct(Reg::RBP)).unwrap();
+    writer.write2(Mnemonic::MOV, Operand::Direct(Reg::RBP), Operand::Direct(Reg::RSP)).unwrap();
+    writer.write1(Mnemonic::PUSH, Operand::Direct(Reg::RBX)).unwrap();
+    writer.write2(Mnemonic::SUB, Operand::Direct(Reg::RSP), Operand::Literal8(24)).unwrap();
+    writer.write2(Mnemonic::MOV, Operand::Direct(Reg::EAX), Operand::Literal32(1)).unwrap();
+    writer.write2(Mnemonic::ADD, Operand::Direct(Reg::RSP), Operand::Literal8(24)).unwrap();
+    writer.write1(Mnemonic::POP, Operand::Direct(Reg::RBX)).unwrap();
+    writer.write1(Mnemonic::POP, Operand::Direct(Reg::RBP)).unwrap();
+    writer.write0(Mnemonic::RET).unwrap();
+    writer.end_frame(end).unwrap()
+}
+
+#[test]
+fn record_prolog() {
+    let mut writer = InstructionWriter::new(Vec::new(), Mode::Long);
+    let info = function(&mut writer);
+    let steps: Vec<(u64, CfiOp)> = info.steps.iter()
+        .map(|&(label, op)| (writer.get_label_offset(label).unwrap(), op)).collect();
+    assert_eq!(steps, vec![
+        (1, CfiOp::CfaOffset(16)), (1, CfiOp::Saved(Reg::RBP, -16)),
+        (4, CfiOp::CfaRegister(Reg::RBP)),
+        (5, CfiOp::Saved(Reg::RBX, -24))]);
+    assert_eq!(writer.get_label_offset(info.start), Some(0));
+    assert_eq!(writer.get_label_offset(info.end), Some(writer.get_offset()));
+    assert_eq!(writer.end_frame(Label::new()), None);
+}
+
+#[test]
+fn record_protected_mode() {
+    let mut writer = InstructionWriter::new(Vec::new(), Mode::Protected);
+    let start = Label::new();
+    writer.begin_frame(start);
+    writer.write1(Mnemonic::PUSH, Operand::Direct(Reg::ESI)).unwrap();
+    writer.write2(Mnemonic::SUB, Operand::Direct(Reg::ESP), Operand::Literal32(0x100)).unwrap();
+    writer.write1(Mnemonic::PUSH, Operand::Direct(Reg::EDI)).unwrap();
+    let info = writer.end_frame(Label::new()).unwrap();
+    let ops: Vec<CfiOp> = info.steps.iter().map(|s| s.1).collect();
+    assert_eq!(ops, vec![CfiOp::CfaOffset(8), CfiOp::Saved(Reg::ESI, -8), CfiOp::CfaOffset(0x108),
+        CfiOp::CfaOffset(0x10C), CfiOp::Saved(Reg::EDI, -0x10C)]);
+}
+
+#[test]
+fn eh_frame() {
+    let mut writer = InstructionWriter::new(Vec::new(), Mode::Long);
+    let info = function(&mut writer);
+    let (bytes, fixups) = build_frame_section(::std::slice::from_ref(&info), Mode::Long, FrameFormat::EhFrame,
+        |label| writer.get_label_offset(label)).unwrap();
+    let size = writer.get_offset() as u8;
+    assert_eq!(bytes, vec![
+        // CIE
+        0x14, 0, 0, 0, 0, 0, 0, 0, 1, b'z', b'R', 0, 1, 0x78, 0x10, 1, 0x1B,
+        0x0C, 7, 8, 0x90, 1, 0, 0,
+        // FDE
+        0x1C, 0, 0, 0, 0x1C, 0, 0, 0, 0, 0, 0, 0, size, 0, 0, 0, 0,
+        0x41, 0x0E, 0x10, 0x86, 2, 0x43, 0x0D, 6, 0x41, 0x83, 3, 0, 0, 0, 0]);
+    assert_eq!(fixups, vec![Fixup { offset: 32, size: OperandSize::Dword, label: info.start,
+        kind: FixupKind::Relative, addend: 0 }]);
+}
+
+#[test]
+fn debug_frame() {
+    let mut writer = InstructionWriter::new(Vec::new(), Mode::Protected);
+    let (start, end) = (Label::new(), Label::new());
+    writer.begin_frame(start);
+    writer.write1(Mnemonic::PUSH, Operand::Direct(Reg::EBP)).unwrap();
+    writer.write2(Mnemonic::MOV, Operand::Direct(Reg::EBP), Operand::Direct(Reg::ESP)).unwrap();
+    writer.write0(Mnemonic::LEAVE).unwrap();
+    writer.write0(Mnemonic::RET).unwrap();
+    let info = writer.end_frame(end).unwrap();
+    let (bytes, fixups) = build_frame_section(&[info], Mode::Protected, FrameFormat::DebugFrame,
+        |label| writer.get_label_offset(label)).unwrap();
+    assert_eq!(bytes, vec![
+        // CIE
+        0x10, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 1, 0, 1, 0x7C, 8, 0x0C, 4, 4, 0x88, 1, 0, 0,
+        // FDE
+        0x14, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 5, 0, 0, 0,
+        0x41, 0x0E, 8, 0x85, 2, 0x42, 0x0D, 5]);
+    assert_eq!(fixups, vec![Fixup { offset: 28, size: OperandSize::Dword, label: start,
+        kind: FixupKind::Absolute, addend: 0 }]);
+}
+
+#[test]
+fn invalid_frames() {
+    let mut writer = InstructionWriter::new(Vec::new(), Mode::Long);
+    let info = function(&mut writer);
+    let offset = |label| writer.get_label_offset(label);
+    let saved = FrameInfo { steps: vec![(info.end, CfiOp::Saved(Reg::RBX, -12))], ..info.clone() };
+    assert_eq!(build_frame_section(&[saved], Mode::Long, FrameFormat::EhFrame, offset),
+        Err(ObjectError::InvalidUnwindInfo(info.start)));
+    let register = FrameInfo { steps: vec![(info.end, CfiOp::CfaRegister(Reg::XMM0))],
+        ..info.clone() };
+    assert_eq!(build_frame_section(&[register], Mode::Long, FrameFormat::EhFrame, offset),
+        Err(ObjectError::InvalidUnwindInfo(info.start)));
+    for &reg in [Reg::RIP, Reg::GDTR, Reg::RFLAGS].iter() {
+        let unnumbered = FrameInfo { steps: vec![(info.end, CfiOp::Saved(reg, -16))],
+            ..info.clone() };
+        assert_eq!(build_frame_section(&[unnumbered], Mode::Long, FrameFormat::EhFrame, offset),
+            Err(ObjectError::InvalidUnwindInfo(info.start)));
+    }
+    let missing = Label::new();
+    let undefined = FrameInfo { end: missing, ..info.clone() };
+    assert_eq!(build_frame_section(&[undefined], Mode::Long, FrameFormat::EhFrame, offset),
+        Err(ObjectError::UndefinedLabel(missing)));
+    assert_eq!(build_frame_section(&[info], Mode::Real, FrameFormat::EhFrame, offset),
+        Err(ObjectError::UnsupportedMode(Mode::Real)));
+}
+
+#[test]
+fn elf_eh_frame() {
+    let mut obj = ObjectWriter::new(Mode::Long);
+    let entry = obj.symbol("entry", Binding::Global);
+    obj.section(Section::Text).write0(Mnemonic::NOP).unwrap();
+    obj.section(Section::Text).set_label(entry);
+    let mut info = function(obj.section(Section::Text));
+    info.start = entry;
+    obj.add_frame_info(info);
+    let mut bytes = Vec::new();
+    obj.write_elf(&mut bytes).unwrap();
+
+    let elf = ElfFile::parse(&bytes).unwrap();
+    let eh_frame = elf.get_section(".eh_frame").unwrap();
+    assert_eq!(eh_frame.section_type, 0x7000_0001);
+    assert_eq!(eh_frame.size, 56);
+    let relocations = elf.get_section(".rela.eh_frame").unwrap();
+    assert_eq!(relocations.info as usize, eh_frame.index);
+    assert_eq!(relocations.link as usize, elf.get_section(".symtab").unwrap().index);
+    // R_X86_64_PC32 against .text, at the FDE's pc_begin, to the function (after the NOP).
+    assert_eq!(elf.get_section_data(relocations).unwrap(),
+        &[32, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0][..]);
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+extern "C" {
+    fn __register_frame(begin: *const u8);
+    fn __deregister_frame(begin: *const u8);
+    fn _Unwind_Backtrace(trace: extern "C" fn(*mut c_void, *mut c_void) -> i32,
+        data: *mut c_void) -> i32;
+    fn _Unwind_GetIP(context: *mut c_void) -> usize;
+}
+
+// Collects the return addresses on the stack.
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+extern "C" fn trace(context: *mut c_void, data: *mut c_void) -> i32 {
+    let addresses = unsafe { &mut *(data as *mut Vec<usize>) };
+    addresses.push(unsafe { _Unwind_GetIP(context) });
+    0
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+extern "C" fn backtrace(addresses: *mut Vec<usize>) {
+    unsafe { _Unwind_Backtrace(trace, addresses as *mut c_void); }
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[test]
+fn unwind_through_jit_code() {
+    // A function with a frame that calls `backtrace`, with the stack pointer moved around so the
+    // unwinder needs the frame info to find its caller.
+    let mut writer = InstructionWriter::new(JitBuffer::new().unwrap(), Mode::Long);
+    let (start, end, callback) = (Label::new(), Label::new(), Label::new());
+    writer.begin_frame(start);
+    writer.write1(Mnemonic::PUSH, Operand::Direct(Reg::RBP)).unwrap();
+    writer.write2(Mnemonic::MOV, Operand::Direct(Reg::RBP), Operand::Direct(Reg::RSP)).unwrap();
+    writer.write1(Mnemonic::PUSH, Operand::Direct(Reg::RBX)).unwrap();
+    writer.write2(Mnemonic::SUB, Operand::Direct(Reg::RSP), Operand::Literal8(40)).unwrap();
+    writer.write1(Mnemonic::CALL, Operand::LabelMemory(callback, Some(OperandSize::Qword), None))
+        .unwrap();
+    writer.write2(Mnemonic::ADD, Operand::Direct(Reg::RSP), Operand::Literal8(40)).unwrap();
+    writer.write1(Mnemonic::POP, Operand::Direct(Reg::RBX)).unwrap();
+    writer.write1(Mnemonic::POP, Operand::Direct(Reg::RBP)).unwrap();
+    writer.write0(Mnemonic::RET).unwrap();
+    let info = writer.end_frame(end).unwrap();
+    writer.set_label(callback);
+    writer.write_bytes(&(backtrace as extern "C" fn(*mut Vec<usize>) as usize as u64).to_le_bytes())
+        .unwrap();
+    writer.finalize().unwrap();
+
+    let eh_frame = writer.build_eh_frame(&[info]).unwrap();
+    let f: extern "C" fn(*mut Vec<usize>) = unsafe { writer.get_function(start) }.unwrap();
+    let (low, high) = (writer.get_label_address(start).unwrap() as usize,
+        writer.get_label_address(end).unwrap() as usize);
+
+    let mut addresses = Vec::new();
+    unsafe { __register_frame(eh_frame.as_ptr()); }
+    f(&mut addresses);
+    unsafe { __deregister_frame(eh_frame.as_ptr()); }
+
+    // The unwinder got past the generated function, back into this test.
+    let jit_frame = addresses.iter().position(|a| *a > low && *a <= high)
+        .expect("The generated function is on the stack.");
+    let caller = unwind_through_jit_code as fn() as usize;
+    assert!(addresses[jit_frame + 1..].iter().any(|a| *a > caller && *a < caller + 0x4000));
+}
@@ -0,0 +1,180 @@
+use byteorder::{ByteOrder, LittleEndian};
+use ::*;
+
+// Just enough of a COFF parser to check the structure of the objects.
+struct Coff<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Coff<'a> {
+    fn u16(&self, at: usize) -> u16 { LittleEndian::read_u16(&self.bytes[at..]) }
+    fn u32(&self, at: usize) -> u32 { LittleEndian::read_u32(&self.bytes[at..]) }
+
+    fn string(&self, field: &[u8]) -> String {
+        let bytes = if field[..4] == [0; 4] {
+            let strings = self.u32(8) as usize + 18 * self.u32(12) as usize;
+            &self.bytes[strings + LittleEndian::read_u32(&field[4..]) as usize..]
+        } else { field };
+        let end = bytes.iter().take(if field[..4] == [0; 4] { bytes.len() } else { 8 })
+            .position(|b| *b == 0).unwrap_or(8);
+        String::from_utf8(bytes[..end].to_vec()).unwrap()
+    }
+
+    // (name, size, contents offset, characteristics)
+    fn section(&self, name: &str) -> (usize, u32, usize, u32) {
+        (0..self.u16(2) as usize).map(|i| 20 + 40 * i)
+            .find(|at| self.string(&self.bytes[*at..*at + 8]) == name)
+            .map(|at| (at, self.u32(at + 16), self.u32(at + 20) as usize, self.u32(at + 36)))
+            .expect("Missing section")
+    }
+
+    fn contents(&self, name: &str) -> &[u8] {
+        let (_, size, offset, _) = self.section(name);
+        &self.bytes[offset..offset + size as usize]
+    }
+
+    // (offset, symbol name, type)
+    fn relocations(&self, name: &str) -> Vec<(u32, String, u16)> {
+        let (at, ..) = self.section(name);
+        let (offset, count) = (self.u32(at + 24) as usize, self.u16(at + 32) as usize);
+        (0..count).map(|i| offset + 10 * i)
+            .map(|at| (self.u32(at), self.symbol(self.u32(at + 4) as usize).0, self.u16(at + 8)))
+            .collect()
+    }
+
+    // (name, value, section number, storage class, aux count)
+    fn symbol(&self, index: usize) -> (String, u32, i16, u8, u8) {
+        let at = self.u32(8) as usize + 18 * index;
+        (self.string(&self.bytes[at..at + 8]), self.u32(at + 8), self.u16(at + 12) as i16,
+            self.bytes[at + 16], self.bytes[at + 17])
+    }
+
+    fn find_symbol(&self, name: &str) -> (String, u32, i16, u8, u8) {
+        (0..self.u32(12) as usize).map(|i| self.symbol(i)).find(|s| s.0 == name)
+            .expect("Missing symbol")
+    }
+}
+
+#[test]
+fn coff_64() {
+    let mut obj = ObjectWriter::new(Mode::Long);
+    let main = obj.symbol("main", Binding::Global);
+    let puts = obj.symbol("puts", Binding::Global);
+    let helper = obj.symbol("a_long_helper_name", Binding::Weak);
+    let message = Label::new();
+    let (pushed, allocated, end) = (Label::new(), Label::new(), Label::new());
+    {
+        let text = obj.section(Section::Text);
+        text.set_label(main);
+        text.write1(Mnemonic::PUSH, Operand::Direct(Reg::RBX)).unwrap();
+        text.set_label(pushed);
+        text.write2(Mnemonic::SUB, Operand::Direct(Reg::RSP), Operand::Literal8(0x28)).unwrap();
+        text.set_label(allocated);
+        text.write2(Mnemonic::MOV, Operand::LabelMemory(message, Some(OperandSize::Dword), None), Operand::Literal32(1)).unwrap();
+        text.write1(Mnemonic::CALL, Operand::Label(puts)).unwrap();
+        text.write1(Mnemonic::CALL, Operand::Label(helper)).unwrap();
+        text.write2(Mnemonic::ADD, Operand::Direct(Reg::RSP), Operand::Literal8(0x28)).unwrap();
+        text.write1(Mnemonic::POP, Operand::Direct(Reg::RBX)).unwrap();
+        text.write0(Mnemonic::RET).unwrap();
+        text.set_label(end);
+        text.set_label(helper);
+        text.write0(Mnemonic::RET).unwrap();
+    }
+    obj.section(Section::Rodata).write_bytes(b"-").unwrap();
+    obj.section(Section::Rodata).set_label(message);
+    obj.section(Section::Data).write_address(main, OperandSize::Qword).unwrap();
+    obj.section(Section::Data).write_address(message, OperandSize::Qword).unwrap();
+    obj.section(Section::Bss).write_bytes(&[0; 8]).unwrap();
+    obj.add_unwind_info(UnwindInfo { start: main, end, prolog: vec![
+        (pushed, UnwindOp::PushNonVolatile(Reg::RBX)), (allocated, UnwindOp::Alloc(0x28))] });
+
+    let mut bytes = Vec::new();
+    obj.write_coff(&mut bytes).unwrap();
+    let coff = Coff { bytes: &bytes };
+    assert_eq!((coff.u16(0), coff.u16(2)), (0x8664, 6));
+
+    assert_eq!(coff.section(".text").3, 0x6050_0020);
+    assert_eq!(coff.section(".data").3, 0xC040_0040);
+    assert_eq!((coff.section(".bss").1, coff.section(".bss").2, coff.section(".bss").3), (8, 0, 0xC040_0080));
+    assert_eq!(coff.section(".rdata").3, 0x4040_0040);
+
+    // REL32 is relative to the end of the field, so the distance to the end of the instruction
+    // (the immediate after the displacement) is left in the field, plus the offset in .rdata.
+    assert_eq!(&coff.contents(".text")[5..15], &[0xC7, 0x05, 0xFD, 0xFF, 0xFF, 0xFF, 1, 0, 0, 0]);
+    assert_eq!(&coff.contents(".text")[16..20], &[0, 0, 0, 0]);
+    assert_eq!(coff.relocations(".text"), vec![
+        (7, ".rdata".to_string(), 4),
+        (16, "puts".to_string(), 4),
+        (21, "a_long_helper_name".to_string(), 4),
+    ]);
+    assert_eq!(coff.relocations(".data"), vec![(0, "main".to_string(), 1), (8, ".rdata".to_string(), 1)]);
+    assert_eq!(&coff.contents(".data")[8..], &[1, 0, 0, 0, 0, 0, 0, 0]);
+
+    assert_eq!(coff.find_symbol("main"), ("main".to_string(), 0, 1, 2, 0));
+    assert_eq!(coff.find_symbol("puts"), ("puts".to_string(), 0, 0, 2, 0));
+    assert_eq!(coff.find_symbol("a_long_helper_name"), ("a_long_helper_name".to_string(), 0, 0, 105, 1));
+    assert_eq!(coff.find_symbol(".weak.a_long_helper_name.default").1, 0x1F);
+
+    // UNWIND_INFO: version 1, 5 byte prolog, SUB RSP, 0x28 then PUSH RBX, and the
+    // RUNTIME_FUNCTION with image relative addresses.
+    assert_eq!(coff.contents(".xdata"), &[1, 5, 2, 0, 5, 0x42, 1, 0x30]);
+    assert_eq!(coff.contents(".pdata"), &[0, 0, 0, 0, 0x1F, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(coff.relocations(".pdata"), vec![
+        (0, ".text".to_string(), 3), (4, ".text".to_string(), 3), (8, ".xdata".to_string(), 3)]);
+}
+
+#[test]
+fn coff_32() {
+    let mut obj = ObjectWriter::new(Mode::Protected);
+    let start = obj.symbol("_start", Binding::Global);
+    let exit = obj.symbol("_exit", Binding::Global);
+    let value = Label::new();
+    {
+        let text = obj.section(Section::Text);
+        text.set_label(start);
+        text.write2(Mnemonic::MOV, Operand::Direct(Reg::EAX), Operand::LabelMemory(value, Some(OperandSize::Dword), None)).unwrap();
+        text.write1(Mnemonic::CALL, Operand::Label(exit)).unwrap();
+    }
+    obj.section(Section::Data).write_bytes(&[0; 4]).unwrap();
+    obj.section(Section::Data).set_label(value);
+
+    let mut bytes = Vec::new();
+    obj.write_coff(&mut bytes).unwrap();
+    let coff = Coff { bytes: &bytes };
+    assert_eq!(coff.u16(0), 0x14C);
+    assert_eq!(coff.contents(".text"), &[0x8B, 0x05, 4, 0, 0, 0, 0xE8, 0, 0, 0, 0]);
+    assert_eq!(coff.relocations(".text"), vec![(2, ".data".to_string(), 6), (7, "_exit".to_string(), 0x14)]);
+}
+
+#[test]
+fn coff_errors() {
+    let mut obj = ObjectWriter::new(Mode::Long);
+    let puts = obj.symbol("puts", Binding::Global);
+    let got = obj.got_entry(puts);
+    obj.section(Section::Text).write2(Mnemonic::MOV, Operand::Direct(Reg::RAX),
+        Operand::LabelMemory(got, Some(OperandSize::Qword), None)).unwrap();
+    assert_eq!(obj.write_coff(&mut Vec::new()),
+        Err(ObjectError::UnsupportedRelocation(FixupKind::Relative, OperandSize::Dword)));
+
+    let mut obj = ObjectWriter::new(Mode::Long);
+    let (start, end) = (Label::new(), Label::new());
+    obj.section(Section::Text).set_label(start);
+    obj.section(Section::Text).write2(Mnemonic::SUB, Operand::Direct(Reg::RSP), Operand::Literal8(0x0C)).unwrap();
+    obj.section(Section::Text).set_label(end);
+    obj.add_unwind_info(UnwindInfo { start, end, prolog: vec![(end, UnwindOp::Alloc(0x0C))] });
+    assert_eq!(obj.write_coff(&mut Vec::new()), Err(ObjectError::InvalidUnwindInfo(start)));
+
+    for &op in &[UnwindOp::PushNonVolatile(Reg::EBX), UnwindOp::PushNonVolatile(Reg::RIP),
+        UnwindOp::SetFramePointer(Reg::XMM0, 0)] {
+        let mut obj = ObjectWriter::new(Mode::Long);
+        obj.section(Section::Text).set_label(start);
+        obj.section(Section::Text).write0(Mnemonic::NOP).unwrap();
+        obj.section(Section::Text).set_label(end);
+        obj.add_unwind_info(UnwindInfo { start, end, prolog: vec![(end, op)] });
+        assert_eq!(obj.write_coff(&mut Vec::new()), Err(ObjectError::InvalidUnwindInfo(start)));
+    }
+
+    let mut obj = ObjectWriter::new(Mode::Protected);
+    obj.add_unwind_info(UnwindInfo { start, end, prolog: Vec::new() });
+    assert_eq!(obj.write_coff(&mut Vec::new()), Err(ObjectError::UnsupportedMode(Mode::Protected)));
+}
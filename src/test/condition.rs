@@ -0,0 +1,83 @@
+use std::io::Cursor;
+use ::*;
+use ::test::*;
+
+const ALL: [Condition; 16] = [
+    Condition::O, Condition::NO, Condition::B, Condition::AE,
+    Condition::E, Condition::NE, Condition::BE, Condition::A,
+    Condition::S, Condition::NS, Condition::P, Condition::NP,
+    Condition::L, Condition::GE, Condition::LE, Condition::G,
+];
+
+#[test]
+fn codes() {
+    for (code, cond) in ALL.iter().enumerate() {
+        assert_eq!(cond.get_code(), code as u8);
+        assert_eq!(Condition::from_code(code as u8), Some(*cond));
+    }
+    assert_eq!(Condition::from_code(0x10), None);
+    assert_eq!(Condition::from_opcode(0x74), Condition::E);
+    assert_eq!(Condition::from_opcode(0x8F), Condition::G);
+    assert_eq!(Condition::from_opcode(0x4C), Condition::L);
+}
+
+#[test]
+fn invert() {
+    assert_eq!(Condition::E.invert(), Condition::NE);
+    assert_eq!(Condition::A.invert(), Condition::BE);
+    assert_eq!(Condition::L.invert(), Condition::GE);
+    for cond in ALL.iter() {
+        assert!(cond.invert() != *cond);
+        assert_eq!(cond.invert().invert(), *cond);
+    }
+}
+
+#[test]
+fn mnemonic_round_trip() {
+    for cond in ALL.iter() {
+        assert_eq!(Mnemonic::jcc(*cond).condition(), Some(*cond));
+        assert_eq!(Mnemonic::setcc(*cond).condition(), Some(*cond));
+        assert_eq!(Mnemonic::cmovcc(*cond).condition(), Some(*cond));
+    }
+    assert_eq!(Mnemonic::JZ.condition(), Some(Condition::E));
+    assert_eq!(Mnemonic::SETNAE.condition(), Some(Condition::B));
+    assert_eq!(Mnemonic::CMOVPO.condition(), Some(Condition::NP));
+    assert_eq!(Mnemonic::MOV.condition(), None);
+    assert_eq!(Mnemonic::LOOPE.condition(), None);
+}
+
+#[test]
+fn encodings_follow_code() {
+    for cond in ALL.iter() {
+        let code = cond.get_code();
+        encode32_helper1(Mnemonic::setcc(*cond), Operand::Direct(Reg::AL), &vec![0x0F, 0x90 | code, 0xC0]);
+        encode32_helper2(Mnemonic::cmovcc(*cond), Operand::Direct(Reg::EAX), Operand::Direct(Reg::ECX),
+            &vec![0x0F, 0x40 | code, 0xC1]);
+        encode32_helper1(Mnemonic::jcc(*cond), Operand::Literal8(0x10), &vec![0x70 | code, 0x10]);
+    }
+}
+
+#[test]
+fn decoded_condition() {
+    for cond in ALL.iter() {
+        let bytes = [0x0F, 0x90 | cond.get_code(), 0xC0];
+        let mut reader = InstructionReader::new(Cursor::new(&bytes[..]), Mode::Long);
+        let instr = reader.read().expect("Decoding failed").0;
+        assert_eq!(instr.mnemonic.condition(), Some(*cond));
+    }
+}
+
+#[test]
+fn jcc_to_label() {
+    // Labels take the near form, rel16 in real mode and rel32 otherwise.
+    for &mode in &[Mode::Real, Mode::Protected, Mode::Long] {
+        for cond in ALL.iter() {
+            let mut bytes = Vec::new();
+            Instruction::new1(Mnemonic::jcc(*cond), Operand::Label(Label::new()))
+                .encode(&mut bytes, mode).expect("Encoding failed");
+            let len = if mode == Mode::Real { 4 } else { 6 };
+            assert_eq!(bytes.len(), len, "{:?} in {:?} mode", cond, mode);
+            assert_eq!(bytes[..2], [0x0F, 0x80 | cond.get_code()]);
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use std::io::Cursor;
 use ::*;
 use ::test::*;
 
@@ -10,6 +11,26 @@ fn extended_regs() {
     decode_helper(&vec![0x40, 0x00, 0xF8], Mode::Long, &Instruction::new2(Mnemonic::ADD, Operand::Direct(Reg::AL), Operand::Direct(Reg::DIL)));
 }
 
+#[test]
+fn rex_b_extends_rm() {
+    decode_helper(&vec![0x49, 0x01, 0xC0], Mode::Long, &Instruction::new2(Mnemonic::ADD, Operand::Direct(Reg::R8), Operand::Direct(Reg::RAX)));
+    decode_helper(&vec![0x4D, 0x01, 0xC8], Mode::Long, &Instruction::new2(Mnemonic::ADD, Operand::Direct(Reg::R8), Operand::Direct(Reg::R9)));
+}
+
+#[test]
+fn invalid_encodings_return_errors() {
+    // A memory operand where only a register is allowed, and a truncated SIB byte (01 04 is
+    // ADD [SI], AX with 16-bit addressing).
+    let cases = [(vec![0x0F, 0x20, 0x00], [Mode::Real, Mode::Protected, Mode::Long].iter()),
+        (vec![0x01, 0x04], [Mode::Protected, Mode::Long].iter())];
+    for (bytes, modes) in cases.iter().cloned() {
+        for mode in modes {
+            let mut reader = InstructionReader::new(Cursor::new(&bytes), *mode);
+            assert!(reader.read().is_err(), "{:?} in {:?}", bytes, mode);
+        }
+    }
+}
+
 // * * * * * * * * * * * * * * * * * * * LEGACY TESTS * * * * * * * * * * * * * * * * * * * *
 // The tests below correspond to the legacy instruction encoding format but have been left here
 // for completeness.
@@ -242,7 +263,7 @@ fn mod_rm_long_mod_0() {
     decode_helper(&vec![0x48, 0x03, 0x04, 0x24], Mode::Long, &Instruction::new2(Mnemonic::ADD, Operand::Direct(Reg::RAX), // ADD RAX, [RSP]
         Operand::Indirect(Reg::RSP, Some(OperandSize::Qword), None)));
     decode_helper(&vec![0x48, 0x03, 0x05, 0x78, 0x56, 0x34, 0x12], Mode::Long, &Instruction::new2(Mnemonic::ADD, Operand::Direct(Reg::RAX), // ADD RAX, [RIP+0x12345678]
-        Operand::Offset(0x12345678, Some(OperandSize::Qword), None)));
+        Operand::IndirectDisplaced(Reg::RIP, 0x12345678, Some(OperandSize::Qword), None)));
     decode_helper(&vec![0x48, 0x03, 0x06], Mode::Long, &Instruction::new2(Mnemonic::ADD, Operand::Direct(Reg::RAX), // ADD RAX, [RSI]
         Operand::Indirect(Reg::RSI, Some(OperandSize::Qword), None)));
     decode_helper(&vec![0x48, 0x03, 0x07], Mode::Long, &Instruction::new2(Mnemonic::ADD, Operand::Direct(Reg::RAX), // ADD RAX, [RDI]
@@ -990,3 +1011,95 @@ fn operand_type_unsized_memory() {
 fn operand_type_fpu_register() {
     decode_helper(&vec![0xD8, 0xC2], Mode::Protected, &Instruction::new2(Mnemonic::FADD, Operand::Direct(Reg::ST), Operand::Direct(Reg::ST2))); // FADD ST(2)
 }
+
+#[test]
+fn length_includes_displacement() {
+    // [ebp+disp8], [ebp+disp32], [disp32], [rip+disp32] and [bx+si+disp16].
+    let cases: [(&[u8], Mode); 5] = [
+        (&[0x8B, 0x45, 0x0C], Mode::Protected),
+        (&[0x8B, 0x85, 0x00, 0x01, 0x00, 0x00], Mode::Protected),
+        (&[0x8B, 0x05, 0x78, 0x56, 0x34, 0x12], Mode::Protected),
+        (&[0x48, 0x8B, 0x05, 0x10, 0x00, 0x00, 0x00], Mode::Long),
+        (&[0x8B, 0x80, 0x00, 0x10], Mode::Real),
+    ];
+    for &(bytes, mode) in cases.iter() {
+        let mut reader = InstructionReader::new(bytes, mode);
+        assert_eq!(reader.read().unwrap().1, bytes.len());
+        assert_eq!(reader.read().err(), Some(InstructionDecodingError::EndOfStream));
+    }
+}
+
+#[test]
+fn opcode_addend_extended_regs() {
+    // REX.B extends the register added to the opcode, as it does the r/m field.
+    decode_helper(&vec![0x41, 0x51], Mode::Long, &Instruction::new1(Mnemonic::PUSH, Operand::Direct(Reg::R9))); // PUSH R9
+    decode_helper(&vec![0x41, 0x5F], Mode::Long, &Instruction::new1(Mnemonic::POP, Operand::Direct(Reg::R15))); // POP R15
+    decode_helper(&vec![0x41, 0xB0, 0x01], Mode::Long, &Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::R8B), Operand::Literal8(1))); // MOV R8B, 1
+    decode_helper(&vec![0x41, 0x0F, 0xCA], Mode::Long, &Instruction::new1(Mnemonic::BSWAP, Operand::Direct(Reg::R10D))); // BSWAP R10D
+}
+
+#[test]
+fn qword_immediate() {
+    // MOV r64, imm64 is the only instruction with an 8 byte immediate.
+    let bytes = vec![0x48, 0xB8, 0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11];
+    decode_helper(&bytes, Mode::Long, &Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::RAX), Operand::Literal64(0x1122334455667788))); // MOV RAX, imm64
+    assert_eq!(InstructionReader::new(&bytes[..], Mode::Long).read().unwrap().1, 10);
+    decode_helper(&vec![0x49, 0xB8, 0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11], Mode::Long,
+        &Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::R8), Operand::Literal64(0x1122334455667788))); // MOV R8, imm64
+    assert_eq!(InstructionReader::new(&bytes[..9], Mode::Long).read().err(),
+        Some(InstructionDecodingError::PartialInstruction));
+}
+
+#[test]
+fn rm_extended_regs() {
+    // REX.B extends the r/m field (not the reg field), and EVEX.X gives it a fifth bit when it's
+    // a register, for registers 16-31.
+    decode_helper(&vec![0x41, 0x89, 0xC0], Mode::Long, &Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::R8D), Operand::Direct(Reg::EAX))); // MOV R8D, EAX
+    decode_helper(&vec![0x44, 0x89, 0xC0], Mode::Long, &Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::EAX), Operand::Direct(Reg::R8D))); // MOV EAX, R8D
+    decode_helper(&vec![0x41, 0x8B, 0x00], Mode::Long, &Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::EAX), // MOV EAX, [R8]
+        Operand::Indirect(Reg::R8, Some(OperandSize::Dword), None)));
+    decode_helper(&vec![0x4C, 0x8B, 0x00], Mode::Long, &Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::R8), // MOV R8, [RAX]
+        Operand::Indirect(Reg::RAX, Some(OperandSize::Qword), None)));
+    decode_helper(&vec![0x62, 0x91, 0x74, 0x48, 0x58, 0xC0], Mode::Long, &Instruction::new3(Mnemonic::VADDPS, // VADDPS ZMM0, ZMM1, ZMM24
+        Operand::Direct(Reg::ZMM0), Operand::Direct(Reg::ZMM1), Operand::Direct(Reg::ZMM24)));
+    decode_helper(&vec![0x62, 0xC1, 0x74, 0x48, 0x58, 0xC0], Mode::Long, &Instruction::new3(Mnemonic::VADDPS, // VADDPS ZMM16, ZMM1, ZMM8
+        Operand::Direct(Reg::ZMM16), Operand::Direct(Reg::ZMM1), Operand::Direct(Reg::ZMM8)));
+    decode_helper(&vec![0x62, 0x91, 0x7C, 0x48, 0x28, 0xC7], Mode::Long, &Instruction::new2(Mnemonic::VMOVAPS, // VMOVAPS ZMM0, ZMM31
+        Operand::Direct(Reg::ZMM0), Operand::Direct(Reg::ZMM31)));
+}
+
+#[test]
+fn rip_relative() {
+    // In long mode, mod=00 r/m=101 is a signed disp32 from the end of the instruction, where in
+    // other modes it's an absolute address.
+    let cases: [(&[u8], Mode, Operand); 4] = [
+        (&[0x8B, 0x05, 0x10, 0x00, 0x00, 0x00], Mode::Long, // MOV EAX, [RIP+0x10]
+            Operand::IndirectDisplaced(Reg::RIP, 0x10, Some(OperandSize::Dword), None)),
+        (&[0x8B, 0x05, 0xF0, 0xFF, 0xFF, 0xFF], Mode::Long, // MOV EAX, [RIP-0x10]
+            Operand::IndirectDisplaced(Reg::RIP, -0x10i64 as u64, Some(OperandSize::Dword), None)),
+        (&[0x67, 0x8B, 0x05, 0xF0, 0xFF, 0xFF, 0xFF], Mode::Long, // MOV EAX, [EIP-0x10]
+            Operand::IndirectDisplaced(Reg::EIP, -0x10i64 as u64, Some(OperandSize::Dword), None)),
+        (&[0x8B, 0x05, 0xF0, 0xFF, 0xFF, 0xFF], Mode::Protected, // MOV EAX, [0xFFFFFFF0]
+            Operand::Memory(0xFFFF_FFF0, Some(OperandSize::Dword), None)),
+    ];
+    for &(bytes, mode, operand) in cases.iter() {
+        let expected = Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::EAX), operand);
+        decode_helper(&bytes.to_vec(), mode, &expected);
+        // They're encoded the same way again.
+        let mut encoded = Vec::new();
+        expected.encode(&mut encoded, mode).unwrap();
+        assert_eq!(&encoded[..], bytes, "{}", expected);
+    }
+}
+
+#[test]
+fn signed_disp32() {
+    // disp32 is sign-extended like disp8, so [ebp-0x1000] is the same operand either way.
+    let disp = -0x1000i64 as u64;
+    decode_helper(&vec![0x8B, 0x85, 0x00, 0xF0, 0xFF, 0xFF], Mode::Protected, &Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::EAX), // MOV EAX, [EBP-0x1000]
+        Operand::IndirectDisplaced(Reg::EBP, disp, Some(OperandSize::Dword), None)));
+    decode_helper(&vec![0x8B, 0x84, 0x24, 0x00, 0xF0, 0xFF, 0xFF], Mode::Protected, &Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::EAX), // MOV EAX, [ESP-0x1000]
+        Operand::IndirectDisplaced(Reg::ESP, disp, Some(OperandSize::Dword), None)));
+    decode_helper(&vec![0x48, 0x8B, 0x84, 0x8B, 0x00, 0xF0, 0xFF, 0xFF], Mode::Long, &Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::RAX), // MOV RAX, [RBX+RCX*4-0x1000]
+        Operand::IndirectScaledIndexedDisplaced(Reg::RBX, Reg::RCX, RegScale::Four, disp, Some(OperandSize::Qword), None)));
+}
@@ -0,0 +1,87 @@
+use ::*;
+
+#[test]
+fn com_program() {
+    let message = Label::new();
+    let mut writer = InstructionWriter::new(Vec::new(), Mode::Real);
+    writer.write2(Mnemonic::MOV, Operand::Direct(Reg::DX), Operand::Label(message)).unwrap();
+    writer.write2(Mnemonic::MOV, Operand::Direct(Reg::AH), Operand::Literal8(9)).unwrap();
+    writer.write1(Mnemonic::INT, Operand::Literal8(0x21)).unwrap();
+    writer.write0(Mnemonic::RET).unwrap();
+    writer.set_label(message);
+    writer.write_bytes(b"hi$").unwrap();
+
+    let mut bytes = Vec::new();
+    assert_eq!(FlatBinary::com().write(&writer, &mut bytes), Ok(11));
+    assert_eq!(bytes, vec![0xBA, 0x08, 0x01, 0xB4, 0x09, 0xCD, 0x21, 0xC3, b'h', b'i', b'$']);
+
+    // The segment the program is loaded at isn't known.
+    writer.write_far_address(message).unwrap();
+    assert_eq!(FlatBinary::com().build(&writer),
+        Err(ObjectError::UnsupportedRelocation(FixupKind::Segment, OperandSize::Word)));
+}
+
+#[test]
+fn mz_executable() {
+    let mut obj = ObjectWriter::new(Mode::Real);
+    let start = Label::new();
+    let message = Label::new();
+    {
+        let text = obj.section(Section::Text);
+        text.set_label(start);
+        text.write2(Mnemonic::MOV, Operand::Direct(Reg::DX), Operand::Label(message)).unwrap();
+        text.write2(Mnemonic::MOV, Operand::Direct(Reg::AH), Operand::Literal8(9)).unwrap();
+        text.write1(Mnemonic::INT, Operand::Literal8(0x21)).unwrap();
+        text.write2(Mnemonic::MOV, Operand::Direct(Reg::AX), Operand::Literal16(0x4C00)).unwrap();
+        text.write1(Mnemonic::INT, Operand::Literal8(0x21)).unwrap();
+        text.write1(Mnemonic::JMP, Operand::MemoryAndSegment16(0, 0)).unwrap();
+        assert_eq!(text.get_segment_fields(), &[15]);
+    }
+    obj.section(Section::Rodata).write_bytes(b"--").unwrap();
+    obj.section(Section::Rodata).set_label(message);
+    obj.section(Section::Rodata).write_bytes(b"hi$").unwrap();
+    obj.section(Section::Data).write_far_address(start).unwrap();
+    obj.section(Section::Data).write_far_address(message).unwrap();
+    obj.section(Section::Bss).write_bytes(&[0; 6]).unwrap();
+
+    let mut bytes = Vec::new();
+    assert_eq!(obj.write_mz(&mut bytes, start, 0x100), Ok(96));
+    assert_eq!(&bytes[..48], &[
+        b'M', b'Z',
+        96, 0, 1, 0, // 96 bytes in the last (only) page
+        3, 0, 3, 0, // 3 relocations, 3 paragraph header
+        17, 0, 0xFF, 0xFF, // Extra paragraphs for .bss and the stack
+        2, 0, 0x16, 0x01, // SS:SP = 0002:0116
+        0, 0, 0, 0, 0, 0, // Checksum, CS:IP = 0000:0000
+        0x1C, 0, 0, 0, // Relocation table, overlay
+        15, 0, 0, 0, // JMP 0000:0000 in .text
+        10, 0, 2, 0, // Far pointer to start in .data
+        14, 0, 2, 0, // Far pointer to message in .data
+        0, 0, 0, 0, 0, 0, 0, 0,
+    ][..]);
+    assert_eq!(&bytes[48..], &[
+        0xBA, 0x02, 0x00, 0xB4, 0x09, 0xCD, 0x21, 0xB8, 0x00, 0x4C, 0xCD, 0x21,
+        0xEA, 0x00, 0x00, 0x00, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        b'-', b'-', b'h', b'i', b'$', 0, 0, 0,
+        0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x02, 0x00,
+    ][..]);
+}
+
+#[test]
+fn mz_errors() {
+    let start = Label::new();
+    let obj = ObjectWriter::new(Mode::Protected);
+    assert_eq!(obj.write_mz(&mut Vec::new(), start, 0x100), Err(ObjectError::UnsupportedMode(Mode::Protected)));
+
+    let mut obj = ObjectWriter::new(Mode::Real);
+    obj.section(Section::Text).set_label(start);
+    obj.section(Section::Bss).write_bytes(&[0; 0xFF00]).unwrap();
+    assert_eq!(obj.write_mz(&mut Vec::new(), start, 0x200), Err(ObjectError::TooLarge(0x10100)));
+
+    // Segment fixups need relocations ELF doesn't have.
+    let mut obj = ObjectWriter::new(Mode::Real);
+    obj.section(Section::Text).set_label(start);
+    obj.section(Section::Data).write_far_address(start).unwrap();
+    assert_eq!(obj.write_elf(&mut Vec::new()),
+        Err(ObjectError::UnsupportedRelocation(FixupKind::Segment, OperandSize::Word)));
+}
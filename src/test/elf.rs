@@ -0,0 +1,351 @@
+use byteorder::{ByteOrder, LittleEndian};
+use ::*;
+
+// Just enough of an ELF parser to check the structure of the objects.
+struct Elf<'a> {
+    bytes: &'a [u8],
+    is_64: bool,
+}
+
+#[derive(Debug)]
+struct SectionInfo { name: String, section_type: u32, offset: usize, size: usize, link: u32, info: u32 }
+
+impl<'a> Elf<'a> {
+    fn new(bytes: &'a [u8]) -> Elf<'a> {
+        assert_eq!(&bytes[..4], &[0x7F, b'E', b'L', b'F']);
+        Elf { bytes, is_64: bytes[4] == 2 }
+    }
+
+    fn u16(&self, at: usize) -> u16 { LittleEndian::read_u16(&self.bytes[at..]) }
+    fn u32(&self, at: usize) -> u32 { LittleEndian::read_u32(&self.bytes[at..]) }
+    fn word(&self, at: usize) -> u64 {
+        if self.is_64 { LittleEndian::read_u64(&self.bytes[at..]) } else { self.u32(at) as u64 }
+    }
+
+    fn string(&self, at: usize) -> String {
+        let end = self.bytes[at..].iter().position(|b| *b == 0).unwrap();
+        String::from_utf8(self.bytes[at..at + end].to_vec()).unwrap()
+    }
+
+    fn sections(&self) -> Vec<SectionInfo> {
+        let (shoff, shnum, shstrndx) = if self.is_64 {
+            (self.word(0x28) as usize, self.u16(0x3C) as usize, self.u16(0x3E) as usize)
+        } else {
+            (self.word(0x20) as usize, self.u16(0x30) as usize, self.u16(0x32) as usize)
+        };
+        let w = if self.is_64 { 8 } else { 4 };
+        let headers: Vec<_> = (0..shnum).map(|i| {
+            let at = shoff + i * (16 + 6 * w);
+            (self.u32(at), SectionInfo { name: String::new(), section_type: self.u32(at + 4),
+                offset: self.word(at + 8 + 2 * w) as usize, size: self.word(at + 8 + 3 * w) as usize,
+                link: self.u32(at + 8 + 4 * w), info: self.u32(at + 12 + 4 * w) })
+        }).collect();
+        let strtab = headers[shstrndx].1.offset;
+        headers.into_iter().map(|(name, s)| SectionInfo { name: self.string(strtab + name as usize), ..s })
+            .collect()
+    }
+
+    fn section(&self, name: &str) -> SectionInfo {
+        self.sections().into_iter().find(|s| s.name == name).expect("Missing section")
+    }
+
+    // (name, value, binding, section index)
+    fn symbols(&self) -> Vec<(String, u64, u8, u16)> {
+        let symtab = self.section(".symtab");
+        let strtab = self.sections().remove(symtab.link as usize);
+        let size = if self.is_64 { 24 } else { 16 };
+        (0..symtab.size / size).map(|i| {
+            let at = symtab.offset + i * size;
+            let name = self.string(strtab.offset + self.u32(at) as usize);
+            if self.is_64 {
+                (name, self.word(at + 8), self.bytes[at + 4] >> 4, self.u16(at + 6))
+            } else {
+                (name, self.word(at + 4), self.bytes[at + 12] >> 4, self.u16(at + 14))
+            }
+        }).collect()
+    }
+
+    // (offset, symbol index, type, addend)
+    fn relocations(&self, name: &str) -> Vec<(u64, u32, u32, i64)> {
+        let section = self.section(name);
+        let size = if self.is_64 { 24 } else { 8 };
+        (0..section.size / size).map(|i| {
+            let at = section.offset + i * size;
+            if self.is_64 {
+                let info = self.word(at + 8);
+                (self.word(at), (info >> 32) as u32, info as u32, self.word(at + 16) as i64)
+            } else {
+                let info = self.u32(at + 4);
+                (self.word(at), info >> 8, info & 0xFF, 0)
+            }
+        }).collect()
+    }
+
+    fn contents(&self, name: &str) -> &[u8] {
+        let section = self.section(name);
+        &self.bytes[section.offset..section.offset + section.size]
+    }
+}
+
+fn symbol_index(elf: &Elf, name: &str) -> u32 {
+    elf.symbols().iter().position(|s| s.0 == name).expect("Missing symbol") as u32
+}
+
+#[test]
+fn object_64() {
+    let mut obj = ObjectWriter::new(Mode::Long);
+    let main = obj.symbol("main", Binding::Global);
+    let puts = obj.symbol("puts", Binding::Global);
+    let helper = obj.symbol("helper", Binding::Weak);
+    let counter = obj.symbol("counter", Binding::Local);
+    let got = obj.got_entry(puts);
+    let message = Label::new();
+    let done = Label::new();
+    {
+        let text = obj.section(Section::Text);
+        text.set_label(main);
+        text.write2(Mnemonic::LEA, Operand::Direct(Reg::RDI), Operand::LabelMemory(message, None, None)).unwrap();
+        text.write1(Mnemonic::CALL, Operand::Label(puts)).unwrap();
+        text.write2(Mnemonic::MOV, Operand::Direct(Reg::RAX), Operand::LabelMemory(got, Some(OperandSize::Qword), None)).unwrap();
+        text.write1(Mnemonic::JMP, Operand::Label(done)).unwrap();
+        text.write0(Mnemonic::NOP).unwrap();
+        text.set_label(done);
+        text.set_label(helper);
+        text.write0(Mnemonic::RET).unwrap();
+    }
+    obj.section(Section::Rodata).set_label(message);
+    obj.section(Section::Rodata).write_bytes(b"hello\0").unwrap();
+    obj.section(Section::Data).set_label(counter);
+    obj.section(Section::Data).write_address(main, OperandSize::Qword).unwrap();
+    obj.section(Section::Bss).write_bytes(&[0; 16]).unwrap();
+
+    let mut bytes = Vec::new();
+    obj.write_elf(&mut bytes).unwrap();
+    let elf = Elf::new(&bytes);
+    assert!(elf.is_64);
+    assert_eq!((elf.u16(0x10), elf.u16(0x12)), (1, 62)); // ET_REL, EM_X86_64
+
+    let bss = elf.section(".bss");
+    assert_eq!((bss.section_type, bss.size), (8, 16));
+    assert_eq!(elf.contents(".rodata"), b"hello\0");
+    // The local jump is resolved in place: JMP +1 over the NOP.
+    assert_eq!(&elf.contents(".text")[19..24], &[0xE9, 0x01, 0x00, 0x00, 0x00]);
+
+    let symbols = elf.symbols();
+    let symtab = elf.section(".symtab");
+    assert!(symbols[..symtab.info as usize].iter().all(|s| s.2 == 0));
+    let main_symbol = &symbols[symbol_index(&elf, "main") as usize];
+    assert_eq!((main_symbol.1, main_symbol.2, main_symbol.3), (0, 1, 1));
+    assert_eq!(symbols[symbol_index(&elf, "puts") as usize].3, 0);
+    assert_eq!(symbols[symbol_index(&elf, "helper") as usize].2, 2);
+    assert_eq!(symbols[symbol_index(&elf, "counter") as usize].3, 2);
+
+    let rodata_symbol = 4;
+    assert_eq!(elf.relocations(".rela.text"), vec![
+        (3, rodata_symbol, 2, -4), // R_X86_64_PC32 .rodata - 4
+        (8, symbol_index(&elf, "puts"), 4, -4), // R_X86_64_PLT32 puts - 4
+        (15, symbol_index(&elf, "puts"), 9, -4), // R_X86_64_GOTPCREL puts - 4
+    ]);
+    assert_eq!(elf.relocations(".rela.data"), vec![(0, symbol_index(&elf, "main"), 1, 0)]);
+}
+
+#[test]
+fn object_32() {
+    let mut obj = ObjectWriter::new(Mode::Protected);
+    let start = obj.symbol("start", Binding::Global);
+    let exit = obj.symbol("exit", Binding::Global);
+    let value = Label::new();
+    {
+        let text = obj.section(Section::Text);
+        text.set_label(start);
+        text.write2(Mnemonic::MOV, Operand::Direct(Reg::EAX), Operand::LabelMemory(value, Some(OperandSize::Dword), None)).unwrap();
+        text.write1(Mnemonic::PUSH, Operand::Direct(Reg::EAX)).unwrap();
+        text.write1(Mnemonic::CALL, Operand::Label(exit)).unwrap();
+    }
+    obj.section(Section::Data).write_bytes(&[0; 4]).unwrap();
+    obj.section(Section::Data).set_label(value);
+    obj.section(Section::Data).write_bytes(&[42, 0, 0, 0]).unwrap();
+
+    let mut bytes = Vec::new();
+    obj.write_elf(&mut bytes).unwrap();
+    let elf = Elf::new(&bytes);
+    assert!(!elf.is_64);
+    assert_eq!((elf.u16(0x10), elf.u16(0x12)), (1, 3)); // ET_REL, EM_386
+
+    // REL relocations keep the addend in the field.
+    assert_eq!(elf.contents(".text"), &[0x8B, 0x05, 0x04, 0x00, 0x00, 0x00, 0x50,
+        0xE8, 0xFC, 0xFF, 0xFF, 0xFF]);
+    assert_eq!(elf.relocations(".rel.text"), vec![
+        (2, 2, 1, 0), // R_386_32 .data
+        (8, symbol_index(&elf, "exit"), 2, 0), // R_386_PC32 exit
+    ]);
+}
+
+#[test]
+fn object_errors() {
+    let mut obj = ObjectWriter::new(Mode::Long);
+    let missing = Label::new();
+    obj.section(Section::Text).write1(Mnemonic::JMP, Operand::Label(missing)).unwrap();
+    assert_eq!(obj.write_elf(&mut Vec::new()), Err(ObjectError::UndefinedLabel(missing)));
+
+    let mut obj = ObjectWriter::new(Mode::Protected);
+    let puts = obj.symbol("puts", Binding::Global);
+    let got = obj.got_entry(puts);
+    obj.section(Section::Text).write2(Mnemonic::MOV, Operand::Direct(Reg::EAX),
+        Operand::LabelMemory(got, Some(OperandSize::Dword), None)).unwrap();
+    assert_eq!(obj.write_elf(&mut Vec::new()),
+        Err(ObjectError::UnsupportedRelocation(FixupKind::Absolute, OperandSize::Dword)));
+}
+
+// Writes `write(1, "hello, world\n", length)` and `exit(status)`, with the length stored in .bss
+// and the status in .data.
+fn hello_world(mode: Mode) -> (ObjectWriter, Label) {
+    let mut obj = ObjectWriter::new(mode);
+    let start = obj.symbol("_start", Binding::Global);
+    let message = obj.symbol("message", Binding::Local);
+    let status = Label::new();
+    let length = Label::new();
+    let dword = |label| Operand::LabelMemory(label, Some(OperandSize::Dword), None);
+    {
+        let text = obj.section(Section::Text);
+        text.set_label(start);
+        text.write2(Mnemonic::MOV, dword(length), Operand::Literal32(13)).unwrap();
+        text.write2(Mnemonic::MOV, Operand::Direct(Reg::EDX), dword(length)).unwrap();
+        if mode == Mode::Long {
+            text.write2(Mnemonic::MOV, Operand::Direct(Reg::EAX), Operand::Literal32(1)).unwrap();
+            text.write2(Mnemonic::MOV, Operand::Direct(Reg::EDI), Operand::Literal32(1)).unwrap();
+            text.write2(Mnemonic::LEA, Operand::Direct(Reg::RSI), Operand::LabelMemory(message, None, None)).unwrap();
+            text.write0(Mnemonic::SYSCALL).unwrap();
+            text.write2(Mnemonic::MOV, Operand::Direct(Reg::EAX), Operand::Literal32(60)).unwrap();
+            text.write2(Mnemonic::MOV, Operand::Direct(Reg::EDI), dword(status)).unwrap();
+            text.write0(Mnemonic::SYSCALL).unwrap();
+        } else {
+            text.write2(Mnemonic::MOV, Operand::Direct(Reg::EAX), Operand::Literal32(4)).unwrap();
+            text.write2(Mnemonic::MOV, Operand::Direct(Reg::EBX), Operand::Literal32(1)).unwrap();
+            text.write2(Mnemonic::MOV, Operand::Direct(Reg::ECX), Operand::Label(message)).unwrap();
+            text.write1(Mnemonic::INT, Operand::Literal8(0x80)).unwrap();
+            text.write2(Mnemonic::MOV, Operand::Direct(Reg::EAX), Operand::Literal32(1)).unwrap();
+            text.write2(Mnemonic::MOV, Operand::Direct(Reg::EBX), dword(status)).unwrap();
+            text.write1(Mnemonic::INT, Operand::Literal8(0x80)).unwrap();
+        }
+    }
+    obj.section(Section::Rodata).set_label(message);
+    obj.section(Section::Rodata).write_bytes(b"hello, world\n").unwrap();
+    obj.section(Section::Data).set_label(status);
+    obj.section(Section::Data).write_bytes(&[7, 0, 0, 0]).unwrap();
+    obj.section(Section::Bss).set_label(length);
+    obj.section(Section::Bss).write_bytes(&[0; 4]).unwrap();
+    (obj, start)
+}
+
+#[test]
+fn executable_64() {
+    let (obj, start) = hello_world(Mode::Long);
+    let mut bytes = Vec::new();
+    obj.write_executable(&mut bytes, start).unwrap();
+    let elf = Elf::new(&bytes);
+    assert!(elf.is_64);
+    assert_eq!((elf.u16(0x10), elf.u16(0x12)), (2, 62)); // ET_EXEC, EM_X86_64
+
+    // Entry point, and the program headers: code, data and GNU_STACK.
+    let text = elf.section(".text");
+    assert_eq!(elf.word(0x18), 0x40_0000 + text.offset as u64);
+    assert_eq!((elf.word(0x20), elf.u16(0x38)), (64, 3));
+    let segment = |i: usize| {
+        let at = 64 + i * 56;
+        (elf.u32(at), elf.u32(at + 4), elf.word(at + 8), elf.word(at + 16), elf.word(at + 32), elf.word(at + 40))
+    };
+    let (data, rodata) = (elf.section(".data"), elf.section(".rodata"));
+    let code_end = (rodata.offset + rodata.size) as u64;
+    assert_eq!(segment(0), (1, 5, 0, 0x40_0000, code_end, code_end));
+    assert_eq!(segment(1), (1, 6, 0x1000, 0x40_1000, 4, 12));
+    assert_eq!(segment(2).0, 0x6474_E551);
+    assert_eq!(data.offset, 0x1000);
+
+    let symbols = elf.symbols();
+    assert_eq!(symbols[symbol_index(&elf, "_start") as usize].1, 0x40_0000 + text.offset as u64);
+    assert_eq!(symbols[symbol_index(&elf, "message") as usize].1, 0x40_0000 + rodata.offset as u64);
+    assert_eq!(elf.contents(".data"), &[7, 0, 0, 0]);
+}
+
+#[test]
+fn executable_32() {
+    let (obj, start) = hello_world(Mode::Protected);
+    let mut bytes = Vec::new();
+    obj.write_executable(&mut bytes, start).unwrap();
+    let elf = Elf::new(&bytes);
+    assert!(!elf.is_64);
+    assert_eq!((elf.u16(0x10), elf.u16(0x12)), (2, 3)); // ET_EXEC, EM_386
+    assert_eq!(elf.word(0x18), 0x0804_8000 + elf.section(".text").offset as u64);
+
+    // The address of the message is an absolute immediate.
+    let rodata = 0x0804_8000 + elf.section(".rodata").offset as u32;
+    let text = elf.contents(".text");
+    let mov = text.windows(5).position(|w| w[0] == 0xB9).unwrap();
+    assert_eq!(LittleEndian::read_u32(&text[mov + 1..]), rodata);
+}
+
+#[test]
+fn executable_errors() {
+    let mut obj = ObjectWriter::new(Mode::Long);
+    let start = obj.symbol("_start", Binding::Global);
+    let puts = obj.symbol("puts", Binding::Global);
+    obj.section(Section::Text).set_label(start);
+    obj.section(Section::Text).write1(Mnemonic::CALL, Operand::Label(puts)).unwrap();
+    assert_eq!(obj.write_executable(&mut Vec::new(), start), Err(ObjectError::UndefinedLabel(puts)));
+
+    // Undefined weak symbols are null.
+    obj.symbol("puts", Binding::Weak);
+    assert!(obj.write_executable(&mut Vec::new(), start).is_ok());
+
+    let obj = ObjectWriter::new(Mode::Real);
+    assert_eq!(obj.write_executable(&mut Vec::new(), start), Err(ObjectError::UnsupportedMode(Mode::Real)));
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+fn run_executable(obj: &ObjectWriter, entry: Label, name: &str)
+    -> ::std::io::Result<::std::process::Output> {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::process::Command;
+    use std::sync::Mutex;
+
+    // Exec fails with ETXTBSY while a child forked by another test still holds the file open
+    // for writing, so the tests that run executables take turns.
+    static RUNNING: Mutex<()> = Mutex::new(());
+    let _running = RUNNING.lock().unwrap_or_else(|e| e.into_inner());
+
+    let path = ::std::env::temp_dir().join(format!("x86asm-{}-{}", name, ::std::process::id()));
+    let mut bytes = Vec::new();
+    obj.write_executable(&mut bytes, entry).unwrap();
+    fs::write(&path, &bytes).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    let output = Command::new(&path).output();
+    fs::remove_file(&path).unwrap();
+    output
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[test]
+fn run_executable_64() {
+    let (obj, start) = hello_world(Mode::Long);
+    let output = run_executable(&obj, start, "hello64").expect("Failed to run the executable");
+    assert_eq!(output.stdout, b"hello, world\n");
+    assert_eq!(output.status.code(), Some(7));
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[test]
+fn run_executable_32() {
+    let (obj, start) = hello_world(Mode::Protected);
+    let output = match run_executable(&obj, start, "hello32") {
+        // The kernel was built or booted without 32-bit emulation.
+        Err(ref e) if e.raw_os_error() == Some(::libc::ENOEXEC) => {
+            println!("Skipping, 32-bit executables aren't supported here");
+            return;
+        },
+        output => output.expect("Failed to run the executable")
+    };
+    assert_eq!(output.stdout, b"hello, world\n");
+    assert_eq!(output.status.code(), Some(7));
+}
@@ -0,0 +1,155 @@
+use byteorder::{ByteOrder, LittleEndian};
+use ::*;
+
+// Two functions in .text, `main` calling `helper`.
+fn functions(mode: Mode) -> ObjectWriter {
+    let mut obj = ObjectWriter::new(mode);
+    let main = obj.symbol("main", Binding::Global);
+    let helper = obj.symbol("helper", Binding::Local);
+    let (ax, cx) = if mode == Mode::Long { (Reg::RAX, Reg::RCX) } else { (Reg::EAX, Reg::ECX) };
+    {
+        let text = obj.section(Section::Text);
+        text.set_label(main);
+        text.write1(Mnemonic::CALL, Operand::Label(helper)).unwrap();
+        text.write0(Mnemonic::RET).unwrap();
+        text.set_label(helper);
+        text.write2(Mnemonic::MOV, Operand::Direct(ax), Operand::Direct(cx)).unwrap();
+        text.write0(Mnemonic::RET).unwrap();
+    }
+    obj
+}
+
+fn summary(file: &ElfFile) -> Vec<(Option<String>, u64, usize, Option<Mnemonic>)> {
+    file.disassemble()
+        .map(|i| (i.symbol.map(|s| s.name.clone()), i.address, i.bytes.len(),
+            i.instruction.ok().map(|i| i.mnemonic)))
+        .collect()
+}
+
+#[test]
+fn disassemble_object() {
+    let mut bytes = Vec::new();
+    functions(Mode::Long).write_elf(&mut bytes).unwrap();
+    let file = ElfFile::parse(&bytes).unwrap();
+    assert!(file.is_64());
+    assert_eq!((file.get_mode(), file.get_file_type()), (Mode::Long, 1));
+    assert!(file.get_section(".text").unwrap().is_executable());
+    assert!(!file.get_section(".data").unwrap().is_executable());
+    assert_eq!(file.get_symbol("helper").map(|s| (s.value, s.get_binding(), s.get_type())),
+        Some((6, 0, 2)));
+
+    let main = Some("main".to_owned());
+    let helper = Some("helper".to_owned());
+    assert_eq!(summary(&file), vec![
+        (main.clone(), 0, 5, Some(Mnemonic::CALL)),
+        (main, 5, 1, Some(Mnemonic::RET)),
+        (helper.clone(), 6, 3, Some(Mnemonic::MOV)),
+        (helper, 9, 1, Some(Mnemonic::RET)),
+    ]);
+
+    let call = file.disassemble().next().unwrap();
+    assert_eq!(call.bytes, &[0xE8, 0x01, 0x00, 0x00, 0x00]);
+    assert_eq!(call.instruction.unwrap(), Instruction::new1(Mnemonic::CALL, Operand::Offset(1, None, None)));
+    assert_eq!(call.section.name, ".text");
+
+    let helper = file.disassemble_symbol("helper").unwrap();
+    assert_eq!(helper.map(|i| i.address).collect::<Vec<_>>(), vec![6, 9]);
+    assert!(file.disassemble_symbol("missing").is_none());
+}
+
+#[test]
+fn disassemble_executable() {
+    let mut obj = functions(Mode::Protected);
+    let start = obj.symbol("_start", Binding::Global);
+    {
+        let text = obj.section(Section::Text);
+        text.write_bytes(&[0xFF, 0xFF]).unwrap(); // Not an instruction
+        text.set_label(start);
+        text.write0(Mnemonic::HLT).unwrap();
+    }
+    let mut bytes = Vec::new();
+    obj.write_executable(&mut bytes, start).unwrap();
+    let file = ElfFile::parse(&bytes).unwrap();
+    assert!(!file.is_64());
+    assert_eq!((file.get_mode(), file.get_file_type()), (Mode::Protected, 2));
+
+    let text = file.get_section(".text").unwrap().address;
+    assert_eq!(file.get_entry(), text + 11);
+    let records = summary(&file);
+    assert_eq!(records.len(), 7);
+    assert_eq!(records[2], (Some("helper".to_owned()), text + 6, 2, Some(Mnemonic::MOV)));
+    assert_eq!(records[4].2, 1);
+    assert_eq!(records[4].3, None);
+    assert_eq!(records[6], (Some("_start".to_owned()), text + 11, 1, Some(Mnemonic::HLT)));
+}
+
+#[test]
+fn disassemble_stripped() {
+    let mut bytes = Vec::new();
+    functions(Mode::Long).write_elf(&mut bytes).unwrap();
+    let file = ElfFile::parse(&bytes).unwrap();
+    let symtab = file.get_section(".symtab").unwrap().index;
+
+    // Turn the symbol table into an unrelated section; the code is then decoded from the start of
+    // .text without a symbol.
+    let at = LittleEndian::read_u64(&bytes[0x28..]) as usize + symtab * 64 + 4;
+    bytes[at] = 1; // SHT_PROGBITS
+    let file = ElfFile::parse(&bytes).unwrap();
+    assert!(file.get_symbols().is_empty());
+    let records = summary(&file);
+    assert_eq!(records.len(), 4);
+    assert!(records.iter().all(|r| r.0.is_none()));
+}
+
+#[test]
+fn parse_errors() {
+    let mut bytes = Vec::new();
+    functions(Mode::Long).write_elf(&mut bytes).unwrap();
+    assert_eq!(ElfFile::parse(b"MZ").err(), Some(ElfError::NotElf));
+    assert_eq!(ElfFile::parse(&bytes[..0x30]).err(), Some(ElfError::Truncated));
+    assert_eq!(ElfFile::parse(&bytes[..bytes.len() - 1]).err(), Some(ElfError::Truncated));
+
+    let mut other = bytes.clone();
+    other[4] = 3;
+    assert_eq!(ElfFile::parse(&other).err(), Some(ElfError::UnsupportedClass(3)));
+    let mut other = bytes.clone();
+    other[5] = 2;
+    assert_eq!(ElfFile::parse(&other).err(), Some(ElfError::UnsupportedEncoding(2)));
+    let mut other = bytes.clone();
+    other[0x12] = 40; // EM_ARM
+    assert_eq!(ElfFile::parse(&other).err(), Some(ElfError::UnsupportedMachine(40)));
+    assert_eq!(format!("{}", ElfError::UnsupportedMachine(40)), "unsupported machine 40");
+}
+
+#[test]
+fn corrupted_headers() {
+    let mut bytes = Vec::new();
+    functions(Mode::Long).write_elf(&mut bytes).unwrap();
+    let headers = LittleEndian::read_u64(&bytes[0x28..]) as usize;
+    let header = |index: usize, field: usize| headers + index * 64 + field;
+    let file = ElfFile::parse(&bytes).unwrap();
+    let (text, symtab) = (file.get_section(".text").unwrap().index,
+        file.get_section(".symtab").unwrap().index);
+    let names = LittleEndian::read_u16(&bytes[0x3E..]) as usize;
+
+    // Offsets that overflow when fields are added to them.
+    let mut other = bytes.clone();
+    LittleEndian::write_u64(&mut other[0x28..], u64::MAX);
+    assert_eq!(ElfFile::parse(&other).err(), Some(ElfError::Truncated));
+    let mut other = bytes.clone();
+    LittleEndian::write_u64(&mut other[header(names, 24)..], u64::MAX - 1);
+    assert_eq!(ElfFile::parse(&other).err(), Some(ElfError::Truncated));
+    let mut other = bytes.clone();
+    LittleEndian::write_u64(&mut other[header(symtab, 24)..], u64::MAX - 8);
+    assert_eq!(ElfFile::parse(&other).err(), Some(ElfError::Truncated));
+    let mut other = bytes.clone();
+    LittleEndian::write_u64(&mut other[header(text, 16)..], u64::MAX);
+    assert_eq!(ElfFile::parse(&other).err(), Some(ElfError::InvalidSection(text)));
+
+    // Any 8 bytes set to 0xFF either fail to parse or disassemble without panicking.
+    for at in 0..bytes.len() - 8 {
+        let mut other = bytes.clone();
+        other[at..at + 8].copy_from_slice(&[0xFF; 8]);
+        if let Ok(file) = ElfFile::parse(&other) { summary(&file); }
+    }
+}
@@ -9,3 +9,64 @@ fn extended_regs() {
     run_test(&Instruction::new2(Mnemonic::ADD, Operand::Direct(Reg::AL), Operand::Direct(Reg::SIL)), &[0x40, 0x00, 0xF0], OperandSize::Qword);
     run_test(&Instruction::new2(Mnemonic::ADD, Operand::Direct(Reg::AL), Operand::Direct(Reg::DIL)), &[0x40, 0x00, 0xF8], OperandSize::Qword);
 }
+
+#[test]
+fn opcode_addend_extended_regs() {
+    encode64_helper(&Instruction::new1(Mnemonic::PUSH, Operand::Direct(Reg::R8)), &vec![0x41, 0x50]);
+    encode64_helper(&Instruction::new1(Mnemonic::POP, Operand::Direct(Reg::R15)), &vec![0x41, 0x5F]);
+}
+
+#[test]
+fn interrupt_vector() {
+    run_test(&Instruction::new1(Mnemonic::INT, Operand::Literal8(0x80)), &[0xCD, 0x80], OperandSize::Dword);
+    run_test(&Instruction::new1(Mnemonic::INT, Operand::Literal8(0x21)), &[0xCD, 0x21], OperandSize::Word);
+}
+
+#[test]
+fn invalid_operands_return_errors() {
+    let mut buffer = Vec::new();
+    let instr = Instruction::new2(Mnemonic::ADD, Operand::Direct(Reg::EAX),
+        Operand::Indirect(Reg::CR0, Some(OperandSize::Dword), None));
+    assert_eq!(instr.encode(&mut buffer, Mode::Protected),
+        Err(InstructionEncodingError::InvalidAddressing));
+    let instr = Instruction::new2(Mnemonic::ADD, Operand::Direct(Reg::RIP), Operand::Direct(Reg::RAX));
+    assert!(instr.encode(&mut buffer, Mode::Long).is_err());
+}
+
+#[test]
+fn instruction_definitions() {
+    let instr = Instruction::new2(Mnemonic::ADD, Operand::Direct(Reg::EAX), Operand::Literal8(1));
+    let def = instr.definition(Mode::Protected).unwrap();
+    assert_eq!(def.to_string(), "ADD r/m32, imm8");
+    assert_eq!(def.describe_opcode(), "83 /0 ib");
+
+    let mnemonics = get_mnemonics();
+    assert!(mnemonics.contains(&Mnemonic::ADD));
+    assert_eq!(mnemonics.first(), Some(&Mnemonic::AAA));
+    assert!(mnemonics.iter().all(|m| !get_instruction_defs(*m).is_empty()));
+}
+
+#[test]
+fn lock_and_segment_prefixes() {
+    let lock = |instr: Instruction| Instruction { lock: true, .. instr };
+    encode64_helper(&lock(Instruction::new2(Mnemonic::ADD,
+        Operand::Indirect(Reg::RAX, Some(OperandSize::Dword), None), Operand::Direct(Reg::ECX))),
+        &vec![0xF0, 0x01, 0x08]);
+    encode32_helper(&lock(Instruction::new2(Mnemonic::XCHG,
+        Operand::Indirect(Reg::EBX, Some(OperandSize::Dword), None), Operand::Direct(Reg::EAX))),
+        &vec![0xF0, 0x87, 0x03]);
+
+    // The override comes after LOCK, from whichever operand is in memory.
+    encode64_helper(&Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::RAX),
+        Operand::IndirectDisplaced(Reg::RBX, 0x28, Some(OperandSize::Qword), Some(SegmentReg::FS))),
+        &vec![0x64, 0x48, 0x8B, 0x43, 0x28]);
+    encode32_helper(&Instruction::new2(Mnemonic::MOV,
+        Operand::Memory(0x10, Some(OperandSize::Dword), Some(SegmentReg::GS)),
+        Operand::Direct(Reg::ECX)), &vec![0x65, 0x89, 0x0D, 0x10, 0x00, 0x00, 0x00]);
+    encode16_helper(&Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::AX),
+        Operand::Indirect(Reg::BX, Some(OperandSize::Word), Some(SegmentReg::ES))),
+        &vec![0x26, 0x8B, 0x07]);
+    encode64_helper(&lock(Instruction::new1(Mnemonic::INC,
+        Operand::Indirect(Reg::RAX, Some(OperandSize::Dword), Some(SegmentReg::GS)))),
+        &vec![0xF0, 0x65, 0xFF, 0x00]);
+}
@@ -0,0 +1,97 @@
+use ::*;
+
+fn kinds(explanation: &Explanation) -> Vec<FieldKind> {
+    explanation.fields.iter().map(|f| f.kind).collect()
+}
+
+#[test]
+fn explain_rex_and_displacement() {
+    let explanation = explain(&[0x48, 0x8B, 0x05, 0x10, 0x00, 0x00, 0x00, 0xC3], Mode::Long).unwrap();
+    assert_eq!(explanation.len(), 7);
+    assert_eq!(kinds(&explanation), vec![FieldKind::Rex, FieldKind::Opcode, FieldKind::ModRm,
+        FieldKind::Displacement]);
+    assert_eq!(explanation.fields[0].notes,
+        vec!["W=1 (64-bit operand size)", "R=0", "X=0", "B=0"]);
+    assert_eq!(explanation.fields[1].notes, vec!["MOV r64, r/m64"]);
+    assert_eq!(explanation.fields[2].notes,
+        vec!["mod=00 (memory)", "reg=000 (rax)", "rm=101 ([rip+disp32])"]);
+    assert_eq!(explanation.fields[3].offset, 3);
+    assert_eq!(explanation.fields[3].notes, vec!["disp32 = 0x10"]);
+}
+
+#[test]
+fn explain_prefixes_and_sib() {
+    let instr = Instruction { lock: true, .. Instruction::new2(Mnemonic::ADD,
+        Operand::IndirectScaledIndexedDisplaced(Reg::RBX, Reg::RCX, RegScale::Four, -0x1000i64 as u64,
+            Some(OperandSize::Dword), Some(SegmentReg::FS)),
+        Operand::Literal32(0x1234)) };
+    let explanation = instr.explain_encoding(Mode::Long).unwrap();
+    assert_eq!(kinds(&explanation), vec![FieldKind::Prefix, FieldKind::Prefix, FieldKind::Opcode,
+        FieldKind::ModRm, FieldKind::Sib, FieldKind::Displacement, FieldKind::Immediate]);
+    assert_eq!(explanation.fields[0].notes, vec!["LOCK"]);
+    assert_eq!(explanation.fields[1].notes, vec!["FS segment override"]);
+    assert_eq!(explanation.fields[3].notes,
+        vec!["mod=10 (memory)", "reg=000 (opcode extension /0)", "rm=100 (SIB follows)"]);
+    assert_eq!(explanation.fields[4].notes,
+        vec!["scale=10 (*4)", "index=001 (rcx)", "base=011 (rbx)"]);
+    assert_eq!(explanation.fields[5].notes, vec!["disp32 = -0x1000"]);
+    assert_eq!(explanation.fields[6].notes, vec!["imm32 = 0x1234"]);
+
+    let explanation = explain(&[0x66, 0x0F, 0x6F, 0xC1], Mode::Long).unwrap();
+    assert_eq!(explanation.fields[0].notes, vec!["operand size (mandatory)"]);
+    assert_eq!(explanation.fields[1].bytes, vec![0x0F, 0x6F]);
+
+    let explanation = explain(&[0x8B, 0x40, 0x04], Mode::Real).unwrap();
+    assert_eq!(explanation.fields[1].notes,
+        vec!["mod=01 (memory)", "reg=000 (ax)", "rm=000 ([bx+si+disp8])"]);
+
+    // Only a 0x67 prefix changes the address size, not a VEX byte of 0x67.
+    let explanation = explain(&[0xC4, 0xE1, 0x67, 0xD0, 0x40, 0x04], Mode::Protected).unwrap();
+    assert_eq!(explanation.fields[2].notes,
+        vec!["mod=01 (memory)", "reg=000 (ymm0)", "rm=000 ([eax+disp8])"]);
+    let explanation = explain(&[0x67, 0x8B, 0x40, 0x04], Mode::Protected).unwrap();
+    assert_eq!(explanation.fields[2].notes,
+        vec!["mod=01 (memory)", "reg=000 (eax)", "rm=000 ([bx+si+disp8])"]);
+}
+
+#[test]
+fn explain_vex_and_evex() {
+    // vblendvps xmm0, xmm1, xmm2, xmm3
+    let explanation = explain(&[0xC4, 0xE3, 0x71, 0x4A, 0xC2, 0x30], Mode::Long).unwrap();
+    assert_eq!(kinds(&explanation), vec![FieldKind::Vex, FieldKind::Opcode, FieldKind::ModRm,
+        FieldKind::Immediate]);
+    assert_eq!(explanation.fields[0].notes, vec!["R\u{305}=1", "X\u{305}=1", "B\u{305}=1",
+        "mmmmm=00011 (0F 3A)", "W=0", "v\u{305}v\u{305}v\u{305}v\u{305}=1110 (xmm1)",
+        "L=0 (128-bit)", "pp=01 (66)"]);
+    assert_eq!(explanation.fields[3].notes, vec!["is4 = xmm3"]);
+
+    // vaddps zmm0{k1}{z}, zmm1, zmm2
+    let explanation = explain(&[0x62, 0xF1, 0x74, 0xC9, 0x58, 0xC2], Mode::Long).unwrap();
+    assert_eq!(explanation.fields[0].notes, vec!["R\u{305}=1", "X\u{305}=1", "B\u{305}=1",
+        "R\u{305}'=1", "mm=01 (0F)", "W=0", "v\u{305}v\u{305}v\u{305}v\u{305}=1110 (zmm1)",
+        "pp=00 (none)", "z=1 (zeroing)", "L'L=10 (512-bit)", "b=0", "V\u{305}'=1", "aaa=001 (k1)"]);
+    assert_eq!(explanation.fields[2].notes,
+        vec!["mod=11 (register)", "reg=000 (zmm0)", "rm=010 (zmm2)"]);
+}
+
+#[test]
+fn explain_opcode_addend_and_branches() {
+    let explanation = Instruction::new1(Mnemonic::PUSH, Operand::Direct(Reg::R9))
+        .explain_encoding(Mode::Long).unwrap();
+    assert_eq!(explanation.fields[1].notes, vec!["PUSH r64", "+1 (r9)"]);
+
+    let explanation = explain(&[0xEB, 0xFE], Mode::Protected).unwrap();
+    assert_eq!(explanation.fields[1].notes, vec!["rel8 = -0x2"]);
+
+    let text = explanation.to_string();
+    assert!(text.starts_with("jmp -0x2  ; JMP rel8\n"));
+    assert!(text.contains("  fe "));
+}
+
+#[test]
+fn explain_errors() {
+    assert_eq!(explain(&[0x8B], Mode::Long).err(),
+        Some(ExplainError::Decoding(InstructionDecodingError::PartialInstruction)));
+    let instr = Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::AL), Operand::Direct(Reg::EAX));
+    assert!(matches!(instr.explain_encoding(Mode::Long), Err(ExplainError::Encoding(_))));
+}
@@ -0,0 +1,94 @@
+use ::*;
+
+// A boot sector that loads a GDT, enables protected mode and jumps to 32-bit code.
+fn boot_sector() -> (InstructionWriter<Vec<u8>>, Label, Label, Label) {
+    let (gdt, gdt_descriptor, protected, message) = (Label::new(), Label::new(), Label::new(), Label::new());
+    let mut writer = InstructionWriter::new(Vec::new(), Mode::Real);
+    writer.write0(Mnemonic::CLI).unwrap();
+    writer.write1(Mnemonic::LGDT, Operand::LabelMemory(gdt_descriptor, None, None)).unwrap();
+    writer.write2(Mnemonic::MOV, Operand::Direct(Reg::EAX), Operand::Direct(Reg::CR0)).unwrap();
+    writer.write2(Mnemonic::OR, Operand::Direct(Reg::EAX), Operand::Literal32(1)).unwrap();
+    writer.write2(Mnemonic::MOV, Operand::Direct(Reg::CR0), Operand::Direct(Reg::EAX)).unwrap();
+    // JMP DWORD 0x08:protected
+    writer.write_bytes(&[0x66, 0xEA]).unwrap();
+    writer.write_address(protected, OperandSize::Dword).unwrap();
+    writer.write_bytes(&[0x08, 0x00]).unwrap();
+
+    writer.set_mode(Mode::Protected);
+    writer.set_label(protected);
+    writer.write2(Mnemonic::MOV, Operand::Direct(Reg::ESI), Operand::Label(message)).unwrap();
+    writer.write1(Mnemonic::JMP, Operand::Label(protected)).unwrap();
+
+    writer.set_label(gdt);
+    writer.write_bytes(&[0; 8]).unwrap();
+    writer.write_bytes(&[0xFF, 0xFF, 0, 0, 0, 0x9A, 0xCF, 0]).unwrap();
+    writer.set_label(gdt_descriptor);
+    writer.write_bytes(&[15, 0]).unwrap();
+    writer.write_address(gdt, OperandSize::Dword).unwrap();
+    writer.set_label(message);
+    writer.write_bytes(b"hi\0").unwrap();
+    (writer, protected, gdt, message)
+}
+
+#[test]
+fn switch_mode() {
+    let mut writer = InstructionWriter::new(Vec::new(), Mode::Real);
+    writer.write1(Mnemonic::PUSH, Operand::Direct(Reg::AX)).unwrap();
+    writer.set_mode(Mode::Long);
+    assert_eq!(writer.get_mode(), Mode::Long);
+    writer.write1(Mnemonic::PUSH, Operand::Direct(Reg::R8)).unwrap();
+    assert_eq!(writer.get_inner_writer_ref(), &vec![0x50, 0x41, 0x50]);
+}
+
+#[test]
+fn boot_sector_image() {
+    let (writer, protected, gdt, message) = boot_sector();
+    let image = FlatBinary::boot_sector();
+    let bytes = image.build(&writer).unwrap();
+    assert_eq!(bytes.len(), 512);
+    assert_eq!(&bytes[510..], &[0x55, 0xAA]);
+
+    let address = |label| image.get_label_address(&writer, label).unwrap();
+    let offset = |label| writer.get_label_offset(label).unwrap() as usize;
+    assert_eq!(address(protected), 0x7C00 + offset(protected) as u64);
+
+    // LGDT [gdt_descriptor] in real mode, with a 16-bit absolute address.
+    let descriptor = 0x7C00 + offset(gdt) as u16 + 16;
+    assert_eq!(&bytes[1..4], &[0x0F, 0x01, 0x16]);
+    assert_eq!(bytes[4] as u16 | (bytes[5] as u16) << 8, descriptor);
+
+    // The far jump, the 32-bit code and the GDT base.
+    let far_jump = offset(protected) - 8;
+    let target = address(protected) as u32;
+    assert_eq!(&bytes[far_jump..far_jump + 8], &[0x66, 0xEA, target as u8, (target >> 8) as u8,
+        0, 0, 0x08, 0x00]);
+    let message = address(message) as u32;
+    let code = offset(protected);
+    assert_eq!(&bytes[code..code + 10], &[0xBE, message as u8, (message >> 8) as u8, 0, 0,
+        0xE9, 0xF6, 0xFF, 0xFF, 0xFF]);
+    let base = descriptor as usize - 0x7C00 + 2;
+    assert_eq!(&bytes[base..base + 4], &[(address(gdt) & 0xFF) as u8, (address(gdt) >> 8) as u8, 0, 0]);
+}
+
+#[test]
+fn flat_image_size() {
+    let (writer, _, _, _) = boot_sector();
+    let image = FlatBinary { size: Some(32), .. FlatBinary::boot_sector() };
+    assert!(matches!(image.build(&writer), Err(ObjectError::TooLarge(_))));
+
+    // Without a size, the image is just the code.
+    let image = FlatBinary::new(0x100);
+    assert_eq!(image.build(&writer).unwrap().len() as u64, writer.get_offset());
+
+    let missing = Label::new();
+    let mut writer = InstructionWriter::new(Vec::new(), Mode::Real);
+    writer.write1(Mnemonic::CALL, Operand::Label(missing)).unwrap();
+    assert_eq!(image.build(&writer), Err(ObjectError::UndefinedLabel(missing)));
+
+    // A .COM program has to fit in its segment after the PSP.
+    let mut writer = InstructionWriter::new(Vec::new(), Mode::Real);
+    writer.write_bytes(&[0x90; 0xFF00]).unwrap();
+    assert_eq!(FlatBinary::com().build(&writer).unwrap().len(), 0xFF00);
+    writer.write0(Mnemonic::RET).unwrap();
+    assert_eq!(FlatBinary::com().build(&writer), Err(ObjectError::TooLarge(0xFF01)));
+}
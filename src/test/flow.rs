@@ -0,0 +1,61 @@
+use std::io::Cursor;
+use ::*;
+use ::test::*;
+
+fn decode_flow(bytes: &[u8], mode: Mode) -> FlowKind {
+    let mut reader = InstructionReader::new(Cursor::new(bytes), mode);
+    reader.read().expect("Decoding failed").0.flow_kind()
+}
+
+#[test]
+fn direct_branches() {
+    assert_eq!(decode_flow(&[0xEB, 0x10], Mode::Long), FlowKind::Jump(BranchTarget::Relative(0x10))); // JMP +0x10
+    assert_eq!(decode_flow(&[0xEB, 0xFE], Mode::Long), FlowKind::Jump(BranchTarget::Relative(-2))); // JMP -2
+    assert_eq!(decode_flow(&[0xE8, 0xFB, 0xFF, 0xFF, 0xFF], Mode::Protected), FlowKind::Call(BranchTarget::Relative(-5))); // CALL -5
+    assert_eq!(decode_flow(&[0xEA, 0x00, 0x7C, 0x00, 0x00], Mode::Real), FlowKind::Jump(BranchTarget::Far(0, 0x7C00))); // JMP 0000:7C00
+}
+
+#[test]
+fn conditional_branches() {
+    assert_eq!(decode_flow(&[0x74, 0x05], Mode::Long),
+        FlowKind::ConditionalJump(BranchCondition::Flags(Condition::E), BranchTarget::Relative(5))); // JE +5
+    assert_eq!(decode_flow(&[0x0F, 0x8F, 0x00, 0x01, 0x00, 0x00], Mode::Protected),
+        FlowKind::ConditionalJump(BranchCondition::Flags(Condition::G), BranchTarget::Relative(0x100))); // JG +0x100
+    assert_eq!(decode_flow(&[0xE3, 0xF0], Mode::Protected),
+        FlowKind::ConditionalJump(BranchCondition::CounterZero, BranchTarget::Relative(-0x10))); // JECXZ -0x10
+    assert_eq!(Instruction::new1(Mnemonic::LOOPNE, Operand::Literal8(0xFE)).flow_kind(),
+        FlowKind::ConditionalJump(BranchCondition::Loop(Some(Condition::NE)), BranchTarget::Relative(-2)));
+}
+
+#[test]
+fn indirect_branches() {
+    assert_eq!(decode_flow(&[0xFF, 0xE0], Mode::Long), FlowKind::IndirectJump(BranchTarget::Indirect(Operand::Direct(Reg::RAX)))); // JMP RAX
+    assert_eq!(decode_flow(&[0xFF, 0x13], Mode::Protected), FlowKind::IndirectCall(
+        BranchTarget::Indirect(Operand::Indirect(Reg::EBX, Some(OperandSize::Dword), None)))); // CALL [EBX]
+    assert!(decode_flow(&[0xFF, 0xE0], Mode::Long).is_indirect());
+}
+
+#[test]
+fn returns_and_interrupts() {
+    assert_eq!(decode_flow(&[0xC3], Mode::Long), FlowKind::Return); // RET
+    assert_eq!(decode_flow(&[0xCF], Mode::Protected), FlowKind::Return); // IRET
+    assert_eq!(decode_flow(&[0x0F, 0x05], Mode::Long), FlowKind::Interrupt); // SYSCALL
+    assert_eq!(decode_flow(&[0xCC], Mode::Protected), FlowKind::Interrupt); // INT 3
+    assert_eq!(decode_flow(&[0x0F, 0x0B], Mode::Long), FlowKind::Interrupt); // UD2
+    assert_eq!(FlowKind::Return.target(), None);
+}
+
+#[test]
+fn fallthrough() {
+    assert_eq!(decode_flow(&[0x90], Mode::Long), FlowKind::Fallthrough); // NOP
+    assert_eq!(Instruction::new2(Mnemonic::ADD, Operand::Direct(Reg::EAX), Operand::Direct(Reg::EBX)).flow_kind(),
+        FlowKind::Fallthrough);
+    assert!(!FlowKind::Fallthrough.is_branch());
+}
+
+#[test]
+fn relative_offsets_round_trip() {
+    // Decoded relative offsets are sign-extended, so they can be re-encoded as-is.
+    decode_helper(&vec![0xEB, 0xFE], Mode::Long, &Instruction::new1(Mnemonic::JMP, Operand::Offset(-2i64 as u64, None, None)));
+    encode64_helper(&Instruction::new1(Mnemonic::JMP, Operand::Offset(-2i64 as u64, None, None)), &vec![0xEB, 0xFE]);
+}
@@ -0,0 +1,92 @@
+use std::io::Cursor;
+use std::panic;
+use ::*;
+
+// Small xorshift generator, so the harness is deterministic and needs no extra dependencies.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn byte(&mut self) -> u8 { self.next() as u8 }
+
+    fn below(&mut self, n: usize) -> usize { (self.next() % n as u64) as usize }
+}
+
+const MODES: [Mode; 3] = [Mode::Real, Mode::Protected, Mode::Long];
+
+// Bytes that are likely to hit interesting paths when placed at the start of an instruction.
+const INTERESTING: [u8; 16] = [0x0F, 0x38, 0x3A, 0x66, 0x67, 0xF2, 0xF3, 0xF0, 0x40, 0x48, 0x4F,
+    0xC4, 0xC5, 0x62, 0x8F, 0x9B];
+
+fn decode_all(bytes: &[u8], mode: Mode) {
+    let result = panic::catch_unwind(|| {
+        let mut reader = InstructionReader::new(Cursor::new(bytes), mode);
+        while let Ok((instr, _)) = reader.read() {
+            // Decoded instructions have to be safe to re-encode and inspect as well.
+            let _ = instr.encode(&mut Vec::new(), mode);
+            let _ = instr.flow_kind();
+        }
+    });
+    if result.is_err() {
+        panic!("Panicked decoding {:02X?} in {:?} mode.", bytes, mode);
+    }
+}
+
+#[test]
+fn decode_random_bytes() {
+    let mut rng = Rng(0x2545_F491_4F6C_DD1D);
+    for _ in 0..2_000 {
+        let len = 1 + rng.below(15);
+        let mut bytes: Vec<u8> = (0..len).map(|_| rng.byte()).collect();
+        // Bias towards prefixes and escape bytes, which random bytes rarely produce in sequence.
+        for b in bytes.iter_mut().take(rng.below(4)) {
+            *b = INTERESTING[rng.below(INTERESTING.len())];
+        }
+        for mode in MODES.iter() {
+            decode_all(&bytes, *mode);
+        }
+    }
+}
+
+#[test]
+fn decode_all_opcodes() {
+    // Every opcode in the one and two byte maps, with a ModR/M byte for each mod and r/m
+    // combination (the reg field varies along with them to cover opcode extensions).
+    for opcode in 0..=255u8 {
+        for mod_rm in (0..=255u8).step_by(9) {
+            for mode in MODES.iter() {
+                decode_all(&[opcode, mod_rm, 0, 0, 0, 0, 0, 0], *mode);
+                decode_all(&[0x0F, opcode, mod_rm, 0, 0, 0, 0, 0], *mode);
+            }
+        }
+    }
+}
+
+#[test]
+fn encode_every_register() {
+    // Registers without an encoding (i.e. ST, RIP or GDTR) are rejected rather than panicking,
+    // in whichever operand they end up.
+    let mnemonics = [Mnemonic::MOV, Mnemonic::ADD, Mnemonic::PUSH, Mnemonic::FLD, Mnemonic::FADD,
+        Mnemonic::VADDPS, Mnemonic::KMOVW, Mnemonic::BNDMOV, Mnemonic::XCHG, Mnemonic::INC];
+    for &mnemonic in mnemonics.iter() {
+        for &reg in Reg::all().iter() {
+            for &other in [Reg::EAX, Reg::ST0, Reg::XMM1, Reg::K1].iter() {
+                for mode in MODES.iter() {
+                    let instrs = [Instruction::new1(mnemonic, Operand::Direct(reg)),
+                        Instruction::new2(mnemonic, Operand::Direct(reg), Operand::Direct(other)),
+                        Instruction::new2(mnemonic, Operand::Direct(other), Operand::Direct(reg))];
+                    for instr in instrs.iter() {
+                        let result = panic::catch_unwind(|| instr.encode(&mut Vec::new(), *mode));
+                        assert!(result.is_ok(), "Panicked encoding {} in {:?} mode.", instr, mode);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use ::*;
+
+fn writer() -> InstructionWriter<JitBuffer> {
+    InstructionWriter::new(JitBuffer::new().unwrap(), Mode::Long)
+}
+
+#[test]
+fn return_constant() {
+    let start = Label::new();
+    let mut writer = writer();
+    writer.set_label(start);
+    writer.write2(Mnemonic::MOV, Operand::Direct(Reg::EAX), Operand::Literal32(42)).unwrap();
+    writer.write0(Mnemonic::RET).unwrap();
+    writer.finalize().unwrap();
+
+    let f: extern "C" fn() -> u32 = unsafe { writer.get_function(start) }.unwrap();
+    assert_eq!(f(), 42);
+}
+
+#[test]
+fn sum_loop() {
+    // Adds up 1..=n with a backward branch.
+    let (start, top, done) = (Label::new(), Label::new(), Label::new());
+    let mut writer = writer();
+    writer.set_label(start);
+    writer.write2(Mnemonic::XOR, Operand::Direct(Reg::EAX), Operand::Direct(Reg::EAX)).unwrap();
+    writer.write2(Mnemonic::TEST, Operand::Direct(Reg::RDI), Operand::Direct(Reg::RDI)).unwrap();
+    writer.write1(Mnemonic::JZ, Operand::Label(done)).unwrap();
+    writer.set_label(top);
+    writer.write2(Mnemonic::ADD, Operand::Direct(Reg::RAX), Operand::Direct(Reg::RDI)).unwrap();
+    writer.write1(Mnemonic::DEC, Operand::Direct(Reg::RDI)).unwrap();
+    writer.write1(Mnemonic::JNZ, Operand::Label(top)).unwrap();
+    writer.set_label(done);
+    writer.write0(Mnemonic::RET).unwrap();
+    writer.finalize().unwrap();
+
+    let f: extern "C" fn(u64) -> u64 = unsafe { writer.get_function(start) }.unwrap();
+    assert_eq!(f(0), 0);
+    assert_eq!(f(10), 55);
+    assert_eq!(f(1000), 500500);
+}
+
+#[test]
+fn absolute_and_relative_labels() {
+    // Calls a second function through its absolute address, stored after the code, and adds a
+    // constant loaded RIP-relatively.
+    let (start, helper) = (Label::new(), Label::new());
+    let (pointer, constant) = (Label::new(), Label::new());
+    let mut writer = writer();
+    writer.set_label(start);
+    writer.write2(Mnemonic::MOV, Operand::Direct(Reg::RCX),
+        Operand::LabelMemory(pointer, Some(OperandSize::Qword), None)).unwrap();
+    writer.write1(Mnemonic::CALL, Operand::Direct(Reg::RCX)).unwrap();
+    writer.write2(Mnemonic::ADD, Operand::Direct(Reg::RAX),
+        Operand::LabelMemory(constant, Some(OperandSize::Qword), None)).unwrap();
+    writer.write0(Mnemonic::RET).unwrap();
+    writer.set_label(helper);
+    writer.write2(Mnemonic::LEA, Operand::Direct(Reg::RAX),
+        Operand::IndirectDisplaced(Reg::RDI, 1, Some(OperandSize::Qword), None)).unwrap();
+    writer.write0(Mnemonic::RET).unwrap();
+    writer.set_label(pointer);
+    writer.write_address(helper, OperandSize::Qword).unwrap();
+    writer.set_label(constant);
+    writer.write_bytes(&[0x00, 0x10, 0, 0, 0, 0, 0, 0]).unwrap();
+    writer.finalize().unwrap();
+
+    let base = writer.get_inner_writer_ref().get_address();
+    assert_eq!(writer.get_label_address(helper), Some(base + writer.get_label_offset(helper).unwrap()));
+    let f: extern "C" fn(u64) -> u64 = unsafe { writer.get_function(start) }.unwrap();
+    let g: extern "C" fn(u64) -> u64 = unsafe { writer.get_function(helper) }.unwrap();
+    assert_eq!(g(5), 6);
+    assert_eq!(f(5), 0x1006);
+}
+
+#[test]
+fn write_xor_execute() {
+    let start = Label::new();
+    let mut writer = writer();
+    writer.set_label(start);
+    writer.write2(Mnemonic::MOV, Operand::Direct(Reg::EAX), Operand::Literal32(1)).unwrap();
+    writer.write0(Mnemonic::RET).unwrap();
+    assert_eq!(unsafe { writer.get_function::<extern "C" fn() -> u32>(start) },
+        Err(JitError::NotExecutable));
+    writer.finalize().unwrap();
+    assert!(writer.get_inner_writer_ref().is_executable());
+    assert!(writer.write0(Mnemonic::RET).is_err());
+
+    // Appending after making the buffer writable again.
+    let second = Label::new();
+    writer.get_inner_writer_mut().make_writable().unwrap();
+    writer.set_label(second);
+    writer.write2(Mnemonic::MOV, Operand::Direct(Reg::EAX), Operand::Literal32(2)).unwrap();
+    writer.write0(Mnemonic::RET).unwrap();
+    writer.finalize().unwrap();
+    let f: extern "C" fn() -> u32 = unsafe { writer.get_function(start) }.unwrap();
+    let g: extern "C" fn() -> u32 = unsafe { writer.get_function(second) }.unwrap();
+    assert_eq!((f(), g()), (1, 2));
+
+    // Only function pointer sized types, and labels and offsets within the code.
+    assert_eq!(unsafe { writer.get_function::<u32>(start) }, Err(JitError::NotFunctionPointer));
+    let unplaced = Label::new();
+    assert_eq!(unsafe { writer.get_function::<extern "C" fn() -> u32>(unplaced) },
+        Err(JitError::UndefinedLabel(unplaced)));
+    let len = writer.get_offset();
+    assert_eq!(unsafe { writer.get_inner_writer_ref().get_function::<extern "C" fn()>(len) },
+        Err(JitError::InvalidOffset(len)));
+}
+
+#[test]
+fn grow() {
+    // Enough NOPs to need several pages, with the absolute address of the end fixed up after the
+    // buffer has moved.
+    let (start, end, pointer) = (Label::new(), Label::new(), Label::new());
+    let mut writer = InstructionWriter::new(JitBuffer::with_capacity(1).unwrap(), Mode::Long);
+    let capacity = writer.get_inner_writer_ref().capacity();
+    writer.set_label(start);
+    writer.write2(Mnemonic::MOV, Operand::Direct(Reg::RAX),
+        Operand::LabelMemory(pointer, Some(OperandSize::Qword), None)).unwrap();
+    for _ in 0..capacity * 3 { writer.write0(Mnemonic::NOP).unwrap(); }
+    writer.set_label(end);
+    writer.write0(Mnemonic::RET).unwrap();
+    writer.set_label(pointer);
+    writer.write_address(end, OperandSize::Qword).unwrap();
+    writer.finalize().unwrap();
+
+    let buffer = writer.get_inner_writer_ref();
+    assert!(buffer.capacity() > capacity * 3);
+    assert_eq!(buffer.len() as u64, writer.get_offset());
+    let f: extern "C" fn() -> u64 = unsafe { writer.get_function(start) }.unwrap();
+    assert_eq!(f(), writer.get_label_address(end).unwrap());
+}
+
+#[test]
+fn undefined_label() {
+    let mut writer = writer();
+    let missing = Label::new();
+    writer.write1(Mnemonic::JMP, Operand::Label(missing)).unwrap();
+    assert_eq!(writer.finalize(), Err(JitError::UndefinedLabel(missing)));
+    assert!(!writer.get_inner_writer_ref().is_executable());
+}
@@ -0,0 +1,79 @@
+use ::*;
+
+fn write_fixups(instr: Instruction, mode: Mode) -> (Vec<u8>, Vec<Fixup>) {
+    let mut writer = InstructionWriter::new(Vec::new(), mode);
+    writer.write0(Mnemonic::NOP).unwrap();
+    writer.write(&instr).expect("Encoding failed");
+    (writer.get_inner_writer_ref().clone(), writer.get_fixups().unwrap().to_vec())
+}
+
+#[test]
+fn branch_to_label() {
+    let label = Label::new();
+    let (bytes, fixups) = write_fixups(Instruction::new1(Mnemonic::JMP, Operand::Label(label)), Mode::Long);
+    assert_eq!(bytes, vec![0x90, 0xE9, 0x00, 0x00, 0x00, 0x00]);
+    assert_eq!(fixups, vec![Fixup { offset: 2, size: OperandSize::Dword, label,
+        kind: FixupKind::Branch, addend: -4 }]);
+
+    // Labels always get the full size form, as their distance isn't known yet.
+    let (bytes, _) = write_fixups(Instruction::new1(Mnemonic::JNE, Operand::Label(label)), Mode::Protected);
+    assert_eq!(bytes, vec![0x90, 0x0F, 0x85, 0x00, 0x00, 0x00, 0x00]);
+    let (bytes, fixups) = write_fixups(Instruction::new1(Mnemonic::CALL, Operand::Label(label)), Mode::Real);
+    assert_eq!(bytes, vec![0x90, 0xE8, 0x00, 0x00]);
+    assert_eq!(fixups[0].size, OperandSize::Word);
+
+    // Literal offsets keep the size they're given, even a rel16 outside real mode.
+    let (bytes, _) = write_fixups(Instruction::new1(Mnemonic::JMP, Operand::Literal16(0x10)), Mode::Protected);
+    assert_eq!(bytes, vec![0x90, 0x66, 0xE9, 0x10, 0x00]);
+}
+
+#[test]
+fn label_address() {
+    let label = Label::new();
+    let (bytes, fixups) = write_fixups(Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::EAX),
+        Operand::Label(label)), Mode::Protected);
+    assert_eq!(bytes, vec![0x90, 0xB8, 0x00, 0x00, 0x00, 0x00]);
+    assert_eq!(fixups[0].kind, FixupKind::Absolute);
+
+    let (bytes, fixups) = write_fixups(Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::RAX),
+        Operand::Label(label)), Mode::Long);
+    assert_eq!(bytes, vec![0x90, 0x48, 0xC7, 0xC0, 0x00, 0x00, 0x00, 0x00]);
+    assert_eq!((fixups[0].offset, fixups[0].kind), (4, FixupKind::SignExtended));
+}
+
+#[test]
+fn label_memory() {
+    let label = Label::new();
+    // RIP-relative, measured from the end of the instruction (after the immediate).
+    let (bytes, fixups) = write_fixups(Instruction::new2(Mnemonic::MOV,
+        Operand::LabelMemory(label, Some(OperandSize::Dword), None), Operand::Literal32(5)), Mode::Long);
+    assert_eq!(bytes, vec![0x90, 0xC7, 0x05, 0x00, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00]);
+    assert_eq!(fixups, vec![Fixup { offset: 3, size: OperandSize::Dword, label,
+        kind: FixupKind::Relative, addend: -8 }]);
+
+    let (bytes, fixups) = write_fixups(Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::AX),
+        Operand::LabelMemory(label, Some(OperandSize::Word), None)), Mode::Real);
+    assert_eq!(bytes, vec![0x90, 0x8B, 0x06, 0x00, 0x00]);
+    assert_eq!((fixups[0].offset, fixups[0].kind), (3, FixupKind::Absolute));
+}
+
+#[test]
+fn apply_fixups() {
+    let label = Label::new();
+    let mut writer = InstructionWriter::new(Vec::new(), Mode::Long);
+    writer.write1(Mnemonic::JMP, Operand::Label(label)).unwrap();
+    writer.write0(Mnemonic::NOP).unwrap();
+    writer.set_label(label);
+    writer.write_address(label, OperandSize::Qword).unwrap();
+    assert_eq!(writer.get_label_offset(label), Some(6));
+
+    let mut bytes = writer.get_inner_writer_ref().clone();
+    for fixup in writer.get_fixups().unwrap() {
+        fixup.apply(&mut bytes, 0x1000 + 6, 0x1000).unwrap();
+    }
+    assert_eq!(bytes, vec![0xE9, 0x01, 0x00, 0x00, 0x00, 0x90,
+        0x06, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+
+    let fixup = writer.get_fixups().unwrap()[0];
+    assert_eq!(fixup.apply(&mut bytes, 0x1_0000_0000, 0), Err(InstructionEncodingError::MismatchedSize));
+}
@@ -0,0 +1,189 @@
+use ::*;
+
+#[test]
+fn format_instructions() {
+    let cases = [
+        (Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::EAX),
+            Operand::IndirectDisplaced(Reg::EBP, 12, Some(OperandSize::Dword), None)),
+            "mov eax, dword ptr [ebp+0xc]"),
+        (Instruction::new2(Mnemonic::LEA, Operand::Direct(Reg::RAX),
+            Operand::IndirectScaledIndexedDisplaced(Reg::RBX, Reg::RCX, RegScale::Four, 0x10, None, None)),
+            "lea rax, [rbx+rcx*4+0x10]"),
+        (Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::RAX),
+            Operand::Memory(0x28, Some(OperandSize::Qword), Some(SegmentReg::FS))),
+            "mov rax, qword ptr fs:[0x28]"),
+        (Instruction::new2(Mnemonic::ADD, Operand::Indirect(Reg::EDI, Some(OperandSize::Byte), None),
+            Operand::Literal8(0x7F)),
+            "add byte ptr [edi], 0x7f"),
+        (Instruction::new2(Mnemonic::FADD, Operand::Direct(Reg::ST0), Operand::Direct(Reg::ST3)),
+            "fadd st(0), st(3)"),
+        (Instruction::new1(Mnemonic::JMP, Operand::MemoryAndSegment16(0x08, 0x7C00)),
+            "jmp 0x8:0x7c00"),
+        (Instruction::new1(Mnemonic::JMP, Operand::Offset(-5i64 as u64, None, None)), "jmp -0x5"),
+        (Instruction::new0(Mnemonic::RET), "ret"),
+    ];
+    for &(ref instr, text) in cases.iter() {
+        assert_eq!(instr.to_string(), text);
+    }
+
+    let locked = Instruction { lock: true, .. Instruction::new2(Mnemonic::ADD,
+        Operand::Indirect(Reg::RAX, Some(OperandSize::Dword), None), Operand::Direct(Reg::ECX)) };
+    assert_eq!(locked.to_string(), "lock add dword ptr [rax], ecx");
+
+    let masked = Instruction {
+        mask: Some(MaskReg::K1),
+        merge_mode: Some(MergeMode::Zero),
+        rounding_mode: Some(RoundingMode::Nearest),
+        .. Instruction::new3(Mnemonic::VADDPS, Operand::Direct(Reg::ZMM1), Operand::Direct(Reg::ZMM2),
+            Operand::Direct(Reg::ZMM3))
+    };
+    assert_eq!(masked.to_string(), "vaddps zmm1{k1}{z}, zmm2, zmm3, {rn-sae}");
+
+    let broadcast = Instruction {
+        broadcast: Some(BroadcastMode::Broadcast1To16),
+        .. Instruction::new3(Mnemonic::VADDPS, Operand::Direct(Reg::ZMM1), Operand::Direct(Reg::ZMM2),
+            Operand::Indirect(Reg::RAX, Some(OperandSize::Dword), None))
+    };
+    assert_eq!(broadcast.to_string(), "vaddps zmm1, zmm2, dword ptr [rax]{1to16}");
+}
+
+#[test]
+fn disassembly_listing() {
+    let code = [
+        0xE8, 0x0B, 0x00, 0x00, 0x00, // call helper
+        0x48, 0xC7, 0x80, 0x00, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, // mov [rax+0x100], 1
+        0x06, // Not an instruction in long mode
+        0x75, 0xFB, // jne helper-4
+        0xC3, // ret
+    ];
+    let mut listing = Listing::new();
+    listing.add_symbol(0x1000, "main");
+    listing.add_symbol(0x1010, "helper");
+    listing.add_symbol(0x1010, "alias");
+    assert_eq!(listing.get_symbol(0x1012), Some(("helper", 2)));
+    assert_eq!(listing.get_symbol(0xFFF), None);
+
+    let mut out = Vec::new();
+    listing.write_code(&mut out, &code, 0x1000, Mode::Long).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "
+0000000000001000 <main>:
+    1000:\te8 0b 00 00 00       \tcall 0x1010 <helper>
+    1005:\t48 c7 80 00 01 00 00 \tmov qword ptr [rax+0x100], 0x1
+    100c:\t01 00 00 00
+
+0000000000001010 <helper>:
+    1010:\t06                   \t(bad)
+    1011:\t75 fb                \tjne 0x100e <main+0xe>
+    1013:\tc3                   \tret
+");
+}
+
+#[test]
+fn elf_listing() {
+    let mut obj = ObjectWriter::new(Mode::Protected);
+    let start = obj.symbol("_start", Binding::Global);
+    let done = obj.symbol("done", Binding::Local);
+    {
+        let text = obj.section(Section::Text);
+        text.set_label(start);
+        text.write1(Mnemonic::JMP, Operand::Label(done)).unwrap();
+        text.set_label(done);
+        text.write0(Mnemonic::HLT).unwrap();
+    }
+    let mut bytes = Vec::new();
+    obj.write_executable(&mut bytes, start).unwrap();
+    let file = ElfFile::parse(&bytes).unwrap();
+    let entry = file.get_entry();
+
+    let mut out = Vec::new();
+    Listing::from_elf(&file).write_elf(&mut out, &file).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.starts_with("\nDisassembly of section .text:\n"));
+    assert!(text.contains(&format!("\tjmp 0x{:x} <done>\n", entry + 5)));
+    assert!(text.contains(&format!("{:016x} <done>:\n", entry + 5)));
+}
+
+#[test]
+fn assembly_listing() {
+    let mut writer = InstructionWriter::new(Vec::new(), Mode::Long);
+    writer.write0(Mnemonic::NOP).unwrap(); // Not recorded
+    writer.enable_listing();
+    let target = Label::new();
+    writer.set_source(3, "call target");
+    writer.write1(Mnemonic::CALL, Operand::Label(target)).unwrap();
+    writer.write2(Mnemonic::MOV, Operand::Direct(Reg::RAX), Operand::Literal64(0x1122334455667788)).unwrap();
+    writer.set_label(target);
+    writer.write_bytes(b"hi").unwrap();
+
+    let listing = writer.get_listing();
+    assert_eq!(listing.len(), 3);
+    assert_eq!((listing[0].offset, &listing[0].bytes[..]), (1, &[0xE8, 0, 0, 0, 0][..]));
+    assert_eq!(listing[0].source, Some((3, "call target".to_owned())));
+    assert_eq!(listing[1].source, None);
+    assert_eq!(listing[2].instruction, None);
+
+    let mut out = Vec::new();
+    write_assembly_listing(&mut out, listing).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), [
+        "     3 00000001 E800000000       call target",
+        "       00000006 48B8887766554433 mov rax, 0x1122334455667788",
+        "       0000000E 2211",
+        "       00000010 6869             db 0x68, 0x69",
+        "",
+    ].join("\n"));
+}
+
+#[test]
+fn displacement_listing() {
+    // Short displacements are signed, and RIP-relative operands show the address they refer to.
+    let code = [
+        0x48, 0x8B, 0x05, 0x10, 0x00, 0x00, 0x00, // mov rax, [rip+0x10]
+        0x48, 0x8B, 0x05, 0xF0, 0xFF, 0xFF, 0xFF, // mov rax, [rip-0x10]
+        0x8B, 0x45, 0xF8, // mov eax, [rbp-8]
+        0x67, 0x8B, 0x0D, 0x00, 0x00, 0x00, 0x00, // mov ecx, [eip]
+    ];
+    let mut listing = Listing::new();
+    listing.add_symbol(0x1000, "main");
+    let mut out = Vec::new();
+    listing.write_code(&mut out, &code, 0x1000, Mode::Long).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "
+0000000000001000 <main>:
+    1000:\t48 8b 05 10 00 00 00 \tmov rax, qword ptr [rip+0x10] # 0x1017 <main+0x17>
+    1007:\t48 8b 05 f0 ff ff ff \tmov rax, qword ptr [rip-0x10] # 0xffe
+    100e:\t8b 45 f8             \tmov eax, dword ptr [rbp-0x8]
+    1011:\t67 8b 0d 00 00 00 00 \tmov ecx, dword ptr [eip+0x0] # 0x1018 <main+0x18>
+");
+
+    let cases: [(&[u8], Mode, &str); 4] = [
+        (&[0x8B, 0x45, 0xF8], Mode::Protected, "mov eax, dword ptr [ebp-0x8]"),
+        (&[0x8B, 0x46, 0xFE], Mode::Real, "mov ax, word ptr [bp-0x2]"),
+        (&[0x8B, 0x86, 0x00, 0x80], Mode::Real, "mov ax, word ptr [bp-0x8000]"),
+        (&[0x8B, 0x44, 0x24, 0x80], Mode::Protected, "mov eax, dword ptr [esp-0x80]"),
+    ];
+    for &(bytes, mode, text) in cases.iter() {
+        let (instr, _) = InstructionReader::new(bytes, mode).read().unwrap();
+        assert_eq!(instr.to_string(), text);
+        // They're encoded the same way again.
+        let mut encoded = Vec::new();
+        instr.encode(&mut encoded, mode).unwrap();
+        assert_eq!(&encoded[..], bytes, "{}", text);
+    }
+}
+
+#[test]
+fn wrapping_listing() {
+    // Branch targets wrap around at the size of the instruction pointer, and addresses past the
+    // end of the address space wrap to zero.
+    let cases: [(&[u8], u64, Mode, &str); 3] = [
+        (&[0xEB, 0x20], 0xFFF0, Mode::Real, "    fff0:\teb 20                \tjmp 0x12\n"),
+        (&[0xEB, 0x10], 0xFFFF_FFF0, Mode::Protected,
+            "fffffff0:\teb 10                \tjmp 0x2\n"),
+        (&[0x90, 0xC3], 0xFFFF_FFFF_FFFF_FFFF, Mode::Long,
+            "ffffffffffffffff:\t90                   \tnop\n       0:\tc3                   \tret\n"),
+    ];
+    for &(code, address, mode, text) in cases.iter() {
+        let mut out = Vec::new();
+        Listing::new().write_code(&mut out, code, address, mode).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), text);
+    }
+}
@@ -0,0 +1,156 @@
+use ::*;
+
+fn reg(reg: Reg) -> Operand { Operand::Direct(reg) }
+
+fn item(mnemonic: Mnemonic, operands: &[Operand]) -> CodeItem {
+    let mut instr = Instruction::new0(mnemonic);
+    instr.operand1 = operands.first().cloned();
+    instr.operand2 = operands.get(1).cloned();
+    CodeItem::Instruction(instr)
+}
+
+fn mnemonics(items: &[CodeItem]) -> Vec<Option<Mnemonic>> {
+    items.iter().map(|item| match *item {
+        CodeItem::Instruction(ref instr) => Some(instr.mnemonic),
+        CodeItem::Label(_) => None
+    }).collect()
+}
+
+fn target(item: &CodeItem) -> Label {
+    match *item {
+        CodeItem::Instruction(Instruction { operand1: Some(Operand::Label(label)), .. }) => label,
+        _ => panic!("{:?} has no label", item)
+    }
+}
+
+#[test]
+fn cet_instructions() {
+    let memory = Operand::Indirect(Reg::RAX, Some(OperandSize::Qword), None);
+    let tests = [
+        (Instruction::new0(Mnemonic::ENDBR64), vec![0xF3, 0x0F, 0x1E, 0xFA]),
+        (Instruction::new0(Mnemonic::ENDBR32), vec![0xF3, 0x0F, 0x1E, 0xFB]),
+        (Instruction::new0(Mnemonic::SETSSBSY), vec![0xF3, 0x0F, 0x01, 0xE8]),
+        (Instruction::new0(Mnemonic::SAVEPREVSSP), vec![0xF3, 0x0F, 0x01, 0xEA]),
+        (Instruction::new1(Mnemonic::INCSSPQ, reg(Reg::RAX)), vec![0xF3, 0x48, 0x0F, 0xAE, 0xE8]),
+        (Instruction::new1(Mnemonic::INCSSPD, reg(Reg::EAX)), vec![0xF3, 0x0F, 0xAE, 0xE8]),
+        (Instruction::new1(Mnemonic::RDSSPQ, reg(Reg::RCX)), vec![0xF3, 0x48, 0x0F, 0x1E, 0xC9]),
+        (Instruction::new1(Mnemonic::RSTORSSP, memory), vec![0xF3, 0x0F, 0x01, 0x28]),
+        (Instruction::new1(Mnemonic::CLRSSBSY, memory), vec![0xF3, 0x0F, 0xAE, 0x30]),
+        (Instruction::new2(Mnemonic::WRSSQ, memory, reg(Reg::RBX)),
+            vec![0x48, 0x0F, 0x38, 0xF6, 0x18]),
+        (Instruction::new2(Mnemonic::WRUSSD, Operand::Indirect(Reg::RAX, Some(OperandSize::Dword),
+            None), reg(Reg::EBX)), vec![0x66, 0x0F, 0x38, 0xF5, 0x18]),
+    ];
+    for (instr, bytes) in tests.iter() {
+        let mut encoded = Vec::new();
+        instr.encode(&mut encoded, Mode::Long).unwrap();
+        assert_eq!(&encoded, bytes, "{:?}", instr);
+        let (decoded, len) = InstructionReader::new(&bytes[..], Mode::Long).read().unwrap();
+        assert_eq!(decoded.mnemonic, instr.mnemonic);
+        assert_eq!(len, bytes.len());
+    }
+    // The secondary opcode doesn't get in the way of instructions sharing the primary one.
+    let bytes = [0xF3, 0x0F, 0x1E, 0xC8];
+    let decoded = InstructionReader::new(&bytes[..], Mode::Long).read().unwrap().0;
+    assert_eq!(decoded, Instruction::new1(Mnemonic::RDSSPD, reg(Reg::EAX)));
+}
+
+#[test]
+fn retpoline() {
+    let items = [item(Mnemonic::CALL, &[reg(Reg::RAX)]), item(Mnemonic::JMP, &[reg(Reg::RCX)]),
+        item(Mnemonic::CALL, &[reg(Reg::RAX)])];
+    let mitigations = Mitigations { retpoline: true, .. Default::default() };
+    let code = mitigate(&items, &mitigations, Mode::Long).unwrap();
+    assert_eq!(code.len(), 3 + 2 * 9);
+    assert_eq!(target(&code[0]), target(&code[2]));
+    assert!(target(&code[0]) != target(&code[1]));
+
+    let mut writer = InstructionWriter::new(Vec::new(), Mode::Long);
+    write_items(&mut writer, &code).unwrap();
+    let bytes = writer.get_inner_writer_ref();
+    // call rax_thunk; jmp rcx_thunk; call rax_thunk, then the rax thunk: call set_up; pause;
+    // lfence; jmp trap; mov [rsp], rax; ret.
+    assert_eq!(bytes.len(), 15 + 2 * 20);
+    assert_eq!(bytes[15..35], [0xE8, 0, 0, 0, 0, 0xF3, 0x90, 0x0F, 0xAE, 0xE8, 0xE9, 0, 0, 0, 0,
+        0x48, 0x89, 0x04, 0x24, 0xC3]);
+    assert_eq!(bytes[50..54], [0x48, 0x89, 0x0C, 0x24]);
+    assert_eq!(writer.get_label_offset(target(&code[0])), Some(15));
+
+    // 32-bit thunks use ESP, and only full size registers can be used.
+    let items = [item(Mnemonic::JMP, &[reg(Reg::EDX)])];
+    let code = mitigate(&items, &mitigations, Mode::Protected).unwrap();
+    assert_eq!(mnemonics(&code[code.len() - 2..]), [Some(Mnemonic::MOV), Some(Mnemonic::RET)]);
+    assert_eq!(mitigate(&items, &mitigations, Mode::Long), Err(MitigationError::Unmitigated(0)));
+    let items = [item(Mnemonic::RET, &[]),
+        item(Mnemonic::CALL, &[Operand::Indirect(Reg::RAX, Some(OperandSize::Qword), None)])];
+    assert_eq!(mitigate(&items, &mitigations, Mode::Long), Err(MitigationError::Unmitigated(1)));
+    assert_eq!(mitigate(&items, &mitigations, Mode::Real),
+        Err(MitigationError::UnsupportedMode(Mode::Real)));
+}
+
+#[test]
+fn return_thunk_and_lfence() {
+    let (skip, other) = (Label::new(), Label::new());
+    let items = [item(Mnemonic::TEST, &[reg(Reg::EAX), reg(Reg::EAX)]),
+        item(Mnemonic::JE, &[Operand::Label(skip)]), item(Mnemonic::RET, &[]),
+        CodeItem::Label(other), CodeItem::Label(skip), item(Mnemonic::RET, &[])];
+    let mitigations = Mitigations { lfence: true, return_thunk: true, .. Default::default() };
+    let code = mitigate(&items, &mitigations, Mode::Long).unwrap();
+    // One LFENCE after the JE and one after both labels, and the RETs jump to the same thunk.
+    assert_eq!(mnemonics(&code[..8]), [Some(Mnemonic::TEST), Some(Mnemonic::JE),
+        Some(Mnemonic::LFENCE), Some(Mnemonic::JMP), None, None, Some(Mnemonic::LFENCE),
+        Some(Mnemonic::JMP)]);
+    assert_eq!(target(&code[3]), target(&code[7]));
+    assert_eq!(mnemonics(&code[code.len() - 2..]), [Some(Mnemonic::LEA), Some(Mnemonic::RET)]);
+
+    let items = [item(Mnemonic::RET, &[Operand::Literal16(8)])];
+    assert_eq!(mitigate(&items, &mitigations, Mode::Long), Err(MitigationError::Unmitigated(0)));
+}
+
+#[test]
+fn endbranch() {
+    let (entry, callback, table, local) = (Label::new(), Label::new(), Label::new(), Label::new());
+    let items = [CodeItem::Label(entry),
+        item(Mnemonic::LEA, &[reg(Reg::RAX), Operand::LabelMemory(callback, None, None)]),
+        item(Mnemonic::MOV, &[reg(Reg::RCX), Operand::LabelMemory(table, None, None)]),
+        item(Mnemonic::JMP, &[Operand::Label(local)]),
+        CodeItem::Label(local), item(Mnemonic::RET, &[]),
+        CodeItem::Label(callback), item(Mnemonic::RET, &[]),
+        CodeItem::Label(table)];
+    let mitigations = Mitigations { endbranch: true, indirect_targets: vec![entry],
+        .. Default::default() };
+    let code = mitigate(&items, &mitigations, Mode::Long).unwrap();
+    // Not at the target of the direct jump, or at data that's only loaded.
+    assert_eq!(mnemonics(&code), [None, Some(Mnemonic::ENDBR64), Some(Mnemonic::LEA),
+        Some(Mnemonic::MOV), Some(Mnemonic::JMP), None, Some(Mnemonic::RET), None,
+        Some(Mnemonic::ENDBR64), Some(Mnemonic::RET), None]);
+    let code = mitigate(&items[..2], &mitigations, Mode::Protected).unwrap();
+    assert_eq!(mnemonics(&code), [None, Some(Mnemonic::ENDBR32), Some(Mnemonic::LEA)]);
+}
+
+#[cfg(all(unix, target_arch = "x86_64"))]
+#[test]
+fn mitigated_function() {
+    // f(n) = g(n) * 2 if n != 0, where g(n) = n + 1 is called through RAX.
+    let (f, g, zero) = (Label::new(), Label::new(), Label::new());
+    let items = [CodeItem::Label(f),
+        item(Mnemonic::XOR, &[reg(Reg::EAX), reg(Reg::EAX)]),
+        item(Mnemonic::TEST, &[reg(Reg::EDI), reg(Reg::EDI)]),
+        item(Mnemonic::JE, &[Operand::Label(zero)]),
+        item(Mnemonic::LEA, &[reg(Reg::RAX), Operand::LabelMemory(g, None, None)]),
+        item(Mnemonic::CALL, &[reg(Reg::RAX)]),
+        item(Mnemonic::ADD, &[reg(Reg::EAX), reg(Reg::EAX)]),
+        CodeItem::Label(zero), item(Mnemonic::RET, &[]),
+        CodeItem::Label(g),
+        item(Mnemonic::LEA, &[reg(Reg::EAX), Operand::IndirectDisplaced(Reg::RDI, 1, None, None)]),
+        item(Mnemonic::RET, &[])];
+    let mitigations = Mitigations { retpoline: true, lfence: true, return_thunk: true,
+        endbranch: true, indirect_targets: vec![f] };
+    let code = mitigate(&items, &mitigations, Mode::Long).unwrap();
+    let mut writer = InstructionWriter::new(JitBuffer::new().unwrap(), Mode::Long);
+    write_items(&mut writer, &code).unwrap();
+    writer.finalize().unwrap();
+    let function: extern "C" fn(u32) -> u32 = unsafe { writer.get_function(f) }.unwrap();
+    assert_eq!(function(0), 0);
+    assert_eq!(function(20), 42);
+}
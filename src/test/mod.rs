@@ -1,9 +1,32 @@
+mod abi;
 mod addressing16;
 mod addressing32;
 mod addressing64;
+mod branch_align;
+mod cfi;
+mod coff;
+mod condition;
 mod decode;
+mod dos;
+mod elf;
+mod elf_file;
 mod encode;
+mod explain;
+mod flat;
+mod flow;
+mod fuzz;
+#[cfg(all(unix, target_arch = "x86_64"))] mod jit;
+mod labels;
+mod listing;
+mod mitigate;
+mod parser;
+mod patch;
+#[cfg(unix)] mod perf;
+mod registers;
+mod relocate;
+mod signature;
 mod size_inference;
+mod validation;
 mod instruction_tests;
 
 use std::io::Cursor;
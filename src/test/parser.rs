@@ -0,0 +1,139 @@
+use ::*;
+
+fn encode_text(text: &str, mode: Mode) -> Vec<u8> {
+    let instr = parse_instruction(text, mode).expect("Parsing failed");
+    let mut bytes = Vec::new();
+    instr.encode(&mut bytes, mode).expect("Encoding failed");
+    bytes
+}
+
+#[test]
+fn parse_instructions() {
+    let cases: &[(&str, Mode, &[u8])] = &[
+        ("add eax, 1", Mode::Protected, &[0x83, 0xC0, 0x01]),
+        ("mov eax, 1", Mode::Protected, &[0xB8, 0x01, 0x00, 0x00, 0x00]),
+        ("push 0x80", Mode::Protected, &[0x68, 0x80, 0x00, 0x00, 0x00]),
+        ("mov al, 0FFh", Mode::Protected, &[0xB0, 0xFF]),
+        ("mov rax, 0x1122334455667788", Mode::Long,
+            &[0x48, 0xB8, 0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11]),
+        ("LEA RAX, [RBX + RCX*4 + 0x10]", Mode::Long, &[0x48, 0x8D, 0x44, 0x8B, 0x10]),
+        ("mov rax, qword ptr fs:[0x28]", Mode::Long,
+            &[0x64, 0x48, 0x8B, 0x04, 0x05, 0x28, 0x00, 0x00, 0x00]),
+        ("jmp 0x08:0x7c00", Mode::Real, &[0xEA, 0x00, 0x7C, 0x08, 0x00]),
+        ("lock add dword [eax], ecx", Mode::Protected, &[0xF0, 0x01, 0x08]),
+        ("fadd st(0), st(3)", Mode::Protected, &[0xD8, 0xC3]),
+        ("movzx eax, byte ptr [esi]", Mode::Protected, &[0x0F, 0xB6, 0x06]),
+        ("enter 16, 0", Mode::Protected, &[0xC8, 0x10, 0x00, 0x00]),
+        ("ret", Mode::Long, &[0xC3]),
+    ];
+    for &(text, mode, expected) in cases.iter() {
+        assert_eq!(encode_text(text, mode), expected, "{}", text);
+    }
+
+    assert_eq!(parse_instruction("mov dword ptr [ebp-8], 5", Mode::Protected).unwrap(),
+        Instruction::new2(Mnemonic::MOV,
+            Operand::IndirectDisplaced(Reg::EBP, -8i64 as u64, Some(OperandSize::Dword), None),
+            Operand::Literal32(5)));
+}
+
+#[test]
+fn parse_decorations() {
+    assert_eq!(encode_text("vaddps zmm1{k1}{z}, zmm2, zmm3", Mode::Long),
+        [0x62, 0xF1, 0x6C, 0xC9, 0x58, 0xCB]);
+    assert_eq!(encode_text("vaddps zmm1, zmm2, zmm3, {rn-sae}", Mode::Long),
+        [0x62, 0xF1, 0x6C, 0x18, 0x58, 0xCB]);
+    // The text they're displayed as parses back to the same instruction.
+    let texts = ["vaddps zmm1{k1}{z}, zmm2, zmm3", "vaddps zmm1{k7}, zmm2, dword ptr [rax]{1to16}",
+        "vaddps zmm1, zmm2, zmm3, {rn-sae}", "vmaxps zmm1{k1}, zmm2, zmm3, {sae}"];
+    for text in texts.iter() {
+        assert_eq!(parse_instruction(text, Mode::Long).unwrap().to_string(), *text);
+    }
+    for text in texts[..2].iter() {
+        let bytes = encode_text(text, Mode::Long);
+        assert_eq!(InstructionReader::new(&bytes[..], Mode::Long).read().unwrap().0,
+            parse_instruction(text, Mode::Long).unwrap());
+    }
+
+    assert_eq!(parse_instruction("vaddps zmm1{k0}, zmm2, zmm3", Mode::Long),
+        Err(ParseError::InvalidOperand("zmm1{k0}".to_owned())));
+    assert_eq!(parse_instruction("vaddps zmm1{k1, zmm2, zmm3", Mode::Long),
+        Err(ParseError::InvalidOperand("zmm1{k1".to_owned())));
+    assert_eq!(parse_instruction("vaddps zmm1, zmm2, [rax]{1to3}", Mode::Long),
+        Err(ParseError::InvalidOperand("[rax]{1to3}".to_owned())));
+}
+
+#[test]
+fn parse_numbers() {
+    assert_eq!(parse_number("42"), Some(42));
+    assert_eq!(parse_number("0x2A"), Some(42));
+    assert_eq!(parse_number("2Ah"), Some(42));
+    assert_eq!(parse_number("0b101010"), Some(42));
+    assert_eq!(parse_number("'*'"), Some(42));
+    assert_eq!(parse_number("-1"), Some(u64::MAX));
+    assert_eq!(parse_number("FFh"), None);
+    assert_eq!(parse_number("eax"), None);
+}
+
+#[test]
+fn parse_errors() {
+    assert_eq!(parse_instruction("frob eax", Mode::Long),
+        Err(ParseError::UnknownMnemonic("frob".to_owned())));
+    assert_eq!(parse_instruction("mov eax, [eax*3]", Mode::Long),
+        Err(ParseError::InvalidOperand("[eax*3]".to_owned())));
+    assert_eq!(parse_instruction("jmp start", Mode::Long),
+        Err(ParseError::UnexpectedLabel("start".to_owned())));
+    assert!(matches!(parse_instruction("mov al, eax", Mode::Long), Err(ParseError::Encoding(_))));
+}
+
+#[test]
+fn assemble_source() {
+    let source = "
+        bits 32
+        global start
+        section .text
+    start:
+        mov ecx, 3          ; counter
+    again: dec ecx
+        jnz again
+        mov eax, [value]
+        ret
+        section .data
+    value: dd 0x12345678, start
+    message: db \"hi\", 0
+    ";
+    let obj = assemble(source, Mode::Long).expect("Assembling failed");
+    assert_eq!(obj.get_mode(), Mode::Long);
+    assert_eq!(obj.get_section(Section::Data).get_inner_writer_ref(),
+        &vec![0x78, 0x56, 0x34, 0x12, 0, 0, 0, 0, b'h', b'i', 0]);
+
+    let symbols: Vec<_> = obj.get_symbols().iter().map(|s| (&s.name[..], s.binding)).collect();
+    assert_eq!(symbols, vec![("start", Binding::Global), ("again", Binding::Local),
+        ("value", Binding::Local), ("message", Binding::Local)]);
+
+    let resolved = obj.link(&[(Section::Text, 0x1000), (Section::Data, 0x2000),
+        (Section::Rodata, 0x3000), (Section::Bss, 0x4000)]).expect("Linking failed");
+    let text = &resolved.iter().find(|s| s.0 == Section::Text).unwrap().1;
+    assert_eq!(text, &vec![0xB9, 0x03, 0x00, 0x00, 0x00, 0x49, 0x0F, 0x85, 0xF9, 0xFF, 0xFF, 0xFF,
+        0x8B, 0x05, 0x00, 0x20, 0x00, 0x00, 0xC3]);
+    let data = &resolved.iter().find(|s| s.0 == Section::Data).unwrap().1;
+    assert_eq!(&data[4..8], &[0x00, 0x10, 0x00, 0x00]);
+
+    let listing = obj.get_section(Section::Text).get_listing();
+    assert_eq!(listing.len(), 5);
+    assert_eq!(listing[1].source, Some((7, "again: dec ecx".to_owned())));
+}
+
+#[test]
+fn assemble_errors() {
+    assert_eq!(assemble("a:\na: ret", Mode::Long).err(),
+        Some(AsmError { line: 2, error: ParseError::DuplicateLabel("a".to_owned()) }));
+    assert_eq!(assemble("nop\njmp missing\njmp missing", Mode::Long).err(),
+        Some(AsmError { line: 2, error: ParseError::UndefinedLabel("missing".to_owned()) }));
+    assert_eq!(assemble("bits 8", Mode::Long).err(),
+        Some(AsmError { line: 1, error: ParseError::InvalidDirective("bits 8".to_owned()) }));
+    assert_eq!(assemble("section .bss\nresq 0x2000000000000001", Mode::Long).err(),
+        Some(AsmError { line: 2,
+            error: ParseError::InvalidDirective("resq 0x2000000000000001".to_owned()) }));
+    assert!(assemble("resb 0x20000000", Mode::Long).is_err());
+    assert!(assemble("extern puts\ncall puts", Mode::Long).is_ok());
+}
@@ -0,0 +1,197 @@
+use ::*;
+
+fn decode_one(bytes: &[u8], mode: Mode) -> usize {
+    InstructionReader::new(bytes, mode).read().unwrap().1
+}
+
+#[test]
+fn nops() {
+    for &mode in &[Mode::Real, Mode::Protected, Mode::Long] {
+        for len in 1..16 {
+            let nop = nop_padding(len, mode);
+            assert_eq!(nop.len(), len);
+            assert_eq!(decode_one(&nop, mode), len, "{} byte NOP in {:?} mode", len, mode);
+        }
+    }
+    assert_eq!(nop_padding(5, Mode::Long), vec![0x0F, 0x1F, 0x44, 0x00, 0x00]);
+    assert_eq!(nop_padding(11, Mode::Long),
+        vec![0x66, 0x66, 0x66, 0x0F, 0x1F, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    assert_eq!(nop_padding(5, Mode::Real), vec![0x0F, 0x1F, 0x80, 0x00, 0x00]);
+    // Longer padding is split into 15 byte NOPs.
+    let nop = nop_padding(20, Mode::Protected);
+    assert_eq!(decode_one(&nop, Mode::Protected), 15);
+    assert_eq!(nop[15..], [0x0F, 0x1F, 0x44, 0x00, 0x00]);
+}
+
+#[test]
+fn align() {
+    let mut writer = InstructionWriter::new(Vec::new(), Mode::Long);
+    writer.write0(Mnemonic::RET).unwrap();
+    assert_eq!(writer.align(16, Fill::Nop), Ok(15));
+    assert_eq!(writer.align(16, Fill::Nop), Ok(0));
+    writer.write0(Mnemonic::RET).unwrap();
+    assert_eq!(writer.align(4, Fill::Int3), Ok(3));
+    writer.write_bytes(&[1]).unwrap();
+    assert_eq!(writer.align(8, Fill::Byte(0)), Ok(3));
+    // No alignment, and alignments that aren't powers of two.
+    writer.write_bytes(&[2]).unwrap();
+    assert_eq!(writer.align(0, Fill::Int3), Ok(0));
+    assert_eq!(writer.align(1, Fill::Int3), Ok(0));
+    assert_eq!(writer.align(3, Fill::Int3), Ok(2));
+    let code = writer.get_inner_writer_ref();
+    assert_eq!(decode_one(&code[1..], Mode::Long), 15);
+    assert_eq!(code[16..], [0xC3, 0xCC, 0xCC, 0xCC, 0x01, 0x00, 0x00, 0x00, 0x02, 0xCC, 0xCC]);
+
+    // 16-bit NOPs have 16-bit addressing.
+    let mut writer = InstructionWriter::new(Vec::new(), Mode::Real);
+    writer.write0(Mnemonic::RET).unwrap();
+    assert_eq!(writer.align(8, Fill::Nop), Ok(7));
+    assert_eq!(writer.get_inner_writer_ref()[1..], [0x66, 0x66, 0x0F, 0x1F, 0x80, 0x00, 0x00]);
+    assert_eq!(decode_one(&writer.get_inner_writer_ref()[1..], Mode::Real), 7);
+}
+
+#[test]
+fn replace() {
+    // mov eax, 0x12345678; ret
+    let mut code = vec![0xB8, 0x78, 0x56, 0x34, 0x12, 0xC3];
+    {
+        let mut patcher = Patcher::new(&mut code, 0x1000, Mode::Long);
+        assert_eq!(patcher.replace(0, &Instruction::new2(Mnemonic::XOR, Operand::Direct(Reg::EAX),
+            Operand::Direct(Reg::EAX))), Ok(2));
+        assert_eq!(patcher.get_code(), &[0x31, 0xC0, 0x0F, 0x1F, 0x00, 0xC3]);
+        assert_eq!(patcher.replace(5, &Instruction::new0(Mnemonic::NOP)), Ok(1));
+        assert_eq!(patcher.replace(5, &Instruction::new2(Mnemonic::XOR, Operand::Direct(Reg::EAX),
+            Operand::Direct(Reg::EAX))), Err(PatchError::TooLarge(2, 1)));
+        let label = Label::new();
+        assert_eq!(patcher.replace(0, &Instruction::new1(Mnemonic::JMP, Operand::Label(label))),
+            Err(PatchError::UnresolvedLabel(label)));
+        patcher.write_nops(0, 6).unwrap();
+        assert_eq!(patcher.decode(0).unwrap().1, 6);
+        assert_eq!(patcher.write_nops(2, 5), Err(PatchError::TooLarge(5, 4)));
+    }
+    assert_eq!(Patcher::new(&mut [0x0F], 0, Mode::Long).decode(0).map(|d| d.1),
+        Err(PatchError::Decoding(InstructionDecodingError::PartialInstruction)));
+}
+
+#[test]
+fn retarget() {
+    // je +0; jmp +0 (near); call +0
+    let mut code = vec![0x74, 0x00, 0xE9, 0, 0, 0, 0, 0xE8, 0, 0, 0, 0];
+    {
+        let mut patcher = Patcher::new(&mut code, 0x1000, Mode::Long);
+        patcher.retarget(0, 0x1010).unwrap();
+        patcher.retarget(2, 0x800).unwrap();
+        patcher.retarget(7, 0x1000).unwrap();
+        assert_eq!(patcher.retarget(0, 0x1100), Err(PatchError::OutOfRange));
+        assert_eq!(patcher.set_immediate(0, 1), Err(PatchError::NoField));
+    }
+    assert_eq!(code, [0x74, 0x0E, 0xE9, 0xF9, 0xF7, 0xFF, 0xFF, 0xE8, 0xF4, 0xFF, 0xFF, 0xFF]);
+    // A ret has no target.
+    assert_eq!(Patcher::new(&mut [0xC3], 0, Mode::Long).retarget(0, 0),
+        Err(PatchError::NoField));
+}
+
+#[test]
+fn set_immediate() {
+    // mov eax, 1; add eax, 2 (imm8); mov rax, imm64
+    let mut code = vec![0xB8, 1, 0, 0, 0, 0x83, 0xC0, 0x02, 0x48, 0xB8, 0, 0, 0, 0, 0, 0, 0, 0];
+    {
+        let mut patcher = Patcher::new(&mut code, 0, Mode::Long);
+        patcher.set_immediate(0, 0xFFFF_FFFF).unwrap();
+        patcher.set_immediate(5, (-3i64) as u64).unwrap();
+        patcher.set_immediate(8, 0x1122_3344_5566_7788).unwrap();
+        assert_eq!(patcher.set_immediate(0, 0x1_0000_0000), Err(PatchError::OutOfRange));
+        assert_eq!(patcher.set_immediate(5, 0x100), Err(PatchError::OutOfRange));
+        assert_eq!(patcher.retarget(0, 0), Err(PatchError::NoField));
+    }
+    assert_eq!(code, [0xB8, 0xFF, 0xFF, 0xFF, 0xFF, 0x83, 0xC0, 0xFD, 0x48, 0xB8,
+        0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11]);
+}
+
+// 8 byte aligned code, since the addresses of the bytes decide which stores are atomic.
+fn aligned(words: &mut [u64]) -> &mut [u8] {
+    unsafe { ::std::slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, words.len() * 8) }
+}
+
+#[test]
+fn patch_sites() {
+    let mut writer = InstructionWriter::new(Vec::new(), Mode::Long);
+    writer.write0(Mnemonic::RET).unwrap();
+    assert_eq!(writer.write_patch_site(), Ok(1));
+    writer.write_bytes(&[0xC3; 4]).unwrap();
+    assert_eq!(writer.write_patch_site(), Ok(10));
+    // At offset 15 the site would cross into the next 8 bytes, so it's padded to 16.
+    assert_eq!(writer.write_patch_site(), Ok(16));
+    writer.write_bytes(&[0xC3; 3]).unwrap();
+    let code = writer.get_inner_writer_ref();
+    assert_eq!(code.len(), 24);
+    assert_eq!(decode_one(&code[15..], Mode::Long), 1);
+
+    let mut words = [0; 3];
+    let buffer = aligned(&mut words);
+    buffer.copy_from_slice(code);
+    let mut patcher = Patcher::new(buffer, 0x1000, Mode::Long);
+    patcher.patch_jump(16, 0x1000).unwrap();
+    assert_eq!(patcher.get_code()[16..21], [0xE9, 0xEB, 0xFF, 0xFF, 0xFF]);
+    assert_eq!(patcher.get_code()[21..], [0xC3; 3]);
+    patcher.clear_patch_site(16).unwrap();
+    assert_eq!(patcher.get_code()[16..21], code[16..21]);
+    assert_eq!(patcher.patch_jump(10, 0x1_0000_1000), Err(PatchError::OutOfRange));
+    assert_eq!(patcher.patch_jump(9, 0x1000), Err(PatchError::InvalidPatchSite));
+
+    // A 5 byte NOP at offset 4 crosses into the next 8 bytes.
+    let mut words = [0; 2];
+    let buffer = aligned(&mut words);
+    buffer[4..9].copy_from_slice(&nop_padding(5, Mode::Long));
+    assert_eq!(Patcher::new(buffer, 0, Mode::Long).patch_jump(4, 0),
+        Err(PatchError::InvalidPatchSite));
+
+    // The sites are only aligned within the code, so it has to be loaded at an aligned address.
+    let mut words = [0; 4];
+    let buffer = &mut aligned(&mut words)[4..28];
+    buffer.copy_from_slice(code);
+    assert_eq!(Patcher::new(buffer, 0x1000, Mode::Long).patch_jump(16, 0x1000),
+        Err(PatchError::InvalidPatchSite));
+
+    // Real mode sites are a JMP rel16 and two bytes of the NOP.
+    let mut words = [0; 1];
+    let buffer = aligned(&mut words);
+    buffer[..5].copy_from_slice(&nop_padding(5, Mode::Real));
+    let mut patcher = Patcher::new(buffer, 0x100, Mode::Real);
+    patcher.patch_jump(0, 0x200).unwrap();
+    assert_eq!(patcher.get_code()[..5], [0xE9, 0xFD, 0x00, 0x00, 0x00]);
+}
+
+#[cfg(all(unix, target_arch = "x86_64"))]
+#[test]
+fn hot_patch() {
+    // f() = 1, with a patch site at its start that's diverted to g() = 2.
+    let (f, g) = (Label::new(), Label::new());
+    let mut writer = InstructionWriter::new(JitBuffer::new().unwrap(), Mode::Long);
+    writer.set_label(f);
+    let site = writer.write_patch_site().unwrap();
+    writer.write2(Mnemonic::MOV, Operand::Direct(Reg::EAX), Operand::Literal32(1)).unwrap();
+    writer.write0(Mnemonic::RET).unwrap();
+    writer.set_label(g);
+    writer.write2(Mnemonic::MOV, Operand::Direct(Reg::EAX), Operand::Literal32(2)).unwrap();
+    writer.write0(Mnemonic::RET).unwrap();
+    writer.finalize().unwrap();
+    let function: extern "C" fn() -> u32 = unsafe { writer.get_function(f) }.unwrap();
+    assert_eq!(function(), 1);
+
+    let target = writer.get_label_address(g).unwrap();
+    let buffer = writer.get_inner_writer_mut();
+    assert!(buffer.as_mut_slice().is_none());
+    buffer.make_writable().unwrap();
+    let base = buffer.get_address();
+    Patcher::new(buffer.as_mut_slice().unwrap(), base, Mode::Long)
+        .patch_jump(site as usize, target).unwrap();
+    buffer.make_executable().unwrap();
+    assert_eq!(function(), 2);
+
+    buffer.make_writable().unwrap();
+    Patcher::new(buffer.as_mut_slice().unwrap(), base, Mode::Long)
+        .clear_patch_site(site as usize).unwrap();
+    buffer.make_executable().unwrap();
+    assert_eq!(function(), 1);
+}
@@ -0,0 +1,106 @@
+use std::fs;
+use std::process;
+use byteorder::{ByteOrder, LittleEndian};
+use ::*;
+
+// Two finalized functions, returning their labels (start, middle, end).
+fn functions() -> (InstructionWriter<JitBuffer>, Label, Label, Label) {
+    let (start, middle, end) = (Label::new(), Label::new(), Label::new());
+    let mut writer = InstructionWriter::new(JitBuffer::new().unwrap(), Mode::Long);
+    writer.set_label(start);
+    writer.write2(Mnemonic::MOV, Operand::Direct(Reg::EAX), Operand::Literal32(7)).unwrap();
+    writer.write0(Mnemonic::RET).unwrap();
+    writer.set_label(middle);
+    writer.write2(Mnemonic::LEA, Operand::Direct(Reg::RAX),
+        Operand::IndirectDisplaced(Reg::RDI, 1, Some(OperandSize::Qword), None)).unwrap();
+    writer.write0(Mnemonic::RET).unwrap();
+    writer.set_label(end);
+    writer.finalize().unwrap();
+    (writer, start, middle, end)
+}
+
+#[test]
+fn perf_map() {
+    let (writer, start, middle, end) = functions();
+    let mut map = PerfMap::new(Vec::new());
+    writer.report_function(&mut map, "seven", start, middle).unwrap();
+    writer.report_function(&mut map, "increment", middle, end).unwrap();
+
+    let text = String::from_utf8(map.get_ref().clone()).unwrap();
+    let start_address = writer.get_label_address(start).unwrap();
+    let middle_address = writer.get_label_address(middle).unwrap();
+    assert_eq!(text, format!("{:x} 6 seven\n{:x} 5 increment\n", start_address, middle_address));
+}
+
+#[test]
+fn jitdump() {
+    let (writer, start, middle, end) = functions();
+    let mut dump = JitDump::new(Vec::new(), Mode::Long).unwrap();
+    writer.report_function(&mut dump, "seven", start, middle).unwrap();
+    writer.report_function(&mut dump, "increment", middle, end).unwrap();
+    dump.close().unwrap();
+    let bytes = dump.get_ref().clone();
+
+    // File header
+    assert_eq!(&bytes[0..4], b"DTiJ");
+    assert_eq!(LittleEndian::read_u32(&bytes[4..]), 1);
+    assert_eq!(LittleEndian::read_u32(&bytes[8..]), 40);
+    assert_eq!(LittleEndian::read_u32(&bytes[12..]), 62);
+    assert_eq!(LittleEndian::read_u32(&bytes[20..]), process::id());
+
+    // JIT_CODE_LOAD records, with the code as it is in the buffer
+    let code = writer.get_inner_writer_ref().as_slice();
+    let mut at = 40;
+    for (index, (name, label, range)) in vec![("seven", start, 0..6), ("increment", middle, 6..11)]
+        .into_iter().enumerate() {
+        let size = LittleEndian::read_u32(&bytes[at + 4..]) as usize;
+        assert_eq!(LittleEndian::read_u32(&bytes[at..]), 0);
+        assert_eq!(size, 16 + 40 + name.len() + 1 + range.len());
+        assert_eq!(LittleEndian::read_u32(&bytes[at + 16..]), process::id());
+        let address = writer.get_label_address(label).unwrap();
+        assert_eq!(LittleEndian::read_u64(&bytes[at + 24..]), address);
+        assert_eq!(LittleEndian::read_u64(&bytes[at + 32..]), address);
+        assert_eq!(LittleEndian::read_u64(&bytes[at + 40..]), range.len() as u64);
+        assert_eq!(LittleEndian::read_u64(&bytes[at + 48..]), index as u64);
+        let name_end = at + 56 + name.len();
+        assert_eq!(&bytes[at + 56..name_end], name.as_bytes());
+        assert_eq!(bytes[name_end], 0);
+        assert_eq!(&bytes[name_end + 1..at + size], &code[range.clone()]);
+        at += size;
+    }
+
+    // JIT_CODE_CLOSE
+    assert_eq!(LittleEndian::read_u32(&bytes[at..]), 3);
+    assert_eq!(LittleEndian::read_u32(&bytes[at + 4..]), 16);
+    assert_eq!(bytes.len(), at + 16);
+}
+
+#[test]
+fn jitdump_file() {
+    let (writer, start, middle, _) = functions();
+    let dir = ::std::env::temp_dir().join(format!("x86asm-jitdump-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    {
+        let mut dump = JitDump::create(&dir, Mode::Long).unwrap();
+        writer.report_function(&mut dump, "seven", start, middle).unwrap();
+        dump.close().unwrap();
+    }
+    let bytes = fs::read(dir.join(format!("jit-{}.dump", process::id()))).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(&bytes[0..4], b"DTiJ");
+    assert_eq!(bytes.len(), 40 + 16 + 40 + 6 + 6 + 16);
+}
+
+#[test]
+fn report_before_finalize() {
+    let (start, end) = (Label::new(), Label::new());
+    let mut writer = InstructionWriter::new(JitBuffer::new().unwrap(), Mode::Long);
+    writer.set_label(start);
+    writer.write0(Mnemonic::RET).unwrap();
+    writer.set_label(end);
+    let mut map = PerfMap::new(Vec::new());
+    assert_eq!(writer.report_function(&mut map, "f", start, end), Err(JitError::NotExecutable));
+    writer.finalize().unwrap();
+    assert!(writer.report_function(&mut map, "f", start, Label::new()).is_err());
+    assert!(map.get_ref().is_empty());
+}
@@ -0,0 +1,69 @@
+use ::*;
+
+#[test]
+fn full_register() {
+    assert_eq!(Reg::AL.full_register(), Reg::RAX);
+    assert_eq!(Reg::AH.full_register(), Reg::RAX);
+    assert_eq!(Reg::SIL.full_register(), Reg::RSI);
+    assert_eq!(Reg::R9W.full_register(), Reg::R9);
+    assert_eq!(Reg::RSP.full_register(), Reg::RSP);
+    assert_eq!(Reg::XMM3.full_register(), Reg::ZMM3);
+    assert_eq!(Reg::YMM20.full_register(), Reg::ZMM20);
+    assert_eq!(Reg::EFLAGS.full_register(), Reg::RFLAGS);
+    assert_eq!(Reg::EIP.full_register(), Reg::RIP);
+    assert_eq!(Reg::CS.full_register(), Reg::CS);
+    assert_eq!(Reg::K1.full_register(), Reg::K1);
+}
+
+#[test]
+fn with_size() {
+    assert_eq!(Reg::EAX.with_size(OperandSize::Word), Some(Reg::AX));
+    assert_eq!(Reg::EAX.with_size(OperandSize::Byte), Some(Reg::AL));
+    assert_eq!(Reg::EAX.with_size(OperandSize::Qword), Some(Reg::RAX));
+    assert_eq!(Reg::AH.with_size(OperandSize::Word), Some(Reg::AX));
+    assert_eq!(Reg::AH.with_size(OperandSize::Byte), Some(Reg::AL));
+    assert_eq!(Reg::RDI.with_size(OperandSize::Byte), Some(Reg::DIL));
+    assert_eq!(Reg::R12.with_size(OperandSize::Dword), Some(Reg::R12D));
+    assert_eq!(Reg::ZMM7.with_size(OperandSize::Xmmword), Some(Reg::XMM7));
+    assert_eq!(Reg::FLAGS.with_size(OperandSize::Qword), Some(Reg::RFLAGS));
+    assert_eq!(Reg::EAX.with_size(OperandSize::Xmmword), None);
+    assert_eq!(Reg::XMM0.with_size(OperandSize::Dword), None);
+    assert_eq!(Reg::CR0.with_size(OperandSize::Dword), Some(Reg::CR0));
+}
+
+#[test]
+fn sub_registers() {
+    assert_eq!(Reg::RAX.sub_registers(), vec![Reg::EAX, Reg::AX, Reg::AL, Reg::AH]);
+    assert_eq!(Reg::BX.sub_registers(), vec![Reg::BL, Reg::BH]);
+    assert_eq!(Reg::RSI.sub_registers(), vec![Reg::ESI, Reg::SI, Reg::SIL]);
+    assert_eq!(Reg::R8D.sub_registers(), vec![Reg::R8W, Reg::R8B]);
+    assert_eq!(Reg::ZMM1.sub_registers(), vec![Reg::YMM1, Reg::XMM1]);
+    assert_eq!(Reg::AL.sub_registers(), vec![]);
+    assert_eq!(Reg::AH.sub_registers(), vec![]);
+}
+
+#[test]
+fn overlaps() {
+    assert!(Reg::AL.overlaps(Reg::RAX));
+    assert!(Reg::RAX.overlaps(Reg::AH));
+    assert!(Reg::AX.overlaps(Reg::AH));
+    assert!(Reg::XMM2.overlaps(Reg::ZMM2));
+    assert!(Reg::ECX.overlaps(Reg::ECX));
+    assert!(!Reg::AL.overlaps(Reg::AH));
+    assert!(!Reg::AH.overlaps(Reg::SPL));
+    assert!(!Reg::EAX.overlaps(Reg::ECX));
+    assert!(!Reg::XMM2.overlaps(Reg::XMM3));
+}
+
+#[test]
+fn high_byte_registers() {
+    assert!(Reg::AH.is_high_byte());
+    assert!(!Reg::AH.is_rex_compatible());
+    assert!(!Reg::SPL.is_high_byte());
+    assert!(Reg::SPL.is_rex_compatible());
+    assert_eq!(Reg::EDX.high_byte(), Some(Reg::DH));
+    assert_eq!(Reg::DL.high_byte(), None);
+    assert_eq!(Reg::RSI.high_byte(), None);
+    // AH and SPL share a register code, which is why AH can't be used with REX.
+    assert_eq!(Reg::AH.get_reg_code(), Reg::SPL.get_reg_code());
+}
@@ -0,0 +1,137 @@
+use ::*;
+
+#[test]
+fn unchanged() {
+    // push rbp; mov rbp, rsp; sub rsp, 16
+    let bytes = [0x55, 0x48, 0x89, 0xE5, 0x48, 0x83, 0xEC, 0x10];
+    assert_eq!(relocate(&bytes, 0x1000, 0x7000_0000, Mode::Long), Ok(bytes.to_vec()));
+}
+
+#[test]
+fn near_branches() {
+    // call 0x1005 from 0x2000
+    assert_eq!(relocate(&[0xE8, 0x00, 0x00, 0x00, 0x00], 0x1000, 0x2000, Mode::Long),
+        Ok(vec![0xE8, 0x00, 0xF0, 0xFF, 0xFF]));
+    // jmp 0x2003 from 0x800 in real mode
+    assert_eq!(relocate(&[0xE9, 0x00, 0x10], 0x1000, 0x800, Mode::Real),
+        Ok(vec![0xE9, 0x00, 0x18]));
+    // A jump to itself stays one.
+    assert_eq!(relocate(&[0xE9, 0xFD, 0xFF], 0x1000, 0x800, Mode::Real),
+        Ok(vec![0xE9, 0xFD, 0xFF]));
+    // Wraps around at 4GB in protected mode.
+    assert_eq!(relocate(&[0xE9, 0x00, 0x00, 0x00, 0x00], 0xFFFF_FFF0, 0x10, Mode::Protected),
+        Ok(vec![0xE9, 0xE0, 0xFF, 0xFF, 0xFF]));
+    assert_eq!(relocate(&[0xE9, 0x00, 0x00, 0x00, 0x00], 0x1000, 0x1_0000_0000, Mode::Long),
+        Err(RelocationError::OutOfRange(0)));
+}
+
+#[test]
+fn short_branches() {
+    // je 0x1012 still reaches from 0x1008.
+    assert_eq!(relocate(&[0x74, 0x10], 0x1000, 0x1008, Mode::Long), Ok(vec![0x74, 0x08]));
+    // jmp and je are widened.
+    assert_eq!(relocate(&[0xEB, 0x10], 0x1000, 0x10_0000, Mode::Long),
+        Ok(vec![0xE9, 0x0D, 0x10, 0xF0, 0xFF]));
+    assert_eq!(relocate(&[0x74, 0x10], 0x1000, 0x10_0000, Mode::Long),
+        Ok(vec![0x0F, 0x84, 0x0C, 0x10, 0xF0, 0xFF]));
+    assert_eq!(relocate(&[0x7C, 0x10], 0x1000, 0x8000, Mode::Real),
+        Ok(vec![0x0F, 0x8C, 0x0E, 0x90]));
+    // loop 0x1012: loop +2; jmp short +5; jmp 0x1012
+    assert_eq!(relocate(&[0xE2, 0x10], 0x1000, 0x10_0000, Mode::Long),
+        Ok(vec![0xE2, 0x02, 0xEB, 0x05, 0xE9, 0x09, 0x10, 0xF0, 0xFF]));
+}
+
+#[test]
+fn widen_every_jcc() {
+    // Each Jcc rel8 to 0x1081 is widened to its rel32 form, including JS.
+    for &mode in &[Mode::Protected, Mode::Long] {
+        for code in 0..16 {
+            let moved = relocate(&[0x70 | code, 0x7F], 0x1000, 0x7FFF_0000, mode).unwrap();
+            let value = 0x1081u32.wrapping_sub(0x7FFF_0006);
+            assert_eq!(moved[..2], [0x0F, 0x80 | code], "{:?} mode", mode);
+            assert_eq!(moved[2..], value.to_le_bytes());
+        }
+    }
+}
+
+#[test]
+fn internal_branches() {
+    // je +3 to the ret, jmp back to 0xFF4, nop, ret. Widening the jmp moves the ret, which the
+    // je follows.
+    let bytes = [0x74, 0x03, 0xEB, 0xF0, 0x90, 0xC3];
+    assert_eq!(relocate(&bytes, 0x1000, 0x20_0000, Mode::Long),
+        Ok(vec![0x74, 0x06, 0xE9, 0xED, 0x0F, 0xE0, 0xFF, 0x90, 0xC3]));
+    // A branch to the end of the instructions isn't moved.
+    assert_eq!(relocate(&[0x74, 0x00], 0x1000, 0x1010, Mode::Long), Ok(vec![0x74, 0xF0]));
+    // je +2 into the middle of mov eax, 1
+    assert_eq!(relocate(&[0x74, 0x02, 0xB8, 0x01, 0x00, 0x00, 0x00], 0x1000, 0x2000, Mode::Long),
+        Err(RelocationError::Unrelocatable(0)));
+}
+
+#[test]
+fn rip_relative() {
+    // mov rax, [rip+0x10]
+    assert_eq!(relocate(&[0x48, 0x8B, 0x05, 0x10, 0x00, 0x00, 0x00], 0x1000, 0x2000, Mode::Long),
+        Ok(vec![0x48, 0x8B, 0x05, 0x10, 0xF0, 0xFF, 0xFF]));
+    // mov dword [rip+0x10], 5 - the displacement is followed by an immediate.
+    let bytes = [0xC7, 0x05, 0x10, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00];
+    assert_eq!(relocate(&bytes, 0x1000, 0x800, Mode::Long),
+        Ok(vec![0xC7, 0x05, 0x10, 0x08, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00]));
+    // Absolute in protected mode.
+    let bytes = [0x8B, 0x05, 0x10, 0x00, 0x00, 0x00];
+    assert_eq!(relocate(&bytes, 0x1000, 0x2000, Mode::Protected), Ok(bytes.to_vec()));
+    assert_eq!(relocate(&[0x48, 0x8B, 0x05, 0, 0, 0, 0], 0, 0x1_0000_0000, Mode::Long),
+        Err(RelocationError::OutOfRange(0)));
+    assert_eq!(relocate(&[0x67, 0x8B, 0x05, 0, 0, 0, 0], 0, 0x100, Mode::Long),
+        Err(RelocationError::Unrelocatable(0)));
+}
+
+#[test]
+fn instruction_boundary() {
+    // push rbp; mov rbp, rsp; sub rsp, 16; ret
+    let bytes = [0x55, 0x48, 0x89, 0xE5, 0x48, 0x83, 0xEC, 0x10, 0xC3];
+    assert_eq!(find_instruction_boundary(&bytes, 0, Mode::Long), Ok(0));
+    assert_eq!(find_instruction_boundary(&bytes, 1, Mode::Long), Ok(1));
+    assert_eq!(find_instruction_boundary(&bytes, 5, Mode::Long), Ok(8));
+    assert_eq!(find_instruction_boundary(&bytes, 10, Mode::Long),
+        Err(RelocationError::Decoding(9, InstructionDecodingError::EndOfStream)));
+    assert_eq!(relocate(&bytes[..6], 0, 0, Mode::Long),
+        Err(RelocationError::Decoding(4, InstructionDecodingError::PartialInstruction)));
+}
+
+#[cfg(all(unix, target_arch = "x86_64"))]
+#[test]
+fn trampoline() {
+    // f(x) = x + [rip+constant], with its first 8 bytes moved to a trampoline that jumps back to
+    // the rest of it.
+    let (start, constant) = (Label::new(), Label::new());
+    let mut original = InstructionWriter::new(JitBuffer::new().unwrap(), Mode::Long);
+    original.set_label(start);
+    original.write2(Mnemonic::MOV, Operand::Direct(Reg::EAX), Operand::Direct(Reg::EDI)).unwrap();
+    original.write2(Mnemonic::ADD, Operand::Direct(Reg::EAX),
+        Operand::LabelMemory(constant, Some(OperandSize::Dword), None)).unwrap();
+    original.write0(Mnemonic::RET).unwrap();
+    original.set_label(constant);
+    original.write_bytes(&[5, 0, 0, 0]).unwrap();
+    original.finalize().unwrap();
+
+    let code = original.get_inner_writer_ref().as_slice();
+    let len = find_instruction_boundary(code, 5, Mode::Long).unwrap();
+    assert_eq!(len, 8);
+    let (entry, back) = (Label::new(), Label::new());
+    let mut trampoline = InstructionWriter::new(JitBuffer::new().unwrap(), Mode::Long);
+    let address = trampoline.get_inner_writer_ref().get_address();
+    let moved = relocate(&code[..len], original.get_label_address(start).unwrap(), address,
+        Mode::Long).unwrap();
+    trampoline.set_label(entry);
+    trampoline.write_bytes(&moved).unwrap();
+    trampoline.write1(Mnemonic::JMP, Operand::LabelMemory(back, Some(OperandSize::Qword), None))
+        .unwrap();
+    trampoline.set_label(back);
+    trampoline.write_bytes(&(original.get_label_address(start).unwrap() + len as u64)
+        .to_le_bytes()).unwrap();
+    trampoline.finalize().unwrap();
+
+    let f: extern "C" fn(u32) -> u32 = unsafe { trampoline.get_function(entry) }.unwrap();
+    assert_eq!(f(3), 8);
+}
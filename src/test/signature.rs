@@ -0,0 +1,101 @@
+use ::*;
+
+fn reg(reg: Reg) -> Operand { Operand::Direct(reg) }
+
+// mov rax, [rip+0x10]; call +0x100; mov rax, [rbp+8]; mov eax, 1; je +5; ret
+const CODE: [u8; 24] = [0x48, 0x8B, 0x05, 0x10, 0x00, 0x00, 0x00, 0xE8, 0x00, 0x01, 0x00, 0x00,
+    0x48, 0x8B, 0x45, 0x08, 0xB8, 0x01, 0x00, 0x00, 0x00, 0x74, 0x05, 0xC3];
+
+#[test]
+fn from_code() {
+    let signature = Signature::from_code(&CODE, Mode::Long, false).unwrap();
+    assert_eq!(signature.to_string(), "48 8B 05 ?? ?? ?? ?? E8 ?? ?? ?? ?? 48 8B 45 08 \
+        B8 01 00 00 00 74 ?? C3");
+    let signature = Signature::from_code(&CODE, Mode::Long, true).unwrap();
+    assert_eq!(signature.to_string(), "48 8B 05 ?? ?? ?? ?? E8 ?? ?? ?? ?? 48 8B 45 08 \
+        B8 ?? ?? ?? ?? 74 ?? C3");
+    assert_eq!(Signature::from_code(&CODE[..10], Mode::Long, false),
+        Err(InstructionDecodingError::PartialInstruction));
+
+    // Absolute addresses: mov eax, [0x1000] (moffs); mov ecx, [0x2000]; jmp far 0x8:0x1234;
+    // jmp [eax*4+0x401000] (a jump table)
+    let code = [0xA1, 0x00, 0x10, 0x00, 0x00, 0x8B, 0x0D, 0x00, 0x20, 0x00, 0x00,
+        0xEA, 0x34, 0x12, 0x00, 0x00, 0x08, 0x00, 0xFF, 0x24, 0x85, 0x00, 0x10, 0x40, 0x00];
+    assert_eq!(Signature::from_code(&code, Mode::Protected, false).unwrap().to_string(),
+        "A1 ?? ?? ?? ?? 8B 0D ?? ?? ?? ?? EA ?? ?? ?? ?? ?? ?? FF 24 85 ?? ?? ?? ??");
+}
+
+#[test]
+fn from_instructions() {
+    let instrs = [
+        Instruction::new1(Mnemonic::CALL, Operand::Literal32(0x100)),
+        Instruction::new2(Mnemonic::MOV, reg(Reg::RAX),
+            Operand::IndirectDisplaced(Reg::RBP, 8, Some(OperandSize::Qword), None)),
+        Instruction::new2(Mnemonic::MOV, reg(Reg::EAX), Operand::Literal32(1)),
+        Instruction::new1(Mnemonic::JE, Operand::Literal8(5)),
+        Instruction::new0(Mnemonic::RET),
+    ];
+    assert_eq!(Signature::from_instructions(&instrs, Mode::Long, false).unwrap(),
+        Signature::from_code(&CODE[7..], Mode::Long, false).unwrap());
+
+    // mov rax, [moffs64], and mov ecx, [0x1000] in protected mode.
+    let moffs = Instruction::new2(Mnemonic::MOV, reg(Reg::RAX),
+        Operand::Offset(0x10, Some(OperandSize::Qword), None));
+    assert_eq!(Signature::from_instructions(&[moffs], Mode::Long, false).unwrap().to_string(),
+        "48 A1 ?? ?? ?? ?? ?? ?? ?? ??");
+    let absolute = Instruction::new2(Mnemonic::MOV, reg(Reg::ECX),
+        Operand::Memory(0x1000, Some(OperandSize::Dword), None));
+    assert_eq!(Signature::from_instructions(&[absolute], Mode::Protected, false).unwrap()
+        .to_string(), "8B 0D ?? ?? ?? ??");
+
+    // Label operands are always wildcards, even as immediates.
+    let label = Label::new();
+    let instrs = [
+        Instruction::new2(Mnemonic::LEA, reg(Reg::RCX), Operand::LabelMemory(label, None, None)),
+        Instruction::new2(Mnemonic::MOV, reg(Reg::RDX), Operand::Label(label)),
+        Instruction::new1(Mnemonic::JMP, Operand::Label(label)),
+    ];
+    let signature = Signature::from_instructions(&instrs, Mode::Long, false).unwrap();
+    assert_eq!(signature.to_string(), "48 8D 0D ?? ?? ?? ?? 48 C7 C2 ?? ?? ?? ?? E9 ?? ?? ?? ??");
+    assert_eq!(Signature::from_instructions(&[Instruction::new0(Mnemonic::SYSCALL)], Mode::Real,
+        false), Err(InstructionEncodingError::NoEncoding));
+}
+
+#[test]
+fn parse() {
+    let signature = Signature::parse("48 8b 05 ?? ? ?? ?? c3").unwrap();
+    assert_eq!(signature.len(), 8);
+    assert_eq!(signature.get_pattern()[..4], [Some(0x48), Some(0x8B), Some(0x05), None]);
+    assert_eq!(signature.to_string(), "48 8B 05 ?? ?? ?? ?? C3");
+    assert_eq!(Signature::parse(&signature.to_string()), Some(signature));
+    assert_eq!(Signature::parse("48 8"), None);
+    assert_eq!(Signature::parse("488B"), None);
+    assert_eq!(Signature::parse("GG"), None);
+    assert_eq!(Signature::parse("+1"), None);
+    assert_eq!(Signature::parse("-1"), None);
+    assert!(Signature::parse("").unwrap().is_empty());
+}
+
+#[test]
+fn find() {
+    let signature = Signature::from_code(&CODE, Mode::Long, true).unwrap();
+    // The same function in another build, with other addresses and constants.
+    let mut other = CODE;
+    other[3] = 0x80;
+    other[9] = 0xFF;
+    other[17] = 0x02;
+    let mut binary = vec![0xCC; 100];
+    binary.extend_from_slice(&other);
+    binary.extend_from_slice(&[0xCC; 5]);
+    assert_eq!(signature.find(&binary), Some(100));
+    assert!(signature.matches(&binary[100..]));
+    assert!(!signature.matches(&binary[100..120]));
+    binary.extend_from_slice(&CODE);
+    assert_eq!(signature.find_all(&binary), vec![100, 129]);
+    binary[130] = 0x89;
+    assert_eq!(signature.find_all(&binary), vec![100]);
+    assert_eq!(signature.find(&binary[..110]), None);
+
+    let wildcards = Signature::parse("CC ?? CC").unwrap();
+    assert_eq!(wildcards.find_all(&[0xCC, 0xCC, 0xCC, 0xCC]), vec![0, 1]);
+}
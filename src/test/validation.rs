@@ -0,0 +1,86 @@
+use ::*;
+
+fn reason(instr: Instruction, mode: Mode) -> (Option<usize>, ValidationReason) {
+    let err = instr.validate(mode).expect_err("Validation should fail");
+    (err.operand, err.reason)
+}
+
+#[test]
+fn valid_instructions() {
+    let add = Instruction::new2(Mnemonic::ADD, Operand::Direct(Reg::EAX), Operand::Literal8(1));
+    assert_eq!(add.validate(Mode::Protected), Ok(()));
+    assert_eq!(add.validate(Mode::Long), Ok(()));
+    let sub = Instruction::new2(Mnemonic::SUB, Operand::Direct(Reg::R8),
+        Operand::IndirectDisplaced(Reg::RSP, 8, Some(OperandSize::Qword), None));
+    assert_eq!(sub.validate(Mode::Long), Ok(()));
+}
+
+#[test]
+fn register_not_available() {
+    let instr = Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::R8D), Operand::Direct(Reg::EAX));
+    assert_eq!(reason(instr, Mode::Protected),
+        (Some(1), ValidationReason::RegisterNotAvailable(Reg::R8D, Mode::Protected)));
+
+    let err = instr.validate(Mode::Protected).unwrap_err();
+    assert_eq!(err.to_string().find("R8D not available in Protected mode"), Some("MOV: operand 1: ".len()));
+}
+
+#[test]
+fn high_byte_with_rex() {
+    let instr = Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::AH), Operand::Direct(Reg::SIL));
+    assert_eq!(reason(instr, Mode::Long), (Some(1), ValidationReason::HighByteWithRex(Reg::AH)));
+    let instr = Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::R9B), Operand::Direct(Reg::BH));
+    assert_eq!(reason(instr, Mode::Long), (Some(2), ValidationReason::HighByteWithRex(Reg::BH)));
+    assert_eq!(ValidationReason::HighByteWithRex(Reg::AH).to_string(), "AH cannot be used with a REX prefix");
+}
+
+#[test]
+fn requires_evex() {
+    let instr = Instruction::new2(Mnemonic::MOVAPS, Operand::Direct(Reg::XMM16), Operand::Direct(Reg::XMM1));
+    assert_eq!(reason(instr, Mode::Long), (Some(1), ValidationReason::RequiresEvex(Reg::XMM16)));
+}
+
+#[test]
+fn invalid_addressing() {
+    let instr = Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::EAX),
+        Operand::IndirectScaledIndexed(Reg::EAX, Reg::ESP, RegScale::Two, None, None));
+    assert_eq!(reason(instr, Mode::Protected).0, Some(2));
+    let instr = Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::AX),
+        Operand::IndirectScaledIndexed(Reg::SI, Reg::DI, RegScale::One, None, None));
+    assert!(matches!(reason(instr, Mode::Real), (Some(2), ValidationReason::InvalidAddressing(_))));
+    let instr = Instruction::new2(Mnemonic::MOV, Operand::Direct(Reg::EAX),
+        Operand::IndirectScaledIndexed(Reg::EAX, Reg::CX, RegScale::One, None, None));
+    assert!(matches!(reason(instr, Mode::Protected), (Some(2), ValidationReason::InvalidAddressing(_))));
+}
+
+#[test]
+fn ambiguous_size() {
+    let instr = Instruction::new2(Mnemonic::ADD, Operand::Indirect(Reg::EAX, None, None), Operand::Literal8(1));
+    assert_eq!(reason(instr, Mode::Protected), (Some(1), ValidationReason::AmbiguousSize));
+    assert_eq!(ValidationReason::AmbiguousSize.to_string(), "memory size ambiguous, add a size");
+}
+
+#[test]
+fn mismatches() {
+    let instr = Instruction::new1(Mnemonic::ADD, Operand::Direct(Reg::EAX));
+    assert_eq!(reason(instr, Mode::Protected), (None, ValidationReason::OperandCount(1)));
+
+    let instr = Instruction::new2(Mnemonic::ADD, Operand::Literal8(1), Operand::Direct(Reg::EAX));
+    let err = instr.validate(Mode::Protected).unwrap_err();
+    assert_eq!((err.operand, err.reason), (Some(1), ValidationReason::OperandMismatch));
+    assert!(err.candidates.contains(&"ADD r/m32, imm8".to_owned()));
+
+    let instr = Instruction::new0(Mnemonic::SYSCALL);
+    assert_eq!(reason(instr, Mode::Real), (None, ValidationReason::NotAvailableInMode(Mode::Real)));
+}
+
+#[test]
+fn error_traits() {
+    fn takes_error<E: ::std::error::Error>(_: &E) { }
+    let err = Instruction::new1(Mnemonic::ADD, Operand::Direct(Reg::EAX))
+        .validate(Mode::Protected).unwrap_err();
+    takes_error(&err);
+    takes_error(&InstructionEncodingError::AmbiguousSize);
+    takes_error(&InstructionDecodingError::UnknownOpcode);
+    assert_eq!(InstructionDecodingError::UnknownOpcode.to_string(), "unknown opcode");
+}
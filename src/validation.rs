@@ -0,0 +1,225 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use ::{Instruction, InstructionEncodingError, Mnemonic, Mode, Operand, Reg};
+use ::instruction_def::{find_instruction_def, get_instruction_defs, InstructionDefinition};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ValidationError {
+    pub mnemonic: Mnemonic,
+
+    // The operand (1-4) that caused the error, if the error is specific to one.
+    pub operand: Option<usize>,
+
+    pub reason: ValidationReason,
+
+    // The forms of the mnemonic available in the requested mode (i.e. "ADD r/m32, imm8").
+    pub candidates: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ValidationReason {
+    // NotAvailableInMode - No form of the mnemonic can be encoded in the mode.
+    NotAvailableInMode(Mode),
+
+    // RegisterNotAvailable - The register can only be used in long mode.
+    RegisterNotAvailable(Reg, Mode),
+
+    // HighByteWithRex - AH, BH, CH or DH is used in an instruction that needs a REX prefix.
+    HighByteWithRex(Reg),
+
+    // RequiresEvex - Vector registers 16-31 need an EVEX encoding, which the mnemonic lacks.
+    RequiresEvex(Reg),
+
+    // InvalidAddressing - The memory operand can't be encoded.
+    InvalidAddressing(&'static str),
+
+    // AmbiguousSize - The operand size can't be inferred and has to be given explicitly.
+    AmbiguousSize,
+
+    // OperandCount - No form of the mnemonic takes this number of operands.
+    OperandCount(usize),
+
+    // OperandMismatch - No form of the mnemonic accepts the operand.
+    OperandMismatch,
+
+    // NoEncoding - Each operand is accepted by some form, but no form accepts all of them.
+    NoEncoding,
+}
+
+impl fmt::Display for ValidationReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ValidationReason::NotAvailableInMode(mode) =>
+                write!(f, "not available in {:?} mode", mode),
+            ValidationReason::RegisterNotAvailable(reg, mode) =>
+                write!(f, "{:?} not available in {:?} mode", reg, mode),
+            ValidationReason::HighByteWithRex(reg) =>
+                write!(f, "{:?} cannot be used with a REX prefix", reg),
+            ValidationReason::RequiresEvex(reg) => write!(f, "{:?} requires EVEX", reg),
+            ValidationReason::InvalidAddressing(why) => f.write_str(why),
+            ValidationReason::AmbiguousSize => f.write_str("memory size ambiguous, add a size"),
+            ValidationReason::OperandCount(count) =>
+                write!(f, "no form takes {} operand(s)", count),
+            ValidationReason::OperandMismatch => f.write_str("operand not accepted by any form"),
+            ValidationReason::NoEncoding => f.write_str("no form accepts this combination of operands")
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}: ", self.mnemonic)?;
+        if let Some(operand) = self.operand {
+            write!(f, "operand {}: ", operand)?;
+        }
+        write!(f, "{}", self.reason)?;
+        if !self.candidates.is_empty() {
+            write!(f, " (expected one of: {})", self.candidates.join("; "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ValidationError { }
+
+pub fn validate(instr: &Instruction, mode: Mode) -> Result<(), ValidationError> {
+    let defs: Vec<_> = get_instruction_defs(instr.mnemonic).into_iter()
+        .filter(|def| def.is_valid_in(mode)).collect();
+    let error = |operand: Option<usize>, reason| Err(ValidationError {
+        mnemonic: instr.mnemonic,
+        operand: operand.map(|i| i + 1),
+        reason,
+        candidates: defs.iter().map(|def| def.to_string()).collect()
+    });
+
+    if defs.is_empty() { return error(None, ValidationReason::NotAvailableInMode(mode)); }
+
+    let operands: Vec<_> = instr.operands().iter().map(|op| **op).collect();
+    for (i, op) in operands.iter().enumerate() {
+        let op = match *op { Some(op) => op, None => continue };
+        if let Some(reg) = registers(&op).into_iter().find(|r| !is_available(*r, mode)) {
+            return error(Some(i), ValidationReason::RegisterNotAvailable(reg, mode));
+        }
+        if let Err(why) = check_addressing(&op, mode) {
+            return error(Some(i), ValidationReason::InvalidAddressing(why));
+        }
+        if let Operand::Direct(reg) = op {
+            if reg.is_high_byte() && operands.iter().flatten().any(needs_rex) {
+                return error(Some(i), ValidationReason::HighByteWithRex(reg));
+            }
+            if reg.is_avx() && reg.get_reg_code() >= 0x10 && !defs.iter().any(|d| d.is_evex()) {
+                return error(Some(i), ValidationReason::RequiresEvex(reg));
+            }
+        }
+    }
+
+    let count = operands.iter().flatten().count();
+    let defs_with_count: Vec<_> = defs.iter()
+        .filter(|def| def.operands.iter().flatten().count() == count).collect();
+    if defs_with_count.is_empty() { return error(None, ValidationReason::OperandCount(count)); }
+
+    match find_instruction_def(instr, mode) {
+        Ok(_) => match instr.encode(&mut io::sink(), mode) {
+            Ok(_) => Ok(()),
+            Err(InstructionEncodingError::InvalidAddressing) => error(first_memory(&operands),
+                ValidationReason::InvalidAddressing("addressing mode cannot be encoded")),
+            Err(_) => error(None, ValidationReason::NoEncoding)
+        },
+        Err(InstructionEncodingError::AmbiguousSize) => {
+            let unsized_memory = operands.iter().position(
+                |op| op.is_some_and(|o| o.is_memory() && o.size().is_none()));
+            error(unsized_memory, ValidationReason::AmbiguousSize)
+        },
+        Err(_) => {
+            // Find the first operand that isn't accepted by any of the forms.
+            let mismatch = operands.iter().enumerate().position(|(i, op)| op.is_some() &&
+                !defs_with_count.iter().any(|def| accepts(def, i, instr)));
+            match mismatch {
+                Some(i) => error(Some(i), ValidationReason::OperandMismatch),
+                None => error(None, ValidationReason::NoEncoding)
+            }
+        }
+    }
+}
+
+fn accepts(def: &InstructionDefinition, index: usize, instr: &Instruction) -> bool {
+    def.operands[index].as_ref()
+        .is_some_and(|d| d.matches_operand(instr.operands()[index], def, instr))
+}
+
+fn first_memory(operands: &[Option<Operand>]) -> Option<usize> {
+    operands.iter().position(|op| op.is_some_and(|o| o.is_memory()))
+}
+
+fn registers(op: &Operand) -> Vec<Reg> {
+    match *op {
+        Operand::Direct(reg) => vec![reg],
+        _ => op.base_reg().into_iter().chain(op.index_reg()).collect()
+    }
+}
+
+// Checks if the register can only be encoded in long mode.
+fn is_long_only(reg: Reg) -> bool {
+    if reg.is_general() {
+        !reg.is_high_byte() &&
+            (reg.get_reg_code() >= 0x8 || reg.needs_rex() || reg.is_64_bit())
+    } else if reg.is_avx() {
+        reg.get_reg_code() >= 0x8
+    } else {
+        matches!(reg, Reg::RIP | Reg::RFLAGS | Reg::CR8)
+    }
+}
+
+fn is_available(reg: Reg, mode: Mode) -> bool {
+    mode == Mode::Long || !is_long_only(reg)
+}
+
+// Checks if the operand forces a REX prefix. 64-bit operand sizes aren't considered, as they only
+// conflict with AH/BH/CH/DH when the operand sizes differ, which is caught while matching.
+fn needs_rex(op: &Operand) -> bool {
+    registers(op).iter().any(|reg| reg.is_general() && !reg.is_high_byte() &&
+        (reg.needs_rex() || reg.get_reg_code() >= 0x8))
+}
+
+fn check_addressing(op: &Operand, mode: Mode) -> Result<(), &'static str> {
+    let base = op.base_reg();
+    let index = op.index_reg();
+    if base.is_none() && index.is_none() { return Ok(()); }
+
+    let is_ip = |reg: Reg| matches!(reg, Reg::RIP | Reg::EIP);
+    if let Some(reg) = base {
+        if is_ip(reg) {
+            if mode != Mode::Long { return Err("RIP-relative addressing requires Long mode"); }
+            if index.is_some() { return Err("RIP-relative addressing cannot use an index"); }
+            return Ok(());
+        }
+    }
+
+    let regs: Vec<_> = base.into_iter().chain(index).collect();
+    if regs.iter().any(|r| !r.is_general()) {
+        return Err("base and index must be general purpose registers");
+    }
+    if regs.iter().any(|r| r.is_8_bit()) {
+        return Err("8-bit registers cannot be used for addressing");
+    }
+    if regs.iter().any(|r| r.size() != regs[0].size()) {
+        return Err("base and index must be the same size");
+    }
+    if let Some(Reg::SP) | Some(Reg::ESP) | Some(Reg::RSP) = index {
+        return Err("the stack pointer cannot be used as an index");
+    }
+
+    if regs[0].is_16_bit() {
+        if mode == Mode::Long { return Err("16-bit addressing is not available in Long mode"); }
+        let valid = matches!((base, index),
+            (Some(Reg::BX), Some(Reg::SI)) | (Some(Reg::BX), Some(Reg::DI)) |
+            (Some(Reg::BP), Some(Reg::SI)) | (Some(Reg::BP), Some(Reg::DI)) |
+            (Some(Reg::BX), None) | (Some(Reg::BP), None) |
+            (Some(Reg::SI), None) | (Some(Reg::DI), None));
+        if !valid || op.scale().is_some_and(|s| s != ::RegScale::One) {
+            return Err("16-bit addressing only supports BX/BP with SI/DI and no scaling");
+        }
+    }
+    Ok(())
+}